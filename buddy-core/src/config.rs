@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 
 const DEFAULT_HOST: &str = "127.0.0.1";
@@ -22,6 +23,9 @@ pub struct Config {
     pub auth: AuthConfig,
     #[serde(default)]
     pub interfaces: InterfacesConfig,
+    /// Per-model prices used to compute spend, keyed by model name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pricing: BTreeMap<String, ModelPrice>,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -73,9 +77,79 @@ pub struct ProviderEntry {
     pub api_key: Option<String>,
     #[serde(default)]
     pub api_key_env: Option<String>,
+    /// Context window size in tokens. Defaults per provider type.
+    #[serde(default)]
+    pub context_window: Option<usize>,
+    /// Whether the model accepts image input. Defaults to a guess from the
+    /// provider type and model name.
+    #[serde(default)]
+    pub vision: Option<bool>,
+    /// Send the model's stored reasoning back to it in later turns. Only
+    /// takes effect when `chat.persist_reasoning` is on.
+    #[serde(default)]
+    pub send_reasoning: bool,
+    /// How tools are offered to the model.
+    #[serde(default)]
+    pub tool_mode: ToolMode,
+    #[serde(flatten)]
+    pub generation: GenerationParams,
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+/// Optional sampling parameters sent with every request to a provider.
+/// Unset fields are omitted so the provider's own defaults apply.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+/// Retry settings for transient failures (network errors, rate limits).
+/// Unset fields use the defaults in `provider::retry`.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct RetryConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Backoff before the first retry in milliseconds; doubles per attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<u64>,
 }
 
 impl ProviderEntry {
+    /// Effective retry policy: configured values over the defaults.
+    pub fn retry_policy(&self) -> crate::provider::retry::RetryPolicy {
+        let mut policy = crate::provider::retry::RetryPolicy::default();
+        if let Some(max_retries) = self.retry.max_retries {
+            policy.max_retries = max_retries;
+        }
+        if let Some(ms) = self.retry.retry_backoff_ms {
+            policy.initial_backoff = std::time::Duration::from_millis(ms);
+        }
+        policy
+    }
+
+    /// Effective context window: the configured value, or the provider
+    /// type's default.
+    pub fn context_window(&self) -> usize {
+        self.context_window
+            .unwrap_or_else(|| crate::context::default_context_window(&self.provider_type))
+    }
+
+    /// Effective image support: the configured value, or a guess from the
+    /// provider type and model name.
+    pub fn vision(&self) -> bool {
+        self.vision.unwrap_or_else(|| {
+            crate::provider::default_vision(&self.provider_type, &self.model)
+        })
+    }
+
     pub fn resolve_api_key(&self) -> Result<String, String> {
         if let Some(ref key) = self.api_key {
             if !key.is_empty() {
@@ -99,12 +173,16 @@ fn default_provider_type() -> String {
 pub struct ChatConfig {
     #[serde(default = "default_system_prompt")]
     pub system_prompt: String,
+    /// Store the reasoning that models stream before their replies.
+    #[serde(default)]
+    pub persist_reasoning: bool,
 }
 
 impl Default for ChatConfig {
     fn default() -> Self {
         Self {
             system_prompt: default_system_prompt(),
+            persist_reasoning: false,
         }
     }
 }
@@ -131,11 +209,65 @@ fn default_database() -> String {
     DEFAULT_DATABASE.to_string()
 }
 
+/// Tool calls from one response that may run at the same time, unless
+/// `max_concurrency` says otherwise.
+pub const DEFAULT_TOOL_CONCURRENCY: usize = 4;
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
 pub struct ToolsConfig {
     pub read_file: Option<ReadFileConfig>,
     pub write_file: Option<WriteFileConfig>,
     pub fetch_url: Option<FetchUrlConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_command: Option<RunCommandConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_request: Option<HttpRequestConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_search: Option<WebSearchConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_reminder: Option<ScheduleReminderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub todos: Option<TodosConfig>,
+    /// Cap on read-only and network tool calls run concurrently.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    /// Limits for every tool that does not set its own.
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+/// Execution limits for a tool. Unset values fall back to the `[tools]`
+/// defaults, then to the built-in ones.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ToolLimitsConfig {
+    /// Seconds a call may run before it is abandoned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Longest result, in bytes, passed back to the model; longer results
+    /// are truncated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_result_bytes: Option<usize>,
+}
+
+impl ToolsConfig {
+    /// Effective concurrency cap: the configured value (at least 1), or
+    /// `DEFAULT_TOOL_CONCURRENCY`.
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+            .map_or(DEFAULT_TOOL_CONCURRENCY, |limit| limit.max(1))
+    }
+}
+
+/// How a provider is offered tools.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolMode {
+    /// Send tool definitions in the API's `tools` field.
+    #[default]
+    Native,
+    /// Describe the tools in the system prompt and parse calls out of the
+    /// model's text, for models without function calling.
+    Emulated,
 }
 
 /// Per-skill approval policy for mutating or network skills.
@@ -155,6 +287,8 @@ pub struct ReadFileConfig {
     pub allowed_directories: Vec<String>,
     #[serde(default)]
     pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -162,13 +296,142 @@ pub struct WriteFileConfig {
     pub allowed_directories: Vec<String>,
     #[serde(default)]
     pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct FetchUrlConfig {
     pub allowed_domains: Vec<String>,
+    /// Private, loopback or link-local networks that may be fetched, as
+    /// CIDR ranges (`192.168.1.0/24`) or single addresses. All other
+    /// non-public addresses are refused.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_private_networks: Vec<String>,
+    /// Bytes of extracted content returned per call; longer content is
+    /// read in pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_bytes: Option<usize>,
+    /// Bytes of response body downloaded; the rest is dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_download_bytes: Option<usize>,
     #[serde(default)]
     pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct HttpRequestConfig {
+    pub allowed_domains: Vec<String>,
+    /// Private, loopback or link-local networks that may be called, as for
+    /// `fetch_url`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_private_networks: Vec<String>,
+    /// Headers added to requests, keyed by domain pattern (same syntax as
+    /// `allowed_domains`). Secrets are named by environment variable and
+    /// never shown to the model.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub credentials: BTreeMap<String, HttpCredentialsConfig>,
+    /// Bytes of response body downloaded; the rest is dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Policy for GET and HEAD requests.
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+    /// Policy for POST, PUT, PATCH and DELETE requests. Unset means ask
+    /// every time, whatever `approval` says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+/// Headers sent to the domains matching one `credentials` pattern.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Default)]
+pub struct HttpCredentialsConfig {
+    /// Environment variable holding a token sent as
+    /// `Authorization: Bearer <token>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_env: Option<String>,
+    /// Headers with fixed, non-secret values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Headers whose values are read from environment variables, as
+    /// header name to variable name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers_env: BTreeMap<String, String>,
+}
+
+/// Search engine behind the `web_search` tool.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackendKind {
+    /// A SearxNG instance with the JSON output format enabled.
+    #[default]
+    Searxng,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct WebSearchConfig {
+    #[serde(default)]
+    pub backend: SearchBackendKind,
+    /// Base URL of the search backend, e.g. `http://localhost:8888`.
+    pub endpoint: String,
+    /// Results returned when the model does not ask for a number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_results: Option<usize>,
+    /// Search language passed to the backend, e.g. `en`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Backend categories to search, e.g. `["general", "it"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+/// Reminders and scheduled prompts, delivered by the Telegram and WhatsApp
+/// interfaces.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ScheduleReminderConfig {
+    /// Jobs one conversation may have scheduled at a time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_conversation: Option<usize>,
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+/// Todo lists kept in the conversation store and shared by every interface.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct TodosConfig {
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RunCommandConfig {
+    /// Executables that may be run, by name (looked up on `PATH`) or path.
+    pub allowed_commands: Vec<String>,
+    /// Directories a command may run in, including their subdirectories.
+    pub allowed_directories: Vec<String>,
+    /// Environment variables passed through to commands in addition to
+    /// `PATH`, `HOME`, `LANG` and `TERM`; everything else is cleared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pass_env: Vec<String>,
+    /// Bytes of stdout and of stderr kept from each command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<usize>,
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub limits: ToolLimitsConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -179,6 +442,13 @@ pub struct MemoryConfig {
     pub auto_retrieve_limit: usize,
     #[serde(default = "default_similarity_threshold")]
     pub similarity_threshold: f32,
+    /// Summarize older turns once this many messages have accumulated beyond
+    /// the current summary. `0` disables summarization.
+    #[serde(default = "default_summarize_after_messages")]
+    pub summarize_after_messages: usize,
+    /// Number of most recent messages always sent verbatim.
+    #[serde(default = "default_summary_keep_recent")]
+    pub summary_keep_recent: usize,
 }
 
 impl Default for MemoryConfig {
@@ -187,10 +457,27 @@ impl Default for MemoryConfig {
             auto_retrieve: default_auto_retrieve(),
             auto_retrieve_limit: default_auto_retrieve_limit(),
             similarity_threshold: default_similarity_threshold(),
+            summarize_after_messages: default_summarize_after_messages(),
+            summary_keep_recent: default_summary_keep_recent(),
         }
     }
 }
 
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    /// Cost in USD of the given token counts.
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
 pub struct AuthConfig {
     pub token_hash: Option<String>,
@@ -303,6 +590,14 @@ fn default_similarity_threshold() -> f32 {
     0.5
 }
 
+fn default_summarize_after_messages() -> usize {
+    40
+}
+
+fn default_summary_keep_recent() -> usize {
+    10
+}
+
 impl Config {
     pub fn from_file(path: &Path) -> Result<Self, String> {
         let contents = std::fs::read_to_string(path)
@@ -544,6 +839,77 @@ allowed_directories = ["/home/user/documents"]
         assert!(config.tools.fetch_url.is_none());
     }
 
+    #[test]
+    fn tools_max_concurrency_defaults_and_is_at_least_one() {
+        let base = r#"
+[[models.chat.providers]]
+type = "lmstudio"
+model = "deepseek-coder"
+endpoint = "http://localhost:1234/v1"
+"#;
+        let config = Config::parse(base).unwrap();
+        assert_eq!(config.tools.max_concurrency(), DEFAULT_TOOL_CONCURRENCY);
+
+        let config = Config::parse(&format!("{base}\n[tools]\nmax_concurrency = 8\n")).unwrap();
+        assert_eq!(config.tools.max_concurrency(), 8);
+
+        let config = Config::parse(&format!("{base}\n[tools]\nmax_concurrency = 0\n")).unwrap();
+        assert_eq!(config.tools.max_concurrency(), 1);
+    }
+
+    #[test]
+    fn tool_limits_parse_at_both_levels() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "lmstudio"
+model = "deepseek-coder"
+endpoint = "http://localhost:1234/v1"
+
+[tools]
+timeout_secs = 30
+
+[tools.fetch_url]
+allowed_domains = ["example.com"]
+timeout_secs = 10
+max_result_bytes = 8000
+"#;
+        let config = Config::parse(toml).unwrap();
+        assert_eq!(config.tools.limits.timeout_secs, Some(30));
+        assert_eq!(config.tools.limits.max_result_bytes, None);
+        let fetch = config.tools.fetch_url.unwrap();
+        assert_eq!(fetch.limits.timeout_secs, Some(10));
+        assert_eq!(fetch.limits.max_result_bytes, Some(8000));
+    }
+
+    #[test]
+    fn http_request_credentials_parse_per_domain() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "lmstudio"
+model = "deepseek-coder"
+endpoint = "http://localhost:1234/v1"
+
+[tools.http_request]
+allowed_domains = ["api.github.com", "*.ci.internal"]
+approval = "trust"
+
+[tools.http_request.credentials."api.github.com"]
+bearer_token_env = "GITHUB_TOKEN"
+headers = { "X-GitHub-Api-Version" = "2022-11-28" }
+
+[tools.http_request.credentials."*.ci.internal"]
+headers_env = { "X-Api-Key" = "CI_API_KEY" }
+"#;
+        let config = Config::parse(toml).unwrap();
+        let http = config.tools.http_request.unwrap();
+        assert_eq!(http.approval, Some(ApprovalPolicy::Trust));
+        assert_eq!(http.write_approval, None);
+        let github = &http.credentials["api.github.com"];
+        assert_eq!(github.bearer_token_env.as_deref(), Some("GITHUB_TOKEN"));
+        assert_eq!(github.headers["X-GitHub-Api-Version"], "2022-11-28");
+        assert_eq!(http.credentials["*.ci.internal"].headers_env["X-Api-Key"], "CI_API_KEY");
+    }
+
     #[test]
     fn full_tools_config_parses() {
         let toml = r#"
@@ -645,6 +1011,12 @@ providers = []
             endpoint: Some("https://api.openai.com/v1".into()),
             api_key: None,
             api_key_env: Some("BUDDY_TEST_API_KEY_018".into()),
+            context_window: None,
+            vision: None,
+            send_reasoning: false,
+            tool_mode: ToolMode::Native,
+            generation: GenerationParams::default(),
+            retry: RetryConfig::default(),
         };
         // SAFETY: test-only; unique env var name avoids conflicts with other tests.
         unsafe { std::env::set_var("BUDDY_TEST_API_KEY_018", "test123") };
@@ -661,6 +1033,12 @@ providers = []
             endpoint: Some("https://api.openai.com/v1".into()),
             api_key: None,
             api_key_env: Some("BUDDY_NONEXISTENT_KEY_018".into()),
+            context_window: None,
+            vision: None,
+            send_reasoning: false,
+            tool_mode: ToolMode::Native,
+            generation: GenerationParams::default(),
+            retry: RetryConfig::default(),
         };
         unsafe { std::env::remove_var("BUDDY_NONEXISTENT_KEY_018") };
         let err = entry.resolve_api_key().unwrap_err();
@@ -678,6 +1056,12 @@ providers = []
             endpoint: Some("https://api.openai.com/v1".into()),
             api_key: Some("sk-direct-key".into()),
             api_key_env: None,
+            context_window: None,
+            vision: None,
+            send_reasoning: false,
+            tool_mode: ToolMode::Native,
+            generation: GenerationParams::default(),
+            retry: RetryConfig::default(),
         };
         assert_eq!(entry.resolve_api_key().unwrap(), "sk-direct-key");
     }
@@ -690,6 +1074,12 @@ providers = []
             endpoint: Some("https://api.openai.com/v1".into()),
             api_key: Some("sk-direct".into()),
             api_key_env: Some("BUDDY_TEST_PRIORITY_KEY".into()),
+            context_window: None,
+            vision: None,
+            send_reasoning: false,
+            tool_mode: ToolMode::Native,
+            generation: GenerationParams::default(),
+            retry: RetryConfig::default(),
         };
         unsafe { std::env::set_var("BUDDY_TEST_PRIORITY_KEY", "from-env") };
         let key = entry.resolve_api_key().unwrap();
@@ -705,6 +1095,12 @@ providers = []
             endpoint: Some("https://api.openai.com/v1".into()),
             api_key: Some("".into()),
             api_key_env: Some("BUDDY_TEST_FALLTHROUGH_KEY".into()),
+            context_window: None,
+            vision: None,
+            send_reasoning: false,
+            tool_mode: ToolMode::Native,
+            generation: GenerationParams::default(),
+            retry: RetryConfig::default(),
         };
         unsafe { std::env::set_var("BUDDY_TEST_FALLTHROUGH_KEY", "env-value") };
         let key = entry.resolve_api_key().unwrap();
@@ -718,6 +1114,8 @@ providers = []
         assert!(config.memory.auto_retrieve);
         assert_eq!(config.memory.auto_retrieve_limit, 3);
         assert!((config.memory.similarity_threshold - 0.5).abs() < f32::EPSILON);
+        assert_eq!(config.memory.summarize_after_messages, 40);
+        assert_eq!(config.memory.summary_keep_recent, 10);
     }
 
     #[test]
@@ -732,11 +1130,15 @@ endpoint = "http://localhost:1234/v1"
 auto_retrieve = false
 auto_retrieve_limit = 10
 similarity_threshold = 0.7
+summarize_after_messages = 0
+summary_keep_recent = 6
 "#;
         let config = Config::parse(toml).unwrap();
         assert!(!config.memory.auto_retrieve);
         assert_eq!(config.memory.auto_retrieve_limit, 10);
         assert!((config.memory.similarity_threshold - 0.7).abs() < f32::EPSILON);
+        assert_eq!(config.memory.summarize_after_messages, 0);
+        assert_eq!(config.memory.summary_keep_recent, 6);
     }
 
     #[test]
@@ -781,6 +1183,41 @@ similarity_threshold = 0.7
         assert_eq!(config, reparsed);
     }
 
+    #[test]
+    fn reasoning_settings_default_off_and_parse() {
+        let config = Config::parse(minimal_chat_toml()).unwrap();
+        assert!(!config.chat.persist_reasoning);
+        assert!(!config.models.chat.providers[0].send_reasoning);
+
+        let toml = r#"
+[[models.chat.providers]]
+type = "ollama"
+model = "qwen3"
+send_reasoning = true
+
+[chat]
+persist_reasoning = true
+"#;
+        let config = Config::parse(toml).unwrap();
+        assert!(config.chat.persist_reasoning);
+        assert!(config.models.chat.providers[0].send_reasoning);
+    }
+
+    #[test]
+    fn tool_mode_defaults_to_native_and_parses_emulated() {
+        let config = Config::parse(minimal_chat_toml()).unwrap();
+        assert_eq!(config.models.chat.providers[0].tool_mode, ToolMode::Native);
+
+        let toml = r#"
+[[models.chat.providers]]
+type = "ollama"
+model = "llama3.2:1b"
+tool_mode = "emulated"
+"#;
+        let config = Config::parse(toml).unwrap();
+        assert_eq!(config.models.chat.providers[0].tool_mode, ToolMode::Emulated);
+    }
+
     #[test]
     fn system_prompt_default_when_chat_section_omitted() {
         let config = Config::parse(minimal_chat_toml()).unwrap();
@@ -1096,4 +1533,117 @@ bot_token = "tok123"
             Some("tok123")
         );
     }
+
+    // Context window per provider entry
+
+    #[test]
+    fn context_window_defaults_per_provider_type() {
+        let config = Config::parse(minimal_chat_toml()).unwrap();
+        let entry = &config.models.chat.providers[0];
+        assert!(entry.context_window.is_none());
+        assert_eq!(
+            entry.context_window(),
+            crate::context::default_context_window(&entry.provider_type)
+        );
+    }
+
+    #[test]
+    fn context_window_parsed_and_round_trips() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "ollama"
+model = "llama3"
+context_window = 4096
+"#;
+        let config = Config::parse(toml).unwrap();
+        assert_eq!(config.models.chat.providers[0].context_window(), 4096);
+        let reparsed = Config::parse(&config.to_toml_string()).unwrap();
+        assert_eq!(reparsed.models.chat.providers[0].context_window, Some(4096));
+    }
+
+    #[test]
+    fn retry_settings_parsed_with_defaults() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "openai"
+model = "gpt-4o"
+max_retries = 5
+retry_backoff_ms = 250
+
+[[models.chat.providers]]
+type = "ollama"
+model = "llama3"
+"#;
+        let config = Config::parse(toml).unwrap();
+        let policy = config.models.chat.providers[0].retry_policy();
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.initial_backoff, std::time::Duration::from_millis(250));
+        assert_eq!(
+            config.models.chat.providers[1].retry_policy(),
+            crate::provider::retry::RetryPolicy::default()
+        );
+        let reparsed = Config::parse(&config.to_toml_string()).unwrap();
+        assert_eq!(reparsed.models.chat.providers[0].retry.max_retries, Some(5));
+        assert!(!config.to_toml_string().contains("retry_backoff_ms = 0"));
+    }
+
+    #[test]
+    fn pricing_table_parsed_and_round_trips() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "openai"
+model = "gpt-4o"
+endpoint = "https://api.openai.com/v1"
+
+[pricing."gpt-4o"]
+prompt = 2.5
+completion = 10.0
+"#;
+        let config = Config::parse(toml).unwrap();
+        let price = config.pricing["gpt-4o"];
+        assert!((price.cost(1_000_000, 100_000) - 3.5).abs() < 1e-9);
+
+        let reparsed = Config::parse(&config.to_toml_string()).unwrap();
+        assert_eq!(config, reparsed);
+    }
+
+    #[test]
+    fn pricing_defaults_to_empty() {
+        let config = Config::parse(minimal_chat_toml()).unwrap();
+        assert!(config.pricing.is_empty());
+        assert!(!config.to_toml_string().contains("pricing"));
+    }
+
+    #[test]
+    fn generation_params_parsed_and_round_trip() {
+        let toml = r#"
+[[models.chat.providers]]
+type = "openai"
+model = "gpt-4o"
+endpoint = "https://api.openai.com/v1"
+temperature = 0.2
+max_tokens = 512
+top_p = 0.9
+stop = ["END"]
+"#;
+        let config = Config::parse(toml).unwrap();
+        let params = &config.models.chat.providers[0].generation;
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.max_tokens, Some(512));
+        assert_eq!(params.top_p, Some(0.9));
+        assert_eq!(params.stop, Some(vec!["END".to_string()]));
+
+        let reparsed = Config::parse(&config.to_toml_string()).unwrap();
+        assert_eq!(config, reparsed);
+    }
+
+    #[test]
+    fn generation_params_default_to_unset() {
+        let config = Config::parse(minimal_chat_toml()).unwrap();
+        assert_eq!(
+            config.models.chat.providers[0].generation,
+            GenerationParams::default()
+        );
+        assert!(!config.to_toml_string().contains("temperature"));
+    }
 }
@@ -0,0 +1,351 @@
+//! Context-window management.
+//!
+//! Providers only accept a bounded number of tokens per request. Before each
+//! call, the conversation is trimmed to fit the provider's context window:
+//! system messages (the system prompt, recalled memories, working memory) are
+//! always kept, and the oldest conversation turns are dropped first. Tool
+//! calls and their results are treated as a single unit so a `ToolResult` is
+//! never sent without the `ToolCall` that produced it.
+//!
+//! Token counts are estimates — no tokenizer is bundled — tuned per provider
+//! family so trimming errs on the side of leaving headroom.
+
+use crate::types::{Message, MessageContent, Role};
+
+/// Fraction of the context window reserved for the model's reply.
+const RESPONSE_RESERVE_DIVISOR: usize = 8;
+
+/// Flat estimate for an attached image. Providers downscale images to a
+/// bounded resolution, so the cost does not grow with the file size.
+const IMAGE_TOKENS: usize = 1_000;
+
+/// Tokenizer family used for estimating token counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerFamily {
+    /// OpenAI models (cl100k / o200k style BPE).
+    OpenAi,
+    /// Mistral's SentencePiece-based tokenizers.
+    Mistral,
+    /// Google Gemini models.
+    Gemini,
+    /// Anthropic Claude models.
+    Anthropic,
+    /// Locally served open-weight models (Ollama, LM Studio).
+    Local,
+}
+
+impl TokenizerFamily {
+    /// Map a provider `type` from the config to its tokenizer family.
+    pub fn for_provider_type(provider_type: &str) -> Self {
+        match provider_type {
+            "mistral" => Self::Mistral,
+            "gemini" => Self::Gemini,
+            "anthropic" => Self::Anthropic,
+            "ollama" | "lmstudio" => Self::Local,
+            _ => Self::OpenAi,
+        }
+    }
+
+    /// Average ASCII characters per token.
+    fn chars_per_token(self) -> f64 {
+        match self {
+            Self::OpenAi => 4.0,
+            Self::Gemini => 4.0,
+            Self::Anthropic => 3.5,
+            Self::Mistral => 3.5,
+            Self::Local => 3.5,
+        }
+    }
+
+    /// Fixed per-message cost (role markers, separators).
+    fn message_overhead(self) -> usize {
+        match self {
+            Self::OpenAi => 4,
+            Self::Gemini => 3,
+            Self::Anthropic => 4,
+            Self::Mistral | Self::Local => 5,
+        }
+    }
+
+    /// Estimate the number of tokens in `text`.
+    ///
+    /// ASCII text is divided by the family's characters-per-token ratio;
+    /// non-ASCII characters (CJK, emoji, accented letters) are counted as one
+    /// token each, which over-estimates slightly for most scripts.
+    pub fn estimate_text(self, text: &str) -> usize {
+        let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+            if c.is_ascii() { (a + 1, o) } else { (a, o + 1) }
+        });
+        (ascii as f64 / self.chars_per_token()).ceil() as usize + other
+    }
+
+    /// Estimate the number of tokens a message occupies in a request.
+    pub fn estimate_message(self, message: &Message) -> usize {
+        let content = match &message.content {
+            MessageContent::Text { text } => self.estimate_text(text),
+            MessageContent::ToolCall {
+                id,
+                name,
+                arguments,
+            } => self.estimate_text(id) + self.estimate_text(name) + self.estimate_text(arguments),
+            MessageContent::ToolResult { id, name, content } => {
+                self.estimate_text(id) + self.estimate_text(name) + self.estimate_text(content)
+            }
+            MessageContent::Image { .. } => IMAGE_TOKENS,
+            MessageContent::Reasoning { text } => self.estimate_text(text),
+        };
+        content + self.message_overhead()
+    }
+}
+
+/// Default context window (in tokens) for a provider type, used when a
+/// `ProviderEntry` does not set `context_window`.
+pub fn default_context_window(provider_type: &str) -> usize {
+    match provider_type {
+        "gemini" => 1_000_000,
+        "anthropic" => 200_000,
+        "mistral" => 32_000,
+        "ollama" | "lmstudio" => 8_192,
+        _ => 128_000,
+    }
+}
+
+/// A provider's context window and how to count tokens against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextWindow {
+    pub max_tokens: usize,
+    pub family: TokenizerFamily,
+}
+
+impl ContextWindow {
+    pub fn new(max_tokens: usize, family: TokenizerFamily) -> Self {
+        Self { max_tokens, family }
+    }
+
+    /// Tokens available for the prompt after reserving room for the reply.
+    pub fn prompt_budget(&self) -> usize {
+        self.max_tokens - self.max_tokens / RESPONSE_RESERVE_DIVISOR
+    }
+
+    /// Trim `messages` so the request fits the window.
+    ///
+    /// `system_prompt` and `tools` are sent alongside the messages and count
+    /// against the budget but are never trimmed. All `Role::System` messages
+    /// are kept. The remaining history is split into units (a text message,
+    /// or a contiguous run of tool calls and results) and the oldest units are
+    /// dropped until the rest fits. The newest unit is always kept, and the
+    /// trimmed history starts at a user message whenever one remains.
+    pub fn trim(
+        &self,
+        messages: Vec<Message>,
+        system_prompt: &str,
+        tools: Option<&Vec<serde_json::Value>>,
+    ) -> Vec<Message> {
+        let family = self.family;
+        let mut fixed = family.estimate_text(system_prompt);
+        if let Some(tools) = tools {
+            let json = serde_json::to_string(tools).unwrap_or_default();
+            fixed += family.estimate_text(&json);
+        }
+
+        let (system, history): (Vec<Message>, Vec<Message>) =
+            messages.into_iter().partition(|m| m.role == Role::System);
+        fixed += system.iter().map(|m| family.estimate_message(m)).sum::<usize>();
+
+        let units = split_units(history);
+        let costs: Vec<usize> = units
+            .iter()
+            .map(|u| u.iter().map(|m| family.estimate_message(m)).sum())
+            .collect();
+
+        let budget = self.prompt_budget();
+        let mut total: usize = fixed + costs.iter().sum::<usize>();
+        let mut start = 0;
+        while total > budget && start + 1 < units.len() {
+            total -= costs[start];
+            start += 1;
+        }
+        // Don't open the history mid-exchange: skip ahead to a user message
+        // if there is one left.
+        if start > 0
+            && let Some(offset) = units[start..].iter().position(|u| starts_with_user_text(u))
+        {
+            start += offset;
+        }
+
+        let mut result = system;
+        result.extend(units.into_iter().skip(start).flatten());
+        result
+    }
+}
+
+/// Group history into units that must be kept or dropped together.
+fn split_units(history: Vec<Message>) -> Vec<Vec<Message>> {
+    let mut units: Vec<Vec<Message>> = Vec::new();
+    for message in history {
+        let is_tool = matches!(
+            message.content,
+            MessageContent::ToolCall { .. } | MessageContent::ToolResult { .. }
+        );
+        let extends_tool_unit = is_tool
+            && units.last().and_then(|u| u.last()).is_some_and(|last| {
+                matches!(
+                    last.content,
+                    MessageContent::ToolCall { .. } | MessageContent::ToolResult { .. }
+                )
+            });
+        if extends_tool_unit {
+            units.last_mut().unwrap().push(message);
+        } else {
+            units.push(vec![message]);
+        }
+    }
+    units
+}
+
+fn starts_with_user_text(unit: &[Message]) -> bool {
+    unit.first().is_some_and(|m| {
+        m.role == Role::User && matches!(m.content, MessageContent::Text { .. })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn text(role: Role, text: &str) -> Message {
+        Message {
+            role,
+            content: MessageContent::Text { text: text.into() },
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn tool_call(id: &str) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: MessageContent::ToolCall {
+                id: id.into(),
+                name: "echo".into(),
+                arguments: "{}".into(),
+            },
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn tool_result(id: &str, content: &str) -> Message {
+        Message {
+            role: Role::User,
+            content: MessageContent::ToolResult {
+                id: id.into(),
+                name: "echo".into(),
+                content: content.into(),
+            },
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| match &m.content {
+                MessageContent::Text { text } => text.clone(),
+                MessageContent::ToolCall { id, .. } => format!("call:{id}"),
+                MessageContent::ToolResult { id, .. } => format!("result:{id}"),
+                MessageContent::Image { blob_id, .. } => format!("image:{blob_id}"),
+                MessageContent::Reasoning { text } => format!("reasoning:{text}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn estimate_text_scales_with_family() {
+        let text = "a".repeat(400);
+        assert_eq!(TokenizerFamily::OpenAi.estimate_text(&text), 100);
+        assert_eq!(TokenizerFamily::Mistral.estimate_text(&text), 115);
+    }
+
+    #[test]
+    fn estimate_text_counts_non_ascii_per_char() {
+        assert_eq!(TokenizerFamily::OpenAi.estimate_text("日本語"), 3);
+    }
+
+    #[test]
+    fn family_for_provider_type() {
+        assert_eq!(TokenizerFamily::for_provider_type("openai"), TokenizerFamily::OpenAi);
+        assert_eq!(TokenizerFamily::for_provider_type("gemini"), TokenizerFamily::Gemini);
+        assert_eq!(TokenizerFamily::for_provider_type("anthropic"), TokenizerFamily::Anthropic);
+        assert_eq!(TokenizerFamily::for_provider_type("ollama"), TokenizerFamily::Local);
+        assert_eq!(TokenizerFamily::for_provider_type("lmstudio"), TokenizerFamily::Local);
+        assert_eq!(TokenizerFamily::for_provider_type("mistral"), TokenizerFamily::Mistral);
+    }
+
+    #[test]
+    fn history_within_budget_is_untouched() {
+        let window = ContextWindow::new(10_000, TokenizerFamily::OpenAi);
+        let messages = vec![text(Role::User, "hi"), text(Role::Assistant, "hello")];
+        let trimmed = window.trim(messages.clone(), "be nice", None);
+        assert_eq!(trimmed, messages);
+    }
+
+    #[test]
+    fn oldest_turns_dropped_and_system_messages_kept() {
+        let window = ContextWindow::new(80, TokenizerFamily::OpenAi);
+        let long = "x".repeat(100);
+        let messages = vec![
+            text(Role::System, "## Recalled Memories"),
+            text(Role::User, &long),
+            text(Role::Assistant, &long),
+            text(Role::User, "latest question"),
+        ];
+        let trimmed = window.trim(messages, "", None);
+        assert_eq!(texts(&trimmed), vec!["## Recalled Memories", "latest question"]);
+    }
+
+    #[test]
+    fn tool_call_and_result_are_dropped_together() {
+        let window = ContextWindow::new(120, TokenizerFamily::OpenAi);
+        let long = "x".repeat(400);
+        let messages = vec![
+            text(Role::User, "first"),
+            tool_call("c1"),
+            tool_result("c1", &long),
+            text(Role::Assistant, "answer"),
+            text(Role::User, "second"),
+            tool_call("c2"),
+            tool_result("c2", "short"),
+        ];
+        let trimmed = window.trim(messages, "", None);
+        assert_eq!(texts(&trimmed), vec!["second", "call:c2", "result:c2"]);
+    }
+
+    #[test]
+    fn trimmed_history_starts_at_user_message() {
+        let window = ContextWindow::new(60, TokenizerFamily::OpenAi);
+        let messages = vec![
+            text(Role::User, &"x".repeat(200)),
+            text(Role::Assistant, "short reply"),
+            text(Role::User, "next"),
+        ];
+        let trimmed = window.trim(messages, "", None);
+        assert_eq!(texts(&trimmed), vec!["next"]);
+    }
+
+    #[test]
+    fn newest_unit_kept_even_when_over_budget() {
+        let window = ContextWindow::new(10, TokenizerFamily::OpenAi);
+        let messages = vec![text(Role::User, "old"), text(Role::User, &"x".repeat(500))];
+        let trimmed = window.trim(messages, "", None);
+        assert_eq!(trimmed.len(), 1);
+    }
+
+    #[test]
+    fn system_prompt_and_tools_count_against_budget() {
+        let window = ContextWindow::new(100, TokenizerFamily::OpenAi);
+        let messages = vec![text(Role::User, &"a".repeat(100)), text(Role::User, "b")];
+        assert_eq!(window.trim(messages.clone(), "", None).len(), 2);
+        let tools = vec![serde_json::json!({ "description": "d".repeat(300) })];
+        assert_eq!(window.trim(messages, "", Some(&tools)).len(), 1);
+    }
+}
@@ -0,0 +1,1888 @@
+//! Shared conversation engine.
+//!
+//! Every interface (web, Telegram, WhatsApp) drives the same agentic loop:
+//! recall long-term memories, inject working memory, call the provider,
+//! dispatch tool calls under the approval policy, persist everything, and
+//! repeat until the model answers with plain text. `Engine::run` implements
+//! that loop once and reports progress as a stream of `EngineEvent`s; each
+//! interface only decides how to render the events.
+//!
+//! Long conversations are kept within reach of the model by a rolling
+//! summary: once enough messages accumulate, the older turns are summarized
+//! by the chat model and the summary replaces them in provider context. The
+//! original messages stay in the store.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use futures_core::Stream;
+use futures_util::StreamExt;
+use tokio::sync::{oneshot, watch};
+
+use crate::config::{ApprovalPolicy, DEFAULT_TOOL_CONCURRENCY, MemoryConfig};
+use crate::embedding::Embedder;
+use crate::memory::VectorStore;
+use crate::provider::{Provider, ProviderError, Token};
+use crate::skill::working_memory::WorkingMemoryMap;
+use crate::skill::{PermissionLevel, SkillRegistry, ToolRegistry};
+use crate::state::ConversationApprovals;
+use crate::store::{MessageUsage, Store};
+use crate::types::{Message, MessageContent, Role};
+
+/// Maximum number of tool-call loop iterations before aborting.
+pub const MAX_TOOL_ITERATIONS: usize = 10;
+
+/// Instructions for the summarization request.
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the conversation below so it can replace the \
+original messages as context for future replies. Keep facts, decisions, open questions, \
+user preferences and the outcome of any tool calls. Write concise prose without preamble.";
+
+/// Longest excerpt of a single message included in a summarization request.
+const SUMMARY_EXCERPT_CHARS: usize = 1_000;
+
+/// Recorded in the conversation where a cancelled turn stopped, so both the
+/// user and the model can see the reply was cut short.
+pub const CANCELLED_MARKER: &str = "[Cancelled by user]";
+
+/// A long-term memory recalled for the current turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecalledMemory {
+    pub text: String,
+    pub category: Option<String>,
+    pub score: f32,
+}
+
+/// A tool call waiting for the user's decision.
+///
+/// The engine pauses until `respond` is called. Dropping the request without
+/// responding counts as a denial, so interfaces that cannot ask the user
+/// (or that time out) simply let it go.
+#[derive(Debug)]
+pub struct ApprovalRequest {
+    pub skill_name: String,
+    pub arguments: String,
+    /// What the call would do, rendered by the tool (e.g. a diff), for
+    /// interfaces to show instead of `arguments`.
+    pub preview: Option<String>,
+    pub permission_level: PermissionLevel,
+    responder: oneshot::Sender<bool>,
+}
+
+impl ApprovalRequest {
+    /// Resolve the request, resuming the engine.
+    pub fn respond(self, approved: bool) {
+        let _ = self.responder.send(approved);
+    }
+}
+
+/// Stops a running turn from another task.
+///
+/// Clones share the same state: cancelling any of them cancels the turn.
+#[derive(Debug, Clone)]
+pub struct CancelToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Whether both handles cancel the same turn.
+    pub fn same_as(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Resolve once `cancel` has been called.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.subscribe();
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Errors that end a turn early.
+#[derive(Debug)]
+pub enum EngineError {
+    /// The provider failed to start or broke off mid-stream.
+    Provider(ProviderError),
+    /// The model kept requesting tools past `MAX_TOOL_ITERATIONS`.
+    MaxIterations,
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Provider(e) => write!(f, "{e}"),
+            Self::MaxIterations => write!(
+                f,
+                "Tool call loop exceeded maximum of {MAX_TOOL_ITERATIONS} iterations"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Progress of a single conversation turn.
+///
+/// A run always ends with exactly one `Done`, `Cancelled` or `Error` event.
+#[derive(Debug)]
+pub enum EngineEvent {
+    /// Long-term memories injected as context for this turn.
+    MemoryContext { memories: Vec<RecalledMemory> },
+    /// A streamed text delta from the model.
+    TextDelta { text: String },
+    /// A streamed delta of the model's reasoning, separate from its reply.
+    ReasoningDelta { text: String },
+    /// A non-fatal warning (e.g. provider fallback occurred).
+    Warning { message: String },
+    /// The provider failed mid-stream and the reply is being regenerated by
+    /// another one: discard the text streamed so far in this completion.
+    StreamReset { message: String },
+    /// The model requested a tool call; it is about to be dispatched.
+    ToolCallStart {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    /// A tool call finished (or was denied); `content` is what the model sees.
+    ToolCallResult {
+        id: String,
+        name: String,
+        content: String,
+    },
+    /// A tool call needs the user's approval before it can run.
+    ApprovalNeeded(ApprovalRequest),
+    /// The turn failed.
+    Error(EngineError),
+    /// The turn completed; `final_text` is the persisted assistant reply
+    /// (empty when the model produced no text).
+    Done { final_text: String },
+    /// The turn was cancelled; `partial_text` is what the model had streamed
+    /// of its reply so far.
+    Cancelled { partial_text: String },
+}
+
+/// Embedder, vector store and settings used for automatic memory retrieval.
+pub struct LongTermMemory<'a> {
+    pub embedder: &'a dyn Embedder,
+    pub vector_store: &'a dyn VectorStore,
+    pub config: &'a MemoryConfig,
+}
+
+/// The conversation engine, borrowing the runtime components for one turn.
+///
+/// Callers typically load a snapshot of the hot-reloadable `AppState` fields
+/// and build an engine from them for the duration of a request.
+pub struct Engine<'a, P> {
+    store: &'a Store,
+    provider: &'a P,
+    registry: &'a ToolRegistry,
+    skill_registry: &'a SkillRegistry,
+    approval_overrides: &'a HashMap<String, ApprovalPolicy>,
+    conversation_approvals: &'a ConversationApprovals,
+    working_memory: Option<&'a WorkingMemoryMap>,
+    long_term_memory: Option<LongTermMemory<'a>>,
+    summarization: Option<&'a MemoryConfig>,
+    cancel: Option<&'a CancelToken>,
+    persist_reasoning: bool,
+    tool_concurrency: usize,
+}
+
+impl<'a, P: Provider> Engine<'a, P> {
+    pub fn new(
+        store: &'a Store,
+        provider: &'a P,
+        registry: &'a ToolRegistry,
+        skill_registry: &'a SkillRegistry,
+        approval_overrides: &'a HashMap<String, ApprovalPolicy>,
+        conversation_approvals: &'a ConversationApprovals,
+    ) -> Self {
+        Self {
+            store,
+            provider,
+            registry,
+            skill_registry,
+            approval_overrides,
+            conversation_approvals,
+            working_memory: None,
+            long_term_memory: None,
+            summarization: None,
+            cancel: None,
+            persist_reasoning: false,
+            tool_concurrency: DEFAULT_TOOL_CONCURRENCY,
+        }
+    }
+
+    /// Inject the conversation's working memory as system context.
+    pub fn with_working_memory(mut self, working_memory: &'a WorkingMemoryMap) -> Self {
+        self.working_memory = Some(working_memory);
+        self
+    }
+
+    /// Enable automatic memory retrieval when both an embedder and a vector
+    /// store are available. Passing `None` for either leaves it disabled.
+    pub fn with_long_term_memory(
+        mut self,
+        embedder: Option<&'a dyn Embedder>,
+        vector_store: Option<&'a dyn VectorStore>,
+        config: &'a MemoryConfig,
+    ) -> Self {
+        self.long_term_memory = match (embedder, vector_store) {
+            (Some(embedder), Some(vector_store)) => Some(LongTermMemory {
+                embedder,
+                vector_store,
+                config,
+            }),
+            _ => None,
+        };
+        self
+    }
+
+    /// Replace older turns with a rolling summary, using the thresholds in
+    /// `config`.
+    pub fn with_summarization(mut self, config: &'a MemoryConfig) -> Self {
+        self.summarization = Some(config);
+        self
+    }
+
+    /// Stop the turn when `cancel` fires: the provider stream or approval
+    /// wait in progress is abandoned and the partial reply is persisted.
+    pub fn with_cancellation(mut self, cancel: &'a CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Store the model's reasoning alongside its replies. Reasoning is
+    /// streamed as `ReasoningDelta` events either way.
+    pub fn with_reasoning_persistence(mut self, persist: bool) -> Self {
+        self.persist_reasoning = persist;
+        self
+    }
+
+    /// Run at most `limit` read-only or network tool calls from one response
+    /// at the same time. Mutating tools always run one at a time.
+    pub fn with_tool_concurrency(mut self, limit: usize) -> Self {
+        self.tool_concurrency = limit;
+        self
+    }
+
+    pub fn store(&self) -> &'a Store {
+        self.store
+    }
+
+    /// Tool definitions offered to the provider, or `None` if there are none.
+    fn tool_definitions(&self) -> Option<Vec<serde_json::Value>> {
+        let mut defs = self.registry.tool_definitions();
+        defs.extend(self.skill_registry.tool_definitions());
+        if defs.is_empty() { None } else { Some(defs) }
+    }
+
+    /// Run one turn of the conversation.
+    ///
+    /// `messages` is the full provider context, ending with the new user
+    /// message(s), which the caller is expected to have persisted already.
+    /// Everything the engine produces (tool calls, tool results and the final
+    /// assistant reply) is persisted to `conversation_id`.
+    ///
+    /// `messages` must mirror the stored conversation (one entry per stored
+    /// message, in order) so the rolling summary lines up with it.
+    ///
+    /// 1. Replace summarized turns with the rolling summary (if enabled),
+    ///    refreshing it first when enough new messages have accumulated.
+    /// 2. Recall relevant long-term memories (if enabled).
+    /// 3. Send messages + tool definitions to the provider.
+    /// 4. If the provider yields tool calls: apply the approval policy, execute
+    ///    them via the `ToolRegistry`, append `ToolCall` and `ToolResult`
+    ///    messages, and call the provider again.
+    /// 5. Repeat until the provider returns only text, or stop after
+    ///    `MAX_TOOL_ITERATIONS`.
+    ///
+    /// If the turn is cancelled (see `with_cancellation`), the reply streamed
+    /// so far is persisted followed by `CANCELLED_MARKER`, and a tool call
+    /// still awaiting approval is answered with a cancellation result.
+    pub fn run(
+        &'a self,
+        conversation_id: &'a str,
+        mut messages: Vec<Message>,
+    ) -> impl Stream<Item = EngineEvent> + Send + 'a {
+        async_stream::stream! {
+            self.registry.begin_turn(conversation_id);
+            let tools = self.tool_definitions();
+            messages = self.apply_summary(conversation_id, messages).await;
+
+            let recalled = self.recall_memories(&messages);
+            let recalled_context = recalled.as_ref().map(|memories| format_recalled_memories(memories));
+            if let Some(memories) = recalled {
+                yield EngineEvent::MemoryContext { memories };
+            }
+
+            for _iteration in 0..MAX_TOOL_ITERATIONS {
+                let provider_messages =
+                    self.provider_context(conversation_id, &messages, recalled_context.as_deref());
+
+                let completion = tokio::select! {
+                    biased;
+                    () = self.cancelled() => None,
+                    result = self.provider.complete(provider_messages, tools.clone()) => Some(result),
+                };
+                let token_stream = match completion {
+                    None => {
+                        self.persist_cancellation(conversation_id, "");
+                        yield EngineEvent::Cancelled { partial_text: String::new() };
+                        return;
+                    }
+                    Some(Ok(s)) => s,
+                    Some(Err(e)) => {
+                        yield EngineEvent::Error(EngineError::Provider(e));
+                        return;
+                    }
+                };
+
+                // Consume the stream, collecting text and tool calls.
+                let mut tool_calls: Vec<(String, String, String)> = Vec::new();
+                let mut full_text = String::new();
+                let mut reasoning = String::new();
+                let mut usage: Option<MessageUsage> = None;
+                let mut failure = None;
+                let mut cancelled = false;
+
+                tokio::pin!(token_stream);
+                loop {
+                    let result = tokio::select! {
+                        biased;
+                        () = self.cancelled() => {
+                            cancelled = true;
+                            break;
+                        }
+                        next = token_stream.next() => match next {
+                            Some(result) => result,
+                            None => break,
+                        },
+                    };
+                    match result {
+                        Ok(Token::Text { text }) => {
+                            full_text.push_str(&text);
+                            yield EngineEvent::TextDelta { text };
+                        }
+                        Ok(Token::Reasoning { text }) => {
+                            reasoning.push_str(&text);
+                            yield EngineEvent::ReasoningDelta { text };
+                        }
+                        Ok(Token::Warning { message }) => {
+                            yield EngineEvent::Warning { message };
+                        }
+                        Ok(Token::Reset { message }) => {
+                            // The provider failed mid-stream and the chain is
+                            // re-running the request elsewhere.
+                            full_text.clear();
+                            reasoning.clear();
+                            tool_calls.clear();
+                            yield EngineEvent::StreamReset { message };
+                        }
+                        Ok(Token::ToolCall { id, name, arguments }) => {
+                            tool_calls.push((id, name, arguments));
+                        }
+                        Ok(Token::Usage { provider, model, prompt, completion }) => {
+                            let total = usage.get_or_insert(MessageUsage {
+                                provider,
+                                model,
+                                prompt_tokens: 0,
+                                completion_tokens: 0,
+                            });
+                            total.prompt_tokens += prompt;
+                            total.completion_tokens += completion;
+                        }
+                        Err(e) => {
+                            failure = Some(e);
+                            break;
+                        }
+                    }
+                }
+                if let Some(e) = failure {
+                    yield EngineEvent::Error(EngineError::Provider(e));
+                    return;
+                }
+                if cancelled {
+                    // Tool calls arrive whole at the end of a stream, so any
+                    // collected so far are dropped without being run.
+                    self.persist_cancellation(conversation_id, &full_text);
+                    yield EngineEvent::Cancelled { partial_text: full_text };
+                    return;
+                }
+
+                if self.persist_reasoning && !reasoning.is_empty() {
+                    let reasoning_msg = Message {
+                        role: Role::Assistant,
+                        content: MessageContent::Reasoning { text: reasoning },
+                        timestamp: Utc::now(),
+                    };
+                    self.persist(conversation_id, &reasoning_msg);
+                    messages.push(reasoning_msg);
+                }
+
+                if tool_calls.is_empty() {
+                    // Final text response — persist and done.
+                    if !full_text.is_empty() {
+                        self.persist_with_usage(conversation_id, &Message {
+                            role: Role::Assistant,
+                            content: MessageContent::Text { text: full_text.clone() },
+                            timestamp: Utc::now(),
+                        }, usage.as_ref());
+                    }
+                    yield EngineEvent::Done { final_text: full_text };
+                    return;
+                }
+
+                if self.is_cancelled() {
+                    self.persist_cancellation(conversation_id, "");
+                    yield EngineEvent::Cancelled { partial_text: String::new() };
+                    return;
+                }
+
+                // Record every call up front; results are appended afterwards
+                // in call order, however the calls finish.
+                for (id, name, arguments) in &tool_calls {
+                    yield EngineEvent::ToolCallStart {
+                        id: id.clone(),
+                        name: name.clone(),
+                        arguments: arguments.clone(),
+                    };
+
+                    let tool_call_msg = Message {
+                        role: Role::Assistant,
+                        content: MessageContent::ToolCall {
+                            id: id.clone(),
+                            name: name.clone(),
+                            arguments: arguments.clone(),
+                        },
+                        timestamp: Utc::now(),
+                    };
+                    // The completion's usage is recorded once, on its first tool call.
+                    self.persist_with_usage(conversation_id, &tool_call_msg, usage.take().as_ref());
+                    messages.push(tool_call_msg);
+                }
+
+                // Settle approvals in call order before anything runs. A
+                // decided outcome (unknown tool, denial) replaces running it.
+                let mut decided: Vec<Option<String>> = Vec::with_capacity(tool_calls.len());
+                for (_, name, arguments) in &tool_calls {
+                    let Some(skill) = self.registry.get(name) else {
+                        decided.push(Some(format!("Error: unknown tool '{name}'")));
+                        continue;
+                    };
+                    let permission_level = skill.permission_level();
+                    let input = serde_json::from_str::<serde_json::Value>(arguments).ok();
+                    let approval_key = match input.as_ref().and_then(|i| skill.approval_scope(i)) {
+                        Some(scope) => format!("{name}:{scope}"),
+                        None => name.clone(),
+                    };
+                    let mut approved = permission_level == PermissionLevel::ReadOnly
+                        || self.is_pre_approved(conversation_id, &approval_key).await;
+                    if !approved {
+                        let (responder, receiver) = oneshot::channel();
+                        let preview = input.as_ref().and_then(|i| skill.approval_preview(i));
+                        yield EngineEvent::ApprovalNeeded(ApprovalRequest {
+                            skill_name: name.clone(),
+                            arguments: arguments.clone(),
+                            preview,
+                            permission_level,
+                            responder,
+                        });
+                        let decision = tokio::select! {
+                            biased;
+                            () = self.cancelled() => None,
+                            decision = receiver => Some(decision.unwrap_or(false)),
+                        };
+                        let Some(decision) = decision else {
+                            // Answer the recorded calls so the stored
+                            // history stays well-formed.
+                            for (id, name, _) in &tool_calls {
+                                self.persist(
+                                    conversation_id,
+                                    &tool_result_message(id, name, CANCELLED_MARKER.to_string()),
+                                );
+                            }
+                            self.persist_cancellation(conversation_id, "");
+                            yield EngineEvent::Cancelled { partial_text: String::new() };
+                            return;
+                        };
+                        approved = decision;
+                        if approved {
+                            self.record_approval(conversation_id, &approval_key).await;
+                        }
+                    }
+                    decided.push((!approved).then(|| format!("User denied execution of {name}")));
+                }
+
+                // Read-only and network tools run concurrently, up to the
+                // concurrency cap; a mutating tool runs on its own, after
+                // everything before it has finished.
+                let is_mutating = |i: usize| {
+                    decided[i].is_none()
+                        && self
+                            .registry
+                            .get(&tool_calls[i].1)
+                            .is_some_and(|s| s.permission_level() == PermissionLevel::Mutating)
+                };
+                let mut next = 0;
+                while next < tool_calls.len() {
+                    if self.is_cancelled() {
+                        for (id, name, _) in &tool_calls[next..] {
+                            self.persist(
+                                conversation_id,
+                                &tool_result_message(id, name, CANCELLED_MARKER.to_string()),
+                            );
+                        }
+                        self.persist_cancellation(conversation_id, "");
+                        yield EngineEvent::Cancelled { partial_text: String::new() };
+                        return;
+                    }
+                    let end = if is_mutating(next) {
+                        next + 1
+                    } else {
+                        (next..tool_calls.len()).find(|&i| is_mutating(i)).unwrap_or(tool_calls.len())
+                    };
+                    let results = futures_util::stream::iter(next..end)
+                        .map(|i| {
+                            let (_, name, arguments) = &tool_calls[i];
+                            let decided = decided[i].clone();
+                            async move {
+                                match decided {
+                                    Some(content) => content,
+                                    None => {
+                                        self.registry
+                                            .execute(name, arguments, conversation_id)
+                                            .await
+                                    }
+                                }
+                            }
+                        })
+                        .buffered(self.tool_concurrency.max(1));
+                    tokio::pin!(results);
+                    let mut index = next;
+                    loop {
+                        // Cancelling drops the calls still running.
+                        let content = tokio::select! {
+                            biased;
+                            () = self.cancelled() => None,
+                            content = results.next() => Some(content),
+                        };
+                        let Some(content) = content else {
+                            for (id, name, _) in &tool_calls[index..] {
+                                self.persist(
+                                    conversation_id,
+                                    &tool_result_message(id, name, CANCELLED_MARKER.to_string()),
+                                );
+                            }
+                            self.persist_cancellation(conversation_id, "");
+                            yield EngineEvent::Cancelled { partial_text: String::new() };
+                            return;
+                        };
+                        let Some(content) = content else { break };
+                        let (id, name, _) = &tool_calls[index];
+                        yield EngineEvent::ToolCallResult {
+                            id: id.clone(),
+                            name: name.clone(),
+                            content: content.clone(),
+                        };
+                        let tool_result_msg = tool_result_message(id, name, content);
+                        self.persist(conversation_id, &tool_result_msg);
+                        messages.push(tool_result_msg);
+                        index += 1;
+                    }
+                    next = end;
+                }
+
+                // Loop: call the provider again with updated messages.
+            }
+
+            yield EngineEvent::Error(EngineError::MaxIterations);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Resolve when the turn is cancelled; never resolves without a token.
+    async fn cancelled(&self) {
+        match self.cancel {
+            Some(cancel) => cancel.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Persist the reply streamed before cancellation, marked as cut short.
+    fn persist_cancellation(&self, conversation_id: &str, partial_text: &str) {
+        let text = if partial_text.is_empty() {
+            CANCELLED_MARKER.to_string()
+        } else {
+            format!("{partial_text}\n\n{CANCELLED_MARKER}")
+        };
+        self.persist(
+            conversation_id,
+            &Message {
+                role: Role::Assistant,
+                content: MessageContent::Text { text },
+                timestamp: Utc::now(),
+            },
+        );
+    }
+
+    /// Persist a message to the store, logging errors without aborting the turn.
+    fn persist(&self, conversation_id: &str, message: &Message) {
+        self.persist_with_usage(conversation_id, message, None);
+    }
+
+    /// Persist a model-produced message together with its completion's usage.
+    fn persist_with_usage(
+        &self,
+        conversation_id: &str,
+        message: &Message,
+        usage: Option<&MessageUsage>,
+    ) {
+        if let Err(e) = self
+            .store
+            .append_message_with_usage(conversation_id, message, usage)
+        {
+            eprintln!("warning: failed to persist message: {e}");
+        }
+    }
+
+    /// Replace the summarized prefix of `messages` with the conversation's
+    /// rolling summary, first folding in older messages if more than
+    /// `summarize_after_messages` have accumulated since the last summary.
+    ///
+    /// Summarization failures are logged and the previous summary (if any)
+    /// is used instead.
+    async fn apply_summary(&self, conversation_id: &str, messages: Vec<Message>) -> Vec<Message> {
+        let Some(config) = self
+            .summarization
+            .filter(|c| c.summarize_after_messages > 0)
+        else {
+            return messages;
+        };
+
+        let stored = self
+            .store
+            .get_rolling_summary(conversation_id)
+            .unwrap_or_else(|e| {
+                eprintln!("warning: failed to load conversation summary: {e}");
+                None
+            });
+        let (mut summary, mut covered) = match stored {
+            Some(s) => (Some(s.summary), s.message_count.min(messages.len())),
+            None => (None, 0),
+        };
+
+        if messages.len() - covered > config.summarize_after_messages
+            && let Some(cut) = summary_cut(&messages, covered, config.summary_keep_recent)
+        {
+            match self
+                .summarize(conversation_id, summary.as_deref(), &messages[covered..cut])
+                .await
+            {
+                Ok(text) => {
+                    if let Err(e) = self.store.set_rolling_summary(conversation_id, &text, cut) {
+                        eprintln!("warning: failed to persist conversation summary: {e}");
+                    }
+                    summary = Some(text);
+                    covered = cut;
+                }
+                Err(e) => eprintln!("warning: failed to summarize conversation: {e}"),
+            }
+        }
+
+        match summary {
+            Some(text) => {
+                let mut result = vec![system_message(format!("[Conversation Summary]\n{text}"))];
+                result.extend(messages.into_iter().skip(covered));
+                result
+            }
+            None => messages,
+        }
+    }
+
+    /// Ask the provider to fold `messages` into the `previous` summary. The
+    /// completion's token usage is recorded against the conversation.
+    async fn summarize(
+        &self,
+        conversation_id: &str,
+        previous: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String, ProviderError> {
+        let mut transcript = String::new();
+        if let Some(previous) = previous {
+            transcript.push_str("Summary so far:\n");
+            transcript.push_str(previous);
+            transcript.push_str("\n\nNew messages:\n");
+        }
+        for message in messages {
+            transcript.push_str(&transcript_line(message));
+            transcript.push('\n');
+        }
+
+        let request = vec![
+            system_message(SUMMARY_INSTRUCTIONS.to_string()),
+            Message {
+                role: Role::User,
+                content: MessageContent::Text { text: transcript },
+                timestamp: Utc::now(),
+            },
+        ];
+        let token_stream = self.provider.complete(request, None).await?;
+        tokio::pin!(token_stream);
+        let mut text = String::new();
+        let mut usage: Option<MessageUsage> = None;
+        let mut failure = None;
+        while let Some(token) = token_stream.next().await {
+            match token {
+                Ok(Token::Text { text: delta }) => text.push_str(&delta),
+                Ok(Token::Usage {
+                    provider,
+                    model,
+                    prompt,
+                    completion,
+                }) => {
+                    let total = usage.get_or_insert(MessageUsage {
+                        provider,
+                        model,
+                        prompt_tokens: 0,
+                        completion_tokens: 0,
+                    });
+                    total.prompt_tokens += prompt;
+                    total.completion_tokens += completion;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        // Tokens were spent even if the summary turns out to be unusable.
+        if let Some(usage) = &usage
+            && let Err(e) = self.store.record_summary_usage(conversation_id, usage)
+        {
+            eprintln!("warning: failed to record summary usage: {e}");
+        }
+        if let Some(e) = failure {
+            return Err(e);
+        }
+
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ProviderError::MalformedResponse("empty summary".into()));
+        }
+        Ok(text.to_string())
+    }
+
+    /// Search long-term memory for memories relevant to the latest user message.
+    fn recall_memories(&self, messages: &[Message]) -> Option<Vec<RecalledMemory>> {
+        let ltm = self.long_term_memory.as_ref()?;
+        if !ltm.config.auto_retrieve {
+            return None;
+        }
+
+        let query_text = messages
+            .iter()
+            .rev()
+            .find_map(|m| match (&m.role, &m.content) {
+                (Role::User, MessageContent::Text { text }) => Some(text.as_str()),
+                _ => None,
+            })?;
+
+        let embedding = ltm.embedder.embed(&[query_text]).ok()?.into_iter().next()?;
+        let results = ltm
+            .vector_store
+            .search(&embedding, ltm.config.auto_retrieve_limit)
+            .ok()?;
+
+        let memories: Vec<RecalledMemory> = results
+            .into_iter()
+            .filter(|r| r.score >= ltm.config.similarity_threshold)
+            .map(|r| RecalledMemory {
+                category: r
+                    .metadata
+                    .get("category")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+                text: r.source_text,
+                score: r.score,
+            })
+            .collect();
+
+        if memories.is_empty() {
+            None
+        } else {
+            Some(memories)
+        }
+    }
+
+    /// Build the provider context: working memory and recalled memories are
+    /// prepended as system messages, and image bytes are loaded from the
+    /// store.
+    fn provider_context(
+        &self,
+        conversation_id: &str,
+        messages: &[Message],
+        recalled_context: Option<&str>,
+    ) -> Vec<Message> {
+        let mut provider_messages = messages.to_vec();
+        for message in &mut provider_messages {
+            if let MessageContent::Image {
+                blob_id,
+                data: data @ None,
+                ..
+            } = &mut message.content
+            {
+                match self.store.get_blob(blob_id) {
+                    Ok(Some(blob)) => *data = Some(BASE64.encode(blob.data)),
+                    Ok(None) => eprintln!("warning: image blob {blob_id} is missing"),
+                    Err(e) => eprintln!("warning: failed to load image blob {blob_id}: {e}"),
+                }
+            }
+        }
+        if let Some(ctx) = recalled_context {
+            provider_messages.insert(0, system_message(ctx.to_string()));
+        }
+        if let Some(map) = self.working_memory {
+            let wm_map = map.lock().unwrap();
+            if let Some(wm) = wm_map.get(conversation_id).filter(|wm| !wm.is_empty()) {
+                provider_messages.insert(
+                    0,
+                    system_message(format!("[Working Memory]\n{}", wm.to_context_string())),
+                );
+            }
+        }
+        provider_messages
+    }
+
+    /// Whether the approval policy lets `skill_name` run without asking.
+    async fn is_pre_approved(&self, conversation_id: &str, skill_name: &str) -> bool {
+        match self.policy(skill_name) {
+            ApprovalPolicy::Trust => true,
+            ApprovalPolicy::Once => self
+                .conversation_approvals
+                .lock()
+                .await
+                .get(conversation_id)
+                .is_some_and(|skills| skills.contains(skill_name)),
+            ApprovalPolicy::Always => false,
+        }
+    }
+
+    /// Remember a user approval for the `once` policy.
+    async fn record_approval(&self, conversation_id: &str, skill_name: &str) {
+        if self.policy(skill_name) == ApprovalPolicy::Once {
+            self.conversation_approvals
+                .lock()
+                .await
+                .entry(conversation_id.to_string())
+                .or_default()
+                .insert(skill_name.to_string());
+        }
+    }
+
+    /// Effective policy: config override, or default Always for non-ReadOnly.
+    fn policy(&self, skill_name: &str) -> ApprovalPolicy {
+        self.approval_overrides
+            .get(skill_name)
+            .copied()
+            .unwrap_or(ApprovalPolicy::Always)
+    }
+}
+
+/// Render recalled memories as the system prompt section injected into context.
+fn format_recalled_memories(memories: &[RecalledMemory]) -> String {
+    let mut lines = vec!["## Recalled Memories".to_string()];
+    for m in memories {
+        lines.push(format!(
+            "- \"{}\" ({}, relevance: {:.2})",
+            m.text,
+            m.category.as_deref().unwrap_or("general"),
+            m.score
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Where the next summary should end: the latest user text message that
+/// leaves at least `keep_recent` messages verbatim. Cutting at a user turn
+/// never separates a tool call from its result. Returns `None` if there is
+/// no such message after `covered`.
+fn summary_cut(messages: &[Message], covered: usize, keep_recent: usize) -> Option<usize> {
+    let limit = messages.len().saturating_sub(keep_recent.max(1));
+    (covered + 1..=limit).rev().find(|&i| {
+        messages[i].role == Role::User && matches!(messages[i].content, MessageContent::Text { .. })
+    })
+}
+
+/// Render a message as one line of a summarization transcript.
+fn transcript_line(message: &Message) -> String {
+    let line = match &message.content {
+        MessageContent::Text { text } => {
+            let speaker = match message.role {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                Role::System => "System",
+            };
+            format!("{speaker}: {text}")
+        }
+        MessageContent::ToolCall {
+            name, arguments, ..
+        } => {
+            format!("Assistant called {name}({arguments})")
+        }
+        MessageContent::ToolResult { name, content, .. } => {
+            format!("{name} returned: {content}")
+        }
+        MessageContent::Image { .. } => "User: [image]".to_string(),
+        MessageContent::Reasoning { text } => format!("Assistant (thinking): {text}"),
+    };
+    match line.char_indices().nth(SUMMARY_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
+
+/// The result of tool call `id`, as recorded in the conversation.
+fn tool_result_message(id: &str, name: &str, content: String) -> Message {
+    Message {
+        role: Role::User,
+        content: MessageContent::ToolResult {
+            id: id.to_string(),
+            name: name.to_string(),
+            content,
+        },
+        timestamp: Utc::now(),
+    }
+}
+
+fn system_message(text: String) -> Message {
+    Message {
+        role: Role::System,
+        content: MessageContent::Text { text },
+        timestamp: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::skill::{Tool, ToolError};
+    use crate::testutil::{
+        MockEchoSkill, MockMutatingSkill, MockProvider, MockResponse, SequencedProvider,
+    };
+
+    struct Fixture {
+        store: Store,
+        registry: ToolRegistry,
+        skill_registry: SkillRegistry,
+        overrides: HashMap<String, ApprovalPolicy>,
+        approvals: ConversationApprovals,
+        conversation_id: String,
+    }
+
+    impl Fixture {
+        fn new(registry: ToolRegistry) -> Self {
+            let store = Store::open_in_memory().unwrap();
+            let conversation_id = store.create_conversation("Test").unwrap().id;
+            Self {
+                store,
+                registry,
+                skill_registry: SkillRegistry::new(Arc::new(ToolRegistry::new())),
+                overrides: HashMap::new(),
+                approvals: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+                conversation_id,
+            }
+        }
+
+        fn engine<'a, P: Provider>(&'a self, provider: &'a P) -> Engine<'a, P> {
+            Engine::new(
+                &self.store,
+                provider,
+                &self.registry,
+                &self.skill_registry,
+                &self.overrides,
+                &self.approvals,
+            )
+        }
+    }
+
+    fn user_message(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            content: MessageContent::Text { text: text.into() },
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Drive a run to completion, answering every approval with `approve`.
+    async fn collect<P: Provider>(
+        engine: &Engine<'_, P>,
+        conversation_id: &str,
+        approve: bool,
+    ) -> Vec<EngineEvent> {
+        let stream = engine.run(conversation_id, vec![user_message("hi")]);
+        tokio::pin!(stream);
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            match event {
+                EngineEvent::ApprovalNeeded(req) => req.respond(approve),
+                other => events.push(other),
+            }
+        }
+        events
+    }
+
+    fn registry_with(tool: Arc<dyn crate::skill::Tool>) -> ToolRegistry {
+        let mut r = ToolRegistry::new();
+        r.register(tool);
+        r
+    }
+
+    #[tokio::test]
+    async fn text_response_streams_deltas_and_persists_reply() {
+        let fx = Fixture::new(ToolRegistry::new());
+        let provider = MockProvider {
+            tokens: vec!["Hello ".into(), "world".into()],
+        };
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        let deltas: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                EngineEvent::TextDelta { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deltas, vec!["Hello ", "world"]);
+        assert!(matches!(
+            events.last(),
+            Some(EngineEvent::Done { final_text }) if final_text == "Hello world"
+        ));
+
+        let conv = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(conv.messages.len(), 1);
+        assert_eq!(conv.messages[0].role, Role::Assistant);
+    }
+
+    #[tokio::test]
+    async fn tool_call_result_is_persisted_in_order() {
+        let fx = Fixture::new(registry_with(Arc::new(MockEchoSkill)));
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![(
+                "c1".into(),
+                "echo".into(),
+                r#"{"value":"x"}"#.into(),
+            )]),
+            MockResponse::Text(vec!["Done.".into()]),
+        ]);
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        assert!(matches!(&events[0], EngineEvent::ToolCallStart { id, .. } if id == "c1"));
+        assert!(matches!(
+            &events[1],
+            EngineEvent::ToolCallResult { name, content, .. } if name == "echo" && content.contains("x")
+        ));
+
+        let conv = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            conv.messages[0].content,
+            MessageContent::ToolCall { .. }
+        ));
+        assert!(matches!(
+            conv.messages[1].content,
+            MessageContent::ToolResult { .. }
+        ));
+        assert!(matches!(
+            conv.messages[2].content,
+            MessageContent::Text { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn denied_approval_feeds_denial_back_to_model() {
+        let fx = Fixture::new(registry_with(Arc::new(MockMutatingSkill)));
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![("c1".into(), "mutating".into(), "{}".into())]),
+            MockResponse::Text(vec!["OK.".into()]),
+        ]);
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, false).await;
+
+        assert!(events.iter().any(|e| matches!(
+            e,
+            EngineEvent::ToolCallResult { content, .. } if content == "User denied execution of mutating"
+        )));
+    }
+
+    #[tokio::test]
+    async fn approval_request_carries_the_tool_preview() {
+        let dir = std::env::temp_dir().join("buddy-test-engine-preview");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+        std::fs::write(&file, "old\n").unwrap();
+        let skill =
+            crate::skill::write_file::WriteFileSkill::new(&crate::config::WriteFileConfig {
+                allowed_directories: vec![dir.to_string_lossy().into_owned()],
+                approval: None,
+                limits: Default::default(),
+            });
+        let fx = Fixture::new(registry_with(Arc::new(skill)));
+        let arguments = serde_json::json!({ "path": file, "content": "new\n" }).to_string();
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![("c1".into(), "write_file".into(), arguments)]),
+            MockResponse::Text(vec!["OK.".into()]),
+        ]);
+
+        let engine = fx.engine(&provider);
+        let stream = engine.run(&fx.conversation_id, vec![user_message("hi")]);
+        tokio::pin!(stream);
+        let mut preview = None;
+        while let Some(event) = stream.next().await {
+            if let EngineEvent::ApprovalNeeded(req) = event {
+                preview = req.preview.clone();
+                req.respond(false);
+            }
+        }
+        let preview = preview.expect("write_file renders a preview");
+        assert!(
+            preview.ends_with("@@ -1,1 +1,1 @@\n-old\n+new\n"),
+            "{preview}"
+        );
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old\n");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// A read-only tool that sleeps for `ms` milliseconds and records how
+    /// many calls to it were running at the same time.
+    #[derive(Default)]
+    struct SlowTool {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            "slow"
+        }
+        fn description(&self) -> &str {
+            "Sleeps"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object", "properties": {"ms": {"type": "integer"}}})
+        }
+        fn execute(
+            &self,
+            input: serde_json::Value,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>>
+        {
+            Box::pin(async move {
+                let ms = input["ms"].as_u64().unwrap_or(0);
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                Ok(serde_json::json!({ "slept": ms }))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn independent_tool_calls_run_concurrently_and_results_keep_call_order() {
+        let slow = Arc::new(SlowTool::default());
+        let mut registry = registry_with(slow.clone());
+        registry.register(Arc::new(MockMutatingSkill));
+        let fx = Fixture::new(registry);
+        let call = |id: &str, name: &str, args: &str| (id.into(), name.into(), args.into());
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![
+                call("c1", "slow", r#"{"ms":60}"#),
+                call("c2", "slow", r#"{"ms":10}"#),
+                call("c3", "slow", r#"{"ms":10}"#),
+                call("c4", "mutating", r#"{"value":"x"}"#),
+                call("c5", "slow", r#"{"ms":10}"#),
+            ]),
+            MockResponse::Text(vec!["Done.".into()]),
+        ]);
+        let engine = fx.engine(&provider).with_tool_concurrency(2);
+        let events = collect(&engine, &fx.conversation_id, true).await;
+
+        // c1..c3 overlap up to the cap; c4 and c5 run after them.
+        assert_eq!(slow.peak.load(Ordering::SeqCst), 2);
+        let result_ids: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                EngineEvent::ToolCallResult { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(result_ids, vec!["c1", "c2", "c3", "c4", "c5"]);
+
+        let conv = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        let stored: Vec<_> = conv
+            .messages
+            .iter()
+            .filter_map(|m| match &m.content {
+                MessageContent::ToolCall { id, .. } => Some(format!("call {id}")),
+                MessageContent::ToolResult { id, content, .. } => {
+                    Some(format!("result {id} {content}"))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                "call c1",
+                "call c2",
+                "call c3",
+                "call c4",
+                "call c5",
+                r#"result c1 {"slept":60}"#,
+                r#"result c2 {"slept":10}"#,
+                r#"result c3 {"slept":10}"#,
+                r#"result c4 {"echo":"x"}"#,
+                r#"result c5 {"slept":10}"#,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn once_policy_records_approval_for_conversation() {
+        let mut fx = Fixture::new(registry_with(Arc::new(MockMutatingSkill)));
+        fx.overrides.insert("mutating".into(), ApprovalPolicy::Once);
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![("c1".into(), "mutating".into(), "{}".into())]),
+            MockResponse::Text(vec!["OK.".into()]),
+        ]);
+        collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        let approvals = fx.approvals.lock().await;
+        assert!(approvals[&fx.conversation_id].contains("mutating"));
+    }
+
+    /// A network tool whose calls with `"write": true` are approved under
+    /// the `write` scope.
+    struct ScopedTool;
+
+    impl Tool for ScopedTool {
+        fn name(&self) -> &str {
+            "scoped"
+        }
+        fn description(&self) -> &str {
+            "Scoped echo"
+        }
+        fn permission_level(&self) -> PermissionLevel {
+            PermissionLevel::Network
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object", "properties": {"write": {"type": "boolean"}}})
+        }
+        fn approval_scope(&self, input: &serde_json::Value) -> Option<&'static str> {
+            (input["write"] == true).then_some("write")
+        }
+        fn execute(
+            &self,
+            input: serde_json::Value,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>>
+        {
+            Box::pin(async move { Ok(input) })
+        }
+    }
+
+    /// A tool that records the conversations whose turns began.
+    #[derive(Default)]
+    struct TurnTool {
+        turns: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Tool for TurnTool {
+        fn name(&self) -> &str {
+            "turns"
+        }
+        fn description(&self) -> &str {
+            "Records turns"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        fn begin_turn(&self, conversation_id: &str) {
+            self.turns.lock().unwrap().push(conversation_id.to_string());
+        }
+        fn execute(
+            &self,
+            _input: serde_json::Value,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>>
+        {
+            Box::pin(async move { Ok(serde_json::json!({})) })
+        }
+    }
+
+    #[tokio::test]
+    async fn tools_are_told_when_a_turn_begins() {
+        let tool = Arc::new(TurnTool::default());
+        let fx = Fixture::new(registry_with(tool.clone()));
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![("c1".into(), "turns".into(), "{}".into())]),
+            MockResponse::Text(vec!["OK.".into()]),
+        ]);
+        collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+        assert_eq!(*tool.turns.lock().unwrap(), vec![fx.conversation_id.clone()]);
+    }
+
+    #[tokio::test]
+    async fn scoped_calls_use_the_policy_for_their_scope() {
+        let mut fx = Fixture::new(registry_with(Arc::new(ScopedTool)));
+        fx.overrides.insert("scoped".into(), ApprovalPolicy::Trust);
+        fx.overrides.insert("scoped:write".into(), ApprovalPolicy::Once);
+        let provider = SequencedProvider::new(vec![
+            MockResponse::ToolCalls(vec![
+                ("c1".into(), "scoped".into(), "{}".into()),
+                ("c2".into(), "scoped".into(), r#"{"write":true}"#.into()),
+            ]),
+            MockResponse::Text(vec!["OK.".into()]),
+        ]);
+
+        let engine = fx.engine(&provider);
+        let stream = engine.run(&fx.conversation_id, vec![user_message("hi")]);
+        tokio::pin!(stream);
+        let mut asked = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let EngineEvent::ApprovalNeeded(req) = event {
+                asked.push(req.arguments.clone());
+                req.respond(true);
+            }
+        }
+        assert_eq!(asked, vec![r#"{"write":true}"#]);
+        let approvals = fx.approvals.lock().await;
+        assert!(approvals[&fx.conversation_id].contains("scoped:write"));
+        assert!(!approvals[&fx.conversation_id].contains("scoped"));
+    }
+
+    #[tokio::test]
+    async fn working_memory_is_injected_as_system_context() {
+        use crate::testutil::RecordingProvider;
+
+        let fx = Fixture::new(ToolRegistry::new());
+        let wm = crate::skill::working_memory::new_working_memory_map();
+        wm.lock()
+            .unwrap()
+            .entry(fx.conversation_id.clone())
+            .or_default()
+            .set("plan".into(), "ship it".into());
+        let provider = RecordingProvider::new(vec!["ok".into()]);
+        let engine = fx.engine(&provider).with_working_memory(&wm);
+        collect(&engine, &fx.conversation_id, true).await;
+
+        let sent = provider.last_messages();
+        assert_eq!(sent[0].role, Role::System);
+        assert!(matches!(
+            &sent[0].content,
+            MessageContent::Text { text } if text.starts_with("[Working Memory]") && text.contains("ship it")
+        ));
+    }
+
+    #[tokio::test]
+    async fn image_bytes_are_loaded_from_the_store_for_the_provider() {
+        use crate::testutil::RecordingProvider;
+
+        let fx = Fixture::new(ToolRegistry::new());
+        let blob_id = fx
+            .store
+            .put_blob(&fx.conversation_id, "image/png", b"png")
+            .unwrap();
+        let image = Message {
+            role: Role::User,
+            content: MessageContent::Image {
+                blob_id: blob_id.clone(),
+                mime_type: "image/png".into(),
+                data: None,
+            },
+            timestamp: Utc::now(),
+        };
+        let provider = RecordingProvider::new(vec!["ok".into()]);
+        let engine = fx.engine(&provider);
+        run_to_end(
+            &engine,
+            &fx.conversation_id,
+            vec![image, user_message("what is it?")],
+        )
+        .await;
+
+        assert_eq!(
+            provider.last_messages()[0].content,
+            MessageContent::Image {
+                blob_id,
+                mime_type: "image/png".into(),
+                data: Some(BASE64.encode(b"png")),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn loop_stops_at_max_iterations() {
+        let fx = Fixture::new(registry_with(Arc::new(MockEchoSkill)));
+        let responses = (0..=MAX_TOOL_ITERATIONS)
+            .map(|i| MockResponse::ToolCalls(vec![(format!("c{i}"), "echo".into(), "{}".into())]))
+            .collect();
+        let provider = SequencedProvider::new(responses);
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        let starts = events
+            .iter()
+            .filter(|e| matches!(e, EngineEvent::ToolCallStart { .. }))
+            .count();
+        assert_eq!(starts, MAX_TOOL_ITERATIONS);
+        assert!(matches!(
+            events.last(),
+            Some(EngineEvent::Error(EngineError::MaxIterations))
+        ));
+    }
+
+    /// Persist alternating user/assistant messages and return them.
+    fn seed_history(fx: &Fixture, count: usize) -> Vec<Message> {
+        let messages: Vec<Message> = (0..count)
+            .map(|i| Message {
+                role: if i % 2 == 0 {
+                    Role::User
+                } else {
+                    Role::Assistant
+                },
+                content: MessageContent::Text {
+                    text: format!("m{i}"),
+                },
+                timestamp: Utc::now(),
+            })
+            .collect();
+        for m in &messages {
+            fx.store.append_message(&fx.conversation_id, m).unwrap();
+        }
+        messages
+    }
+
+    async fn run_to_end<P: Provider>(
+        engine: &Engine<'_, P>,
+        conversation_id: &str,
+        messages: Vec<Message>,
+    ) {
+        let stream = engine.run(conversation_id, messages);
+        tokio::pin!(stream);
+        while stream.next().await.is_some() {}
+    }
+
+    #[tokio::test]
+    async fn long_history_is_summarized_and_replaced() {
+        use crate::testutil::RecordingProvider;
+
+        let fx = Fixture::new(ToolRegistry::new());
+        let history = seed_history(&fx, 9);
+        let config = MemoryConfig {
+            summarize_after_messages: 6,
+            summary_keep_recent: 3,
+            ..MemoryConfig::default()
+        };
+        let provider = RecordingProvider::new(vec!["recap".into()]);
+        let engine = fx.engine(&provider).with_summarization(&config);
+        run_to_end(&engine, &fx.conversation_id, history).await;
+
+        // m0..m5 are summarized; the verbatim tail starts at user message m6.
+        let stored = fx
+            .store
+            .get_rolling_summary(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.summary, "recap");
+        assert_eq!(stored.message_count, 6);
+
+        let sent = provider.last_messages();
+        assert_eq!(sent.len(), 4);
+        assert!(matches!(
+            &sent[0].content,
+            MessageContent::Text { text } if text == "[Conversation Summary]\nrecap"
+        ));
+        assert!(matches!(&sent[1].content, MessageContent::Text { text } if text == "m6"));
+
+        // The original messages are untouched.
+        let conv = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(conv.messages.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn summary_usage_counts_towards_totals() {
+        let fx = Fixture::new(ToolRegistry::new());
+        let history = seed_history(&fx, 9);
+        let config = MemoryConfig {
+            summarize_after_messages: 6,
+            summary_keep_recent: 3,
+            ..MemoryConfig::default()
+        };
+        let usage = |model: &str, prompt, completion| Token::Usage {
+            provider: "openai".into(),
+            model: model.into(),
+            prompt,
+            completion,
+        };
+        let provider = SequencedProvider::new(vec![
+            MockResponse::Tokens(vec![
+                Token::Text {
+                    text: "recap".into(),
+                },
+                usage("gpt-4o-mini", 100, 10),
+            ]),
+            MockResponse::Tokens(vec![
+                Token::Text {
+                    text: "Done.".into(),
+                },
+                usage("gpt-4o", 20, 3),
+            ]),
+        ]);
+        let engine = fx.engine(&provider).with_summarization(&config);
+        run_to_end(&engine, &fx.conversation_id, history).await;
+
+        let totals = fx
+            .store
+            .usage_totals(
+                crate::store::UsageGrouping::Model,
+                Some(&fx.conversation_id),
+            )
+            .unwrap();
+        let by_model: Vec<_> = totals
+            .iter()
+            .map(|t| (t.key.as_str(), t.prompt_tokens, t.completion_tokens))
+            .collect();
+        assert_eq!(by_model, [("gpt-4o", 20, 3), ("gpt-4o-mini", 100, 10)]);
+    }
+
+    #[tokio::test]
+    async fn existing_summary_reused_below_threshold() {
+        use crate::testutil::RecordingProvider;
+
+        let fx = Fixture::new(ToolRegistry::new());
+        let history = seed_history(&fx, 7);
+        fx.store
+            .set_rolling_summary(&fx.conversation_id, "earlier", 4)
+            .unwrap();
+        let config = MemoryConfig {
+            summarize_after_messages: 6,
+            summary_keep_recent: 2,
+            ..MemoryConfig::default()
+        };
+        let provider = RecordingProvider::new(vec!["ok".into()]);
+        let engine = fx.engine(&provider).with_summarization(&config);
+        run_to_end(&engine, &fx.conversation_id, history).await;
+
+        let stored = fx
+            .store
+            .get_rolling_summary(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.summary, "earlier");
+        let sent = provider.last_messages();
+        assert_eq!(
+            texts_of(&sent),
+            vec!["[Conversation Summary]\nearlier", "m4", "m5", "m6"]
+        );
+    }
+
+    #[test]
+    fn summary_cut_keeps_tool_pairs_and_recent_messages() {
+        let m = |role: Role, content: MessageContent| Message {
+            role,
+            content,
+            timestamp: Utc::now(),
+        };
+        let text = |role: Role, t: &str| m(role, MessageContent::Text { text: t.into() });
+        let messages = vec![
+            text(Role::User, "a"),
+            text(Role::Assistant, "b"),
+            text(Role::User, "c"),
+            m(
+                Role::Assistant,
+                MessageContent::ToolCall {
+                    id: "1".into(),
+                    name: "echo".into(),
+                    arguments: "{}".into(),
+                },
+            ),
+            m(
+                Role::User,
+                MessageContent::ToolResult {
+                    id: "1".into(),
+                    name: "echo".into(),
+                    content: "x".into(),
+                },
+            ),
+            text(Role::Assistant, "d"),
+        ];
+        assert_eq!(summary_cut(&messages, 0, 2), Some(2));
+        assert_eq!(summary_cut(&messages, 2, 2), None);
+        assert_eq!(summary_cut(&messages, 0, 5), None);
+    }
+
+    fn texts_of(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.content {
+                MessageContent::Text { text } => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn usage_is_persisted_with_the_message_it_produced() {
+        let fx = Fixture::new(registry_with(Arc::new(MockEchoSkill)));
+        let usage = |prompt, completion| Token::Usage {
+            provider: "openai".into(),
+            model: "gpt-4o".into(),
+            prompt,
+            completion,
+        };
+        let provider = SequencedProvider::new(vec![
+            MockResponse::Tokens(vec![
+                Token::ToolCall {
+                    id: "c1".into(),
+                    name: "echo".into(),
+                    arguments: r#"{"value":"x"}"#.into(),
+                },
+                usage(10, 2),
+            ]),
+            MockResponse::Tokens(vec![
+                Token::Text {
+                    text: "Done.".into(),
+                },
+                usage(20, 3),
+            ]),
+        ]);
+        collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        let totals = fx
+            .store
+            .usage_totals(
+                crate::store::UsageGrouping::Model,
+                Some(&fx.conversation_id),
+            )
+            .unwrap();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].key, "gpt-4o");
+        assert_eq!(totals[0].prompt_tokens, 30);
+        assert_eq!(totals[0].completion_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn stream_reset_discards_partial_text() {
+        let fx = Fixture::new(registry_with(Arc::new(MockEchoSkill)));
+        let provider = SequencedProvider::new(vec![MockResponse::Tokens(vec![
+            Token::Text {
+                text: "Half an ans".into(),
+            },
+            Token::Reset {
+                message: "Connection to a was lost, retrying with b".into(),
+            },
+            Token::Text {
+                text: "A full answer.".into(),
+            },
+        ])]);
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, EngineEvent::StreamReset { .. }))
+        );
+        assert!(matches!(
+            events.last(),
+            Some(EngineEvent::Done { final_text }) if final_text == "A full answer."
+        ));
+        let stored = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(texts_of(&stored.messages).last().unwrap(), "A full answer.");
+    }
+
+    #[tokio::test]
+    async fn reasoning_streamed_and_only_stored_when_enabled() {
+        let fx = Fixture::new(ToolRegistry::new());
+        let response = || {
+            MockResponse::Tokens(vec![
+                Token::Reasoning {
+                    text: "The user said hi.".into(),
+                },
+                Token::Text {
+                    text: "Hello!".into(),
+                },
+            ])
+        };
+        let provider = SequencedProvider::new(vec![response(), response()]);
+
+        let events = collect(&fx.engine(&provider), &fx.conversation_id, true).await;
+        assert!(matches!(
+            &events[0],
+            EngineEvent::ReasoningDelta { text } if text == "The user said hi."
+        ));
+        let stored = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.messages.len(), 1);
+
+        let engine = fx.engine(&provider).with_reasoning_persistence(true);
+        collect(&engine, &fx.conversation_id, true).await;
+        let stored = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.messages.len(), 3);
+        assert_eq!(
+            stored.messages[1].content,
+            MessageContent::Reasoning {
+                text: "The user said hi.".into()
+            }
+        );
+        assert_eq!(
+            stored.messages[2].content,
+            MessageContent::Text {
+                text: "Hello!".into()
+            }
+        );
+    }
+
+    /// Streams `text`, then stalls until the turn is cancelled.
+    struct StallingProvider {
+        text: &'static str,
+    }
+
+    impl Provider for StallingProvider {
+        async fn complete(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<serde_json::Value>>,
+        ) -> Result<crate::provider::TokenStream, ProviderError> {
+            let text = self.text.to_string();
+            Ok(Box::pin(
+                futures_util::stream::once(async move { Ok(Token::Text { text }) })
+                    .chain(futures_util::stream::pending()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_mid_stream_persists_partial_reply() {
+        let fx = Fixture::new(ToolRegistry::new());
+        let provider = StallingProvider {
+            text: "Half an ans",
+        };
+        let cancel = CancelToken::new();
+        let engine = fx.engine(&provider).with_cancellation(&cancel);
+
+        let stream = engine.run(&fx.conversation_id, vec![user_message("hi")]);
+        tokio::pin!(stream);
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            if matches!(event, EngineEvent::TextDelta { .. }) {
+                cancel.cancel();
+            }
+            last = Some(event);
+        }
+
+        assert!(matches!(
+            last,
+            Some(EngineEvent::Cancelled { partial_text }) if partial_text == "Half an ans"
+        ));
+        let stored = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            texts_of(&stored.messages),
+            vec![format!("Half an ans\n\n{CANCELLED_MARKER}")]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_during_approval_answers_the_pending_call() {
+        let fx = Fixture::new(registry_with(Arc::new(MockMutatingSkill)));
+        let provider = SequencedProvider::new(vec![MockResponse::ToolCalls(vec![(
+            "c1".into(),
+            "mutating".into(),
+            r#"{"value":"x"}"#.into(),
+        )])]);
+        let cancel = CancelToken::new();
+        let engine = fx.engine(&provider).with_cancellation(&cancel);
+
+        let stream = engine.run(&fx.conversation_id, vec![user_message("hi")]);
+        tokio::pin!(stream);
+        let mut pending = Vec::new();
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            match event {
+                // Keep the request unanswered, as if the user never decided.
+                EngineEvent::ApprovalNeeded(request) => {
+                    pending.push(request);
+                    cancel.cancel();
+                }
+                other => last = Some(other),
+            }
+        }
+
+        assert!(matches!(last, Some(EngineEvent::Cancelled { .. })));
+        let stored = fx
+            .store
+            .get_conversation(&fx.conversation_id)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            &stored.messages[1].content,
+            MessageContent::ToolResult { id, content, .. } if id == "c1" && content == CANCELLED_MARKER
+        ));
+        assert_eq!(
+            texts_of(&stored.messages),
+            vec![CANCELLED_MARKER.to_string()]
+        );
+    }
+}
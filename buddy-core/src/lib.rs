@@ -2,6 +2,7 @@
 
 pub mod types;
 pub mod config;
+pub mod context;
 pub mod store;
 pub mod embedding;
 pub mod memory;
@@ -10,6 +11,8 @@ pub mod skill;
 pub mod reload;
 pub mod warning;
 pub mod state;
+pub mod engine;
+pub mod scheduler;
 
 // Test utilities - always available for use by buddy-server and tests
 pub mod testutil;
@@ -0,0 +1,882 @@
+use std::collections::HashMap;
+
+use crate::types::{Message, MessageContent, Role};
+use futures_util::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::config::GenerationParams;
+use crate::context::{ContextWindow, TokenizerFamily};
+use crate::provider::retry::parse_retry_after;
+use crate::provider::{IMAGE_UNAVAILABLE, Provider, ProviderError, Token, TokenStream};
+
+/// Version of the Messages API this provider speaks.
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// `max_tokens` is mandatory in the Messages API; used when the provider
+/// entry does not set one.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic provider.
+///
+/// Uses the Messages API (`/v1/messages`) with streaming enabled. Unlike the
+/// OpenAI format, the system prompt is a top-level `system` field, tool calls
+/// and results are `tool_use` / `tool_result` content blocks, and consecutive
+/// same-role messages are merged into one message with multiple blocks.
+pub struct AnthropicProvider {
+    client: Client,
+    api_key: String,
+    model: String,
+    endpoint: String,
+    system_prompt: String,
+    context_window: Option<ContextWindow>,
+    generation: GenerationParams,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: &str, model: &str, endpoint: &str, system_prompt: &str) -> Self {
+        Self {
+            client: Client::builder()
+                .connect_timeout(std::time::Duration::from_secs(5))
+                .build()
+                .expect("failed to build HTTP client"),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            endpoint: endpoint.to_string(),
+            system_prompt: system_prompt.to_string(),
+            context_window: None,
+            generation: GenerationParams::default(),
+        }
+    }
+
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(mut self, max_tokens: usize) -> Self {
+        self.context_window = Some(ContextWindow::new(max_tokens, TokenizerFamily::Anthropic));
+        self
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(mut self, generation: GenerationParams) -> Self {
+        self.generation = generation;
+        self
+    }
+}
+
+// --- Request body construction ---
+
+/// Convert a single message to an Anthropic content block.
+fn to_content_block(content: &MessageContent) -> Option<serde_json::Value> {
+    match content {
+        // The API rejects empty text blocks.
+        MessageContent::Text { text } if text.is_empty() => None,
+        MessageContent::Text { text } => Some(serde_json::json!({
+            "type": "text",
+            "text": text,
+        })),
+        MessageContent::ToolCall {
+            id,
+            name,
+            arguments,
+        } => {
+            let input: serde_json::Value =
+                serde_json::from_str(arguments).unwrap_or_else(|_| serde_json::json!({}));
+            Some(serde_json::json!({
+                "type": "tool_use",
+                "id": id,
+                "name": name,
+                "input": input,
+            }))
+        }
+        MessageContent::ToolResult { id, content, .. } => Some(serde_json::json!({
+            "type": "tool_result",
+            "tool_use_id": id,
+            "content": content,
+        })),
+        MessageContent::Image {
+            mime_type,
+            data: Some(data),
+            ..
+        } => Some(serde_json::json!({
+            "type": "image",
+            "source": {
+                "type": "base64",
+                "media_type": mime_type,
+                "data": data,
+            },
+        })),
+        MessageContent::Image { data: None, .. } => Some(serde_json::json!({
+            "type": "text",
+            "text": IMAGE_UNAVAILABLE,
+        })),
+        // Thinking blocks are only accepted back with the signature the API
+        // issued for them, which is not stored.
+        MessageContent::Reasoning { .. } => None,
+    }
+}
+
+/// Convert buddy messages to Anthropic's `messages` format.
+///
+/// System messages are skipped (they are folded into the `system` field by
+/// [`build_request_body`]) and consecutive messages with the same role are
+/// merged into one message, so a `tool_result` always directly follows the
+/// assistant turn holding its `tool_use`.
+fn to_anthropic_messages(messages: &[Message]) -> Vec<serde_json::Value> {
+    let mut result: Vec<serde_json::Value> = Vec::new();
+    let mut last_role: Option<&Role> = None;
+
+    for msg in messages {
+        let role = match msg.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::System => continue,
+        };
+        let Some(block) = to_content_block(&msg.content) else {
+            continue;
+        };
+        if last_role == Some(&msg.role)
+            && let Some(blocks) = result
+                .last_mut()
+                .and_then(|m| m.get_mut("content"))
+                .and_then(|c| c.as_array_mut())
+        {
+            blocks.push(block);
+            continue;
+        }
+        result.push(serde_json::json!({
+            "role": role,
+            "content": [block],
+        }));
+        last_role = Some(&msg.role);
+    }
+
+    result
+}
+
+/// Map OpenAI tool definitions to Anthropic's `name` / `input_schema` format.
+fn to_anthropic_tools(tools: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    tools
+        .iter()
+        .filter_map(|tool| {
+            let function = tool.get("function")?;
+            let name = function.get("name")?.as_str()?;
+            let description = function.get("description")?.as_str()?;
+            let parameters = function.get("parameters")?;
+
+            Some(serde_json::json!({
+                "name": name,
+                "description": description,
+                "input_schema": parameters,
+            }))
+        })
+        .collect()
+}
+
+/// Build the request body for the streaming Messages API.
+///
+/// The configured system prompt and any `Role::System` messages (recalled
+/// memories, working memory, conversation summary) are joined into the
+/// top-level `system` field.
+fn build_request_body(
+    messages: &[Message],
+    model: &str,
+    system_prompt: &str,
+    tools: Option<&Vec<serde_json::Value>>,
+    params: &GenerationParams,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": to_anthropic_messages(messages),
+        "max_tokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "stream": true,
+    });
+
+    let mut system: Vec<&str> = Vec::new();
+    if !system_prompt.is_empty() {
+        system.push(system_prompt);
+    }
+    for msg in messages.iter().filter(|m| m.role == Role::System) {
+        if let MessageContent::Text { text } = &msg.content {
+            system.push(text);
+        }
+    }
+    if !system.is_empty() {
+        body["system"] = serde_json::json!(system.join("\n\n"));
+    }
+
+    if let Some(tool_list) = tools {
+        let anthropic_tools = to_anthropic_tools(tool_list);
+        if !anthropic_tools.is_empty() {
+            body["tools"] = serde_json::json!(anthropic_tools);
+        }
+    }
+
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(top_p) = params.top_p {
+        body["top_p"] = serde_json::json!(top_p);
+    }
+    if let Some(stop) = &params.stop {
+        body["stop_sequences"] = serde_json::json!(stop);
+    }
+
+    body
+}
+
+// --- Response types ---
+
+/// A streamed event. Every `data:` payload carries its own `type`, so the
+/// preceding `event:` line is not needed.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: MessageStart,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        #[serde(default)]
+        usage: Option<UsageCounts>,
+    },
+    Error {
+        error: AnthropicError,
+    },
+    /// `ping`, `message_stop` and event types added in later API versions.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessageStart {
+    #[serde(default)]
+    usage: Option<UsageCounts>,
+}
+
+#[derive(Deserialize)]
+struct UsageCounts {
+    #[serde(default)]
+    input_tokens: Option<u32>,
+    #[serde(default)]
+    output_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    Thinking {
+        #[serde(default)]
+        thinking: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AnthropicErrorResponse {
+    error: AnthropicError,
+}
+
+#[derive(Deserialize)]
+struct AnthropicError {
+    #[serde(rename = "type", default)]
+    error_type: String,
+    message: String,
+}
+
+// --- SSE parsing ---
+
+/// Accumulates state across SSE events: tool-call arguments arrive as
+/// partial JSON spread over several deltas and are emitted as one
+/// `Token::ToolCall` when their content block stops.
+#[derive(Default)]
+struct StreamState {
+    /// In-progress tool calls keyed by content block index: (id, name, json).
+    tool_blocks: HashMap<usize, (String, String, String)>,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+impl StreamState {
+    /// Handle a single SSE line, returning any tokens it completes.
+    fn handle_line(&mut self, line: &str) -> Result<Vec<Token>, ProviderError> {
+        let data = match line.strip_prefix("data:") {
+            Some(d) => d.trim_start(),
+            None => return Ok(Vec::new()),
+        };
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let event: StreamEvent = serde_json::from_str(data)
+            .map_err(|e| ProviderError::MalformedResponse(format!("invalid JSON in SSE: {e}")))?;
+
+        let mut tokens = Vec::new();
+        match event {
+            StreamEvent::MessageStart { message } => {
+                if let Some(usage) = message.usage {
+                    self.record_usage(usage);
+                }
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                ContentBlock::Text { text } if !text.is_empty() => {
+                    tokens.push(Token::Text { text });
+                }
+                ContentBlock::ToolUse { id, name } => {
+                    self.tool_blocks.insert(index, (id, name, String::new()));
+                }
+                ContentBlock::Thinking { thinking } if !thinking.is_empty() => {
+                    tokens.push(Token::Reasoning { text: thinking });
+                }
+                _ => {}
+            },
+            StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                ContentDelta::TextDelta { text } if !text.is_empty() => {
+                    tokens.push(Token::Text { text });
+                }
+                ContentDelta::ThinkingDelta { thinking } if !thinking.is_empty() => {
+                    tokens.push(Token::Reasoning { text: thinking });
+                }
+                ContentDelta::InputJsonDelta { partial_json } => {
+                    if let Some((_, _, json)) = self.tool_blocks.get_mut(&index) {
+                        json.push_str(&partial_json);
+                    }
+                }
+                _ => {}
+            },
+            StreamEvent::ContentBlockStop { index } => {
+                if let Some((id, name, json)) = self.tool_blocks.remove(&index) {
+                    let arguments = if json.trim().is_empty() {
+                        "{}".to_string()
+                    } else {
+                        json
+                    };
+                    tokens.push(Token::ToolCall {
+                        id,
+                        name,
+                        arguments,
+                    });
+                }
+            }
+            StreamEvent::MessageDelta { usage } => {
+                if let Some(usage) = usage {
+                    self.record_usage(usage);
+                }
+            }
+            StreamEvent::Error { error } => {
+                return Err(map_stream_error(error));
+            }
+            StreamEvent::Other => {}
+        }
+
+        Ok(tokens)
+    }
+
+    /// Input tokens arrive in `message_start`; the output count is cumulative
+    /// and the value in the final `message_delta` wins.
+    fn record_usage(&mut self, usage: UsageCounts) {
+        if let Some(input) = usage.input_tokens {
+            self.prompt_tokens = Some(input);
+        }
+        if let Some(output) = usage.output_tokens {
+            self.completion_tokens = Some(output);
+        }
+    }
+}
+
+/// Map an `error` event received mid-stream to a ProviderError.
+fn map_stream_error(error: AnthropicError) -> ProviderError {
+    match error.error_type.as_str() {
+        "overloaded_error" | "rate_limit_error" => ProviderError::RateLimit {
+            message: error.message,
+            retry_after: None,
+        },
+        "authentication_error" | "permission_error" => ProviderError::Auth(error.message),
+        _ => ProviderError::Other(error.message),
+    }
+}
+
+/// Convert a streaming response into a TokenStream, ending with a
+/// `Token::Usage` for `model` when Anthropic reports usage.
+fn parse_anthropic_stream(response: reqwest::Response, model: String) -> TokenStream {
+    let stream = async_stream::try_stream! {
+        let mut byte_stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut state = StreamState::default();
+
+        while let Some(chunk) = byte_stream.next().await {
+            let bytes = chunk.map_err(|e| ProviderError::Network(e.to_string()))?;
+            buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+            while let Some(pos) = buffer.find('\n') {
+                let line = buffer[..pos].trim_end_matches('\r').to_string();
+                buffer.drain(..pos + 1);
+
+                for token in state.handle_line(&line)? {
+                    yield token;
+                }
+            }
+        }
+
+        // Flush remaining buffer.
+        let remaining = buffer.trim();
+        if !remaining.is_empty() {
+            for token in state.handle_line(remaining)? {
+                yield token;
+            }
+        }
+
+        if state.prompt_tokens.is_some() || state.completion_tokens.is_some() {
+            yield Token::Usage {
+                provider: "anthropic".to_string(),
+                model,
+                prompt: state.prompt_tokens.unwrap_or(0),
+                completion: state.completion_tokens.unwrap_or(0),
+            };
+        }
+    };
+
+    Box::pin(stream)
+}
+
+/// Map HTTP error status to ProviderError.
+///
+/// 529 means the API is temporarily overloaded; it is reported as a rate
+/// limit so a provider chain falls back instead of failing.
+pub fn map_anthropic_error(status: u16, body: &str) -> ProviderError {
+    let message = serde_json::from_str::<AnthropicErrorResponse>(body)
+        .map(|r| r.error.message)
+        .unwrap_or_else(|_| body.to_string());
+
+    match status {
+        401 | 403 => ProviderError::Auth(message),
+        429 | 529 => ProviderError::RateLimit {
+            message,
+            retry_after: None,
+        },
+        400 => ProviderError::MalformedResponse(message),
+        _ => ProviderError::Other(format!("HTTP {status}: {message}")),
+    }
+}
+
+// --- Provider implementation ---
+
+impl Provider for AnthropicProvider {
+    async fn complete(
+        &self,
+        mut messages: Vec<Message>,
+        tools: Option<Vec<serde_json::Value>>,
+    ) -> Result<TokenStream, ProviderError> {
+        if let Some(window) = &self.context_window {
+            messages = window.trim(messages, &self.system_prompt, tools.as_ref());
+        }
+        let url = format!("{}/v1/messages", self.endpoint.trim_end_matches('/'));
+
+        let body = build_request_body(
+            &messages,
+            &self.model,
+            &self.system_prompt,
+            tools.as_ref(),
+            &self.generation,
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
+            let body_text = response.text().await.unwrap_or_default();
+            return Err(
+                map_anthropic_error(status.as_u16(), &body_text).with_retry_after(retry_after)
+            );
+        }
+
+        Ok(parse_anthropic_stream(response, self.model.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{http_response, serve};
+    use chrono::Utc;
+
+    fn make_message(role: Role, text: &str) -> Message {
+        Message {
+            role,
+            content: MessageContent::Text {
+                text: text.to_string(),
+            },
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn sse(events: &[serde_json::Value]) -> String {
+        events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {e}\n\n", e["type"].as_str().unwrap()))
+            .collect()
+    }
+
+    async fn collect(stream: TokenStream) -> Vec<Result<Token, ProviderError>> {
+        stream.collect().await
+    }
+
+    #[test]
+    fn to_anthropic_messages_maps_tool_blocks_and_merges_roles() {
+        let messages = vec![
+            make_message(Role::System, "## Recalled Memories"),
+            make_message(Role::User, "Weather?"),
+            make_message(Role::Assistant, "Let me check."),
+            Message {
+                role: Role::Assistant,
+                content: MessageContent::ToolCall {
+                    id: "toolu_1".into(),
+                    name: "get_weather".into(),
+                    arguments: r#"{"city":"Paris"}"#.into(),
+                },
+                timestamp: Utc::now(),
+            },
+            Message {
+                role: Role::User,
+                content: MessageContent::ToolResult {
+                    id: "toolu_1".into(),
+                    name: "get_weather".into(),
+                    content: "sunny".into(),
+                },
+                timestamp: Utc::now(),
+            },
+        ];
+        let converted = to_anthropic_messages(&messages);
+
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[0]["role"], "user");
+        assert_eq!(converted[1]["role"], "assistant");
+        let blocks = converted[1]["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["type"], "text");
+        assert_eq!(blocks[1]["type"], "tool_use");
+        assert_eq!(blocks[1]["id"], "toolu_1");
+        assert_eq!(blocks[1]["input"]["city"], "Paris");
+        assert_eq!(converted[2]["content"][0]["type"], "tool_result");
+        assert_eq!(converted[2]["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(converted[2]["content"][0]["content"], "sunny");
+    }
+
+    #[test]
+    fn to_content_block_maps_image_to_base64_source() {
+        let block = to_content_block(&MessageContent::Image {
+            blob_id: "blob-1".into(),
+            mime_type: "image/jpeg".into(),
+            data: Some("/9j/4AAQ".into()),
+        })
+        .unwrap();
+        assert_eq!(block["type"], "image");
+        assert_eq!(block["source"]["type"], "base64");
+        assert_eq!(block["source"]["media_type"], "image/jpeg");
+        assert_eq!(block["source"]["data"], "/9j/4AAQ");
+    }
+
+    #[test]
+    fn build_request_body_folds_system_messages_and_maps_params() {
+        let messages = vec![
+            make_message(Role::System, "## Working Memory"),
+            make_message(Role::User, "Hi"),
+        ];
+        let tools = vec![serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": "read_file",
+                "description": "Read a file",
+                "parameters": { "type": "object", "properties": {} }
+            }
+        })];
+        let params = GenerationParams {
+            temperature: Some(0.5),
+            max_tokens: Some(256),
+            top_p: Some(0.9),
+            stop: Some(vec!["END".into()]),
+        };
+        let body = build_request_body(&messages, "claude-x", "Be nice", Some(&tools), &params);
+
+        assert_eq!(body["system"], "Be nice\n\n## Working Memory");
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["top_p"], 0.9);
+        assert_eq!(body["stop_sequences"], serde_json::json!(["END"]));
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["tools"][0]["name"], "read_file");
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+
+        let body = build_request_body(
+            &messages[1..],
+            "claude-x",
+            "",
+            None,
+            &GenerationParams::default(),
+        );
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert!(body.get("system").is_none());
+        assert!(body.get("tools").is_none());
+        assert!(body.get("temperature").is_none());
+    }
+
+    #[test]
+    fn stream_state_assembles_tool_call_from_partial_json() {
+        let mut state = StreamState::default();
+        let lines = [
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_9","name":"read_file","input":{}}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"path\":"}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"a.txt\"}"}}"#,
+        ];
+        for line in lines {
+            assert!(state.handle_line(line).unwrap().is_empty());
+        }
+        let tokens = state
+            .handle_line(r#"data: {"type":"content_block_stop","index":1}"#)
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![Token::ToolCall {
+                id: "toolu_9".into(),
+                name: "read_file".into(),
+                arguments: r#"{"path":"a.txt"}"#.into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn stream_state_emits_thinking_as_reasoning() {
+        let mut state = StreamState::default();
+        let tokens = state
+            .handle_line(r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me see"}}"#)
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![Token::Reasoning {
+                text: "Let me see".into()
+            }]
+        );
+        assert!(
+            state
+                .handle_line(r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"abc"}}"#)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            to_content_block(&MessageContent::Reasoning {
+                text: "Let me see".into()
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn stream_state_tool_call_without_input_gets_empty_object() {
+        let mut state = StreamState::default();
+        state
+            .handle_line(r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"t","name":"noop","input":{}}}"#)
+            .unwrap();
+        let tokens = state
+            .handle_line(r#"data: {"type":"content_block_stop","index":0}"#)
+            .unwrap();
+        assert!(matches!(&tokens[0], Token::ToolCall { arguments, .. } if arguments == "{}"));
+    }
+
+    #[test]
+    fn stream_state_ignores_ping_and_event_lines() {
+        let mut state = StreamState::default();
+        assert!(state.handle_line("event: ping").unwrap().is_empty());
+        assert!(
+            state
+                .handle_line(r#"data: {"type": "ping"}"#)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            state
+                .handle_line(r#"data: {"type":"message_stop"}"#)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn stream_state_error_event_maps_overloaded_to_rate_limit() {
+        let mut state = StreamState::default();
+        let result = state.handle_line(
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+        assert!(
+            matches!(result, Err(ProviderError::RateLimit { message: msg, .. }) if msg == "Overloaded")
+        );
+    }
+
+    #[test]
+    fn map_anthropic_error_statuses() {
+        let body = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        assert!(
+            matches!(map_anthropic_error(401, body), ProviderError::Auth(m) if m == "invalid x-api-key")
+        );
+        let body = r#"{"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#;
+        assert!(matches!(
+            map_anthropic_error(429, body),
+            ProviderError::RateLimit { .. }
+        ));
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        assert!(
+            matches!(map_anthropic_error(529, body), ProviderError::RateLimit { message: m, .. } if m == "Overloaded")
+        );
+        assert!(
+            matches!(map_anthropic_error(500, "oops"), ProviderError::Other(m) if m == "HTTP 500: oops")
+        );
+    }
+
+    #[tokio::test]
+    async fn anthropic_provider_streams_text_tool_call_and_usage() {
+        let body = sse(&[
+            serde_json::json!({"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":25,"output_tokens":1}}}),
+            serde_json::json!({"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}),
+            serde_json::json!({"type":"ping"}),
+            serde_json::json!({"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}),
+            serde_json::json!({"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" there"}}),
+            serde_json::json!({"type":"content_block_stop","index":0}),
+            serde_json::json!({"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"echo","input":{}}}),
+            serde_json::json!({"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"value\":\"hi\"}"}}),
+            serde_json::json!({"type":"content_block_stop","index":1}),
+            serde_json::json!({"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":12}}),
+            serde_json::json!({"type":"message_stop"}),
+        ]);
+        let response = http_response("200 OK", "content-type: text/event-stream\r\n", body);
+        let (endpoint, mut requests) = serve(move |_| response.clone()).await;
+        let provider = AnthropicProvider::new("sk-test", "claude-test", &endpoint, "Be brief");
+
+        let stream = provider
+            .complete(vec![make_message(Role::User, "hi")], None)
+            .await
+            .unwrap();
+        let tokens: Vec<Token> = collect(stream)
+            .await
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Text {
+                    text: "Hello".into()
+                },
+                Token::Text {
+                    text: " there".into()
+                },
+                Token::ToolCall {
+                    id: "toolu_1".into(),
+                    name: "echo".into(),
+                    arguments: r#"{"value":"hi"}"#.into(),
+                },
+                Token::Usage {
+                    provider: "anthropic".into(),
+                    model: "claude-test".into(),
+                    prompt: 25,
+                    completion: 12,
+                },
+            ]
+        );
+
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("POST /v1/messages "));
+        let lower = request.to_ascii_lowercase();
+        assert!(lower.contains("x-api-key: sk-test"));
+        assert!(lower.contains(&format!("anthropic-version: {ANTHROPIC_VERSION}")));
+        assert!(request.contains(r#""system":"Be brief""#));
+    }
+
+    #[tokio::test]
+    async fn anthropic_provider_maps_http_errors() {
+        for (status, expected) in [
+            ("401 Unauthorized", "auth"),
+            ("429 Too Many Requests", "rate"),
+            ("529 Overloaded", "rate"),
+        ] {
+            let body =
+                r#"{"type":"error","error":{"type":"some_error","message":"nope"}}"#.to_string();
+            let headers = "content-type: application/json\r\nretry-after: 7\r\n";
+            let response = http_response(status, headers, body);
+            let (endpoint, _) = serve(move |_| response.clone()).await;
+            let provider = AnthropicProvider::new("sk-test", "claude-test", &endpoint, "");
+            let result = provider
+                .complete(vec![make_message(Role::User, "hi")], None)
+                .await;
+            match (expected, result) {
+                ("auth", Err(ProviderError::Auth(msg))) => assert_eq!(msg, "nope"),
+                (
+                    "rate",
+                    Err(ProviderError::RateLimit {
+                        message,
+                        retry_after,
+                    }),
+                ) => {
+                    assert_eq!(message, "nope");
+                    assert_eq!(retry_after, Some(std::time::Duration::from_secs(7)));
+                }
+                (_, Err(e)) => panic!("unexpected error for {status}: {e:?}"),
+                (_, Ok(_)) => panic!("expected error for {status}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn anthropic_provider_network_error_on_unreachable_endpoint() {
+        let provider = AnthropicProvider::new("sk-test", "claude-test", "http://localhost:1", "");
+        let result = provider
+            .complete(vec![make_message(Role::User, "test")], None)
+            .await;
+        assert!(matches!(result, Err(ProviderError::Network(_))));
+    }
+}
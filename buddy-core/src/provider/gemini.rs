@@ -3,7 +3,10 @@ use futures_util::StreamExt;
 use reqwest::Client;
 use serde::Deserialize;
 
-use crate::provider::{Provider, ProviderError, Token, TokenStream};
+use crate::config::GenerationParams;
+use crate::context::{ContextWindow, TokenizerFamily};
+use crate::provider::retry::parse_retry_after;
+use crate::provider::{IMAGE_UNAVAILABLE, Provider, ProviderError, Token, TokenStream};
 
 /// Google Gemini provider.
 ///
@@ -17,6 +20,8 @@ pub struct GeminiProvider {
     model: String,
     endpoint: String,
     system_prompt: String,
+    context_window: Option<ContextWindow>,
+    generation: GenerationParams,
 }
 
 impl GeminiProvider {
@@ -30,8 +35,22 @@ impl GeminiProvider {
             model: model.to_string(),
             endpoint: endpoint.to_string(),
             system_prompt: system_prompt.to_string(),
+            context_window: None,
+            generation: GenerationParams::default(),
         }
     }
+
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(mut self, max_tokens: usize) -> Self {
+        self.context_window = Some(ContextWindow::new(max_tokens, TokenizerFamily::Gemini));
+        self
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(mut self, generation: GenerationParams) -> Self {
+        self.generation = generation;
+        self
+    }
 }
 
 // --- Request body construction ---
@@ -86,6 +105,24 @@ fn to_gemini_contents(messages: &[Message]) -> Vec<serde_json::Value> {
                         }
                     })
                 }
+                MessageContent::Image {
+                    mime_type,
+                    data: Some(data),
+                    ..
+                } => {
+                    serde_json::json!({
+                        "inline_data": {
+                            "mime_type": mime_type,
+                            "data": data,
+                        }
+                    })
+                }
+                MessageContent::Image { data: None, .. } => {
+                    serde_json::json!({ "text": IMAGE_UNAVAILABLE })
+                }
+                MessageContent::Reasoning { text } => {
+                    serde_json::json!({ "text": text, "thought": true })
+                }
             };
             parts.push(part);
             i += 1;
@@ -132,10 +169,14 @@ fn to_gemini_tools(tools: &[serde_json::Value]) -> Option<serde_json::Value> {
 }
 
 /// Build the request body for Gemini's streamGenerateContent endpoint.
+///
+/// Generation parameters go in `generationConfig` under Gemini's names
+/// (`temperature`, `maxOutputTokens`, `topP`, `stopSequences`).
 fn build_request_body(
     messages: &[Message],
     system_prompt: &str,
     tools: Option<&Vec<serde_json::Value>>,
+    params: &GenerationParams,
 ) -> serde_json::Value {
     let mut body = serde_json::json!({
         "contents": to_gemini_contents(messages),
@@ -153,6 +194,23 @@ fn build_request_body(
         }
     }
 
+    let mut generation_config = serde_json::Map::new();
+    if let Some(temperature) = params.temperature {
+        generation_config.insert("temperature".into(), serde_json::json!(temperature));
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        generation_config.insert("maxOutputTokens".into(), serde_json::json!(max_tokens));
+    }
+    if let Some(top_p) = params.top_p {
+        generation_config.insert("topP".into(), serde_json::json!(top_p));
+    }
+    if let Some(stop) = &params.stop {
+        generation_config.insert("stopSequences".into(), serde_json::json!(stop));
+    }
+    if !generation_config.is_empty() {
+        body["generationConfig"] = serde_json::Value::Object(generation_config);
+    }
+
     body
 }
 
@@ -179,6 +237,9 @@ struct GeminiContent {
 #[derive(Deserialize)]
 struct GeminiPart {
     text: Option<String>,
+    /// Marks `text` as the model's reasoning rather than its answer.
+    #[serde(default)]
+    thought: bool,
     #[serde(rename = "functionCall")]
     function_call: Option<GeminiFunctionCall>,
 }
@@ -189,6 +250,20 @@ struct GeminiFunctionCall {
     args: serde_json::Value,
 }
 
+#[derive(Deserialize)]
+struct GeminiUsageChunk {
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct GeminiUsageMetadata {
+    #[serde(rename = "promptTokenCount", default)]
+    prompt_token_count: u32,
+    #[serde(rename = "candidatesTokenCount", default)]
+    candidates_token_count: u32,
+}
+
 #[derive(Deserialize)]
 struct GeminiErrorResponse {
     error: GeminiError,
@@ -225,7 +300,11 @@ fn parse_gemini_sse_line(line: &str) -> Result<Vec<Token>, ProviderError> {
                 for part in content.parts {
                     if let Some(text) = part.text {
                         if !text.is_empty() {
-                            tokens.push(Token::Text { text });
+                            tokens.push(if part.thought {
+                                Token::Reasoning { text }
+                            } else {
+                                Token::Text { text }
+                            });
                         }
                     }
                     if let Some(fc) = part.function_call {
@@ -245,11 +324,29 @@ fn parse_gemini_sse_line(line: &str) -> Result<Vec<Token>, ProviderError> {
     Ok(tokens)
 }
 
-/// Convert a streaming response into a TokenStream.
-fn parse_gemini_stream(response: reqwest::Response) -> TokenStream {
+/// Extract `usageMetadata` from a `data:` line as
+/// `(prompt_tokens, completion_tokens)`.
+///
+/// Gemini repeats the metadata on every chunk with running totals, so the
+/// last value seen is the final count.
+fn parse_gemini_usage(line: &str) -> Option<(u32, u32)> {
+    let data = line.strip_prefix("data: ")?;
+    if !data.contains("\"usageMetadata\"") {
+        return None;
+    }
+    let chunk: GeminiUsageChunk = serde_json::from_str(data).ok()?;
+    chunk
+        .usage_metadata
+        .map(|u| (u.prompt_token_count, u.candidates_token_count))
+}
+
+/// Convert a streaming response into a TokenStream, ending with a
+/// `Token::Usage` for `model` when Gemini reports usage.
+fn parse_gemini_stream(response: reqwest::Response, model: String) -> TokenStream {
     let stream = async_stream::try_stream! {
         let mut byte_stream = response.bytes_stream();
         let mut buffer = String::new();
+        let mut usage = None;
 
         while let Some(chunk) = byte_stream.next().await {
             let bytes = chunk.map_err(|e| ProviderError::Network(e.to_string()))?;
@@ -259,6 +356,9 @@ fn parse_gemini_stream(response: reqwest::Response) -> TokenStream {
                 let line = buffer[..pos].trim_end_matches('\r').to_string();
                 buffer.drain(..pos + 1);
 
+                if let Some(counts) = parse_gemini_usage(&line) {
+                    usage = Some(counts);
+                }
                 for token in parse_gemini_sse_line(&line)? {
                     yield token;
                 }
@@ -268,10 +368,22 @@ fn parse_gemini_stream(response: reqwest::Response) -> TokenStream {
         // Flush remaining buffer.
         let remaining = buffer.trim();
         if !remaining.is_empty() {
+            if let Some(counts) = parse_gemini_usage(remaining) {
+                usage = Some(counts);
+            }
             for token in parse_gemini_sse_line(remaining)? {
                 yield token;
             }
         }
+
+        if let Some((prompt, completion)) = usage {
+            yield Token::Usage {
+                provider: "gemini".to_string(),
+                model,
+                prompt,
+                completion,
+            };
+        }
     };
 
     Box::pin(stream)
@@ -292,7 +404,10 @@ pub fn map_gemini_error(status: u16, body: &str) -> ProviderError {
                 ProviderError::MalformedResponse(message)
             }
         }
-        429 => ProviderError::RateLimit(message),
+        429 => ProviderError::RateLimit {
+            message,
+            retry_after: None,
+        },
         _ => ProviderError::Other(format!("HTTP {status}: {message}")),
     }
 }
@@ -302,9 +417,12 @@ pub fn map_gemini_error(status: u16, body: &str) -> ProviderError {
 impl Provider for GeminiProvider {
     async fn complete(
         &self,
-        messages: Vec<Message>,
+        mut messages: Vec<Message>,
         tools: Option<Vec<serde_json::Value>>,
     ) -> Result<TokenStream, ProviderError> {
+        if let Some(window) = &self.context_window {
+            messages = window.trim(messages, &self.system_prompt, tools.as_ref());
+        }
         let url = format!(
             "{}/v1beta/models/{}:streamGenerateContent?alt=sse&key={}",
             self.endpoint.trim_end_matches('/'),
@@ -312,7 +430,12 @@ impl Provider for GeminiProvider {
             self.api_key
         );
 
-        let body = build_request_body(&messages, &self.system_prompt, tools.as_ref());
+        let body = build_request_body(
+            &messages,
+            &self.system_prompt,
+            tools.as_ref(),
+            &self.generation,
+        );
 
         let response = self
             .client
@@ -324,11 +447,12 @@ impl Provider for GeminiProvider {
 
         let status = response.status();
         if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
             let body_text = response.text().await.unwrap_or_default();
-            return Err(map_gemini_error(status.as_u16(), &body_text));
+            return Err(map_gemini_error(status.as_u16(), &body_text).with_retry_after(retry_after));
         }
 
-        Ok(parse_gemini_stream(response))
+        Ok(parse_gemini_stream(response, self.model.clone()))
     }
 }
 
@@ -435,7 +559,12 @@ mod tests {
     #[test]
     fn build_request_body_includes_system_instruction() {
         let messages = vec![make_user_message("Hello")];
-        let body = build_request_body(&messages, "You are helpful", None);
+        let body = build_request_body(
+            &messages,
+            "You are helpful",
+            None,
+            &GenerationParams::default(),
+        );
 
         assert!(body.get("systemInstruction").is_some());
         assert_eq!(
@@ -447,11 +576,31 @@ mod tests {
     #[test]
     fn build_request_body_omits_empty_system_prompt() {
         let messages = vec![make_user_message("Hello")];
-        let body = build_request_body(&messages, "", None);
+        let body = build_request_body(&messages, "", None, &GenerationParams::default());
 
         assert!(body.get("systemInstruction").is_none());
     }
 
+    #[test]
+    fn build_request_body_maps_generation_config() {
+        let messages = vec![make_user_message("Hello")];
+        let params = GenerationParams {
+            temperature: Some(1.2),
+            max_tokens: Some(1024),
+            top_p: Some(0.95),
+            stop: Some(vec!["STOP".into()]),
+        };
+        let body = build_request_body(&messages, "", None, &params);
+        let config = &body["generationConfig"];
+        assert_eq!(config["temperature"], 1.2);
+        assert_eq!(config["maxOutputTokens"], 1024);
+        assert_eq!(config["topP"], 0.95);
+        assert_eq!(config["stopSequences"], serde_json::json!(["STOP"]));
+
+        let body = build_request_body(&messages, "", None, &GenerationParams::default());
+        assert!(body.get("generationConfig").is_none());
+    }
+
     #[test]
     fn to_gemini_tools_maps_function_declarations() {
         let tools = vec![serde_json::json!({
@@ -486,6 +635,24 @@ mod tests {
         assert_eq!(tokens[0], Token::Text { text: "Hello".into() });
     }
 
+    #[test]
+    fn parse_gemini_sse_thought_part_is_reasoning() {
+        let line = r#"data: {"candidates":[{"content":{"parts":[{"text":"Considering","thought":true},{"text":"Answer"}]}}]}"#;
+        let tokens = parse_gemini_sse_line(line).unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Reasoning {
+                    text: "Considering".into()
+                },
+                Token::Text {
+                    text: "Answer".into()
+                },
+            ]
+        );
+    }
+
     #[test]
     fn parse_gemini_sse_function_call() {
         let line = r#"data: {"candidates":[{"content":{"parts":[{"functionCall":{"name":"read_file","args":{"path":"test.txt"}}}]}}]}"#;
@@ -507,6 +674,13 @@ mod tests {
         assert!(tokens.is_empty());
     }
 
+    #[test]
+    fn parse_gemini_usage_metadata() {
+        let line = r#"data: {"candidates":[{"content":{"parts":[{"text":"Hi"}],"role":"model"}}],"usageMetadata":{"promptTokenCount":12,"candidatesTokenCount":3,"totalTokenCount":15}}"#;
+        assert_eq!(parse_gemini_usage(line), Some((12, 3)));
+        assert_eq!(parse_gemini_usage(r#"data: {"candidates":[]}"#), None);
+    }
+
     #[test]
     fn map_gemini_error_auth() {
         let body = r#"{"error":{"message":"API key not valid","status":"PERMISSION_DENIED"}}"#;
@@ -520,7 +694,7 @@ mod tests {
     fn map_gemini_error_rate_limit() {
         let body = r#"{"error":{"message":"Quota exceeded","status":"RESOURCE_EXHAUSTED"}}"#;
         match map_gemini_error(429, body) {
-            ProviderError::RateLimit(msg) => assert!(msg.contains("Quota")),
+            ProviderError::RateLimit { message: msg, .. } => assert!(msg.contains("Quota")),
             other => panic!("expected RateLimit error, got: {other:?}"),
         }
     }
@@ -551,7 +725,12 @@ api_key_env = "GEMINI_API_KEY"
             make_user_message("Hello"),
             make_assistant_message("Hi there"),
         ];
-        let body = build_request_body(&messages, "You are a test assistant", None);
+        let body = build_request_body(
+            &messages,
+            "You are a test assistant",
+            None,
+            &GenerationParams::default(),
+        );
 
         // System message in systemInstruction
         assert_eq!(
@@ -574,7 +753,7 @@ api_key_env = "GEMINI_API_KEY"
             make_user_message("First message"),
             make_user_message("Second message"),
         ];
-        let body = build_request_body(&messages, "", None);
+        let body = build_request_body(&messages, "", None, &GenerationParams::default());
 
         let contents = body["contents"].as_array().unwrap();
         assert_eq!(contents.len(), 1);
@@ -586,6 +765,29 @@ api_key_env = "GEMINI_API_KEY"
         assert_eq!(parts[1]["text"], "Second message");
     }
 
+    #[test]
+    fn build_gemini_request_maps_image_to_inline_data() {
+        let messages = vec![
+            Message {
+                role: Role::User,
+                content: MessageContent::Image {
+                    blob_id: "blob-1".into(),
+                    mime_type: "image/png".into(),
+                    data: Some("iVBORw0K".into()),
+                },
+                timestamp: Utc::now(),
+            },
+            make_user_message("What is this?"),
+        ];
+        let body = build_request_body(&messages, "", None, &GenerationParams::default());
+
+        let parts = body["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["inline_data"]["mime_type"], "image/png");
+        assert_eq!(parts[0]["inline_data"]["data"], "iVBORw0K");
+        assert_eq!(parts[1]["text"], "What is this?");
+    }
+
     #[test]
     fn build_gemini_request_with_tools() {
         let messages = vec![make_user_message("Read file")];
@@ -604,7 +806,7 @@ api_key_env = "GEMINI_API_KEY"
             }
         })];
 
-        let body = build_request_body(&messages, "", Some(&tools));
+        let body = build_request_body(&messages, "", Some(&tools), &GenerationParams::default());
 
         assert!(body.get("tools").is_some());
         let tool_array = body["tools"].as_array().unwrap();
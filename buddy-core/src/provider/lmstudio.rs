@@ -1,7 +1,12 @@
 use crate::types::Message;
 use reqwest::Client;
 
-use crate::provider::openai::{build_request_body, map_error_status, parse_sse_stream};
+use crate::provider::openai::{
+    build_request_body, map_error_status, parse_sse_stream, request_stream_usage,
+};
+use crate::config::GenerationParams;
+use crate::context::{ContextWindow, TokenizerFamily};
+use crate::provider::retry::parse_retry_after;
 use crate::provider::{Provider, ProviderError, TokenStream};
 
 /// LM Studio provider. Connects to a local LM Studio server using its
@@ -11,6 +16,8 @@ pub struct LmStudioProvider {
     model: String,
     endpoint: String,
     system_prompt: String,
+    context_window: Option<ContextWindow>,
+    generation: GenerationParams,
 }
 
 impl LmStudioProvider {
@@ -23,21 +30,45 @@ impl LmStudioProvider {
             model: model.to_string(),
             endpoint: endpoint.to_string(),
             system_prompt: system_prompt.to_string(),
+            context_window: None,
+            generation: GenerationParams::default(),
         }
     }
+
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(mut self, max_tokens: usize) -> Self {
+        self.context_window = Some(ContextWindow::new(max_tokens, TokenizerFamily::Local));
+        self
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(mut self, generation: GenerationParams) -> Self {
+        self.generation = generation;
+        self
+    }
 }
 
 impl Provider for LmStudioProvider {
     async fn complete(
         &self,
-        messages: Vec<Message>,
+        mut messages: Vec<Message>,
         tools: Option<Vec<serde_json::Value>>,
     ) -> Result<TokenStream, ProviderError> {
+        if let Some(window) = &self.context_window {
+            messages = window.trim(messages, &self.system_prompt, tools.as_ref());
+        }
         let url = format!(
             "{}/chat/completions",
             self.endpoint.trim_end_matches('/')
         );
-        let body = build_request_body(&messages, &self.model, &self.system_prompt, tools.as_ref());
+        let mut body = build_request_body(
+            &messages,
+            &self.model,
+            &self.system_prompt,
+            tools.as_ref(),
+            &self.generation,
+        );
+        request_stream_usage(&mut body);
 
         let response = self
             .client
@@ -49,11 +80,12 @@ impl Provider for LmStudioProvider {
 
         let status = response.status();
         if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
             let body_text = response.text().await.unwrap_or_default();
-            return Err(map_error_status(status.as_u16(), &body_text));
+            return Err(map_error_status(status.as_u16(), &body_text).with_retry_after(retry_after));
         }
 
-        Ok(parse_sse_stream(response))
+        Ok(parse_sse_stream(response, "lmstudio", self.model.clone()))
     }
 }
 
@@ -83,7 +115,13 @@ mod tests {
     #[test]
     fn request_body_matches_openai_compatible_spec() {
         let messages = make_messages();
-        let body = build_request_body(&messages, "deepseek-coder", "You are helpful.", None);
+        let body = build_request_body(
+            &messages,
+            "deepseek-coder",
+            "You are helpful.",
+            None,
+            &GenerationParams::default(),
+        );
 
         assert_eq!(body["model"], "deepseek-coder");
         assert_eq!(body["stream"], true);
@@ -162,7 +200,7 @@ mod tests {
     fn error_status_429_maps_to_rate_limit() {
         let body = r#"{"error":{"message":"Too many requests","type":"rate_limit","code":"rate_limit"}}"#;
         match map_error_status(429, body) {
-            ProviderError::RateLimit(msg) => assert_eq!(msg, "Too many requests"),
+            ProviderError::RateLimit { message: msg, .. } => assert_eq!(msg, "Too many requests"),
             other => panic!("expected RateLimit, got: {other:?}"),
         }
     }
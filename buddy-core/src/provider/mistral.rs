@@ -2,6 +2,9 @@ use crate::types::Message;
 use reqwest::Client;
 
 use crate::provider::openai::{build_request_body, map_error_status, parse_sse_stream};
+use crate::config::GenerationParams;
+use crate::context::{ContextWindow, TokenizerFamily};
+use crate::provider::retry::parse_retry_after;
 use crate::provider::{Provider, ProviderError, TokenStream};
 
 /// Mistral AI provider. Uses Mistral's OpenAI-compatible API endpoint.
@@ -12,6 +15,8 @@ pub struct MistralProvider {
     model: String,
     endpoint: String,
     system_prompt: String,
+    context_window: Option<ContextWindow>,
+    generation: GenerationParams,
 }
 
 impl MistralProvider {
@@ -25,21 +30,44 @@ impl MistralProvider {
             model: model.to_string(),
             endpoint: endpoint.to_string(),
             system_prompt: system_prompt.to_string(),
+            context_window: None,
+            generation: GenerationParams::default(),
         }
     }
+
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(mut self, max_tokens: usize) -> Self {
+        self.context_window = Some(ContextWindow::new(max_tokens, TokenizerFamily::Mistral));
+        self
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(mut self, generation: GenerationParams) -> Self {
+        self.generation = generation;
+        self
+    }
 }
 
 impl Provider for MistralProvider {
     async fn complete(
         &self,
-        messages: Vec<Message>,
+        mut messages: Vec<Message>,
         tools: Option<Vec<serde_json::Value>>,
     ) -> Result<TokenStream, ProviderError> {
+        if let Some(window) = &self.context_window {
+            messages = window.trim(messages, &self.system_prompt, tools.as_ref());
+        }
         let url = format!(
             "{}/v1/chat/completions",
             self.endpoint.trim_end_matches('/')
         );
-        let body = build_request_body(&messages, &self.model, &self.system_prompt, tools.as_ref());
+        let body = build_request_body(
+            &messages,
+            &self.model,
+            &self.system_prompt,
+            tools.as_ref(),
+            &self.generation,
+        );
 
         let response = self
             .client
@@ -52,11 +80,12 @@ impl Provider for MistralProvider {
 
         let status = response.status();
         if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
             let body_text = response.text().await.unwrap_or_default();
-            return Err(map_error_status(status.as_u16(), &body_text));
+            return Err(map_error_status(status.as_u16(), &body_text).with_retry_after(retry_after));
         }
 
-        Ok(parse_sse_stream(response))
+        Ok(parse_sse_stream(response, "mistral", self.model.clone()))
     }
 }
 
@@ -89,6 +118,7 @@ mod tests {
             "mistral-large-latest",
             "You are helpful.",
             None,
+            &GenerationParams::default(),
         );
 
         assert_eq!(body["model"], "mistral-large-latest");
@@ -168,7 +198,7 @@ mod tests {
     fn error_status_429_maps_to_rate_limit() {
         let body = r#"{"error":{"message":"Rate limit exceeded","type":"rate_limit_error","code":"rate_limit"}}"#;
         match map_error_status(429, body) {
-            ProviderError::RateLimit(msg) => assert_eq!(msg, "Rate limit exceeded"),
+            ProviderError::RateLimit { message: msg, .. } => assert_eq!(msg, "Rate limit exceeded"),
             other => panic!("expected RateLimit, got: {other:?}"),
         }
     }
@@ -1,22 +1,32 @@
+pub mod anthropic;
 pub mod gemini;
 pub mod lmstudio;
 pub mod mistral;
 pub mod ollama;
 pub mod openai;
+pub mod retry;
+mod tool_emulation;
 
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::types::Message;
+use crate::config::{GenerationParams, ToolMode};
+use crate::types::{Message, MessageContent};
 use futures_core::Stream;
 use futures_util::StreamExt;
+use retry::RetryPolicy;
 
 /// A chunk of streamed LLM output.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     /// A text content delta.
     Text { text: String },
+    /// A delta of the model's reasoning, streamed separately from the answer
+    /// by models that think before replying.
+    Reasoning { text: String },
     /// The LLM is requesting a tool execution.
     ToolCall {
         id: String,
@@ -25,6 +35,17 @@ pub enum Token {
     },
     /// A non-fatal warning (e.g. provider fallback occurred).
     Warning { message: String },
+    /// The stream failed partway and is continuing on another provider:
+    /// discard any text streamed so far for this completion.
+    Reset { message: String },
+    /// Token counts reported by the provider for this completion, attributed
+    /// to the provider type and model that served it.
+    Usage {
+        provider: String,
+        model: String,
+        prompt: u32,
+        completion: u32,
+    },
 }
 
 /// Errors that can occur when calling an LLM provider.
@@ -34,8 +55,12 @@ pub enum ProviderError {
     Network(String),
     /// Authentication failure (invalid or expired API key)
     Auth(String),
-    /// Rate limit exceeded
-    RateLimit(String),
+    /// Rate limit exceeded (or the service is overloaded). `retry_after` is
+    /// the wait the server asked for, when it sent one.
+    RateLimit {
+        message: String,
+        retry_after: Option<Duration>,
+    },
     /// Response could not be parsed
     MalformedResponse(String),
     /// Any other error
@@ -47,7 +72,7 @@ impl std::fmt::Display for ProviderError {
         match self {
             Self::Network(msg) => write!(f, "network error: {msg}"),
             Self::Auth(msg) => write!(f, "auth error: {msg}"),
-            Self::RateLimit(msg) => write!(f, "rate limited: {msg}"),
+            Self::RateLimit { message, .. } => write!(f, "rate limited: {message}"),
             Self::MalformedResponse(msg) => write!(f, "malformed response: {msg}"),
             Self::Other(msg) => write!(f, "{msg}"),
         }
@@ -56,6 +81,28 @@ impl std::fmt::Display for ProviderError {
 
 impl std::error::Error for ProviderError {}
 
+impl ProviderError {
+    /// Attach a `Retry-After` duration to a `RateLimit` error; other errors
+    /// are returned unchanged.
+    pub fn with_retry_after(self, wait: Option<Duration>) -> Self {
+        match self {
+            Self::RateLimit {
+                message,
+                retry_after,
+            } => Self::RateLimit {
+                message,
+                retry_after: wait.or(retry_after),
+            },
+            other => other,
+        }
+    }
+
+    /// Whether the error is transient and worth retrying or falling back on.
+    fn is_transient(&self) -> bool {
+        matches!(self, Self::Network(_) | Self::RateLimit { .. })
+    }
+}
+
 /// A stream of tokens from an LLM provider.
 pub type TokenStream = Pin<Box<dyn Stream<Item = Result<Token, ProviderError>> + Send>>;
 
@@ -77,6 +124,33 @@ pub enum AnyProvider {
     Mistral(mistral::MistralProvider),
     Ollama(ollama::OllamaProvider),
     Gemini(gemini::GeminiProvider),
+    Anthropic(anthropic::AnthropicProvider),
+}
+
+impl AnyProvider {
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(self, max_tokens: usize) -> Self {
+        match self {
+            Self::OpenAi(p) => Self::OpenAi(p.with_context_window(max_tokens)),
+            Self::LmStudio(p) => Self::LmStudio(p.with_context_window(max_tokens)),
+            Self::Mistral(p) => Self::Mistral(p.with_context_window(max_tokens)),
+            Self::Ollama(p) => Self::Ollama(p.with_context_window(max_tokens)),
+            Self::Gemini(p) => Self::Gemini(p.with_context_window(max_tokens)),
+            Self::Anthropic(p) => Self::Anthropic(p.with_context_window(max_tokens)),
+        }
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(self, generation: GenerationParams) -> Self {
+        match self {
+            Self::OpenAi(p) => Self::OpenAi(p.with_generation_params(generation)),
+            Self::LmStudio(p) => Self::LmStudio(p.with_generation_params(generation)),
+            Self::Mistral(p) => Self::Mistral(p.with_generation_params(generation)),
+            Self::Ollama(p) => Self::Ollama(p.with_generation_params(generation)),
+            Self::Gemini(p) => Self::Gemini(p.with_generation_params(generation)),
+            Self::Anthropic(p) => Self::Anthropic(p.with_generation_params(generation)),
+        }
+    }
 }
 
 impl Provider for AnyProvider {
@@ -91,91 +165,406 @@ impl Provider for AnyProvider {
             Self::Mistral(p) => p.complete(messages, tools).await,
             Self::Ollama(p) => p.complete(messages, tools).await,
             Self::Gemini(p) => p.complete(messages, tools).await,
+            Self::Anthropic(p) => p.complete(messages, tools).await,
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of the
+/// ASCII `tag`: streamed text that may turn out to be the start of the tag.
+pub(crate) fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&n| text.ends_with(&tag[..n]))
+        .unwrap_or(0)
+}
+
+/// Sent in place of an image whose bytes could not be loaded.
+pub(crate) const IMAGE_UNAVAILABLE: &str = "[image unavailable]";
+
+/// Sent in place of an image when the model cannot accept images.
+const IMAGE_OMITTED: &str = "[The user attached an image, but this model cannot view images.]";
+
+/// Model name fragments of OpenAI models that accept images.
+const OPENAI_VISION_MODELS: &[&str] = &["gpt-4o", "gpt-4.1", "gpt-4-turbo", "gpt-5", "o1", "o3", "o4"];
+
+/// Model name fragments that mark open-weight models as vision models.
+const LOCAL_VISION_MARKERS: &[&str] =
+    &["llava", "vision", "-vl", "gemma3", "minicpm-v", "moondream", "pixtral"];
+
+/// Best guess at whether `model` accepts image input, used when a
+/// `ProviderEntry` does not set `vision`. Gemini and Claude models all do;
+/// other models are recognised by name.
+pub fn default_vision(provider_type: &str, model: &str) -> bool {
+    let model = model.to_ascii_lowercase();
+    let matches = |names: &[&str]| names.iter().any(|name| model.contains(name));
+    match provider_type {
+        "gemini" | "anthropic" => true,
+        "mistral" => matches(&["pixtral", "mistral-small", "mistral-medium"]),
+        "ollama" | "lmstudio" => matches(LOCAL_VISION_MARKERS),
+        _ => {
+            OPENAI_VISION_MODELS.iter().any(|name| model.starts_with(name))
+                || matches(LOCAL_VISION_MARKERS)
         }
     }
 }
 
+/// Replace every image in `messages` with a note saying it was left out.
+fn omit_images(messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| match message.content {
+            MessageContent::Image { .. } => Message {
+                content: MessageContent::Text {
+                    text: IMAGE_OMITTED.to_string(),
+                },
+                ..message.clone()
+            },
+            _ => message.clone(),
+        })
+        .collect()
+}
+
+/// How long the chain stays on a fallback before trying the primary again.
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Ordered list of providers with automatic fallback on transient errors.
 ///
-/// Tries providers in order. On `Network` or `RateLimit` errors, advances to
-/// the next provider. On `Auth` or `MalformedResponse` errors, stops
-/// immediately (configuration problems should not be masked). If all providers
-/// fail, returns the last error.
+/// Each provider is retried according to its `RetryPolicy` on `Network` or
+/// `RateLimit` errors before the chain advances to the next provider. On
+/// `Auth` or `MalformedResponse` errors, stops immediately (configuration
+/// problems should not be masked). If all providers fail, returns the last
+/// error.
+///
+/// If a stream fails with a transient error after it has started, the turn is
+/// re-run on the next provider: the stream yields `Token::Reset` and then the
+/// new provider's output.
+///
+/// After falling back, requests go straight to the provider that last
+/// succeeded; once per probe interval the primary is tried again (without
+/// retries) so the chain returns to it when it recovers.
+///
+/// Images are only sent to providers marked as accepting them; for the others
+/// they are replaced by a note and the stream starts with a `Token::Warning`.
+/// Stored reasoning is likewise only sent to providers that opt in to it.
+/// Providers in emulated tool mode get the tools described in a system
+/// message instead of the `tools` field, and tool calls are parsed out of
+/// their text.
+///
+/// State is shared behind `Arc`s so streams can outlive the `complete` call
+/// that created them and still fail over.
 pub struct ProviderChain<P> {
-    providers: Vec<(P, String)>,
+    providers: Arc<Vec<(P, String)>>,
+    retry: Arc<Vec<RetryPolicy>>,
+    vision: Arc<Vec<bool>>,
+    replay_reasoning: Arc<Vec<bool>>,
+    tool_modes: Arc<Vec<ToolMode>>,
     /// Index of the last provider that completed successfully. Subsequent
     /// requests start here to avoid repeatedly timing out on a known-bad
     /// provider.
-    last_ok: AtomicUsize,
+    last_ok: Arc<AtomicUsize>,
+    /// When the primary was last given up on or probed.
+    last_probe: Arc<Mutex<Option<Instant>>>,
+    probe_interval: Duration,
+}
+
+impl<P> Clone for ProviderChain<P> {
+    fn clone(&self) -> Self {
+        Self {
+            providers: Arc::clone(&self.providers),
+            retry: Arc::clone(&self.retry),
+            vision: Arc::clone(&self.vision),
+            replay_reasoning: Arc::clone(&self.replay_reasoning),
+            tool_modes: Arc::clone(&self.tool_modes),
+            last_ok: Arc::clone(&self.last_ok),
+            last_probe: Arc::clone(&self.last_probe),
+            probe_interval: self.probe_interval,
+        }
+    }
 }
 
 impl<P: Provider> ProviderChain<P> {
+    /// Build a chain whose providers are not retried, are all sent images,
+    /// are never sent stored reasoning and use native tool calling; see
+    /// [`with_retry_policies`](Self::with_retry_policies),
+    /// [`with_vision`](Self::with_vision),
+    /// [`with_reasoning_replay`](Self::with_reasoning_replay) and
+    /// [`with_tool_modes`](Self::with_tool_modes).
     pub fn new(providers: Vec<(P, String)>) -> Self {
         assert!(!providers.is_empty(), "ProviderChain requires at least one provider");
+        let retry = vec![RetryPolicy::none(); providers.len()];
+        let vision = vec![true; providers.len()];
+        let replay_reasoning = vec![false; providers.len()];
+        let tool_modes = vec![ToolMode::Native; providers.len()];
         Self {
-            providers,
-            last_ok: AtomicUsize::new(0),
+            providers: Arc::new(providers),
+            retry: Arc::new(retry),
+            vision: Arc::new(vision),
+            replay_reasoning: Arc::new(replay_reasoning),
+            tool_modes: Arc::new(tool_modes),
+            last_ok: Arc::new(AtomicUsize::new(0)),
+            last_probe: Arc::new(Mutex::new(None)),
+            probe_interval: DEFAULT_PROBE_INTERVAL,
         }
     }
 
+    /// Retry each provider according to the policy at the same index.
+    pub fn with_retry_policies(mut self, policies: Vec<RetryPolicy>) -> Self {
+        assert_eq!(
+            policies.len(),
+            self.providers.len(),
+            "one retry policy is required per provider"
+        );
+        self.retry = Arc::new(policies);
+        self
+    }
+
+    /// Mark which providers accept images, one flag per provider.
+    pub fn with_vision(mut self, vision: Vec<bool>) -> Self {
+        assert_eq!(
+            vision.len(),
+            self.providers.len(),
+            "one vision flag is required per provider"
+        );
+        self.vision = Arc::new(vision);
+        self
+    }
+
+    /// Mark which providers are sent the reasoning stored from earlier
+    /// turns, one flag per provider. Reasoning is dropped for the others.
+    pub fn with_reasoning_replay(mut self, replay: Vec<bool>) -> Self {
+        assert_eq!(
+            replay.len(),
+            self.providers.len(),
+            "one reasoning replay flag is required per provider"
+        );
+        self.replay_reasoning = Arc::new(replay);
+        self
+    }
+
+    /// Set how each provider is offered tools, one mode per provider.
+    pub fn with_tool_modes(mut self, modes: Vec<ToolMode>) -> Self {
+        assert_eq!(
+            modes.len(),
+            self.providers.len(),
+            "one tool mode is required per provider"
+        );
+        self.tool_modes = Arc::new(modes);
+        self
+    }
+
+    /// Re-probe the primary at most once per `interval` while on a fallback.
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
     /// Returns the number of providers in the chain.
     pub fn len(&self) -> usize {
         self.providers.len()
     }
-}
 
-impl<P: Provider> Provider for ProviderChain<P> {
-    async fn complete(
+    /// Whether it is time to try the primary again, recording the probe.
+    fn take_probe(&self) -> bool {
+        let mut last_probe = self.last_probe.lock().unwrap();
+        match *last_probe {
+            Some(at) if at.elapsed() >= self.probe_interval => {
+                *last_probe = Some(Instant::now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The warning to show when provider `index` is sent `messages` with
+    /// their images left out, if it was.
+    fn image_warning(&self, index: usize, messages: &[Message]) -> Option<Token> {
+        let has_images = messages
+            .iter()
+            .any(|m| matches!(m.content, MessageContent::Image { .. }));
+        (has_images && !self.vision[index]).then(|| Token::Warning {
+            message: format!(
+                "{} cannot view images, so the attached images were left out",
+                self.providers[index].1
+            ),
+        })
+    }
+
+    /// Remember that provider `index` served a request.
+    fn record_success(&self, index: usize, previous: usize) {
+        self.last_ok.store(index, Ordering::Relaxed);
+        if index == 0 {
+            *self.last_probe.lock().unwrap() = None;
+        } else if previous == 0 {
+            *self.last_probe.lock().unwrap() = Some(Instant::now());
+        }
+    }
+
+    /// Call provider `index`, retrying transient errors per `policy`.
+    async fn complete_with_retries(
         &self,
-        messages: Vec<Message>,
-        tools: Option<Vec<serde_json::Value>>,
+        index: usize,
+        policy: RetryPolicy,
+        messages: &[Message],
+        tools: &Option<Vec<serde_json::Value>>,
     ) -> Result<TokenStream, ProviderError> {
-        let start = self.last_ok.load(Ordering::Relaxed);
-
-        // Try the last-known-good provider first.
-        let (ref provider, ref name) = self.providers[start];
-        let mut last_error = match provider.complete(messages.clone(), tools.clone()).await {
-            Ok(stream) => return Ok(stream),
-            Err(e) => match &e {
-                ProviderError::Network(_) | ProviderError::RateLimit(_) => {
-                    eprintln!("Provider {start} ({name}) failed: {e}, trying next");
-                    e
-                }
-                _ => return Err(e),
-            },
+        let (provider, name) = &self.providers[index];
+        let messages: Vec<Message> = messages
+            .iter()
+            .filter(|m| {
+                self.replay_reasoning[index]
+                    || !matches!(m.content, MessageContent::Reasoning { .. })
+            })
+            .cloned()
+            .collect();
+        let messages = if self.vision[index] {
+            messages
+        } else {
+            omit_images(&messages)
         };
-
-        // Last-known-good failed — try remaining providers in order, skipping
-        // the one we already tried.
-        for (i, (provider, name)) in self.providers.iter().enumerate() {
-            if i == start {
-                continue;
-            }
+        let emulated = self.tool_modes[index] == ToolMode::Emulated;
+        let (messages, tools) = if emulated {
+            (tool_emulation::emulate_tools(&messages, tools.as_deref()), None)
+        } else {
+            (messages, tools.clone())
+        };
+        let mut attempt = 0;
+        loop {
             match provider.complete(messages.clone(), tools.clone()).await {
-                Ok(stream) => {
-                    self.last_ok.store(i, Ordering::Relaxed);
-                    if i > 0 {
-                        let warning_msg =
-                            format!("Primary model unavailable, using fallback: {name}");
-                        eprintln!("{warning_msg}");
-                        let warning_stream = futures_util::stream::once(async move {
-                            Ok(Token::Warning { message: warning_msg })
-                        });
-                        return Ok(Box::pin(warning_stream.chain(stream)));
+                Ok(stream) if emulated => return Ok(tool_emulation::parse_tool_calls(stream)),
+                Ok(stream) => return Ok(stream),
+                Err(e) => match policy.delay_for(attempt, &e) {
+                    Some(delay) => {
+                        eprintln!("Provider {index} ({name}) failed: {e}, retrying in {delay:?}");
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
                     }
-                    return Ok(stream);
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// Open a stream on the first provider in `order` that accepts the
+    /// request, returning its index. `probe` marks a single-attempt probe of
+    /// the primary.
+    async fn open_stream(
+        &self,
+        order: impl IntoIterator<Item = usize>,
+        probe: bool,
+        messages: &[Message],
+        tools: &Option<Vec<serde_json::Value>>,
+    ) -> Result<(usize, TokenStream), ProviderError> {
+        let mut last_error = None;
+        for i in order {
+            let policy = if probe && i == 0 {
+                RetryPolicy::none()
+            } else {
+                self.retry[i]
+            };
+            match self.complete_with_retries(i, policy, messages, tools).await {
+                Ok(stream) => return Ok((i, stream)),
+                Err(e) if e.is_transient() => {
+                    eprintln!("Provider {i} ({}) failed: {e}, trying next", self.providers[i].1);
+                    last_error = Some(e);
                 }
-                Err(e) => match &e {
-                    ProviderError::Network(_) | ProviderError::RateLimit(_) => {
-                        eprintln!("Provider {i} ({name}) failed: {e}, trying next");
-                        last_error = e;
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("open_stream called with at least one provider"))
+    }
+}
+
+impl<P: Provider + 'static> ProviderChain<P> {
+    /// Forward `stream` from provider `index`; on a transient mid-stream
+    /// error, re-run the request on the providers not yet tried this turn
+    /// and yield `Token::Reset` before the replacement output.
+    fn with_failover(
+        self,
+        index: usize,
+        stream: TokenStream,
+        messages: Vec<Message>,
+        tools: Option<Vec<serde_json::Value>>,
+    ) -> TokenStream {
+        let stream = async_stream::try_stream! {
+            let mut current = index;
+            let mut stream = stream;
+            let mut failed = vec![index];
+            loop {
+                let mut interrupted = None;
+                while let Some(item) = stream.next().await {
+                    match item {
+                        Ok(token) => yield token,
+                        Err(e) if e.is_transient() => {
+                            interrupted = Some(e);
+                            break;
+                        }
+                        Err(e) => Err(e)?,
                     }
-                    _ => return Err(e),
-                },
+                }
+                let Some(error) = interrupted else { break };
+
+                let failed_name = self.providers[current].1.clone();
+                eprintln!("Provider {current} ({failed_name}) failed mid-stream: {error}");
+                let remaining: Vec<usize> =
+                    (0..self.providers.len()).filter(|i| !failed.contains(i)).collect();
+                if remaining.is_empty() {
+                    Err(error)?;
+                }
+                let (next, next_stream) =
+                    self.open_stream(remaining, false, &messages, &tools).await?;
+                self.record_success(next, current);
+                failed.push(next);
+                current = next;
+                stream = next_stream;
+                yield Token::Reset {
+                    message: format!(
+                        "Connection to {failed_name} was lost, retrying with {}",
+                        self.providers[next].1
+                    ),
+                };
+                if let Some(warning) = self.image_warning(next, &messages) {
+                    yield warning;
+                }
             }
+        };
+        Box::pin(stream)
+    }
+}
+
+impl<P: Provider + 'static> Provider for ProviderChain<P> {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<serde_json::Value>>,
+    ) -> Result<TokenStream, ProviderError> {
+        let sticky = self.last_ok.load(Ordering::Relaxed);
+        let probing = sticky != 0 && self.take_probe();
+        let start = if probing { 0 } else { sticky };
+
+        // Try the starting provider first, then the rest in order.
+        let order = std::iter::once(start).chain((0..self.providers.len()).filter(|&i| i != start));
+        let (i, stream) = self.open_stream(order, probing, &messages, &tools).await?;
+        self.record_success(i, sticky);
+        if i == 0 && probing {
+            eprintln!("Primary model recovered: {}", self.providers[0].1);
+        }
+        let mut warnings = Vec::new();
+        if i > 0 && i != sticky {
+            let warning_msg = format!(
+                "Primary model unavailable, using fallback: {}",
+                self.providers[i].1
+            );
+            eprintln!("{warning_msg}");
+            warnings.push(Ok(Token::Warning { message: warning_msg }));
         }
+        warnings.extend(self.image_warning(i, &messages).map(Ok));
+        let stream = self.clone().with_failover(i, stream, messages, tools);
 
-        Err(last_error)
+        if warnings.is_empty() {
+            return Ok(stream);
+        }
+        Ok(Box::pin(futures_util::stream::iter(warnings).chain(stream)))
     }
 }
 
@@ -223,7 +612,10 @@ mod tests {
             _messages: Vec<Message>,
             _tools: Option<Vec<serde_json::Value>>,
         ) -> Result<TokenStream, ProviderError> {
-            Err(ProviderError::RateLimit("429 too many requests".into()))
+            Err(ProviderError::RateLimit {
+                message: "429 too many requests".into(),
+                retry_after: None,
+            })
         }
     }
 
@@ -239,6 +631,51 @@ mod tests {
         }
     }
 
+    /// Fails with a copy of `error` for the first `failures` calls, then
+    /// succeeds with `text`.
+    struct FlakyMock {
+        failures: AtomicUsize,
+        calls: AtomicUsize,
+        error: fn() -> ProviderError,
+        text: &'static str,
+    }
+
+    impl Provider for FlakyMock {
+        async fn complete(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<serde_json::Value>>,
+        ) -> Result<TokenStream, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err((self.error)());
+            }
+            let text = self.text.to_string();
+            Ok(Box::pin(futures_util::stream::once(async move { Ok(Token::Text { text }) })))
+        }
+    }
+
+    /// Streams `text`, then the connection drops.
+    struct BrokenStreamMock {
+        text: &'static str,
+    }
+
+    impl Provider for BrokenStreamMock {
+        async fn complete(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<serde_json::Value>>,
+        ) -> Result<TokenStream, ProviderError> {
+            let text = self.text.to_string();
+            Ok(Box::pin(futures_util::stream::iter(vec![
+                Ok(Token::Text { text }),
+                Err(network_error()),
+            ])))
+        }
+    }
+
     // ProviderChain needs all elements to be the same type. Use an enum to
     // allow mixing different behaviours in a single chain.
     enum FlexMock {
@@ -246,6 +683,8 @@ mod tests {
         NetworkFail(NetworkFailMock),
         RateLimitFail(RateLimitFailMock),
         AuthFail(AuthFailMock),
+        Flaky(FlakyMock),
+        BrokenStream(BrokenStreamMock),
     }
 
     impl Provider for FlexMock {
@@ -259,6 +698,8 @@ mod tests {
                 Self::NetworkFail(p) => p.complete(messages, tools).await,
                 Self::RateLimitFail(p) => p.complete(messages, tools).await,
                 Self::AuthFail(p) => p.complete(messages, tools).await,
+                Self::Flaky(p) => p.complete(messages, tools).await,
+                Self::BrokenStream(p) => p.complete(messages, tools).await,
             }
         }
     }
@@ -284,6 +725,41 @@ mod tests {
         (FlexMock::AuthFail(AuthFailMock), "auth-fail-model".into())
     }
 
+    fn flex_flaky(failures: usize, error: fn() -> ProviderError, text: &'static str) -> (FlexMock, String) {
+        (
+            FlexMock::Flaky(FlakyMock {
+                failures: AtomicUsize::new(failures),
+                calls: AtomicUsize::new(0),
+                error,
+                text,
+            }),
+            format!("{text}-model"),
+        )
+    }
+
+    fn flex_broken_stream(text: &'static str) -> (FlexMock, String) {
+        (FlexMock::BrokenStream(BrokenStreamMock { text }), "broken-model".into())
+    }
+
+    fn flaky_calls(chain: &ProviderChain<FlexMock>, index: usize) -> usize {
+        match &chain.providers[index].0 {
+            FlexMock::Flaky(p) => p.calls.load(Ordering::SeqCst),
+            _ => panic!("provider {index} is not flaky"),
+        }
+    }
+
+    fn network_error() -> ProviderError {
+        ProviderError::Network("connection reset".into())
+    }
+
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(50),
+        }
+    }
+
     /// Consume a TokenStream and return all tokens.
     async fn collect_tokens(stream: TokenStream) -> Vec<Token> {
         tokio::pin!(stream);
@@ -406,4 +882,263 @@ mod tests {
         let tokens = collect_tokens(stream).await;
         assert_eq!(tokens, vec![Token::Text { text: "fallback".into() }]);
     }
+
+    #[tokio::test]
+    async fn transient_error_retried_on_same_provider() {
+        let chain = ProviderChain::new(vec![
+            flex_flaky(2, network_error, "primary"),
+            flex_success(vec!["fallback"]),
+        ])
+        .with_retry_policies(vec![fast_retries(2), fast_retries(2)]);
+
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "primary".into() }]);
+        assert_eq!(flaky_calls(&chain, 0), 3);
+        assert_eq!(chain.last_ok.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn falls_back_once_retries_are_exhausted() {
+        let chain = ProviderChain::new(vec![
+            flex_flaky(5, network_error, "primary"),
+            flex_success(vec!["fallback"]),
+        ])
+        .with_retry_policies(vec![fast_retries(1), fast_retries(1)]);
+
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert!(matches!(&tokens[0], Token::Warning { .. }));
+        assert_eq!(tokens[1], Token::Text { text: "fallback".into() });
+        assert_eq!(flaky_calls(&chain, 0), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_waits_for_retry_after() {
+        fn limited() -> ProviderError {
+            ProviderError::RateLimit {
+                message: "slow down".into(),
+                retry_after: Some(Duration::from_millis(40)),
+            }
+        }
+        let chain = ProviderChain::new(vec![flex_flaky(1, limited, "primary")])
+            .with_retry_policies(vec![fast_retries(1)]);
+
+        let started = Instant::now();
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "primary".into() }]);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_moves_to_fallback() {
+        fn limited() -> ProviderError {
+            ProviderError::RateLimit {
+                message: "slow down".into(),
+                retry_after: Some(Duration::from_secs(3600)),
+            }
+        }
+        let chain = ProviderChain::new(vec![
+            flex_flaky(1, limited, "primary"),
+            flex_success(vec!["fallback"]),
+        ])
+        .with_retry_policies(vec![fast_retries(3), fast_retries(3)]);
+
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens[1], Token::Text { text: "fallback".into() });
+        assert_eq!(flaky_calls(&chain, 0), 1);
+    }
+
+    #[tokio::test]
+    async fn primary_reprobed_after_interval() {
+        let chain = ProviderChain::new(vec![
+            flex_flaky(1, network_error, "primary"),
+            flex_success(vec!["fallback"]),
+        ])
+        .with_probe_interval(Duration::from_millis(20));
+
+        // Primary fails once: fall back.
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens[1], Token::Text { text: "fallback".into() });
+
+        // Within the interval: stay on the fallback without touching the primary.
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "fallback".into() }]);
+        assert_eq!(flaky_calls(&chain, 0), 1);
+
+        // After the interval: the recovered primary is used again.
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "primary".into() }]);
+        assert_eq!(chain.last_ok.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn failed_probe_stays_on_fallback_without_warning() {
+        let chain = ProviderChain::new(vec![
+            flex_flaky(usize::MAX, network_error, "primary"),
+            flex_success(vec!["fallback"]),
+        ])
+        .with_retry_policies(vec![fast_retries(2), fast_retries(2)])
+        .with_probe_interval(Duration::ZERO);
+
+        collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(flaky_calls(&chain, 0), 3);
+
+        // The probe is a single attempt, and the user already saw the warning.
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "fallback".into() }]);
+        assert_eq!(flaky_calls(&chain, 0), 4);
+    }
+
+    #[test]
+    fn with_retry_after_only_touches_rate_limits() {
+        let wait = Some(Duration::from_secs(3));
+        let err = ProviderError::RateLimit {
+            message: "x".into(),
+            retry_after: None,
+        }
+        .with_retry_after(wait);
+        assert!(matches!(err, ProviderError::RateLimit { retry_after, .. } if retry_after == wait));
+        let err = ProviderError::Auth("x".into()).with_retry_after(wait);
+        assert!(matches!(err, ProviderError::Auth(_)));
+    }
+
+    #[tokio::test]
+    async fn mid_stream_failure_resets_and_continues_on_fallback() {
+        let chain = ProviderChain::new(vec![
+            flex_broken_stream("partial"),
+            flex_success(vec!["complete"]),
+        ]);
+        let tokens = collect_tokens(chain.complete(vec![], None).await.unwrap()).await;
+        assert_eq!(tokens[0], Token::Text { text: "partial".into() });
+        assert!(matches!(
+            &tokens[1],
+            Token::Reset { message }
+                if message.contains("broken-model") && message.contains("success-model")
+        ));
+        assert_eq!(tokens[2], Token::Text { text: "complete".into() });
+        assert_eq!(tokens.len(), 3);
+        // The fallback that finished the turn is used for the next one.
+        assert_eq!(chain.last_ok.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn mid_stream_failure_without_fallback_returns_error() {
+        let chain = ProviderChain::new(vec![flex_broken_stream("partial")]);
+        let stream = chain.complete(vec![], None).await.unwrap();
+        tokio::pin!(stream);
+        assert_eq!(stream.next().await.unwrap().unwrap(), Token::Text { text: "partial".into() });
+        assert!(matches!(stream.next().await, Some(Err(ProviderError::Network(_)))));
+    }
+
+    /// Streams back a description of each message it was sent.
+    struct ContentEchoMock;
+
+    impl Provider for ContentEchoMock {
+        async fn complete(
+            &self,
+            messages: Vec<Message>,
+            _tools: Option<Vec<serde_json::Value>>,
+        ) -> Result<TokenStream, ProviderError> {
+            let stream = async_stream::try_stream! {
+                for message in messages {
+                    let text = match message.content {
+                        MessageContent::Image { .. } => "<image>".to_string(),
+                        MessageContent::Text { text } => text,
+                        MessageContent::Reasoning { text } => format!("<thinking:{text}>"),
+                        _ => String::new(),
+                    };
+                    yield Token::Text { text };
+                }
+            };
+            Ok(Box::pin(stream))
+        }
+    }
+
+    fn image_message() -> Message {
+        Message {
+            role: crate::types::Role::User,
+            content: MessageContent::Image {
+                blob_id: "blob-1".into(),
+                mime_type: "image/png".into(),
+                data: Some("iVBORw0K".into()),
+            },
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn images_omitted_with_warning_for_models_without_vision() {
+        let chain = ProviderChain::new(vec![(ContentEchoMock, "text-only".to_string())])
+            .with_vision(vec![false]);
+        let tokens = collect_tokens(chain.complete(vec![image_message()], None).await.unwrap()).await;
+        assert!(matches!(
+            &tokens[0],
+            Token::Warning { message } if message.contains("text-only cannot view images")
+        ));
+        assert_eq!(tokens[1], Token::Text { text: IMAGE_OMITTED.into() });
+
+        let chain = ProviderChain::new(vec![(ContentEchoMock, "vision".to_string())]);
+        let tokens = collect_tokens(chain.complete(vec![image_message()], None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "<image>".into() }]);
+    }
+
+    #[tokio::test]
+    async fn stored_reasoning_only_sent_to_providers_that_opt_in() {
+        let messages = vec![Message {
+            role: crate::types::Role::Assistant,
+            content: MessageContent::Reasoning {
+                text: "hmm".into(),
+            },
+            timestamp: chrono::Utc::now(),
+        }];
+        let chain = ProviderChain::new(vec![(ContentEchoMock, "default".to_string())]);
+        let tokens = collect_tokens(chain.complete(messages.clone(), None).await.unwrap()).await;
+        assert!(tokens.is_empty());
+
+        let chain = ProviderChain::new(vec![(ContentEchoMock, "replay".to_string())])
+            .with_reasoning_replay(vec![true]);
+        let tokens = collect_tokens(chain.complete(messages, None).await.unwrap()).await;
+        assert_eq!(tokens, vec![Token::Text { text: "<thinking:hmm>".into() }]);
+    }
+
+    #[tokio::test]
+    async fn emulated_tool_mode_prompts_for_tools_and_parses_calls() {
+        let tools = Some(vec![serde_json::json!({
+            "type": "function",
+            "function": {"name": "recall", "description": "Search memory", "parameters": {}},
+        })]);
+        let user = Message {
+            role: crate::types::Role::User,
+            content: MessageContent::Text {
+                text: "<tool_call>{\"name\": \"recall\"}</tool_call>".into(),
+            },
+            timestamp: chrono::Utc::now(),
+        };
+        let chain = ProviderChain::new(vec![(ContentEchoMock, "small".to_string())])
+            .with_tool_modes(vec![ToolMode::Emulated]);
+        let tokens = collect_tokens(chain.complete(vec![user], tools).await.unwrap()).await;
+
+        // The echoed system message describes the tools...
+        assert!(tokens.iter().any(
+            |t| matches!(t, Token::Text { text } if text.contains("## recall\nSearch memory"))
+        ));
+        // ...and the echoed user message is parsed as a call.
+        assert!(matches!(
+            tokens.last(),
+            Some(Token::ToolCall { name, arguments, .. }) if name == "recall" && arguments == "{}"
+        ));
+    }
+
+    #[test]
+    fn default_vision_recognises_multimodal_models() {
+        assert!(default_vision("openai", "gpt-4o-mini"));
+        assert!(!default_vision("openai", "gpt-3.5-turbo"));
+        assert!(default_vision("gemini", "gemini-2.0-flash"));
+        assert!(default_vision("anthropic", "claude-sonnet-4"));
+        assert!(default_vision("mistral", "pixtral-12b"));
+        assert!(!default_vision("mistral", "mistral-tiny"));
+        assert!(default_vision("ollama", "llava:13b"));
+        assert!(default_vision("lmstudio", "qwen2.5-vl-7b-instruct"));
+        assert!(!default_vision("ollama", "llama3"));
+    }
 }
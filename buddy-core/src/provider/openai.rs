@@ -3,7 +3,12 @@ use futures_util::StreamExt;
 use reqwest::Client;
 use serde::Deserialize;
 
-use crate::provider::{Provider, ProviderError, Token, TokenStream};
+use crate::config::GenerationParams;
+use crate::context::{ContextWindow, TokenizerFamily};
+use crate::provider::retry::parse_retry_after;
+use crate::provider::{
+    IMAGE_UNAVAILABLE, Provider, ProviderError, Token, TokenStream, partial_tag_len,
+};
 
 /// OpenAI-compatible provider (works with OpenAI, Azure OpenAI, and any
 /// endpoint that speaks the same chat-completions protocol).
@@ -13,6 +18,8 @@ pub struct OpenAiProvider {
     model: String,
     endpoint: String,
     system_prompt: String,
+    context_window: Option<ContextWindow>,
+    generation: GenerationParams,
 }
 
 impl OpenAiProvider {
@@ -26,8 +33,22 @@ impl OpenAiProvider {
             model: model.to_string(),
             endpoint: endpoint.to_string(),
             system_prompt: system_prompt.to_string(),
+            context_window: None,
+            generation: GenerationParams::default(),
         }
     }
+
+    /// Trim conversation history to fit a context window of `max_tokens`.
+    pub fn with_context_window(mut self, max_tokens: usize) -> Self {
+        self.context_window = Some(ContextWindow::new(max_tokens, TokenizerFamily::OpenAi));
+        self
+    }
+
+    /// Send these generation parameters with every request.
+    pub fn with_generation_params(mut self, generation: GenerationParams) -> Self {
+        self.generation = generation;
+        self
+    }
 }
 
 // --- Request body construction ---
@@ -36,7 +57,10 @@ impl OpenAiProvider {
 ///
 /// Consecutive `ToolCall` messages from the assistant are grouped into a single
 /// assistant message with a `tool_calls` array. `ToolResult` messages become
-/// `role: "tool"` messages with `tool_call_id`.
+/// `role: "tool"` messages with `tool_call_id`. `Image` messages become a
+/// user message with a base64 `image_url` part. `Reasoning` is sent back
+/// wrapped in `<think>` tags, the convention of open-weight reasoning models,
+/// at the start of the assistant reply that followed it.
 fn to_chat_messages(messages: &[Message]) -> Vec<serde_json::Value> {
     let mut result: Vec<serde_json::Value> = Vec::new();
     let mut i = 0;
@@ -105,6 +129,40 @@ fn to_chat_messages(messages: &[Message]) -> Vec<serde_json::Value> {
                 }));
                 i += 1;
             }
+            MessageContent::Image {
+                mime_type, data, ..
+            } => {
+                let content = match data {
+                    Some(data) => serde_json::json!([{
+                        "type": "image_url",
+                        "image_url": { "url": format!("data:{mime_type};base64,{data}") },
+                    }]),
+                    None => serde_json::json!(IMAGE_UNAVAILABLE),
+                };
+                result.push(serde_json::json!({
+                    "role": "user",
+                    "content": content,
+                }));
+                i += 1;
+            }
+            MessageContent::Reasoning { text } => {
+                let mut content = format!("<think>\n{text}\n</think>");
+                i += 1;
+                if let Some(Message {
+                    role: Role::Assistant,
+                    content: MessageContent::Text { text: reply },
+                    ..
+                }) = messages.get(i)
+                {
+                    content.push_str("\n\n");
+                    content.push_str(reply);
+                    i += 1;
+                }
+                result.push(serde_json::json!({
+                    "role": "assistant",
+                    "content": content,
+                }));
+            }
         }
     }
 
@@ -112,11 +170,15 @@ fn to_chat_messages(messages: &[Message]) -> Vec<serde_json::Value> {
 }
 
 /// Build the full request body for an OpenAI-compatible chat completions endpoint.
+///
+/// Generation parameters that are set map directly onto the OpenAI names
+/// (`temperature`, `max_tokens`, `top_p`, `stop`).
 pub(crate) fn build_request_body(
     messages: &[Message],
     model: &str,
     system_prompt: &str,
     tools: Option<&Vec<serde_json::Value>>,
+    params: &GenerationParams,
 ) -> serde_json::Value {
     let mut chat_messages = Vec::new();
     if !system_prompt.is_empty() {
@@ -139,16 +201,49 @@ pub(crate) fn build_request_body(
         }
     }
 
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        body["max_tokens"] = serde_json::json!(max_tokens);
+    }
+    if let Some(top_p) = params.top_p {
+        body["top_p"] = serde_json::json!(top_p);
+    }
+    if let Some(stop) = &params.stop {
+        body["stop"] = serde_json::json!(stop);
+    }
+
     body
 }
 
+/// Ask the endpoint to report token usage in the final stream chunk.
+///
+/// Mistral always includes usage and rejects unknown fields, so this is only
+/// added for the other OpenAI-compatible providers.
+pub(crate) fn request_stream_usage(body: &mut serde_json::Value) {
+    body["stream_options"] = serde_json::json!({ "include_usage": true });
+}
+
 // --- Response types (shared with other OpenAI-compatible providers) ---
 
 #[derive(Deserialize)]
 struct ChatChunk {
+    #[serde(default)]
     choices: Vec<ChunkChoice>,
 }
 
+#[derive(Deserialize)]
+struct UsageChunk {
+    usage: Option<UsageCounts>,
+}
+
+#[derive(Deserialize)]
+struct UsageCounts {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
 #[derive(Deserialize)]
 struct ChunkChoice {
     delta: ChunkDelta,
@@ -158,6 +253,11 @@ struct ChunkChoice {
 #[derive(Deserialize)]
 struct ChunkDelta {
     content: Option<String>,
+    /// Reasoning as sent by DeepSeek and LM Studio.
+    reasoning_content: Option<String>,
+    /// Reasoning as sent by Ollama and OpenRouter.
+    #[serde(alias = "thinking")]
+    reasoning: Option<String>,
     tool_calls: Option<Vec<ToolCallChunk>>,
 }
 
@@ -191,6 +291,8 @@ struct ErrorDetail {
 pub(crate) enum SseChunk {
     /// Text content delta.
     TextDelta(String),
+    /// Reasoning delta, occasionally sharing its chunk with answer text.
+    ReasoningDelta { reasoning: String, text: String },
     /// Partial tool call data to accumulate.
     ToolCallDelta(Vec<ToolCallChunk>),
     /// The model finished with tool calls — drain the accumulator.
@@ -244,6 +346,17 @@ pub(crate) fn parse_sse_line(line: &str) -> Result<SseChunk, ProviderError> {
             return Ok(SseChunk::FinishToolCalls);
         }
 
+        let reasoning = [&choice.delta.reasoning_content, &choice.delta.reasoning]
+            .into_iter()
+            .flatten()
+            .find(|r| !r.is_empty());
+        if let Some(reasoning) = reasoning {
+            return Ok(SseChunk::ReasoningDelta {
+                reasoning: reasoning.clone(),
+                text: choice.delta.content.clone().unwrap_or_default(),
+            });
+        }
+
         // Text content.
         if let Some(ref text) = choice.delta.content {
             if !text.is_empty() {
@@ -255,6 +368,22 @@ pub(crate) fn parse_sse_line(line: &str) -> Result<SseChunk, ProviderError> {
     Ok(SseChunk::Empty)
 }
 
+/// Extract the `usage` block from a `data:` line, if it carries one.
+///
+/// Usage usually arrives in the final chunk, sometimes alongside a
+/// `finish_reason`, so it is read separately from `parse_sse_line`.
+/// Returns `(prompt_tokens, completion_tokens)`.
+pub(crate) fn parse_sse_usage(line: &str) -> Option<(u32, u32)> {
+    let data = line.strip_prefix("data: ")?;
+    if !data.contains("\"usage\"") {
+        return None;
+    }
+    let chunk: UsageChunk = serde_json::from_str(data).ok()?;
+    chunk
+        .usage
+        .map(|u| (u.prompt_tokens, u.completion_tokens))
+}
+
 /// Accumulator for tool call chunks that arrive across multiple SSE events.
 pub(crate) struct ToolCallAccumulator {
     /// (id, name, arguments_buffer) indexed by the tool_call index.
@@ -301,6 +430,96 @@ impl ToolCallAccumulator {
     }
 }
 
+/// Where a [`ThinkTagSplitter`] is in the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThinkState {
+    /// Not yet known whether the response opens with a `<think>` block.
+    Start,
+    Thinking,
+    Answer,
+}
+
+/// Splits the `<think>…</think>` block that some open-weight reasoning
+/// models put at the start of their content into `Token::Reasoning`.
+///
+/// Only a block opening the response is recognised, so a reply that merely
+/// mentions the tag is left alone. Text that could be the start of a tag is
+/// held back until the next delta decides it.
+pub(crate) struct ThinkTagSplitter {
+    state: ThinkState,
+    pending: String,
+}
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+impl ThinkTagSplitter {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: ThinkState::Start,
+            pending: String::new(),
+        }
+    }
+
+    /// Feed a content delta, returning the tokens it completes.
+    pub(crate) fn push(&mut self, text: &str) -> Vec<Token> {
+        if text.is_empty() {
+            return Vec::new();
+        }
+        if self.state == ThinkState::Answer {
+            return vec![Token::Text {
+                text: text.to_string(),
+            }];
+        }
+        self.pending.push_str(text);
+        let mut tokens = Vec::new();
+        if self.state == ThinkState::Start {
+            let trimmed = self.pending.trim_start();
+            if let Some(rest) = trimmed.strip_prefix(THINK_OPEN) {
+                self.pending = rest.to_string();
+                self.state = ThinkState::Thinking;
+            } else if THINK_OPEN.starts_with(trimmed) {
+                return tokens;
+            } else {
+                self.state = ThinkState::Answer;
+                return self.finish();
+            }
+        }
+        if let Some(end) = self.pending.find(THINK_CLOSE) {
+            let answer = self.pending.split_off(end + THINK_CLOSE.len());
+            self.pending.truncate(end);
+            tokens.extend(self.finish());
+            self.state = ThinkState::Answer;
+            let answer = answer.trim_start();
+            if !answer.is_empty() {
+                tokens.push(Token::Text {
+                    text: answer.to_string(),
+                });
+            }
+            return tokens;
+        }
+        // Hold back a suffix that may be the start of the closing tag.
+        let held = partial_tag_len(&self.pending, THINK_CLOSE);
+        let rest = self.pending.split_off(self.pending.len() - held);
+        tokens.extend(self.finish());
+        self.pending = rest;
+        tokens
+    }
+
+    /// Emit whatever is still held back, as reasoning inside the block and
+    /// as text otherwise.
+    pub(crate) fn finish(&mut self) -> Vec<Token> {
+        let text = std::mem::take(&mut self.pending);
+        if text.is_empty() {
+            return Vec::new();
+        }
+        vec![match self.state {
+            ThinkState::Thinking => Token::Reasoning { text },
+            ThinkState::Start | ThinkState::Answer => Token::Text { text },
+        }]
+    }
+}
+
 /// Map an HTTP error status code and body to a `ProviderError`.
 pub fn map_error_status(status: u16, body: &str) -> ProviderError {
     let message = serde_json::from_str::<ErrorResponse>(body)
@@ -309,7 +528,10 @@ pub fn map_error_status(status: u16, body: &str) -> ProviderError {
 
     match status {
         401 => ProviderError::Auth(message),
-        429 => ProviderError::RateLimit(message),
+        429 => ProviderError::RateLimit {
+            message,
+            retry_after: None,
+        },
         _ => ProviderError::Other(format!("HTTP {status}: {message}")),
     }
 }
@@ -318,11 +540,20 @@ pub fn map_error_status(status: u16, body: &str) -> ProviderError {
 
 /// Convert a streaming `reqwest::Response` from an OpenAI-compatible chat
 /// completions endpoint into a `TokenStream`.
-pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
+///
+/// Reported usage is emitted as a final `Token::Usage` attributed to
+/// `provider` and `model`.
+pub(crate) fn parse_sse_stream(
+    response: reqwest::Response,
+    provider: &'static str,
+    model: String,
+) -> TokenStream {
     let stream = async_stream::try_stream! {
         let mut byte_stream = response.bytes_stream();
         let mut buffer = String::new();
         let mut tool_acc = ToolCallAccumulator::new();
+        let mut think = ThinkTagSplitter::new();
+        let mut usage = None;
 
         while let Some(chunk) = byte_stream.next().await {
             let bytes = chunk.map_err(|e| ProviderError::Network(e.to_string()))?;
@@ -332,8 +563,21 @@ pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
                 let line = buffer[..pos].trim_end_matches('\r').to_string();
                 buffer.drain(..pos + 1);
 
+                if let Some(counts) = parse_sse_usage(&line) {
+                    usage = Some(counts);
+                }
                 match parse_sse_line(&line)? {
-                    SseChunk::TextDelta(text) => yield Token::Text { text },
+                    SseChunk::TextDelta(text) => {
+                        for token in think.push(&text) {
+                            yield token;
+                        }
+                    }
+                    SseChunk::ReasoningDelta { reasoning, text } => {
+                        yield Token::Reasoning { text: reasoning };
+                        for token in think.push(&text) {
+                            yield token;
+                        }
+                    }
                     SseChunk::ToolCallDelta(chunks) => {
                         tool_acc.process(&chunks);
                     }
@@ -350,8 +594,21 @@ pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
         // Flush remaining buffer.
         let remaining = buffer.trim();
         if !remaining.is_empty() {
+            if let Some(counts) = parse_sse_usage(remaining) {
+                usage = Some(counts);
+            }
             match parse_sse_line(remaining)? {
-                SseChunk::TextDelta(text) => yield Token::Text { text },
+                SseChunk::TextDelta(text) => {
+                    for token in think.push(&text) {
+                        yield token;
+                    }
+                }
+                SseChunk::ReasoningDelta { reasoning, text } => {
+                    yield Token::Reasoning { text: reasoning };
+                    for token in think.push(&text) {
+                        yield token;
+                    }
+                }
                 SseChunk::FinishToolCalls => {
                     for token in tool_acc.drain() {
                         yield token;
@@ -368,6 +625,10 @@ pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
             }
         }
 
+        for token in think.finish() {
+            yield token;
+        }
+
         // If tool calls were accumulated but never flushed (no finish_reason
         // line), drain them now.
         if !tool_acc.is_empty() {
@@ -375,6 +636,15 @@ pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
                 yield token;
             }
         }
+
+        if let Some((prompt, completion)) = usage {
+            yield Token::Usage {
+                provider: provider.to_string(),
+                model,
+                prompt,
+                completion,
+            };
+        }
     };
 
     Box::pin(stream)
@@ -385,14 +655,24 @@ pub(crate) fn parse_sse_stream(response: reqwest::Response) -> TokenStream {
 impl Provider for OpenAiProvider {
     async fn complete(
         &self,
-        messages: Vec<Message>,
+        mut messages: Vec<Message>,
         tools: Option<Vec<serde_json::Value>>,
     ) -> Result<TokenStream, ProviderError> {
+        if let Some(window) = &self.context_window {
+            messages = window.trim(messages, &self.system_prompt, tools.as_ref());
+        }
         let url = format!(
             "{}/chat/completions",
             self.endpoint.trim_end_matches('/')
         );
-        let body = build_request_body(&messages, &self.model, &self.system_prompt, tools.as_ref());
+        let mut body = build_request_body(
+            &messages,
+            &self.model,
+            &self.system_prompt,
+            tools.as_ref(),
+            &self.generation,
+        );
+        request_stream_usage(&mut body);
 
         let response = self
             .client
@@ -405,11 +685,12 @@ impl Provider for OpenAiProvider {
 
         let status = response.status();
         if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
             let body_text = response.text().await.unwrap_or_default();
-            return Err(map_error_status(status.as_u16(), &body_text));
+            return Err(map_error_status(status.as_u16(), &body_text).with_retry_after(retry_after));
         }
 
-        Ok(parse_sse_stream(response))
+        Ok(parse_sse_stream(response, "openai", self.model.clone()))
     }
 }
 
@@ -441,7 +722,13 @@ mod tests {
     #[test]
     fn request_body_matches_openai_spec() {
         let messages = make_messages();
-        let body = build_request_body(&messages, "gpt-4", "You are a helpful assistant.", None);
+        let body = build_request_body(
+            &messages,
+            "gpt-4",
+            "You are a helpful assistant.",
+            None,
+            &GenerationParams::default(),
+        );
 
         assert_eq!(body["model"], "gpt-4");
         assert_eq!(body["stream"], true);
@@ -471,8 +758,13 @@ mod tests {
                 "parameters": { "type": "object" }
             }
         })];
-        let body =
-            build_request_body(&messages, "gpt-4", "", Some(&tools));
+        let body = build_request_body(
+            &messages,
+            "gpt-4",
+            "",
+            Some(&tools),
+            &GenerationParams::default(),
+        );
 
         assert!(body["tools"].is_array());
         assert_eq!(body["tools"].as_array().unwrap().len(), 1);
@@ -482,14 +774,34 @@ mod tests {
     #[test]
     fn request_body_omits_tools_when_none() {
         let messages = make_messages();
-        let body = build_request_body(&messages, "gpt-4", "", None);
+        let body = build_request_body(&messages, "gpt-4", "", None, &GenerationParams::default());
         assert!(body.get("tools").is_none());
     }
 
+    #[test]
+    fn request_body_maps_generation_params() {
+        let params = GenerationParams {
+            temperature: Some(0.3),
+            max_tokens: Some(256),
+            top_p: Some(0.8),
+            stop: Some(vec!["###".into()]),
+        };
+        let body = build_request_body(&make_messages(), "gpt-4", "", None, &params);
+        assert_eq!(body["temperature"], 0.3);
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["top_p"], 0.8);
+        assert_eq!(body["stop"], serde_json::json!(["###"]));
+
+        let body =
+            build_request_body(&make_messages(), "gpt-4", "", None, &GenerationParams::default());
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("stop").is_none());
+    }
+
     #[test]
     fn empty_system_prompt_is_not_prepended() {
         let messages = make_messages();
-        let body = build_request_body(&messages, "gpt-4", "", None);
+        let body = build_request_body(&messages, "gpt-4", "", None, &GenerationParams::default());
         let msgs = body["messages"].as_array().unwrap();
         assert_eq!(msgs.len(), 2);
         assert_eq!(msgs[0]["role"], "system");
@@ -562,6 +874,40 @@ mod tests {
         assert_eq!(chat[3]["tool_call_id"], "call_2");
     }
 
+    #[test]
+    fn image_becomes_user_message_with_data_url() {
+        let messages = vec![
+            Message {
+                role: Role::User,
+                content: MessageContent::Image {
+                    blob_id: "blob-1".into(),
+                    mime_type: "image/png".into(),
+                    data: Some("iVBORw0K".into()),
+                },
+                timestamp: Utc::now(),
+            },
+            Message {
+                role: Role::User,
+                content: MessageContent::Image {
+                    blob_id: "blob-2".into(),
+                    mime_type: "image/jpeg".into(),
+                    data: None,
+                },
+                timestamp: Utc::now(),
+            },
+        ];
+
+        let chat = to_chat_messages(&messages);
+        assert_eq!(chat.len(), 2);
+        assert_eq!(chat[0]["role"], "user");
+        assert_eq!(chat[0]["content"][0]["type"], "image_url");
+        assert_eq!(
+            chat[0]["content"][0]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0K"
+        );
+        assert_eq!(chat[1]["content"], IMAGE_UNAVAILABLE);
+    }
+
     #[test]
     fn parse_sse_text_tokens() {
         let lines = [
@@ -581,6 +927,97 @@ mod tests {
         assert_eq!(texts, vec!["Hello", " world"]);
     }
 
+    #[test]
+    fn parse_sse_reasoning_fields() {
+        let deepseek = r#"data: {"choices":[{"index":0,"delta":{"content":null,"reasoning_content":"Let me think"},"finish_reason":null}]}"#;
+        let ollama = r#"data: {"choices":[{"index":0,"delta":{"role":"assistant","content":"","reasoning":"Hmm"},"finish_reason":null}]}"#;
+        let both = r#"data: {"choices":[{"index":0,"delta":{"content":"Yes","reasoning":"."},"finish_reason":null}]}"#;
+
+        let parsed: Vec<(String, String)> = [deepseek, ollama, both]
+            .iter()
+            .map(|line| match parse_sse_line(line).unwrap() {
+                SseChunk::ReasoningDelta { reasoning, text } => (reasoning, text),
+                other => panic!("expected ReasoningDelta, got: {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("Let me think".to_string(), String::new()),
+                ("Hmm".to_string(), String::new()),
+                (".".to_string(), "Yes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn think_tags_split_into_reasoning_across_deltas() {
+        let mut splitter = ThinkTagSplitter::new();
+        let mut tokens = Vec::new();
+        for delta in ["<thi", "nk>\nPondering", " deeply</th", "ink>\n\nThe answer", " is 4."] {
+            tokens.extend(splitter.push(delta));
+        }
+        tokens.extend(splitter.finish());
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Reasoning {
+                    text: "\nPondering".into()
+                },
+                Token::Reasoning {
+                    text: " deeply".into()
+                },
+                Token::Text {
+                    text: "The answer".into()
+                },
+                Token::Text {
+                    text: " is 4.".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn think_tags_only_recognised_at_start_of_reply() {
+        let mut splitter = ThinkTagSplitter::new();
+        let mut tokens = splitter.push("Use <think> tags");
+        tokens.extend(splitter.push("</think> like so"));
+        tokens.extend(splitter.finish());
+        let text: String = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text { text } => text.as_str(),
+                other => panic!("expected text, got: {other:?}"),
+            })
+            .collect();
+        assert_eq!(text, "Use <think> tags</think> like so");
+    }
+
+    #[test]
+    fn reasoning_sent_back_in_think_tags_before_reply() {
+        let now = Utc::now();
+        let messages = vec![
+            Message {
+                role: Role::Assistant,
+                content: MessageContent::Reasoning {
+                    text: "User greets me.".into(),
+                },
+                timestamp: now,
+            },
+            Message {
+                role: Role::Assistant,
+                content: MessageContent::Text {
+                    text: "Hello!".into(),
+                },
+                timestamp: now,
+            },
+        ];
+        let chat = to_chat_messages(&messages);
+        assert_eq!(chat.len(), 1);
+        assert_eq!(chat[0]["role"], "assistant");
+        assert_eq!(chat[0]["content"], "<think>\nUser greets me.\n</think>\n\nHello!");
+    }
+
     #[test]
     fn parse_sse_tool_call_chunks() {
         let lines = [
@@ -628,6 +1065,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_sse_usage_from_final_chunk() {
+        let line = r#"data: {"id":"1","choices":[],"usage":{"prompt_tokens":21,"completion_tokens":7,"total_tokens":28}}"#;
+        assert_eq!(parse_sse_usage(line), Some((21, 7)));
+        assert!(matches!(parse_sse_line(line).unwrap(), SseChunk::Empty));
+    }
+
+    #[test]
+    fn parse_sse_usage_ignores_null_and_missing_usage() {
+        assert_eq!(parse_sse_usage(r#"data: {"choices":[],"usage":null}"#), None);
+        assert_eq!(
+            parse_sse_usage(r#"data: {"choices":[{"delta":{"content":"Hi"},"finish_reason":null}]}"#),
+            None
+        );
+        assert_eq!(parse_sse_usage("data: [DONE]"), None);
+    }
+
+    #[test]
+    fn stream_usage_requested_in_body() {
+        let mut body = build_request_body(
+            &make_messages(),
+            "gpt-4",
+            "",
+            None,
+            &GenerationParams::default(),
+        );
+        request_stream_usage(&mut body);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+    }
+
     #[test]
     fn error_status_401_maps_to_auth() {
         let body = r#"{"error":{"message":"Invalid API key","type":"invalid_request_error","code":"invalid_api_key"}}"#;
@@ -641,7 +1108,7 @@ mod tests {
     fn error_status_429_maps_to_rate_limit() {
         let body = r#"{"error":{"message":"Rate limit exceeded","type":"rate_limit_error","code":"rate_limit"}}"#;
         match map_error_status(429, body) {
-            ProviderError::RateLimit(msg) => assert_eq!(msg, "Rate limit exceeded"),
+            ProviderError::RateLimit { message: msg, .. } => assert_eq!(msg, "Rate limit exceeded"),
             other => panic!("expected RateLimit, got: {other:?}"),
         }
     }
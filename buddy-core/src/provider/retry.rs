@@ -0,0 +1,198 @@
+//! Retry policy for provider requests.
+//!
+//! Transient failures (`Network`, `RateLimit`) are retried on the same
+//! provider with exponential backoff and jitter before a `ProviderChain`
+//! falls back to the next entry. A `Retry-After` hint from a rate-limited
+//! response replaces the computed backoff.
+
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+
+use super::ProviderError;
+
+/// Retries per provider when the config does not say otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+/// Backoff before the first retry when the config does not say otherwise.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound on a single backoff. A `Retry-After` longer than this is not
+/// waited out; the chain moves on to the next provider instead.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How often a single provider is retried, and how long to wait in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Backoff before retry number `attempt` (0-based): the initial backoff
+    /// doubled per attempt, capped at `max_backoff`, with "equal jitter" — a
+    /// random delay between half and all of that value.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        let half = ceiling / 2;
+        half + half.mul_f64(random_fraction())
+    }
+
+    /// How long to wait before retrying after `error`, or `None` when the
+    /// request should not be retried on this provider.
+    pub fn delay_for(&self, attempt: u32, error: &ProviderError) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        match error {
+            ProviderError::Network(_) => Some(self.backoff(attempt)),
+            ProviderError::RateLimit {
+                retry_after: Some(wait),
+                ..
+            } => (*wait <= self.max_backoff).then_some(*wait),
+            ProviderError::RateLimit { .. } => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+}
+
+/// Read how long the server asked us to wait from a response's headers.
+///
+/// Understands `retry-after-ms` (sent by OpenAI-compatible APIs) and
+/// `Retry-After` as either delay-seconds or an HTTP date.
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = headers
+        .get("retry-after-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|ms| ms.is_finite() && *ms >= 0.0)
+    {
+        return Some(Duration::from_secs_f64(ms / 1000.0));
+    }
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// A uniformly distributed value in `[0, 1)`, good enough for jitter.
+fn random_fraction() -> f64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_and_is_capped() {
+        let p = policy(10);
+        for attempt in 0..6 {
+            let ceiling = Duration::from_millis(100 * 2u64.pow(attempt)).min(p.max_backoff);
+            let delay = p.backoff(attempt);
+            assert!(
+                delay >= ceiling / 2 && delay <= ceiling,
+                "attempt {attempt}: {delay:?}"
+            );
+        }
+        assert!(p.backoff(30) <= p.max_backoff);
+    }
+
+    #[test]
+    fn delay_for_only_retries_transient_errors_within_budget() {
+        let p = policy(2);
+        let network = ProviderError::Network("reset".into());
+        assert!(p.delay_for(0, &network).is_some());
+        assert!(p.delay_for(1, &network).is_some());
+        assert!(p.delay_for(2, &network).is_none());
+        assert!(
+            p.delay_for(0, &ProviderError::Auth("bad key".into()))
+                .is_none()
+        );
+        assert!(RetryPolicy::none().delay_for(0, &network).is_none());
+    }
+
+    #[test]
+    fn delay_for_honours_retry_after_up_to_max_backoff() {
+        let p = policy(2);
+        let short = ProviderError::RateLimit {
+            message: "slow down".into(),
+            retry_after: Some(Duration::from_millis(750)),
+        };
+        assert_eq!(p.delay_for(0, &short), Some(Duration::from_millis(750)));
+        let long = ProviderError::RateLimit {
+            message: "slow down".into(),
+            retry_after: Some(Duration::from_secs(120)),
+        };
+        assert_eq!(p.delay_for(0, &long), None);
+    }
+
+    #[test]
+    fn parse_retry_after_seconds_ms_and_date() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("1500"));
+        assert_eq!(
+            parse_retry_after(&headers),
+            Some(Duration::from_millis(1500))
+        );
+
+        let mut headers = HeaderMap::new();
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        headers.insert("retry-after", HeaderValue::from_str(&date).unwrap());
+        let wait = parse_retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+
+        headers.insert("retry-after", HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! Prompt-based tool calling for models without native function calling.
+//!
+//! In emulated mode the tool schemas are rendered into a system message and
+//! the model is asked to call a tool by writing a `<tool_call>` block holding
+//! a JSON object. The blocks are parsed out of the streamed text and yielded
+//! as ordinary `Token::ToolCall`s. Earlier calls and results in the history
+//! are rewritten as text in the same format, so the model never sees the
+//! native tool message shapes it does not understand.
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::Deserialize;
+
+use super::{Token, TokenStream, partial_tag_len};
+use crate::types::{Message, MessageContent, Role};
+
+const CALL_OPEN: &str = "<tool_call>";
+const CALL_CLOSE: &str = "</tool_call>";
+
+/// Explains the call format; the tool list is appended after it.
+const INSTRUCTIONS: &str = "You can use the tools listed below. To call a tool, reply with a \
+block in exactly this format:
+
+<tool_call>
+{\"name\": \"tool_name\", \"arguments\": {\"argument\": \"value\"}}
+</tool_call>
+
+Write one block per call and nothing after the last block. The results will be sent back \
+to you in a <tool_result> block, after which you continue. If no tool is needed, answer \
+directly.
+
+# Tools";
+
+/// Render OpenAI-format tool definitions into instructions for the model.
+fn tool_prompt(tools: &[serde_json::Value]) -> String {
+    let mut prompt = INSTRUCTIONS.to_string();
+    for function in tools.iter().filter_map(|tool| tool.get("function")) {
+        let field = |key: &str| {
+            function
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+        };
+        let parameters = function
+            .get("parameters")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        prompt.push_str(&format!(
+            "\n\n## {}\n{}\nArguments (JSON Schema): {parameters}",
+            field("name"),
+            field("description"),
+        ));
+    }
+    prompt
+}
+
+/// A tool call written out in the format the model is asked to use.
+fn format_call(name: &str, arguments: &str) -> String {
+    let arguments: serde_json::Value =
+        serde_json::from_str(arguments).unwrap_or_else(|_| serde_json::json!({}));
+    let name = serde_json::Value::from(name);
+    format!("{CALL_OPEN}\n{{\"name\": {name}, \"arguments\": {arguments}}}\n{CALL_CLOSE}")
+}
+
+/// Rewrite `messages` for a model without native tool calling: the tool
+/// prompt (if there are tools) is prepended as a system message, and earlier
+/// tool calls and results become plain text.
+pub(crate) fn emulate_tools(
+    messages: &[Message],
+    tools: Option<&[serde_json::Value]>,
+) -> Vec<Message> {
+    let mut result = Vec::with_capacity(messages.len() + 1);
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        result.push(Message {
+            role: Role::System,
+            content: MessageContent::Text {
+                text: tool_prompt(tools),
+            },
+            timestamp: Utc::now(),
+        });
+    }
+    result.extend(messages.iter().map(|message| {
+        let text = match &message.content {
+            MessageContent::ToolCall {
+                name, arguments, ..
+            } => format_call(name, arguments),
+            MessageContent::ToolResult { name, content, .. } => {
+                format!("<tool_result name=\"{name}\">\n{content}\n</tool_result>")
+            }
+            _ => return message.clone(),
+        };
+        Message {
+            content: MessageContent::Text { text },
+            ..message.clone()
+        }
+    }));
+    result
+}
+
+#[derive(Deserialize)]
+struct EmulatedCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+/// Turn the body of a `<tool_call>` block into a `Token::ToolCall`, or give
+/// the block back as text if it does not hold a call.
+fn call_token(body: &str) -> Token {
+    // Small models like to wrap the JSON in a Markdown code fence.
+    let json = body.trim();
+    let json = json
+        .strip_prefix("```json")
+        .or_else(|| json.strip_prefix("```"))
+        .and_then(|j| j.strip_suffix("```"))
+        .unwrap_or(json);
+    match serde_json::from_str::<EmulatedCall>(json.trim()) {
+        Ok(call) => Token::ToolCall {
+            id: format!("call_{}", uuid::Uuid::new_v4()),
+            name: call.name,
+            arguments: match call.arguments {
+                serde_json::Value::Null => "{}".to_string(),
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            },
+        },
+        Err(_) => Token::Text {
+            text: format!("{CALL_OPEN}{body}{CALL_CLOSE}"),
+        },
+    }
+}
+
+/// Splits `<tool_call>` blocks out of streamed text. Text that could be the
+/// start of a tag is held back until the next delta decides it.
+#[derive(Default)]
+struct CallParser {
+    pending: String,
+    in_call: bool,
+}
+
+impl CallParser {
+    /// Feed a text delta, returning the tokens it completes.
+    fn push(&mut self, text: &str) -> Vec<Token> {
+        self.pending.push_str(text);
+        let mut tokens = Vec::new();
+        loop {
+            let tag = if self.in_call { CALL_CLOSE } else { CALL_OPEN };
+            if let Some(pos) = self.pending.find(tag) {
+                let rest = self.pending.split_off(pos + tag.len());
+                self.pending.truncate(pos);
+                let before = std::mem::replace(&mut self.pending, rest);
+                if self.in_call {
+                    tokens.push(call_token(&before));
+                } else if !before.is_empty() {
+                    tokens.push(Token::Text { text: before });
+                }
+                self.in_call = !self.in_call;
+                continue;
+            }
+            if !self.in_call {
+                let held = partial_tag_len(&self.pending, CALL_OPEN);
+                let rest = self.pending.split_off(self.pending.len() - held);
+                let text = std::mem::replace(&mut self.pending, rest);
+                if !text.is_empty() {
+                    tokens.push(Token::Text { text });
+                }
+            }
+            return tokens;
+        }
+    }
+
+    /// Flush what is still held back at the end of the stream. A block the
+    /// model never closed still counts as a call if its JSON is complete.
+    fn finish(&mut self) -> Vec<Token> {
+        let pending = std::mem::take(&mut self.pending);
+        if self.in_call {
+            match call_token(&pending) {
+                Token::Text { .. } => vec![Token::Text {
+                    text: format!("{CALL_OPEN}{pending}"),
+                }],
+                call => vec![call],
+            }
+        } else if pending.is_empty() {
+            Vec::new()
+        } else {
+            vec![Token::Text { text: pending }]
+        }
+    }
+}
+
+/// Parse `<tool_call>` blocks out of a provider's text stream, yielding them
+/// as `Token::ToolCall`s. Other tokens pass through unchanged.
+pub(crate) fn parse_tool_calls(stream: TokenStream) -> TokenStream {
+    let stream = async_stream::try_stream! {
+        let mut stream = stream;
+        let mut parser = CallParser::default();
+        while let Some(token) = stream.next().await {
+            match token? {
+                Token::Text { text } => {
+                    for token in parser.push(&text) {
+                        yield token;
+                    }
+                }
+                other => yield other,
+            }
+        }
+        for token in parser.finish() {
+            yield token;
+        }
+    };
+    Box::pin(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: MessageContent) -> Message {
+        Message {
+            role,
+            content,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn parse(deltas: &[&str]) -> Vec<Token> {
+        let mut parser = CallParser::default();
+        let mut tokens: Vec<Token> = deltas.iter().flat_map(|d| parser.push(d)).collect();
+        tokens.extend(parser.finish());
+        tokens
+    }
+
+    #[test]
+    fn tool_prompt_lists_each_tool_with_its_schema() {
+        let tools = vec![serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": "read_file",
+                "description": "Read a file",
+                "parameters": {"type": "object", "properties": {"path": {"type": "string"}}},
+            }
+        })];
+        let prompt = tool_prompt(&tools);
+        assert!(prompt.starts_with(INSTRUCTIONS));
+        assert!(prompt.contains("## read_file\nRead a file\n"));
+        assert!(prompt.contains(r#""path":{"type":"string"}"#));
+    }
+
+    #[test]
+    fn emulate_tools_prepends_prompt_and_rewrites_tool_messages() {
+        let messages = vec![
+            message(
+                Role::Assistant,
+                MessageContent::ToolCall {
+                    id: "c1".into(),
+                    name: "recall".into(),
+                    arguments: r#"{"query":"cats"}"#.into(),
+                },
+            ),
+            message(
+                Role::User,
+                MessageContent::ToolResult {
+                    id: "c1".into(),
+                    name: "recall".into(),
+                    content: "likes cats".into(),
+                },
+            ),
+        ];
+        let tools = [serde_json::json!({"type": "function", "function": {"name": "recall"}})];
+        let rewritten = emulate_tools(&messages, Some(&tools));
+
+        assert_eq!(rewritten.len(), 3);
+        assert_eq!(rewritten[0].role, Role::System);
+        assert_eq!(
+            rewritten[1].content,
+            MessageContent::Text {
+                text: "<tool_call>\n{\"name\": \"recall\", \"arguments\": {\"query\":\"cats\"}}\n</tool_call>"
+                    .into()
+            }
+        );
+        assert_eq!(rewritten[1].role, Role::Assistant);
+        assert_eq!(
+            rewritten[2].content,
+            MessageContent::Text {
+                text: "<tool_result name=\"recall\">\nlikes cats\n</tool_result>".into()
+            }
+        );
+
+        assert_eq!(emulate_tools(&messages, None).len(), 2);
+    }
+
+    #[test]
+    fn parser_extracts_calls_split_across_deltas() {
+        let tokens = parse(&[
+            "Let me check.\n<tool",
+            "_call>\n{\"name\": \"read_file\", \"argu",
+            "ments\": {\"path\": \"a.txt\"}}\n</tool_",
+            "call>",
+        ]);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[0],
+            Token::Text {
+                text: "Let me check.\n".into()
+            }
+        );
+        assert!(matches!(
+            &tokens[1],
+            Token::ToolCall { name, arguments, id }
+                if name == "read_file" && arguments == r#"{"path":"a.txt"}"# && id.starts_with("call_")
+        ));
+    }
+
+    #[test]
+    fn parser_accepts_fenced_and_unclosed_calls() {
+        let tokens = parse(&["<tool_call>```json\n{\"name\": \"recall\"}\n```</tool_call>"]);
+        assert!(matches!(
+            &tokens[..],
+            [Token::ToolCall { name, arguments, .. }] if name == "recall" && arguments == "{}"
+        ));
+
+        let tokens =
+            parse(&["<tool_call>\n{\"name\": \"remember\", \"arguments\": {\"text\": \"x\"}}"]);
+        assert!(matches!(&tokens[..], [Token::ToolCall { name, .. }] if name == "remember"));
+    }
+
+    #[test]
+    fn parser_leaves_text_without_valid_calls_alone() {
+        let text: String = parse(&[
+            "Tags look like <tool",
+            " and <tool_call>not json</tool_call>.",
+        ])
+        .into_iter()
+        .map(|token| match token {
+            Token::Text { text } => text,
+            other => panic!("expected text, got: {other:?}"),
+        })
+        .collect();
+        assert_eq!(
+            text,
+            "Tags look like <tool and <tool_call>not json</tool_call>."
+        );
+    }
+}
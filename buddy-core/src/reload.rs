@@ -11,6 +11,7 @@ use crate::config::{ApprovalPolicy, Config};
 use crate::embedding;
 use crate::embedding::Embedder;
 use crate::memory;
+use crate::provider::anthropic::AnthropicProvider;
 use crate::provider::gemini::GeminiProvider;
 use crate::provider::lmstudio::LmStudioProvider;
 use crate::provider::mistral::MistralProvider;
@@ -19,6 +20,7 @@ use crate::provider::openai::OpenAiProvider;
 use crate::provider::{AnyProvider, ProviderChain};
 use crate::skill;
 use crate::skill::{InstructionStep, SkillDefinition, SkillRegistry};
+use crate::store::Store;
 use crate::warning;
 
 /// Errors that can occur during hot-reload.
@@ -45,6 +47,10 @@ impl std::error::Error for ReloadError {}
 pub fn build_provider_chain(config: &Config) -> Result<ProviderChain<AnyProvider>, ReloadError> {
     let system_prompt = &config.chat.system_prompt;
     let mut chain_entries: Vec<(AnyProvider, String)> = Vec::new();
+    let mut retry_policies = Vec::new();
+    let mut vision = Vec::new();
+    let mut send_reasoning = Vec::new();
+    let mut tool_modes = Vec::new();
 
     for entry in &config.models.chat.providers {
         let api_key = entry
@@ -119,16 +125,44 @@ pub fn build_provider_chain(config: &Config) -> Result<ProviderChain<AnyProvider
                     system_prompt,
                 ))
             }
+            "anthropic" => {
+                let endpoint = entry
+                    .endpoint
+                    .as_deref()
+                    .unwrap_or("https://api.anthropic.com");
+                if api_key.is_empty() {
+                    return Err(ReloadError::InvalidConfig(
+                        "an API key is required when type = \"anthropic\"".into(),
+                    ));
+                }
+                AnyProvider::Anthropic(AnthropicProvider::new(
+                    &api_key,
+                    &entry.model,
+                    endpoint,
+                    system_prompt,
+                ))
+            }
             other => {
                 return Err(ReloadError::InvalidConfig(format!(
                     "unknown provider type '{other}'"
                 )));
             }
         };
+        let provider = provider
+            .with_context_window(entry.context_window())
+            .with_generation_params(entry.generation.clone());
         chain_entries.push((provider, entry.model.clone()));
+        retry_policies.push(entry.retry_policy());
+        vision.push(entry.vision());
+        send_reasoning.push(entry.send_reasoning);
+        tool_modes.push(entry.tool_mode);
     }
 
-    Ok(ProviderChain::new(chain_entries))
+    Ok(ProviderChain::new(chain_entries)
+        .with_retry_policies(retry_policies)
+        .with_vision(vision)
+        .with_reasoning_replay(send_reasoning)
+        .with_tool_modes(tool_modes))
 }
 
 /// Build the embedder from config.
@@ -205,11 +239,13 @@ pub fn build_vector_store(
 /// Build the tool registry from config, including memory tools.
 pub fn build_tool_registry(
     config: &Config,
+    store: &Store,
     working_memory: skill::working_memory::WorkingMemoryMap,
     embedder: &Option<Arc<dyn embedding::Embedder>>,
     vector_store: &Option<Arc<dyn memory::VectorStore>>,
 ) -> skill::ToolRegistry {
-    let registry = skill::build_tool_registry(&config.tools, Some(working_memory.clone()));
+    let registry =
+        skill::build_tool_registry(&config.tools, Some(working_memory.clone()), Some(store));
     if let (Some(_emb), Some(_vs)) = (embedder, vector_store) {}
     registry
 }
@@ -296,6 +332,7 @@ pub fn build_approval_overrides(config: &Config) -> HashMap<String, ApprovalPoli
     if let Some(ref cfg) = config.tools.write_file {
         if let Some(policy) = cfg.approval {
             map.insert("write_file".to_string(), policy);
+            map.insert("edit_file".to_string(), policy);
         }
     }
     if let Some(ref cfg) = config.tools.fetch_url {
@@ -303,6 +340,38 @@ pub fn build_approval_overrides(config: &Config) -> HashMap<String, ApprovalPoli
             map.insert("fetch_url".to_string(), policy);
         }
     }
+    if let Some(ref cfg) = config.tools.run_command
+        && let Some(policy) = cfg.approval
+    {
+        map.insert("run_command".to_string(), policy);
+    }
+    if let Some(ref cfg) = config.tools.http_request {
+        if let Some(policy) = cfg.approval {
+            map.insert("http_request".to_string(), policy);
+        }
+        // Requests that change something are approved separately.
+        if let Some(policy) = cfg.write_approval {
+            map.insert("http_request:write".to_string(), policy);
+        }
+    }
+    if let Some(ref cfg) = config.tools.web_search
+        && let Some(policy) = cfg.approval
+    {
+        map.insert("web_search".to_string(), policy);
+    }
+    if let Some(ref cfg) = config.tools.schedule_reminder
+        && let Some(policy) = cfg.approval
+    {
+        map.insert("schedule_reminder".to_string(), policy);
+        map.insert("cancel_reminder".to_string(), policy);
+    }
+    if let Some(ref cfg) = config.tools.todos
+        && let Some(policy) = cfg.approval
+    {
+        for name in ["add_todo", "complete_todo", "delete_todo"] {
+            map.insert(name.to_string(), policy);
+        }
+    }
     map
 }
 
@@ -428,11 +497,36 @@ endpoint = "http://localhost:1234/v1"
 [tools.read_file]
 allowed_directories = ["/tmp"]
 approval = "trust"
+
+[tools.write_file]
+allowed_directories = ["/tmp"]
+approval = "once"
+
+[tools.http_request]
+allowed_domains = ["api.example.com"]
+approval = "trust"
+
+[tools.schedule_reminder]
+approval = "once"
+
+[tools.todos]
+approval = "trust"
 "#,
         )
         .unwrap();
         let overrides = build_approval_overrides(&config);
         assert_eq!(overrides.get("read_file"), Some(&ApprovalPolicy::Trust));
+        assert_eq!(overrides.get("write_file"), Some(&ApprovalPolicy::Once));
+        assert_eq!(overrides.get("edit_file"), Some(&ApprovalPolicy::Once));
+        assert_eq!(overrides.get("http_request"), Some(&ApprovalPolicy::Trust));
+        // Without write_approval, non-GET requests keep asking.
+        assert_eq!(overrides.get("http_request:write"), None);
+        assert_eq!(overrides.get("schedule_reminder"), Some(&ApprovalPolicy::Once));
+        assert_eq!(overrides.get("cancel_reminder"), Some(&ApprovalPolicy::Once));
+        assert_eq!(overrides.get("list_reminders"), None);
+        assert_eq!(overrides.get("add_todo"), Some(&ApprovalPolicy::Trust));
+        assert_eq!(overrides.get("delete_todo"), Some(&ApprovalPolicy::Trust));
+        assert_eq!(overrides.get("list_todos"), None);
     }
 
     #[test]
@@ -638,4 +732,40 @@ api_key_env = "BUDDY_TEST_GEMINI_KEY_047"
         );
         assert_eq!(chain.unwrap().len(), 1);
     }
+
+    #[test]
+    fn build_provider_chain_with_anthropic_provider_defaults_endpoint() {
+        // SAFETY: test-only; unique env var name avoids conflicts.
+        unsafe { std::env::set_var("BUDDY_TEST_ANTHROPIC_KEY", "test-key") };
+        let config = Config::parse(
+            r#"
+[[models.chat.providers]]
+type = "anthropic"
+model = "claude-sonnet-4-5"
+api_key_env = "BUDDY_TEST_ANTHROPIC_KEY"
+"#,
+        )
+        .unwrap();
+        let chain = build_provider_chain(&config);
+        unsafe { std::env::remove_var("BUDDY_TEST_ANTHROPIC_KEY") };
+        assert!(
+            chain.is_ok(),
+            "should build successfully with default endpoint"
+        );
+        assert_eq!(chain.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn build_provider_chain_anthropic_requires_api_key() {
+        let config = Config::parse(
+            r#"
+[[models.chat.providers]]
+type = "anthropic"
+model = "claude-sonnet-4-5"
+"#,
+        )
+        .unwrap();
+        let err = build_provider_chain(&config).err().expect("should fail without a key");
+        assert!(err.to_string().contains("anthropic"));
+    }
 }
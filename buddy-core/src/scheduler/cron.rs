@@ -0,0 +1,214 @@
+//! Five-field cron expressions: `minute hour day-of-month month day-of-week`.
+//!
+//! Each field accepts `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+//! (`*/15`, `9-17/2`). Day of week runs 0-7, where both 0 and 7 are Sunday.
+//! As in classic cron, when both day fields are restricted a day matching
+//! either of them is due.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+
+/// How many days ahead to search for the next run before giving up. Long
+/// enough to reach the next 29 February.
+const SEARCH_DAYS: u32 = 366 * 8;
+
+/// A parsed cron expression. Each field is a bit set of allowed values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse a five-field cron expression.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, "day of week")?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days: parse_field(day, 1, 31, "day of month")?,
+            months: parse_field(month, 1, 12, "month")?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    /// The first run strictly after `after`, in the same time zone.
+    ///
+    /// Local times skipped by a daylight-saving change are skipped; repeated
+    /// ones run at their first occurrence. Returns `None` for expressions that
+    /// never match, such as `0 0 30 2 *`.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_day(date) {
+                let first = if date == start.date() {
+                    start.hour() * 60 + start.minute()
+                } else {
+                    0
+                };
+                for minute_of_day in first..24 * 60 {
+                    let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                    if self.hours & (1 << hour) == 0 || self.minutes & (1 << minute) == 0 {
+                        continue;
+                    }
+                    let naive = date.and_hms_opt(hour, minute, 0)?;
+                    if let Some(time) = tz.from_local_datetime(&naive).earliest()
+                        && time > *after
+                    {
+                        return Some(time);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+/// Parse one field into a bit set of the values in `min..=max` it allows.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let invalid = || format!("invalid {name} '{part}'");
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(invalid()),
+            },
+            None => (part, None),
+        };
+        let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else if let Some((low, high)) = range.split_once('-') {
+            (number(low)?, number(high)?)
+        } else {
+            // `5/15` means every 15 starting at 5.
+            let value = number(range)?;
+            (value, if step.is_some() { max } else { value })
+        };
+        if low < min || high > max {
+            return Err(format!("{name} '{part}' is outside {min}-{max}"));
+        }
+        if low > high {
+            return Err(invalid());
+        }
+        for value in (low..=high).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expr: &str, after: &str) -> Option<DateTime<Utc>> {
+        CronSchedule::parse(expr).unwrap().next_after(&at(after))
+    }
+
+    #[test]
+    fn next_run_follows_each_field() {
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-17T10:07:30Z"),
+            Some(at("2026-10-17T10:15:00Z"))
+        );
+        // Exactly on a run time, the next one is returned.
+        assert_eq!(
+            next("0 8 * * *", "2026-10-17T08:00:00Z"),
+            Some(at("2026-10-18T08:00:00Z"))
+        );
+        // Weekdays only: Saturday 17 October goes to Monday.
+        assert_eq!(
+            next("30 9 * * 1-5", "2026-10-17T10:00:00Z"),
+            Some(at("2026-10-19T09:30:00Z"))
+        );
+        assert_eq!(
+            next("0 0 1 1,7 *", "2026-10-17T10:00:00Z"),
+            Some(at("2027-01-01T00:00:00Z"))
+        );
+        assert_eq!(
+            next("0 12 29 2 *", "2026-10-17T10:00:00Z"),
+            Some(at("2028-02-29T12:00:00Z"))
+        );
+        assert_eq!(next("0 0 30 2 *", "2026-10-17T10:00:00Z"), None);
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        let sunday = Some(at("2026-10-18T09:00:00Z"));
+        assert_eq!(next("0 9 * * 0", "2026-10-17T10:00:00Z"), sunday);
+        assert_eq!(next("0 9 * * 7", "2026-10-17T10:00:00Z"), sunday);
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 20th (a Tuesday) or any Sunday, whichever comes first.
+        assert_eq!(
+            next("0 9 20 * 0", "2026-10-17T10:00:00Z"),
+            Some(at("2026-10-18T09:00:00Z"))
+        );
+        assert_eq!(
+            next("0 9 20 * 0", "2026-10-18T10:00:00Z"),
+            Some(at("2026-10-20T09:00:00Z"))
+        );
+        // A stepped day-of-week still counts as unrestricted.
+        assert_eq!(
+            next("0 9 20 * */1", "2026-10-17T10:00:00Z"),
+            Some(at("2026-10-20T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for (expr, error) in [
+            ("* * * *", "expected 5 fields"),
+            ("60 * * * *", "minute '60' is outside 0-59"),
+            ("* * 0 * *", "day of month '0' is outside 1-31"),
+            ("* * * * 8", "day of week '8' is outside 0-7"),
+            ("*/0 * * * *", "invalid minute '*/0'"),
+            ("5-1 * * * *", "invalid minute '5-1'"),
+            ("mon * * * *", "invalid minute 'mon'"),
+        ] {
+            let err = CronSchedule::parse(expr).unwrap_err();
+            assert!(err.contains(error), "{expr}: {err}");
+        }
+    }
+}
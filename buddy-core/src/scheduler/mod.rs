@@ -0,0 +1,242 @@
+//! Delivery of scheduled jobs (reminders and scheduled prompts).
+//!
+//! Jobs live in the [`Store`], so they survive restarts. Each chat interface
+//! runs [`run`] for its own channel and supplies the delivery: sending a
+//! reminder, or running a prompt through the engine and sending the reply.
+//! Delivered one-shot jobs are deleted, and failed ones are retried a few
+//! times first; recurring jobs move to their next run.
+
+pub mod cron;
+
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::store::{Job, JobKind, Store};
+use crate::types::{Message, MessageContent, Role};
+
+pub use cron::CronSchedule;
+
+/// How often chat interfaces check for due jobs.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Deliveries tried for a one-shot job before it is dropped.
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// Wait before retrying a failed delivery, multiplied by the number of
+/// failures so far.
+const RETRY_DELAY: chrono::TimeDelta = chrono::TimeDelta::minutes(2);
+
+/// The next run of the cron expression `schedule` after `after`. Schedules
+/// are evaluated in the server's local time zone.
+pub fn next_run(schedule: &str, after: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    CronSchedule::parse(schedule)?
+        .next_after(&after.with_timezone(&Local))
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("'{schedule}' never runs"))
+}
+
+/// The text sent for a `message` job.
+pub fn reminder_text(job: &Job) -> String {
+    format!("⏰ Reminder: {}", job.text)
+}
+
+/// The user message a `prompt` job runs as.
+pub fn prompt_message(job: &Job) -> Message {
+    Message {
+        role: Role::User,
+        content: MessageContent::Text {
+            text: format!("[Scheduled task] {}", job.text),
+        },
+        timestamp: Utc::now(),
+    }
+}
+
+/// Deliver the due jobs of `channel` every `interval`, forever.
+pub async fn run<F, Fut>(store: &Store, channel: &str, interval: Duration, deliver: F)
+where
+    F: Fn(Job) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    loop {
+        run_due_jobs(store, channel, Utc::now(), &deliver).await;
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Deliver the jobs of `channel` that are due at `now`, returning how many
+/// were delivered.
+///
+/// A one-shot job whose delivery fails is retried after a growing delay, up
+/// to [`MAX_DELIVERY_ATTEMPTS`] deliveries, then dropped. A failed run of a
+/// recurring job is skipped. Recurring jobs resume from `now`, so runs missed
+/// while the interface was down are not replayed. Delivered reminders are
+/// recorded in the conversation so the model sees them as context.
+pub async fn run_due_jobs<F, Fut>(
+    store: &Store,
+    channel: &str,
+    now: DateTime<Utc>,
+    deliver: &F,
+) -> usize
+where
+    F: Fn(Job) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let jobs = match store.due_jobs(channel, now) {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("warning: failed to load due jobs: {e}");
+            return 0;
+        }
+    };
+    let mut delivered = 0;
+    for job in jobs {
+        let id = job.id.clone();
+        let conversation_id = job.conversation_id.clone();
+        let schedule = job.schedule.clone();
+        let attempts = job.attempts + 1;
+        let reminder = (job.kind == JobKind::Message).then(|| reminder_text(&job));
+        let failed = match deliver(job).await {
+            Ok(()) => {
+                delivered += 1;
+                if let Some(text) = reminder {
+                    let message = Message {
+                        role: Role::Assistant,
+                        content: MessageContent::Text { text },
+                        timestamp: Utc::now(),
+                    };
+                    if let Err(e) = store.append_message(&conversation_id, &message) {
+                        eprintln!("warning: failed to record reminder {id}: {e}");
+                    }
+                }
+                false
+            }
+            Err(e) => {
+                eprintln!("warning: failed to deliver job {id} (attempt {attempts}): {e}");
+                true
+            }
+        };
+        let updated = match schedule.map(|schedule| next_run(&schedule, now)) {
+            Some(Ok(next)) => store.reschedule_job(&id, next),
+            Some(Err(e)) => {
+                eprintln!("warning: dropping job {id}: {e}");
+                store.delete_job(&id).map(|_| ())
+            }
+            None if failed && attempts < MAX_DELIVERY_ATTEMPTS => {
+                store.retry_job(&id, now + RETRY_DELAY * attempts as i32)
+            }
+            None => {
+                if failed {
+                    eprintln!("warning: dropping job {id} after {attempts} failed deliveries");
+                }
+                store.delete_job(&id).map(|_| ())
+            }
+        };
+        if let Err(e) = updated {
+            eprintln!("warning: failed to update job {id}: {e}");
+        }
+    }
+    delivered
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::store::NewJob;
+
+    fn schedule(store: &Store, conversation_id: &str, text: &str, cron: Option<&str>) -> Job {
+        store
+            .create_job(&NewJob {
+                conversation_id,
+                channel: "telegram",
+                target: "42",
+                kind: JobKind::Message,
+                text,
+                schedule: cron,
+                next_run_at: Utc::now() - chrono::Duration::minutes(1),
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn due_jobs_are_delivered_then_rescheduled_or_removed() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store
+            .create_conversation_with_source("Chat", "telegram")
+            .unwrap();
+        let once = schedule(&store, &conv.id, "call mom", None);
+        let daily = schedule(&store, &conv.id, "stretch", Some("0 9 * * *"));
+
+        let sent = Mutex::new(Vec::new());
+        let now = Utc::now();
+        let deliver = |job: Job| {
+            sent.lock().unwrap().push(job.text);
+            async { Ok(()) }
+        };
+        assert_eq!(run_due_jobs(&store, "telegram", now, &deliver).await, 2);
+        assert_eq!(*sent.lock().unwrap(), ["call mom", "stretch"]);
+
+        assert!(store.get_job(&once.id).unwrap().is_none());
+        let daily = store.get_job(&daily.id).unwrap().unwrap();
+        assert!(daily.next_run_at > now);
+        assert_eq!(daily.next_run_at, next_run("0 9 * * *", now).unwrap());
+
+        // Nothing is due any more, and the reminders are in the conversation.
+        assert_eq!(run_due_jobs(&store, "telegram", now, &deliver).await, 0);
+        let conv = store.get_conversation(&conv.id).unwrap().unwrap();
+        let texts: Vec<_> = conv
+            .messages
+            .iter()
+            .map(|m| match &m.content {
+                MessageContent::Text { text } => text.as_str(),
+                other => panic!("unexpected content {other:?}"),
+            })
+            .collect();
+        assert_eq!(texts, ["⏰ Reminder: call mom", "⏰ Reminder: stretch"]);
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried_a_few_times() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store
+            .create_conversation_with_source("Chat", "telegram")
+            .unwrap();
+        let once = schedule(&store, &conv.id, "call mom", None);
+        let daily = schedule(&store, &conv.id, "stretch", Some("0 9 * * *"));
+
+        let fail = |_: Job| async { Err("network unreachable".to_string()) };
+        let mut now = Utc::now();
+        assert_eq!(run_due_jobs(&store, "telegram", now, &fail).await, 0);
+        // The failed run of the recurring job is skipped.
+        let daily = store.get_job(&daily.id).unwrap().unwrap();
+        assert_eq!(daily.next_run_at, next_run("0 9 * * *", now).unwrap());
+        assert_eq!(daily.attempts, 0);
+
+        // The one-shot job is retried later, until it is delivered...
+        let retry = store.get_job(&once.id).unwrap().unwrap();
+        assert_eq!(retry.attempts, 1);
+        assert!(retry.next_run_at > now);
+        assert_eq!(run_due_jobs(&store, "telegram", now, &fail).await, 0);
+        now = retry.next_run_at;
+        let deliver = |_: Job| async { Ok(()) };
+        assert_eq!(run_due_jobs(&store, "telegram", now, &deliver).await, 1);
+        assert!(store.get_job(&once.id).unwrap().is_none());
+
+        // ...or dropped after the last attempt.
+        let job = schedule(&store, &conv.id, "water plants", None);
+        for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+            assert_eq!(
+                store.get_job(&job.id).unwrap().unwrap().attempts,
+                attempt - 1
+            );
+            run_due_jobs(&store, "telegram", now, &fail).await;
+            now += chrono::TimeDelta::hours(1);
+        }
+        assert!(store.get_job(&job.id).unwrap().is_none());
+        let conv = store.get_conversation(&conv.id).unwrap().unwrap();
+        assert_eq!(conv.messages.len(), 1);
+    }
+}
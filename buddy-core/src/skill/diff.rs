@@ -0,0 +1,341 @@
+//! Line diffs: rendering unified diffs for approval previews and applying
+//! unified-diff hunks for `edit_file`.
+//!
+//! No diff crate is pulled in for this; the inputs are single text files, so
+//! a plain LCS over the lines that differ is fast enough.
+
+use super::ToolError;
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+/// Beyond this many LCS cells the changed region is shown as one
+/// removal followed by one insertion instead of a minimal diff.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Diff two line lists into a sequence of operations, each with its line.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<(Op, &str)> = old[..prefix].iter().map(|l| (Op::Equal, *l)).collect();
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if n.saturating_mul(m) > MAX_LCS_CELLS {
+        ops.extend(old_mid.iter().map(|l| (Op::Delete, *l)));
+        ops.extend(new_mid.iter().map(|l| (Op::Insert, *l)));
+    } else {
+        // lcs[i][j]: length of the LCS of old_mid[i..] and new_mid[j..].
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_mid[i] == new_mid[j] {
+                ops.push((Op::Equal, old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                ops.push((Op::Delete, old_mid[i]));
+                i += 1;
+            } else {
+                ops.push((Op::Insert, new_mid[j]));
+                j += 1;
+            }
+        }
+        ops.extend(old_mid[i..].iter().map(|l| (Op::Delete, *l)));
+        ops.extend(new_mid[j..].iter().map(|l| (Op::Insert, *l)));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| (Op::Equal, *l)));
+    ops
+}
+
+/// Render the change from `old` to `new` as a unified diff with `path` in
+/// the file headers. Returns an empty string when nothing changes.
+pub(crate) fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    // Group the changes into hunks (ranges of `ops`) with their context,
+    // merging hunks whose context would touch.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+    {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + 1 + CONTEXT_LINES).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {path}\n+++ {path}\n");
+    // Lines of each file before the current position in `ops`.
+    let (mut old_pos, mut new_pos, mut at) = (0, 0, 0);
+    for (start, end) in hunks {
+        for (op, _) in &ops[at..start] {
+            old_pos += usize::from(*op != Op::Insert);
+            new_pos += usize::from(*op != Op::Delete);
+        }
+        let old_count = ops[start..end]
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .count();
+        let new_count = ops[start..end]
+            .iter()
+            .filter(|(op, _)| *op != Op::Delete)
+            .count();
+        // An empty range is numbered by the line before it.
+        let old_start = if old_count == 0 { old_pos } else { old_pos + 1 };
+        let new_start = if new_count == 0 { new_pos } else { new_pos + 1 };
+        out.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+        for (op, line) in &ops[start..end] {
+            let sign = match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            };
+            out.push(sign);
+            out.push_str(line);
+            out.push('\n');
+        }
+        old_pos += old_count;
+        new_pos += new_count;
+        at = end;
+    }
+    out
+}
+
+/// One hunk of a unified diff.
+#[derive(Debug)]
+struct Hunk {
+    /// 1-based start line in the original file, from the `@@` header.
+    old_start: Option<usize>,
+    /// Lines the hunk expects: context and removed lines.
+    old: Vec<String>,
+    /// Lines that replace them: context and added lines.
+    new: Vec<String>,
+}
+
+fn parse_hunks(diff: &str) -> Result<Vec<Hunk>, ToolError> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        if line.starts_with("@@") {
+            // `@@ -12,5 +12,6 @@`; a bare `@@` is accepted without a line hint.
+            let old_start = line
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('-'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok());
+            hunks.push(Hunk {
+                old_start,
+                old: Vec::new(),
+                new: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // File headers and anything else before the first hunk.
+            continue;
+        };
+        let next_is_header = lines.get(number + 1).is_some_and(|l| l.starts_with("+++ "));
+        if line.starts_with("diff ") || (line.starts_with("--- ") && next_is_header) {
+            // The headers of a following file; only one file is edited.
+            break;
+        }
+        match line.chars().next() {
+            Some('+') => hunk.new.push(line[1..].to_string()),
+            Some('-') => hunk.old.push(line[1..].to_string()),
+            Some(' ') => {
+                hunk.old.push(line[1..].to_string());
+                hunk.new.push(line[1..].to_string());
+            }
+            // Editors often strip the space from blank context lines.
+            None => {
+                hunk.old.push(String::new());
+                hunk.new.push(String::new());
+            }
+            Some('\\') => {}
+            Some(_) => {
+                return Err(ToolError::InvalidInput(format!(
+                    "diff line {}: expected ' ', '-' or '+' at the start of a hunk line",
+                    number + 1
+                )));
+            }
+        }
+    }
+    if hunks.is_empty() {
+        return Err(ToolError::InvalidInput(
+            "diff contains no hunks (lines starting with '@@')".into(),
+        ));
+    }
+    Ok(hunks)
+}
+
+/// Apply the hunks of a unified diff to `content`.
+///
+/// Each hunk must match the file exactly, after the previous hunk. When its
+/// lines occur more than once, the occurrence nearest the line number in
+/// the hunk header is used. Any hunk that does not match fails the whole
+/// diff, leaving the content untouched.
+pub(crate) fn apply_unified_diff(content: &str, diff: &str) -> Result<String, ToolError> {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    // Where the search for the next hunk starts, and how far earlier hunks
+    // have shifted the line numbers in the headers.
+    let mut cursor = 0;
+    let mut shift: isize = 0;
+
+    for (index, hunk) in parse_hunks(diff)?.into_iter().enumerate() {
+        let number = index + 1;
+        let hint = hunk
+            .old_start
+            .map(|start| (start as isize - 1 + shift).max(0) as usize);
+        let position = if hunk.old.is_empty() {
+            // A pure insertion is placed after the header's line.
+            let start = hunk.old_start.ok_or_else(|| {
+                ToolError::InvalidInput(format!(
+                    "hunk {number} has no context lines and no line number"
+                ))
+            })?;
+            let at = (start as isize + shift).max(0) as usize;
+            if at > lines.len() {
+                return Err(ToolError::InvalidInput(format!(
+                    "hunk {number} inserts after line {start}, past the end of the file"
+                )));
+            }
+            at
+        } else {
+            let candidates: Vec<usize> = (cursor..=lines.len().saturating_sub(hunk.old.len()))
+                .filter(|&at| lines[at..].starts_with(&hunk.old))
+                .collect();
+            match (candidates.as_slice(), hint) {
+                ([], _) => {
+                    return Err(ToolError::InvalidInput(format!(
+                        "hunk {number} does not match the file: these lines were not found{}:\n{}",
+                        hunk.old_start
+                            .map(|start| format!(" (expected near line {start})"))
+                            .unwrap_or_default(),
+                        hunk.old.join("\n")
+                    )));
+                }
+                ([at], _) => *at,
+                (_, Some(hint)) => *candidates
+                    .iter()
+                    .min_by_key(|&&at| at.abs_diff(hint))
+                    .expect("candidates is not empty"),
+                (_, None) => {
+                    return Err(ToolError::InvalidInput(format!(
+                        "hunk {number} matches {} places in the file; add a line number \
+                         to its '@@' header or more context lines",
+                        candidates.len()
+                    )));
+                }
+            }
+        };
+
+        let removed = hunk.old.len();
+        let added = hunk.new.len();
+        lines.splice(position..position + removed, hunk.new);
+        cursor = position + added;
+        if let Some(start) = hunk.old_start {
+            shift = position as isize - (start as isize - 1) + added as isize - removed as isize;
+        }
+    }
+
+    let mut result = lines.join(newline);
+    if content.ends_with('\n') || (content.is_empty() && !result.is_empty()) {
+        result.push_str(newline);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_shows_changes_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            unified_diff("notes.txt", old, new),
+            "--- notes.txt\n+++ notes.txt\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -8,3 +8,4 @@\n h\n i\n j\n+k\n"
+        );
+        assert_eq!(unified_diff("same.txt", old, old), "");
+        assert_eq!(
+            unified_diff("new.txt", "", "hello\n"),
+            "--- new.txt\n+++ new.txt\n@@ -0,0 +1,1 @@\n+hello\n"
+        );
+    }
+
+    #[test]
+    fn apply_unified_diff_round_trips_rendered_diffs() {
+        let old = "fn main() {\n    println!(\"hi\");\n}\n\nfn helper() {}\n";
+        let new = "fn main() {\n    println!(\"hello\");\n    helper();\n}\n\nfn helper() {}\n";
+        let diff = unified_diff("main.rs", old, new);
+        assert_eq!(apply_unified_diff(old, &diff).unwrap(), new);
+    }
+
+    #[test]
+    fn apply_unified_diff_uses_the_header_line_to_pick_between_matches() {
+        let content = "x = 1\ny = 2\nx = 1\ny = 2\n";
+        let diff = "@@ -3,2 +3,2 @@\n x = 1\n-y = 2\n+y = 3\n";
+        assert_eq!(
+            apply_unified_diff(content, diff).unwrap(),
+            "x = 1\ny = 2\nx = 1\ny = 3\n"
+        );
+
+        let err = apply_unified_diff(content, "@@\n x = 1\n-y = 2\n+y = 3\n").unwrap_err();
+        assert!(err.to_string().contains("matches 2 places"), "{err}");
+    }
+
+    #[test]
+    fn apply_unified_diff_fails_cleanly_when_a_hunk_does_not_match() {
+        let content = "one\ntwo\nthree\n";
+        let diff = "@@ -1,2 +1,2 @@\n one\n-two\n+2\n@@ -3,1 +3,1 @@\n-four\n+4\n";
+        let err = apply_unified_diff(content, diff).unwrap_err();
+        assert!(
+            matches!(&err, ToolError::InvalidInput(msg) if msg.starts_with("hunk 2 does not match")),
+            "{err}"
+        );
+
+        assert!(apply_unified_diff(content, "just some text").is_err());
+        assert!(apply_unified_diff(content, "@@ -1 +1 @@\n*one\n").is_err());
+    }
+}
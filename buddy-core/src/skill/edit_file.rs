@@ -0,0 +1,318 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::config::WriteFileConfig;
+
+use super::diff::{apply_unified_diff, unified_diff};
+use super::read_file::validate_path;
+use super::{PermissionLevel, Tool, ToolError};
+
+/// Skill that applies targeted edits to existing files in the `write_file`
+/// sandbox, either as search/replace pairs or as unified-diff hunks.
+pub struct EditFileSkill {
+    allowed_directories: Vec<PathBuf>,
+}
+
+impl EditFileSkill {
+    pub fn new(config: &WriteFileConfig) -> Self {
+        Self {
+            allowed_directories: config
+                .allowed_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+        }
+    }
+
+    /// Resolve and read the file named by the input.
+    fn read_target(&self, input: &serde_json::Value) -> Result<(PathBuf, String), ToolError> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidInput("missing required field: path".into()))?;
+        let resolved = validate_path(path, &self.allowed_directories)?;
+        if !resolved.is_file() {
+            return Err(ToolError::InvalidInput(format!("'{path}' is not a file")));
+        }
+        let content = std::fs::read_to_string(&resolved).map_err(|e| {
+            ToolError::ExecutionFailed(format!("failed to read '{path}' as text: {e}"))
+        })?;
+        Ok((resolved, content))
+    }
+}
+
+/// Apply the `edits` or the `diff` in `input` to `content`.
+fn apply_edits(content: &str, input: &serde_json::Value) -> Result<String, ToolError> {
+    match (
+        input.get("edits"),
+        input.get("diff").and_then(|v| v.as_str()),
+    ) {
+        (Some(_), Some(_)) => Err(ToolError::InvalidInput(
+            "give either edits or diff, not both".into(),
+        )),
+        (None, None) => Err(ToolError::InvalidInput(
+            "missing required field: edits or diff".into(),
+        )),
+        (None, Some(diff)) => apply_unified_diff(content, diff),
+        (Some(edits), None) => {
+            let edits = edits
+                .as_array()
+                .ok_or_else(|| ToolError::InvalidInput("edits must be an array".into()))?;
+            let mut result = content.to_string();
+            for (index, edit) in edits.iter().enumerate() {
+                let number = index + 1;
+                let old_text = edit["old_text"].as_str().unwrap_or_default();
+                let new_text = edit["new_text"].as_str().ok_or_else(|| {
+                    ToolError::InvalidInput(format!("edit {number}: missing new_text"))
+                })?;
+                if old_text.is_empty() {
+                    return Err(ToolError::InvalidInput(format!(
+                        "edit {number}: old_text must not be empty"
+                    )));
+                }
+                let replace_all = edit["replace_all"].as_bool().unwrap_or(false);
+                match result.matches(old_text).count() {
+                    0 => {
+                        return Err(ToolError::InvalidInput(format!(
+                            "edit {number}: old_text was not found in the file; it must match \
+                             exactly, including whitespace"
+                        )));
+                    }
+                    1 => {}
+                    count if !replace_all => {
+                        return Err(ToolError::InvalidInput(format!(
+                            "edit {number}: old_text matches {count} places in the file; \
+                             include more surrounding text or set replace_all"
+                        )));
+                    }
+                    _ => {}
+                }
+                result = result.replace(old_text, new_text);
+            }
+            Ok(result)
+        }
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+impl Tool for EditFileSkill {
+    fn name(&self) -> &str {
+        "edit_file"
+    }
+
+    fn description(&self) -> &str {
+        "Edit an existing file in an allowed directory by replacing exact text or applying \
+         unified-diff hunks. Nothing is written if any edit does not match."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Path to the file to edit" },
+                "edits": {
+                    "type": "array",
+                    "description": "Replacements applied in order (use this or diff)",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "old_text": {
+                                "type": "string",
+                                "description": "Exact text to replace; must occur once \
+                                                unless replace_all is set"
+                            },
+                            "new_text": { "type": "string", "description": "Replacement text" },
+                            "replace_all": {
+                                "type": "boolean",
+                                "description": "Replace every occurrence (default: false)"
+                            }
+                        },
+                        "required": ["old_text", "new_text"]
+                    }
+                },
+                "diff": {
+                    "type": "string",
+                    "description": "Unified diff hunks (starting with @@) to apply (use this \
+                                    or edits)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let preview = self.read_target(input).and_then(|(path, content)| {
+            let edited = apply_edits(&content, input)?;
+            Ok(unified_diff(&display_path(&path), &content, &edited))
+        });
+        Some(match preview {
+            Ok(diff) if diff.is_empty() => "(no changes)".to_string(),
+            Ok(diff) => diff,
+            Err(e) => format!("This edit will fail: {e}"),
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let (resolved, content) = self.read_target(&input)?;
+            let edited = apply_edits(&content, &input)?;
+            std::fs::write(&resolved, &edited)
+                .map_err(|e| ToolError::ExecutionFailed(format!("failed to write file: {e}")))?;
+
+            Ok(serde_json::json!({
+                "path": display_path(&resolved),
+                "diff": unified_diff(&display_path(&resolved), &content, &edited)
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(name: &str) -> (PathBuf, EditFileSkill) {
+        let dir = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = std::fs::canonicalize(dir).unwrap();
+        let skill = EditFileSkill {
+            allowed_directories: vec![dir.clone()],
+        };
+        (dir, skill)
+    }
+
+    #[tokio::test]
+    async fn applies_search_replace_edits_in_order() {
+        let (dir, skill) = sandbox("buddy-test-edit-file-replace");
+        let file = dir.join("config.toml");
+        std::fs::write(&file, "name = \"a\"\nport = 80\nhost = \"a\"\n").unwrap();
+
+        let result = skill
+            .execute(serde_json::json!({
+                "path": file.to_str().unwrap(),
+                "edits": [
+                    { "old_text": "port = 80", "new_text": "port = 8080" },
+                    { "old_text": "\"a\"", "new_text": "\"b\"", "replace_all": true }
+                ]
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "name = \"b\"\nport = 8080\nhost = \"b\"\n"
+        );
+        let diff = result["diff"].as_str().unwrap();
+        assert!(diff.contains("\n-port = 80\n"), "{diff}");
+        assert!(diff.contains("\n+port = 8080\n"), "{diff}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn failed_edit_leaves_the_file_untouched() {
+        let (dir, skill) = sandbox("buddy-test-edit-file-nomatch");
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "one\ntwo\ntwo\n").unwrap();
+        let path = file.to_str().unwrap();
+
+        for edits in [
+            serde_json::json!([
+                { "old_text": "one", "new_text": "1" },
+                { "old_text": "three", "new_text": "3" }
+            ]),
+            serde_json::json!([{ "old_text": "two", "new_text": "2" }]),
+        ] {
+            let result = skill
+                .execute(serde_json::json!({ "path": path, "edits": edits }))
+                .await;
+            assert!(
+                matches!(result, Err(ToolError::InvalidInput(_))),
+                "{result:?}"
+            );
+        }
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "diff": "@@ -1 +1 @@\n-uno\n+1\n" }))
+            .await;
+        assert!(
+            matches!(result, Err(ToolError::InvalidInput(_))),
+            "{result:?}"
+        );
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one\ntwo\ntwo\n");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn applies_unified_diff() {
+        let (dir, skill) = sandbox("buddy-test-edit-file-diff");
+        let file = dir.join("main.py");
+        std::fs::write(&file, "def main():\n    print('hi')\n").unwrap();
+        skill
+            .execute(serde_json::json!({
+                "path": file.to_str().unwrap(),
+                "diff": "--- main.py\n+++ main.py\n@@ -1,2 +1,3 @@\n def main():\n\
+                         -    print('hi')\n+    print('hello')\n+    return 0\n"
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "def main():\n    print('hello')\n    return 0\n"
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn approval_preview_renders_the_diff_or_the_failure() {
+        let (dir, skill) = sandbox("buddy-test-edit-file-preview");
+        let file = dir.join("a.txt");
+        std::fs::write(&file, "hello\nworld\n").unwrap();
+        let path = file.to_str().unwrap();
+
+        let preview = skill
+            .approval_preview(&serde_json::json!({
+                "path": path,
+                "edits": [{ "old_text": "world", "new_text": "there" }]
+            }))
+            .unwrap();
+        assert_eq!(
+            preview,
+            format!("--- {path}\n+++ {path}\n@@ -1,2 +1,2 @@\n hello\n-world\n+there\n")
+        );
+
+        let preview = skill
+            .approval_preview(&serde_json::json!({
+                "path": path,
+                "edits": [{ "old_text": "moon", "new_text": "sun" }]
+            }))
+            .unwrap();
+        assert!(preview.starts_with("This edit will fail: "), "{preview}");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello\nworld\n");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn file_outside_sandbox_is_forbidden() {
+        let (dir, skill) = sandbox("buddy-test-edit-file-sandbox");
+        let result = skill
+            .execute(serde_json::json!({
+                "path": "/etc/hostname",
+                "edits": [{ "old_text": "a", "new_text": "b" }]
+            }))
+            .await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -2,32 +2,93 @@ use std::future::Future;
 use std::pin::Pin;
 use std::time::Duration;
 
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+
 use crate::config::FetchUrlConfig;
 
+use super::html;
+use super::ssrf::{GuardedClient, parse_networks};
+use super::web_search::SearchGrants;
 use super::{PermissionLevel, Tool, ToolError};
 
+/// Bytes of extracted content returned per call when the config does not
+/// say otherwise; longer documents are paged with `offset`.
+pub const DEFAULT_MAX_CONTENT_BYTES: usize = 20_000;
+/// Bytes of response body downloaded when the config does not say otherwise.
+pub const DEFAULT_MAX_DOWNLOAD_BYTES: usize = 5 * 1024 * 1024;
+
 /// Skill that fetches URLs via HTTP GET, restricted to allowlisted domains.
+///
+/// Private and local addresses are refused unless allowed by the config,
+/// and every redirect is checked again (see `ssrf`). Web pages are reduced
+/// to their main content as Markdown, JSON is pretty-printed and binary
+/// content is refused without being downloaded.
+///
+/// With search grants, URLs `web_search` returned in the current turn may
+/// be fetched too, along with redirects that stay on their host.
 pub struct FetchUrlSkill {
     allowed_domains: Vec<String>,
-    client: reqwest::Client,
+    search_grants: Option<SearchGrants>,
+    client: GuardedClient,
+    max_content_bytes: usize,
+    max_download_bytes: usize,
 }
 
 impl FetchUrlSkill {
     pub fn new(config: &FetchUrlConfig) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .expect("failed to build HTTP client");
+        let client = GuardedClient::new(
+            Duration::from_secs(10),
+            parse_networks(&config.allowed_private_networks),
+        );
 
         Self {
             allowed_domains: config.allowed_domains.clone(),
+            search_grants: None,
             client,
+            max_content_bytes: config
+                .max_content_bytes
+                .unwrap_or(DEFAULT_MAX_CONTENT_BYTES)
+                .max(1),
+            max_download_bytes: config
+                .max_download_bytes
+                .unwrap_or(DEFAULT_MAX_DOWNLOAD_BYTES),
+        }
+    }
+
+    /// Also allow the URLs granted by `web_search`.
+    pub fn with_search_grants(mut self, grants: SearchGrants) -> Self {
+        self.search_grants = Some(grants);
+        self
+    }
+
+    /// Check `url` against the allowlist, or failing that against the
+    /// search results of `conversation_id`: a granted URL, or a redirect
+    /// from `granted` to the same host.
+    fn check_url(
+        &self,
+        url: &url::Url,
+        conversation_id: &str,
+        granted: Option<&url::Url>,
+    ) -> Result<(), ToolError> {
+        let refusal = match validate_domain(url.as_str(), &self.allowed_domains) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let Some(grants) = &self.search_grants else {
+            return Err(refusal);
+        };
+        if grants.is_granted(conversation_id, url.as_str())
+            || granted.is_some_and(|g| g.host_str() == url.host_str())
+        {
+            return Ok(());
         }
+        Err(refusal)
     }
 }
 
 /// Validate that a URL's domain is in the allowlist.
-fn validate_domain(url_str: &str, allowed_domains: &[String]) -> Result<(), ToolError> {
+pub(super) fn validate_domain(url_str: &str, allowed_domains: &[String]) -> Result<(), ToolError> {
     let parsed = url::Url::parse(url_str)
         .map_err(|e| ToolError::InvalidInput(format!("invalid URL: {e}")))?;
 
@@ -35,22 +96,11 @@ fn validate_domain(url_str: &str, allowed_domains: &[String]) -> Result<(), Tool
         .host_str()
         .ok_or_else(|| ToolError::InvalidInput("URL has no host".into()))?;
 
-    // Check for exact match or wildcard match
-    for allowed in allowed_domains {
-        if allowed == domain {
-            return Ok(());
-        }
-        // "*" matches everything
-        if allowed == "*" {
-            return Ok(());
-        }
-        // "*.example.com" matches subdomains
-        if allowed.starts_with("*.") {
-            let suffix = &allowed[1..]; // ".example.com"
-            if domain.ends_with(suffix) {
-                return Ok(());
-            }
-        }
+    if allowed_domains
+        .iter()
+        .any(|allowed| domain_matches(allowed, domain))
+    {
+        return Ok(());
     }
 
     // No match found
@@ -59,13 +109,167 @@ fn validate_domain(url_str: &str, allowed_domains: &[String]) -> Result<(), Tool
     )))
 }
 
+/// Whether `domain` matches an allowlist entry: an exact name, `*` for
+/// everything, or `*.example.com` for subdomains.
+pub(super) fn domain_matches(allowed: &str, domain: &str) -> bool {
+    if allowed == domain || allowed == "*" {
+        return true;
+    }
+    // "*.example.com" matches subdomains
+    allowed
+        .strip_prefix('*')
+        .is_some_and(|suffix| suffix.starts_with('.') && domain.ends_with(suffix))
+}
+
+/// How a response body is turned into text for the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ContentKind {
+    Html,
+    Json,
+    Text,
+    Binary,
+}
+
+/// Classify a response by its MIME type (without parameters), or by
+/// sniffing the start of the body when the server sent none.
+pub(super) fn classify(mime: Option<&str>, body_start: &[u8]) -> ContentKind {
+    let Some(mime) = mime else {
+        if body_start.contains(&0) {
+            return ContentKind::Binary;
+        }
+        let start = String::from_utf8_lossy(body_start);
+        let start = start.trim_start().to_ascii_lowercase();
+        return if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            ContentKind::Html
+        } else if start.starts_with('{') || start.starts_with('[') {
+            ContentKind::Json
+        } else {
+            ContentKind::Text
+        };
+    };
+    match mime {
+        "text/html" | "application/xhtml+xml" => ContentKind::Html,
+        "application/json" => ContentKind::Json,
+        _ if mime.ends_with("+json") => ContentKind::Json,
+        _ if mime.starts_with("text/") || mime.ends_with("+xml") => ContentKind::Text,
+        "application/xml"
+        | "application/javascript"
+        | "application/ecmascript"
+        | "application/x-javascript"
+        | "application/yaml"
+        | "application/x-yaml"
+        | "application/toml"
+        | "application/x-ndjson"
+        | "application/x-sh"
+        | "application/sql"
+        | "application/graphql"
+        | "application/x-www-form-urlencoded" => ContentKind::Text,
+        _ => ContentKind::Binary,
+    }
+}
+
+/// The MIME type of a Content-Type header, lowercased and without
+/// parameters.
+pub(super) fn mime_type(content_type: Option<&str>) -> Option<String> {
+    content_type
+        .map(|ct| {
+            ct.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        })
+        .filter(|mime| !mime.is_empty())
+}
+
+/// The `charset` parameter of a Content-Type header.
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+/// The charset declared by a `<meta>` tag near the start of an HTML page.
+fn meta_charset(body: &[u8]) -> Option<String> {
+    let start = String::from_utf8_lossy(&body[..body.len().min(1024)]);
+    let re =
+        Regex::new(r#"(?i)<meta[^>]*?charset\s*=\s*["']?\s*([a-z0-9_.:-]+)"#).expect("valid regex");
+    re.captures(&start).map(|c| c[1].to_string())
+}
+
+/// Decode a body using the charset from the header, then from the page's
+/// `<meta>` tag, falling back to UTF-8. A byte-order mark wins over both.
+pub(super) fn decode_body(body: &[u8], content_type: Option<&str>, kind: ContentKind) -> String {
+    let label = content_type
+        .and_then(charset_param)
+        .map(str::to_string)
+        .or_else(|| {
+            (kind == ContentKind::Html)
+                .then(|| meta_charset(body))
+                .flatten()
+        });
+    let encoding = label
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(body).0.into_owned()
+}
+
+/// Read the body of `response`, keeping at most `cap` bytes. Returns the
+/// bytes and whether anything was left unread.
+pub(super) async fn read_body(
+    mut response: reqwest::Response,
+    cap: usize,
+) -> Result<(Vec<u8>, bool), ToolError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(format!("failed to read response: {e}")))?
+    {
+        let room = cap - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+/// The part of `content` starting at byte `offset` (moved back to a char
+/// boundary) and at most `max` bytes long, preferring to end at a line
+/// break. Returns the page, its start and the offset of the next page.
+fn page(content: &str, offset: usize, max: usize) -> (&str, usize, Option<usize>) {
+    let mut start = offset.min(content.len());
+    while !content.is_char_boundary(start) {
+        start -= 1;
+    }
+    if content.len() - start <= max {
+        return (&content[start..], start, None);
+    }
+    let mut end = start + max;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Break after the last newline in the second half of the page.
+    if let Some(newline) = content[start..end].rfind('\n').filter(|&n| n >= max / 2) {
+        end = start + newline + 1;
+    }
+    (&content[start..end], start, Some(end))
+}
+
 impl Tool for FetchUrlSkill {
     fn name(&self) -> &str {
         "fetch_url"
     }
 
     fn description(&self) -> &str {
-        "Fetch the contents of a URL via HTTP GET"
+        "Fetch a URL via HTTP GET. Web pages are returned as Markdown of their main content, \
+         JSON is pretty-printed and binary files are not returned. Long content is split into \
+         pages: call again with the returned next_offset to continue."
     }
 
     fn permission_level(&self) -> PermissionLevel {
@@ -76,7 +280,13 @@ impl Tool for FetchUrlSkill {
         serde_json::json!({
             "type": "object",
             "properties": {
-                "url": { "type": "string", "description": "URL to fetch" }
+                "url": { "type": "string", "description": "URL to fetch" },
+                "offset": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Where to continue reading the content, from a previous \
+                                    call's next_offset (default: 0)"
+                }
             },
             "required": ["url"]
         })
@@ -91,26 +301,95 @@ impl Tool for FetchUrlSkill {
                 .get("url")
                 .and_then(|v| v.as_str())
                 .ok_or_else(|| ToolError::InvalidInput("missing required field: url".into()))?;
+            let offset = input["offset"].as_u64().unwrap_or(0) as usize;
+            let conversation_id = input["conversation_id"].as_str().unwrap_or_default();
 
-            validate_domain(url, &self.allowed_domains)?;
+            let parsed = url::Url::parse(url)
+                .map_err(|e| ToolError::InvalidInput(format!("invalid URL: {e}")))?;
+            self.check_url(&parsed, conversation_id, None)?;
+            let granted = validate_domain(url, &self.allowed_domains)
+                .is_err()
+                .then_some(&parsed);
 
-            let response = self
+            let request = self
                 .client
+                .client()
                 .get(url)
-                .send()
-                .await
-                .map_err(|e| ToolError::ExecutionFailed(format!("HTTP request failed: {e}")))?;
+                .build()
+                .map_err(|e| ToolError::InvalidInput(format!("invalid URL: {e}")))?;
+            let response = self
+                .client
+                .send_following_redirects(request, |request| {
+                    self.check_url(request.url(), conversation_id, granted)
+                })
+                .await?;
 
             let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .map_err(|e| ToolError::ExecutionFailed(format!("failed to read response: {e}")))?;
+            let final_url = response.url().clone();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let mime = mime_type(content_type.as_deref());
+            let refusal = |size: Option<u64>| {
+                serde_json::json!({
+                    "url": final_url.as_str(),
+                    "status": status,
+                    "content_type": content_type,
+                    "size": size,
+                    "error": "binary content is not returned; fetch_url only reads text"
+                })
+            };
 
-            Ok(serde_json::json!({
+            // Known binary types are refused before downloading the body.
+            if mime.is_some() && classify(mime.as_deref(), &[]) == ContentKind::Binary {
+                return Ok(refusal(response.content_length()));
+            }
+            let (body, download_truncated) = read_body(response, self.max_download_bytes).await?;
+            let kind = classify(mime.as_deref(), &body[..body.len().min(8192)]);
+            if kind == ContentKind::Binary {
+                return Ok(refusal(Some(body.len() as u64)));
+            }
+
+            let text = decode_body(&body, content_type.as_deref(), kind);
+            let (content, title) = match kind {
+                ContentKind::Html => {
+                    let doc = html::to_markdown(&text, Some(&final_url));
+                    (doc.markdown, doc.title)
+                }
+                ContentKind::Json if !download_truncated => {
+                    let pretty = serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|v| serde_json::to_string_pretty(&v).ok());
+                    (pretty.unwrap_or(text), None)
+                }
+                _ => (text, None),
+            };
+            if offset > content.len() {
+                return Err(ToolError::InvalidInput(format!(
+                    "offset {offset} is past the end of the content ({} bytes)",
+                    content.len()
+                )));
+            }
+            let (body, offset, next_offset) = page(&content, offset, self.max_content_bytes);
+
+            let mut result = serde_json::json!({
+                "url": final_url.as_str(),
                 "status": status,
-                "body": body
-            }))
+                "content_type": content_type,
+                "body": body,
+                "offset": offset,
+                "next_offset": next_offset,
+                "total_length": content.len()
+            });
+            if let Some(title) = title {
+                result["title"] = title.into();
+            }
+            if download_truncated {
+                result["download_truncated"] = true.into();
+            }
+            Ok(result)
         })
     }
 }
@@ -118,6 +397,7 @@ impl Tool for FetchUrlSkill {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutil::http_response;
 
     #[test]
     fn validate_domain_allowlisted() {
@@ -163,7 +443,11 @@ mod tests {
     async fn fetch_url_rejects_non_allowlisted_domain() {
         let config = FetchUrlConfig {
             allowed_domains: vec!["example.com".into()],
+            allowed_private_networks: Vec::new(),
+            max_content_bytes: None,
+            max_download_bytes: None,
             approval: None,
+            limits: Default::default(),
         };
         let skill = FetchUrlSkill::new(&config);
         let result = skill
@@ -176,13 +460,273 @@ mod tests {
         }
     }
 
+    /// Send `response` to every request on a local port, returning the URL
+    /// of a page on it.
+    async fn serve_raw(response: Vec<u8>) -> String {
+        let (base, _) = crate::testutil::serve(move |_| response.clone()).await;
+        format!("{base}/docs/page")
+    }
+
+    /// Serve `body` with the given Content-Type, see `serve_raw`.
+    async fn serve(content_type: Option<&str>, body: Vec<u8>) -> String {
+        let headers = content_type
+            .map(|content_type| format!("Content-Type: {content_type}\r\n"))
+            .unwrap_or_default();
+        serve_raw(http_response("200 OK", &headers, body)).await
+    }
+
+    /// Serve a redirect to `location`, see `serve_raw`.
+    async fn serve_redirect(location: &str) -> String {
+        let headers = format!("Location: {location}\r\n");
+        serve_raw(http_response("302 Found", &headers, "")).await
+    }
+
+    fn local_skill(max_content_bytes: Option<usize>) -> FetchUrlSkill {
+        FetchUrlSkill::new(&FetchUrlConfig {
+            allowed_domains: vec!["127.0.0.1".into()],
+            allowed_private_networks: vec!["127.0.0.1".into()],
+            max_content_bytes,
+            max_download_bytes: Some(64),
+            approval: None,
+            limits: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn html_is_reduced_to_markdown_of_the_main_content() {
+        let html = "<html><head><title>Guide</title><script>track()</script></head><body>\
+                    <nav><a href=\"/\">Home</a></nav><main><h2>Install</h2>\
+                    <p>See <a href=\"setup\">setup</a>.</p></main></body></html>";
+        let url = serve(Some("text/html; charset=utf-8"), html.into()).await;
+        let skill = FetchUrlSkill::new(&FetchUrlConfig {
+            allowed_domains: vec!["127.0.0.1".into()],
+            allowed_private_networks: vec!["127.0.0.1".into()],
+            max_content_bytes: None,
+            max_download_bytes: None,
+            approval: None,
+            limits: Default::default(),
+        });
+        let result = skill
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["status"], 200);
+        assert_eq!(result["title"], "Guide");
+        let port = url.split(':').nth(2).unwrap().split('/').next().unwrap();
+        assert_eq!(
+            result["body"],
+            format!("## Install\n\nSee [setup](http://127.0.0.1:{port}/docs/setup).")
+        );
+        assert_eq!(result["next_offset"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn json_is_pretty_printed_and_binary_is_refused() {
+        let url = serve(Some("application/json"), br#"{"a":[1,2]}"#.to_vec()).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["body"], "{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+
+        let url = serve(Some("image/png"), b"\x89PNG\r\n".to_vec()).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["content_type"], "image/png");
+        assert_eq!(result["size"], 6);
+        assert!(result["error"].as_str().unwrap().contains("binary"));
+        assert!(result.get("body").is_none());
+
+        // Without a Content-Type, the body is sniffed.
+        let url = serve(None, b"GIF89a\0\0".to_vec()).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert!(result.get("error").is_some(), "{result}");
+    }
+
+    #[tokio::test]
+    async fn body_is_decoded_with_the_declared_charset() {
+        let url = serve(Some("text/plain; charset=ISO-8859-1"), b"caf\xe9".to_vec()).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["body"], "café");
+
+        let html = b"<meta charset=\"windows-1252\"><p>\x93quoted\x94</p>".to_vec();
+        let url = serve(Some("text/html"), html).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["body"], "\u{201c}quoted\u{201d}");
+    }
+
+    #[tokio::test]
+    async fn long_content_is_paged_and_the_download_capped() {
+        let text = "line one\nline two\nline three\nline four\n";
+        let url = serve(Some("text/plain"), text.into()).await;
+        let skill = local_skill(Some(16));
+
+        let mut pages = Vec::new();
+        let mut offset = serde_json::json!(0);
+        while !offset.is_null() {
+            let result = skill
+                .execute(serde_json::json!({ "url": url, "offset": offset }))
+                .await
+                .unwrap();
+            assert_eq!(result["total_length"], text.len());
+            pages.push(result["body"].as_str().unwrap().to_string());
+            offset = result["next_offset"].clone();
+        }
+        assert_eq!(
+            pages,
+            ["line one\n", "line two\n", "line three\n", "line four\n"]
+        );
+
+        let result = skill
+            .execute(serde_json::json!({ "url": url, "offset": 1000 }))
+            .await;
+        assert!(
+            matches!(result, Err(ToolError::InvalidInput(_))),
+            "{result:?}"
+        );
+
+        let url = serve(Some("text/plain"), vec![b'x'; 100]).await;
+        let result = local_skill(None)
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["total_length"], 64);
+        assert_eq!(result["download_truncated"], true);
+    }
+
+    fn skill_for(domains: &[&str], private_networks: &[&str]) -> FetchUrlSkill {
+        FetchUrlSkill::new(&FetchUrlConfig {
+            allowed_domains: domains.iter().map(|d| d.to_string()).collect(),
+            allowed_private_networks: private_networks.iter().map(|n| n.to_string()).collect(),
+            max_content_bytes: None,
+            max_download_bytes: None,
+            approval: None,
+            limits: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn private_addresses_are_refused_unless_allowed() {
+        let url = serve(Some("text/plain"), b"internal".to_vec()).await;
+        let port = url.split(':').nth(2).unwrap().split('/').next().unwrap();
+
+        // A wildcard allowlist does not admit private addresses...
+        let skill = skill_for(&["*"], &[]);
+        for target in [
+            url.clone(),
+            format!("http://localhost:{port}/"),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+            "http://[::1]/".to_string(),
+        ] {
+            let result = skill.execute(serde_json::json!({ "url": target })).await;
+            assert!(
+                matches!(&result, Err(ToolError::Forbidden(msg)) if msg.contains("private")),
+                "{target}: {result:?}"
+            );
+        }
+
+        // ...but an explicitly allowed network is reachable, by IP or name.
+        let skill = skill_for(&["*"], &["127.0.0.0/8"]);
+        for target in [url.clone(), format!("http://localhost:{port}/")] {
+            let result = skill
+                .execute(serde_json::json!({ "url": target }))
+                .await
+                .unwrap();
+            assert_eq!(result["body"], "internal");
+        }
+    }
+
+    #[tokio::test]
+    async fn every_redirect_hop_is_checked() {
+        let target = serve(Some("text/plain"), b"landed".to_vec()).await;
+        let skill = skill_for(&["127.0.0.1"], &["127.0.0.1"]);
+
+        let url = serve_redirect(&target).await;
+        let result = skill
+            .execute(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result["body"], "landed");
+        assert_eq!(result["url"], target);
+
+        let url = serve_redirect("http://example.com/").await;
+        let result = skill.execute(serde_json::json!({ "url": url })).await;
+        assert!(
+            matches!(&result, Err(ToolError::Forbidden(msg)) if msg.contains("example.com")),
+            "{result:?}"
+        );
+
+        let skill = skill_for(&["*"], &["127.0.0.1"]);
+        let url = serve_redirect("http://169.254.169.254/latest/meta-data/").await;
+        let result = skill.execute(serde_json::json!({ "url": url })).await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+
+        let url = serve_redirect("file:///etc/passwd").await;
+        let result = skill.execute(serde_json::json!({ "url": url })).await;
+        assert!(
+            matches!(result, Err(ToolError::InvalidInput(_))),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_results_are_fetchable_in_their_conversation_only() {
+        let target = serve(Some("text/plain"), b"found".to_vec()).await;
+        let port = target.split(':').nth(2).unwrap().split('/').next().unwrap();
+        let grants = SearchGrants::default();
+        let skill =
+            skill_for(&["example.com"], &["127.0.0.1", "::1"]).with_search_grants(grants.clone());
+        let redirect = serve_redirect(&target).await;
+        let off_host = serve_redirect(&format!("http://localhost:{port}/docs/page")).await;
+        grants.grant(
+            "c1",
+            [target.as_str(), redirect.as_str(), off_host.as_str()],
+        );
+
+        // A granted URL, and a redirect from one that stays on its host.
+        for url in [&target, &redirect] {
+            let result = skill
+                .execute(serde_json::json!({ "url": url, "conversation_id": "c1" }))
+                .await
+                .unwrap();
+            assert_eq!(result["body"], "found");
+        }
+
+        let result = skill
+            .execute(serde_json::json!({ "url": target, "conversation_id": "c2" }))
+            .await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+        let result = skill
+            .execute(serde_json::json!({ "url": off_host, "conversation_id": "c1" }))
+            .await;
+        assert!(
+            matches!(&result, Err(ToolError::Forbidden(msg)) if msg.contains("localhost")),
+            "{result:?}"
+        );
+    }
+
     /// This test requires network access — run with `cargo test -- --ignored` to include it.
     #[tokio::test]
     #[ignore]
     async fn fetch_url_with_allowlisted_domain() {
         let config = FetchUrlConfig {
             allowed_domains: vec!["example.com".into()],
+            allowed_private_networks: Vec::new(),
+            max_content_bytes: None,
+            max_download_bytes: None,
             approval: None,
+            limits: Default::default(),
         };
         let skill = FetchUrlSkill::new(&config);
         let result = skill
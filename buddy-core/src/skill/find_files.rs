@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::config::ReadFileConfig;
+
+use super::read_file::validate_path;
+use super::walk::{EntryKind, MAX_VISITED_ENTRIES, glob_to_regex, walk};
+use super::{Tool, ToolError};
+
+/// Matches returned when the input does not say otherwise.
+const DEFAULT_MAX_RESULTS: usize = 200;
+/// Upper bound on `max_results`.
+const MAX_RESULTS_LIMIT: usize = 1000;
+
+/// Skill that finds files by glob pattern within the `read_file` sandbox.
+pub struct FindFilesSkill {
+    allowed_directories: Vec<PathBuf>,
+    max_visited: usize,
+}
+
+impl FindFilesSkill {
+    pub fn new(config: &ReadFileConfig) -> Self {
+        Self {
+            allowed_directories: config
+                .allowed_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            max_visited: MAX_VISITED_ENTRIES,
+        }
+    }
+}
+
+impl Tool for FindFilesSkill {
+    fn name(&self) -> &str {
+        "find_files"
+    }
+
+    fn description(&self) -> &str {
+        "Find files matching a glob pattern below a directory in an allowed directory"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Directory to search" },
+                "pattern": {
+                    "type": "string",
+                    "description": "Glob such as *.rs or src/**/*.{rs,toml}; a pattern without \
+                                    '/' is matched against file names at any depth"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_RESULTS_LIMIT,
+                    "description": "Maximum matches to return (default: 200)"
+                },
+                "include_hidden": {
+                    "type": "boolean",
+                    "description": "Search entries whose names start with '.' (default: false)"
+                }
+            },
+            "required": ["path", "pattern"]
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let path = input
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: path".into()))?;
+            let pattern = input
+                .get("pattern")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: pattern".into()))?;
+            let max_results = input["max_results"]
+                .as_u64()
+                .map_or(DEFAULT_MAX_RESULTS, |n| n as usize)
+                .min(MAX_RESULTS_LIMIT);
+            let include_hidden = input["include_hidden"].as_bool().unwrap_or(false);
+
+            let glob = glob_to_regex(pattern)?;
+            let match_name_only = !pattern.contains('/');
+            let dir = validate_path(path, &self.allowed_directories)?;
+            if !dir.is_dir() {
+                return Err(ToolError::InvalidInput(format!(
+                    "'{path}' is not a directory"
+                )));
+            }
+
+            let mut matches = Vec::new();
+            let mut truncated = false;
+            let mut visited = 0;
+            walk(&dir, usize::MAX, include_hidden, |entry| {
+                visited += 1;
+                if visited > self.max_visited {
+                    truncated = true;
+                    return ControlFlow::Break(());
+                }
+                if entry.kind == EntryKind::Dir {
+                    return ControlFlow::Continue(());
+                }
+                let candidate = if match_name_only {
+                    entry.relative.rsplit('/').next().unwrap_or_default()
+                } else {
+                    entry.relative.as_str()
+                };
+                if glob.is_match(candidate) {
+                    if matches.len() == max_results {
+                        truncated = true;
+                        return ControlFlow::Break(());
+                    }
+                    matches.push(entry.relative);
+                }
+                ControlFlow::Continue(())
+            })?;
+
+            Ok(serde_json::json!({
+                "path": dir.to_string_lossy(),
+                "matches": matches,
+                "truncated": truncated
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finds_by_name_or_path_pattern() {
+        let dir = std::env::temp_dir().join("buddy-test-find-files");
+        std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.join("src/nested/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("src/notes.md"), "").unwrap();
+        let mut skill = FindFilesSkill {
+            allowed_directories: vec![dir.clone()],
+            max_visited: MAX_VISITED_ENTRIES,
+        };
+        let path = dir.to_str().unwrap();
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "*.rs" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            result["matches"],
+            serde_json::json!(["src/main.rs", "src/nested/lib.rs"])
+        );
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "src/*.{rs,md}" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            result["matches"],
+            serde_json::json!(["src/main.rs", "src/notes.md"])
+        );
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "**", "max_results": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(result["matches"], serde_json::json!(["Cargo.toml"]));
+        assert_eq!(result["truncated"], true);
+
+        // The walk stops after visiting `max_visited` entries: Cargo.toml
+        // and the src directory.
+        skill.max_visited = 2;
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "*.rs" }))
+            .await
+            .unwrap();
+        assert_eq!(result["matches"], serde_json::json!([]));
+        assert_eq!(result["truncated"], true);
+
+        let result = skill
+            .execute(serde_json::json!({ "path": "/", "pattern": "*" }))
+            .await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
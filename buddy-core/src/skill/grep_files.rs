@@ -0,0 +1,277 @@
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use regex::RegexBuilder;
+
+use crate::config::ReadFileConfig;
+
+use super::read_file::validate_path;
+use super::walk::{EntryKind, MAX_VISITED_ENTRIES, glob_to_regex, walk};
+use super::{Tool, ToolError};
+
+/// Matching lines returned when the input does not say otherwise.
+const DEFAULT_MAX_RESULTS: usize = 100;
+/// Upper bound on `max_results`.
+const MAX_RESULTS_LIMIT: usize = 500;
+/// Files larger than this are not searched.
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Bytes of file content one search reads before it stops and reports its
+/// results as truncated.
+const MAX_TOTAL_BYTES: u64 = 64 * 1024 * 1024;
+/// Matching lines are cut to this many characters.
+const MAX_LINE_CHARS: usize = 300;
+
+/// Skill that searches file contents by regex within the `read_file` sandbox.
+pub struct GrepFilesSkill {
+    allowed_directories: Vec<PathBuf>,
+    max_visited: usize,
+    max_total_bytes: u64,
+}
+
+impl GrepFilesSkill {
+    pub fn new(config: &ReadFileConfig) -> Self {
+        Self {
+            allowed_directories: config
+                .allowed_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            max_visited: MAX_VISITED_ENTRIES,
+            max_total_bytes: MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+impl Tool for GrepFilesSkill {
+    fn name(&self) -> &str {
+        "grep_files"
+    }
+
+    fn description(&self) -> &str {
+        "Search the text files below a directory in an allowed directory for lines matching \
+         a regular expression, returning each match with its file and line number"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Directory (or single file) to search" },
+                "pattern": { "type": "string", "description": "Regular expression (Rust syntax)" },
+                "glob": {
+                    "type": "string",
+                    "description": "Only search files matching this glob, e.g. *.rs"
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case when matching (default: false)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_RESULTS_LIMIT,
+                    "description": "Maximum matching lines to return (default: 100)"
+                }
+            },
+            "required": ["path", "pattern"]
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let path = input
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: path".into()))?;
+            let pattern = input
+                .get("pattern")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: pattern".into()))?;
+            let max_results = input["max_results"]
+                .as_u64()
+                .map_or(DEFAULT_MAX_RESULTS, |n| n as usize)
+                .min(MAX_RESULTS_LIMIT);
+
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(input["case_insensitive"].as_bool().unwrap_or(false))
+                .build()
+                .map_err(|e| ToolError::InvalidInput(format!("invalid regex: {e}")))?;
+            let glob = match input["glob"].as_str() {
+                Some(glob) => Some((glob_to_regex(glob)?, !glob.contains('/'))),
+                None => None,
+            };
+            let target = validate_path(path, &self.allowed_directories)?;
+
+            let mut matches = Vec::new();
+            let mut bytes_read = 0;
+            // Search one file; breaks once there are more matches than
+            // `max_results` or reading it would exceed `max_total_bytes`.
+            let mut search = |relative: &str, file: &Path, size: u64| {
+                if bytes_read + size > self.max_total_bytes {
+                    return ControlFlow::Break(());
+                }
+                let Ok(bytes) = std::fs::read(file) else {
+                    return ControlFlow::Continue(());
+                };
+                bytes_read += bytes.len() as u64;
+                // Skip binary files.
+                if bytes[..bytes.len().min(8192)].contains(&0) {
+                    return ControlFlow::Continue(());
+                }
+                let text = String::from_utf8_lossy(&bytes);
+                for (number, line) in text.lines().enumerate() {
+                    if !regex.is_match(line) {
+                        continue;
+                    }
+                    if matches.len() == max_results {
+                        return ControlFlow::Break(());
+                    }
+                    let mut shown: String = line.chars().take(MAX_LINE_CHARS).collect();
+                    if shown.len() < line.len() {
+                        shown.push_str("...");
+                    }
+                    matches.push(serde_json::json!({
+                        "path": relative,
+                        "line": number + 1,
+                        "text": shown
+                    }));
+                }
+                ControlFlow::Continue(())
+            };
+
+            let mut truncated = false;
+            if target.is_dir() {
+                let mut visited = 0;
+                walk(&target, usize::MAX, false, |entry| {
+                    visited += 1;
+                    if visited > self.max_visited {
+                        truncated = true;
+                        return ControlFlow::Break(());
+                    }
+                    if entry.kind != EntryKind::File || entry.size > MAX_FILE_BYTES {
+                        return ControlFlow::Continue(());
+                    }
+                    let included = glob.as_ref().is_none_or(|(glob, name_only)| {
+                        let candidate = if *name_only {
+                            entry.relative.rsplit('/').next().unwrap_or_default()
+                        } else {
+                            entry.relative.as_str()
+                        };
+                        glob.is_match(candidate)
+                    });
+                    if !included {
+                        return ControlFlow::Continue(());
+                    }
+                    let flow = search(&entry.relative, &entry.path, entry.size);
+                    truncated = flow.is_break();
+                    flow
+                })?;
+            } else {
+                let name = target
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let size = target.metadata().map_or(0, |m| m.len());
+                truncated = search(&name, &target, size).is_break();
+            }
+
+            Ok(serde_json::json!({
+                "path": target.to_string_lossy(),
+                "matches": matches,
+                "truncated": truncated
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_matching_lines_with_line_numbers() {
+        let dir = std::env::temp_dir().join("buddy-test-grep-files");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {\n    todo!()\n}\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "// TODO: docs\npub fn lib() {}\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "todo: more tests\n").unwrap();
+        std::fs::write(dir.join("blob.bin"), b"todo\0\x01").unwrap();
+        let mut skill = GrepFilesSkill {
+            allowed_directories: vec![dir.clone()],
+            max_visited: MAX_VISITED_ENTRIES,
+            max_total_bytes: MAX_TOTAL_BYTES,
+        };
+        let path = dir.to_str().unwrap();
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "todo" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            result["matches"],
+            serde_json::json!([
+                { "path": "notes.md", "line": 1, "text": "todo: more tests" },
+                { "path": "src/main.rs", "line": 2, "text": "    todo!()" }
+            ])
+        );
+
+        let result = skill
+            .execute(serde_json::json!({
+                "path": path,
+                "pattern": "^\\s*//.*todo",
+                "glob": "*.rs",
+                "case_insensitive": true
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            result["matches"],
+            serde_json::json!([{ "path": "src/lib.rs", "line": 1, "text": "// TODO: docs" }])
+        );
+
+        let result = skill
+            .execute(serde_json::json!({
+                "path": path,
+                "pattern": "(?i)todo",
+                "max_results": 1
+            }))
+            .await
+            .unwrap();
+        assert_eq!(result["matches"].as_array().unwrap().len(), 1);
+        assert_eq!(result["truncated"], true);
+
+        // The search stops at the entry and byte caps. Entries are visited
+        // blob.bin, notes.md, src, then the files in src.
+        skill.max_visited = 3;
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "todo" }))
+            .await
+            .unwrap();
+        assert_eq!(result["matches"].as_array().unwrap().len(), 1);
+        assert_eq!(result["truncated"], true);
+        skill.max_visited = MAX_VISITED_ENTRIES;
+        skill.max_total_bytes = 10;
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "todo" }))
+            .await
+            .unwrap();
+        assert_eq!(result["matches"], serde_json::json!([]));
+        assert_eq!(result["truncated"], true);
+        skill.max_total_bytes = MAX_TOTAL_BYTES;
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "pattern": "(" }))
+            .await;
+        assert!(
+            matches!(result, Err(ToolError::InvalidInput(_))),
+            "{result:?}"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,690 @@
+//! HTML to Markdown conversion for `fetch_url`.
+//!
+//! This is a forgiving parser for pulling the readable text out of a web
+//! page, not a conforming HTML5 implementation: it builds a loose element
+//! tree, picks the main content (`<main>`, else the longest `<article>`,
+//! else `<body>`), drops scripts, navigation and other page chrome, and
+//! renders what is left as Markdown.
+
+use url::Url;
+
+/// Elements whose text is never content.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+/// Elements without closing tags.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+/// Subtrees left out of the extracted content.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "nav",
+    "header", "footer", "aside", "form", "button", "select", "textarea", "dialog", "head",
+];
+/// Elements rendered as separate blocks.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "figure",
+    "figcaption",
+    "address",
+    "dl",
+    "dt",
+    "dd",
+    "details",
+    "summary",
+    "center",
+];
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Depth-first search for elements matching `pred`.
+    fn find_all<'a>(&'a self, pred: &dyn Fn(&Element) -> bool, found: &mut Vec<&'a Element>) {
+        for el in self.elements() {
+            if pred(el) {
+                found.push(el);
+            }
+            el.find_all(pred, found);
+        }
+    }
+
+    fn text_len(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| match child {
+                Node::Text(text) => text.trim().len(),
+                Node::Element(el) if SKIPPED_ELEMENTS.contains(&el.name.as_str()) => 0,
+                Node::Element(el) => el.text_len(),
+            })
+            .sum()
+    }
+}
+
+/// A page converted to Markdown.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Document {
+    pub title: Option<String>,
+    pub markdown: String,
+}
+
+/// Extract the main content of an HTML page as Markdown. Relative links
+/// are resolved against `base`.
+pub(crate) fn to_markdown(html: &str, base: Option<&Url>) -> Document {
+    let (root, title) = parse(html);
+    let mut base = base.cloned();
+    let mut bases = Vec::new();
+    root.find_all(&|el| el.name == "base", &mut bases);
+    if let Some(href) = bases.first().and_then(|el| el.attr("href")) {
+        base = match base {
+            Some(url) => url.join(href).ok().or(Some(url)),
+            None => Url::parse(href).ok(),
+        };
+    }
+
+    let mut mains = Vec::new();
+    root.find_all(
+        &|el| el.name == "main" || el.attr("role") == Some("main"),
+        &mut mains,
+    );
+    let mut articles = Vec::new();
+    root.find_all(&|el| el.name == "article", &mut articles);
+    let mut bodies = Vec::new();
+    root.find_all(&|el| el.name == "body", &mut bodies);
+    let content = mains
+        .first()
+        .copied()
+        .or_else(|| articles.iter().copied().max_by_key(|el| el.text_len()))
+        .or_else(|| bodies.first().copied())
+        .unwrap_or(&root);
+
+    let mut renderer = Renderer::new(base.as_ref());
+    renderer.children(content);
+    Document {
+        title: title.filter(|t| !t.is_empty()),
+        markdown: tidy(&renderer.out),
+    }
+}
+
+/// Parse `html` into an element tree, returning it with the page title.
+fn parse(html: &str) -> (Element, Option<String>) {
+    let mut stack = vec![Element::default()];
+    let mut title = None;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut stack, rest);
+            break;
+        };
+        push_text(&mut stack, &rest[..lt]);
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(close) = rest.strip_prefix("</") {
+            let end = close.find('>').unwrap_or(close.len());
+            let name = close[..end].trim().to_ascii_lowercase();
+            close_element(&mut stack, &name);
+            rest = close.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (element, self_closing, after) = parse_tag(&rest[1..]);
+            rest = after;
+            let name = element.name.clone();
+            if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                let end = find_ignore_case(rest, &format!("</{name}")).unwrap_or(rest.len());
+                if name == "title" && title.is_none() {
+                    title = Some(collapse_whitespace(&decode_entities(&rest[..end])));
+                }
+                rest = &rest[end..];
+                rest = rest.find('>').map_or("", |gt| &rest[gt + 1..]);
+                continue;
+            }
+            open_element(&mut stack, element);
+            if self_closing || VOID_ELEMENTS.contains(&name.as_str()) {
+                close_element(&mut stack, &name);
+            }
+        } else {
+            push_text(&mut stack, "<");
+            rest = &rest[1..];
+        }
+    }
+
+    while stack.len() > 1 {
+        pop_into_parent(&mut stack);
+    }
+    (stack.pop().expect("root element"), title)
+}
+
+/// Parse a start tag after its `<`, returning the element, whether it was
+/// self-closing (`/>`) and the input after the tag.
+fn parse_tag(input: &str) -> (Element, bool, &str) {
+    let name_end = input
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(input.len());
+    let mut element = Element {
+        name: input[..name_end].to_ascii_lowercase(),
+        ..Element::default()
+    };
+    let mut rest = &input[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return (element, true, after);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (element, false, after);
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+        if rest.is_empty() {
+            return (element, false, rest);
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            rest = after.trim_start();
+            let (raw, after) = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &rest[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = rest
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = after;
+        }
+        element.attrs.push((key, value));
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let parent = stack.last_mut().expect("root element");
+    parent.children.push(Node::Text(decode_entities(text)));
+}
+
+fn open_element(stack: &mut Vec<Element>, element: Element) {
+    // Tags whose start implicitly closes an open sibling, e.g. `<li>` after
+    // an unclosed `<li>`.
+    let closes: &[&str] = match element.name.as_str() {
+        "p" | "ul" | "ol" | "pre" | "table" | "blockquote" | "h1" | "h2" | "h3" | "h4" | "h5"
+        | "h6" => &["p"],
+        "li" => &["li"],
+        "dt" | "dd" => &["dt", "dd"],
+        "tr" => &["tr", "td", "th"],
+        "td" | "th" => &["td", "th"],
+        _ => &[],
+    };
+    if stack.len() > 1 && closes.contains(&stack.last().expect("root element").name.as_str()) {
+        pop_into_parent(stack);
+    }
+    stack.push(element);
+}
+
+fn close_element(stack: &mut Vec<Element>, name: &str) {
+    // Ignore stray end tags.
+    let Some(depth) = stack.iter().rposition(|el| el.name == name) else {
+        return;
+    };
+    if depth == 0 {
+        return;
+    }
+    while stack.len() > depth {
+        pop_into_parent(stack);
+    }
+}
+
+fn pop_into_parent(stack: &mut Vec<Element>) {
+    let element = stack.pop().expect("element to close");
+    let parent = stack.last_mut().expect("root element");
+    parent.children.push(Node::Element(element));
+}
+
+/// Decode character references such as `&amp;` and `&#8217;`. Unknown
+/// references are left as they are.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        // Entity names are ASCII, so the `;` is looked for in a few bytes.
+        let decoded = rest.as_bytes()[1..]
+            .iter()
+            .take(12)
+            .position(|&b| b == b';')
+            .and_then(|end| decode_entity(&rest[1..=end]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "euro" => '€',
+        "pound" => '£',
+        "times" => '×',
+        "deg" => '°',
+        _ => return None,
+    })
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trim trailing spaces and collapse runs of blank lines.
+fn tidy(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut blank_lines = 0;
+    for line in markdown.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        out.push_str(line);
+        blank_lines = 0;
+    }
+    out
+}
+
+struct Renderer<'a> {
+    out: String,
+    base: Option<&'a Url>,
+    /// Kind of each enclosing list: `None` for `<ul>`, the next number for `<ol>`.
+    lists: Vec<Option<usize>>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(base: Option<&'a Url>) -> Self {
+        Self {
+            out: String::new(),
+            base,
+            lists: Vec::new(),
+        }
+    }
+
+    /// Render `element`'s children on their own, e.g. a link's text.
+    fn inner(&self, element: &Element) -> String {
+        let mut renderer = Renderer {
+            out: String::new(),
+            base: self.base,
+            lists: self.lists.clone(),
+        };
+        renderer.children(element);
+        renderer.out.trim().to_string()
+    }
+
+    fn children(&mut self, element: &Element) {
+        for child in &element.children {
+            match child {
+                Node::Text(text) => self.text(text),
+                Node::Element(el) => self.element(el),
+            }
+        }
+    }
+
+    /// Append text with its whitespace collapsed, as a browser shows it.
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if !c.is_whitespace() {
+                self.out.push(c);
+            } else if !self.out.is_empty() && !self.out.ends_with([' ', '\n']) {
+                self.out.push(' ');
+            }
+        }
+    }
+
+    /// Start a new paragraph.
+    fn block_break(&mut self) {
+        if self.out.is_empty() {
+            return;
+        }
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.ends_with("\n\n") {
+            self.out.push_str(if self.out.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+    }
+
+    fn line_break(&mut self) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn link(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        Some(match self.base.and_then(|base| base.join(href).ok()) {
+            Some(url) => url.to_string(),
+            None => href.to_string(),
+        })
+    }
+
+    fn element(&mut self, el: &Element) {
+        let name = el.name.as_str();
+        if SKIPPED_ELEMENTS.contains(&name)
+            || el.attr("hidden").is_some()
+            || el.attr("aria-hidden") == Some("true")
+            || el.attr("role") == Some("navigation")
+        {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = collapse_whitespace(&self.inner(el));
+                if !text.is_empty() {
+                    self.block_break();
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    self.out.push_str(&format!("{} {text}", "#".repeat(level)));
+                    self.block_break();
+                }
+            }
+            "br" => self.line_break(),
+            "hr" => {
+                self.block_break();
+                self.out.push_str("---");
+                self.block_break();
+            }
+            "a" => {
+                let text = self.inner(el);
+                match el.attr("href").and_then(|href| self.link(href)) {
+                    Some(url) if !text.is_empty() => self.out.push_str(&format!("[{text}]({url})")),
+                    _ => self.out.push_str(&text),
+                }
+            }
+            "img" => {
+                let alt = el.attr("alt").map(collapse_whitespace).unwrap_or_default();
+                if let (false, Some(src)) = (alt.is_empty(), el.attr("src")) {
+                    let src = self.link(src).unwrap_or_else(|| src.to_string());
+                    self.out.push_str(&format!("![{alt}]({src})"));
+                }
+            }
+            "strong" | "b" => self.wrap_inline(el, "**"),
+            "em" | "i" => self.wrap_inline(el, "*"),
+            "del" | "s" | "strike" => self.wrap_inline(el, "~~"),
+            "code" => {
+                let text = raw_text(el);
+                if !text.is_empty() {
+                    self.out
+                        .push_str(&format!("`{}`", collapse_whitespace(&text)));
+                }
+            }
+            "pre" => {
+                self.block_break();
+                let text = raw_text(el);
+                self.out.push_str("```\n");
+                self.out.push_str(text.trim_matches('\n'));
+                self.out.push_str("\n```");
+                self.block_break();
+            }
+            "blockquote" => {
+                let inner = tidy(&self.inner(el));
+                if !inner.is_empty() {
+                    self.block_break();
+                    let quoted: Vec<String> = inner
+                        .lines()
+                        .map(|line| {
+                            if line.is_empty() {
+                                ">".into()
+                            } else {
+                                format!("> {line}")
+                            }
+                        })
+                        .collect();
+                    self.out.push_str(&quoted.join("\n"));
+                    self.block_break();
+                }
+            }
+            "ul" | "ol" => {
+                let start = el.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                self.lists.push((name == "ol").then_some(start));
+                if self.lists.len() == 1 {
+                    self.block_break();
+                } else {
+                    self.line_break();
+                }
+                self.children(el);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.line_break();
+                }
+            }
+            "li" => {
+                self.line_break();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "-".to_string(),
+                };
+                self.out.push_str(&format!("{indent}{marker} "));
+                self.children(el);
+                self.line_break();
+            }
+            "table" => self.table(el),
+            _ if BLOCK_ELEMENTS.contains(&name) => {
+                self.block_break();
+                self.children(el);
+                self.block_break();
+            }
+            _ => self.children(el),
+        }
+    }
+
+    fn wrap_inline(&mut self, el: &Element, marker: &str) {
+        let text = self.inner(el);
+        if !text.is_empty() {
+            self.out.push_str(&format!("{marker}{text}{marker}"));
+        }
+    }
+
+    fn table(&mut self, table: &Element) {
+        let mut rows = Vec::new();
+        table.find_all(&|el| el.name == "tr", &mut rows);
+        let rows: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                row.elements()
+                    .filter(|cell| cell.name == "td" || cell.name == "th")
+                    .map(|cell| collapse_whitespace(&self.inner(cell)).replace('|', "\\|"))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|cells| !cells.is_empty())
+            .collect();
+        let Some(width) = rows.iter().map(Vec::len).max() else {
+            return;
+        };
+        self.block_break();
+        for (i, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(width, String::new());
+            self.out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if i == 0 {
+                self.out.push_str(&format!("|{}\n", " --- |".repeat(width)));
+            }
+        }
+        self.block_break();
+    }
+}
+
+/// The text of `element`'s subtree, whitespace preserved.
+fn raw_text(element: &Element) -> String {
+    let mut out = String::new();
+    for child in &element.children {
+        match child {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) if el.name == "br" => out.push('\n'),
+            Node::Element(el) => out.push_str(&raw_text(el)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_main_content_as_markdown() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Release &amp; notes</title>
+<style>body { color: red }</style><script>var x = "<p>not content</p>";</script></head>
+<body>
+  <nav><a href="/">Home</a> | <a href="/blog">Blog</a></nav>
+  <main>
+    <h1>Version 2.0</h1>
+    <p>The <strong>new</strong> release is
+       <a href="/download?v=2">available now</a>.<br>Enjoy!</p>
+    <ul><li>Faster<li>Smaller <ol><li>by 10%</li><li>by 20%</li></ol></li></ul>
+    <pre><code>cargo install buddy
+buddy --help</code></pre>
+    <blockquote><p>It just works.</p></blockquote>
+    <table><tr><th>OS</th><th>Status</th></tr><tr><td>Linux</td><td>ok</td></tr></table>
+    <p>Inline <code>x &lt; y</code> and an <img src="a.png" alt="diagram">.</p>
+  </main>
+  <footer>Copyright 2024</footer>
+</body></html>"#;
+        let base = Url::parse("https://example.com/blog/post").unwrap();
+        let doc = to_markdown(html, Some(&base));
+        assert_eq!(doc.title.as_deref(), Some("Release & notes"));
+        assert_eq!(
+            doc.markdown,
+            "# Version 2.0\n\n\
+             The **new** release is [available now](https://example.com/download?v=2).\n\
+             Enjoy!\n\n\
+             - Faster\n\
+             - Smaller\n  \
+               1. by 10%\n  \
+               2. by 20%\n\n\
+             ```\ncargo install buddy\nbuddy --help\n```\n\n\
+             > It just works.\n\n\
+             | OS | Status |\n| --- | --- |\n| Linux | ok |\n\n\
+             Inline `x < y` and an ![diagram](https://example.com/blog/a.png)."
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_longest_article_then_the_body() {
+        let html = "<body><article>Short teaser</article>\
+                    <article><p>The full story, which is much longer.</p></article></body>";
+        assert_eq!(
+            to_markdown(html, None).markdown,
+            "The full story, which is much longer."
+        );
+
+        let html = "<div>Loose text &mdash; with <em>emphasis</em> &#x263A; &bogus;</div>";
+        let doc = to_markdown(html, None);
+        assert_eq!(doc.title, None);
+        assert_eq!(doc.markdown, "Loose text — with *emphasis* ☺ &bogus;");
+    }
+}
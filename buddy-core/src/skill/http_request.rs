@@ -0,0 +1,657 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::config::{HttpCredentialsConfig, HttpRequestConfig};
+
+use super::fetch_url::{
+    ContentKind, classify, decode_body, domain_matches, mime_type, read_body, validate_domain,
+};
+use super::ssrf::{GuardedClient, parse_networks};
+use super::{PermissionLevel, Tool, ToolError};
+
+/// Bytes of response body downloaded when the config does not say otherwise.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Methods the tool accepts; all but GET and HEAD are approved under the
+/// `write` scope.
+const METHODS: [&str; 6] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+/// Headers the model may not set.
+const RESERVED_HEADERS: [&str; 4] = ["host", "content-length", "transfer-encoding", "connection"];
+/// Replaces secret values wherever they show up in a response.
+const REDACTED: &str = "[REDACTED]";
+
+/// Skill that calls HTTP APIs on allowlisted domains with any common method
+/// and an optional JSON body.
+///
+/// Requests go through the same private-network guard as `fetch_url`.
+/// Headers configured for a domain are added to each request (and each
+/// redirect hop) to that domain; secret values are read from environment
+/// variables at call time and redacted from the response, so the model
+/// never sees them. A request that sets one of those headers itself is
+/// refused.
+pub struct HttpRequestSkill {
+    allowed_domains: Vec<String>,
+    credentials: Vec<(String, HttpCredentialsConfig)>,
+    client: GuardedClient,
+    max_response_bytes: usize,
+}
+
+impl HttpRequestSkill {
+    pub fn new(config: &HttpRequestConfig) -> Self {
+        let client = GuardedClient::new(
+            Duration::from_secs(30),
+            parse_networks(&config.allowed_private_networks),
+        );
+
+        Self {
+            allowed_domains: config.allowed_domains.clone(),
+            credentials: config
+                .credentials
+                .iter()
+                .map(|(pattern, creds)| (pattern.clone(), creds.clone()))
+                .collect(),
+            client,
+            max_response_bytes: config
+                .max_response_bytes
+                .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+        }
+    }
+
+    /// The credentials for `host`: an exact pattern wins over wildcards,
+    /// and a longer wildcard over a shorter one.
+    fn credentials_for(&self, host: &str) -> Option<(&str, &HttpCredentialsConfig)> {
+        self.credentials
+            .iter()
+            .filter(|(pattern, _)| domain_matches(pattern, host))
+            .max_by_key(|(pattern, _)| {
+                if pattern == host {
+                    usize::MAX
+                } else {
+                    pattern.len()
+                }
+            })
+            .map(|(pattern, creds)| (pattern.as_str(), creds))
+    }
+
+    /// Every secret value currently set in the environment, for redaction.
+    fn secret_values(&self) -> Vec<String> {
+        self.credentials
+            .iter()
+            .flat_map(|(_, creds)| {
+                creds
+                    .bearer_token_env
+                    .iter()
+                    .chain(creds.headers_env.values())
+            })
+            .filter_map(|var| std::env::var(var).ok())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// Vet a request against the allowlist and set the credential headers
+    /// for its host. `added` tracks the credential headers set on the
+    /// previous hop, which are removed first so they do not follow a
+    /// redirect; the model's own headers are left alone, but setting one the
+    /// host's credentials provide is an error.
+    fn prepare(
+        &self,
+        request: &mut reqwest::Request,
+        added: &Mutex<Vec<HeaderName>>,
+    ) -> Result<(), ToolError> {
+        validate_domain(request.url().as_str(), &self.allowed_domains)?;
+        let mut added = added.lock().unwrap_or_else(PoisonError::into_inner);
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let headers = request.headers_mut();
+        for name in added.drain(..) {
+            headers.remove(name);
+        }
+        let Some((pattern, creds)) = self.credentials_for(&host) else {
+            return Ok(());
+        };
+        for (name, value) in resolve_credentials(pattern, creds)? {
+            if headers.contains_key(&name) {
+                return Err(ToolError::InvalidInput(format!(
+                    "header '{name}' is set by the configured credentials for '{pattern}'; \
+                     leave it out"
+                )));
+            }
+            headers.insert(name.clone(), value);
+            added.push(name);
+        }
+        Ok(())
+    }
+}
+
+/// Read the headers of one `credentials` entry, secrets from the
+/// environment.
+fn resolve_credentials(
+    pattern: &str,
+    creds: &HttpCredentialsConfig,
+) -> Result<Vec<(HeaderName, HeaderValue)>, ToolError> {
+    let secret = |var: &str| {
+        std::env::var(var).map_err(|_| {
+            ToolError::ExecutionFailed(format!(
+                "environment variable {var} for the '{pattern}' credentials is not set"
+            ))
+        })
+    };
+    let invalid = |name: &str| {
+        ToolError::ExecutionFailed(format!(
+            "invalid header '{name}' in the '{pattern}' credentials"
+        ))
+    };
+
+    let mut headers = Vec::new();
+    for (name, value) in &creds.headers {
+        let value = HeaderValue::from_str(value).map_err(|_| invalid(name))?;
+        headers.push((
+            HeaderName::try_from(name.as_str()).map_err(|_| invalid(name))?,
+            value,
+        ));
+    }
+    for (name, var) in &creds.headers_env {
+        let mut value = HeaderValue::from_str(&secret(var)?).map_err(|_| invalid(name))?;
+        value.set_sensitive(true);
+        headers.push((
+            HeaderName::try_from(name.as_str()).map_err(|_| invalid(name))?,
+            value,
+        ));
+    }
+    if let Some(var) = &creds.bearer_token_env {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", secret(var)?))
+            .map_err(|_| invalid("Authorization"))?;
+        value.set_sensitive(true);
+        headers.push((reqwest::header::AUTHORIZATION, value));
+    }
+    Ok(headers)
+}
+
+/// The request method in `input`, uppercased; GET when unset.
+fn method(input: &serde_json::Value) -> String {
+    input["method"]
+        .as_str()
+        .unwrap_or("GET")
+        .to_ascii_uppercase()
+}
+
+/// The headers given by the model.
+fn input_headers(input: &serde_json::Value) -> Result<HeaderMap, ToolError> {
+    let mut headers = HeaderMap::new();
+    let Some(given) = input.get("headers") else {
+        return Ok(headers);
+    };
+    let given = given
+        .as_object()
+        .ok_or_else(|| ToolError::InvalidInput("headers must be an object".into()))?;
+    for (name, value) in given {
+        let header = HeaderName::try_from(name.as_str())
+            .map_err(|_| ToolError::InvalidInput(format!("invalid header name '{name}'")))?;
+        if RESERVED_HEADERS.contains(&header.as_str()) {
+            return Err(ToolError::InvalidInput(format!(
+                "header '{name}' cannot be set"
+            )));
+        }
+        let value = value
+            .as_str()
+            .and_then(|v| HeaderValue::from_str(v).ok())
+            .ok_or_else(|| ToolError::InvalidInput(format!("invalid value for header '{name}'")))?;
+        headers.insert(header, value);
+    }
+    Ok(headers)
+}
+
+/// Replace every secret in `text` with `REDACTED`.
+fn redact(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret.as_str(), REDACTED)
+    })
+}
+
+impl Tool for HttpRequestSkill {
+    fn name(&self) -> &str {
+        "http_request"
+    }
+
+    fn description(&self) -> &str {
+        "Call an HTTP API on an allowlisted domain with GET, HEAD, POST, PUT, PATCH or DELETE \
+         and an optional JSON body. Credentials configured for the domain are added \
+         automatically; never ask for them or send them yourself. Returns the status, \
+         response headers and body (parsed when it is JSON)."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Network
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "method": {
+                    "type": "string",
+                    "enum": METHODS,
+                    "description": "HTTP method (default: GET)"
+                },
+                "url": { "type": "string", "description": "URL to call" },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Extra request headers"
+                },
+                "body": {
+                    "description": "JSON request body, sent as application/json (not for GET \
+                                    or HEAD)"
+                }
+            },
+            "required": ["url"]
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let url = input["url"].as_str()?;
+        let mut lines = vec![format!("{} {url}", method(input))];
+        if let Err(e) = validate_domain(url, &self.allowed_domains) {
+            lines.push(format!("This request will fail: {e}"));
+            return Some(lines.join("\n"));
+        }
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        if let Some((_, creds)) = self.credentials_for(&host) {
+            if let Some(var) = &creds.bearer_token_env {
+                lines.push(format!("Authorization: Bearer (from ${var})"));
+            }
+            for (name, value) in &creds.headers {
+                lines.push(format!("{name}: {value}"));
+            }
+            for (name, var) in &creds.headers_env {
+                lines.push(format!("{name}: (from ${var})"));
+            }
+        }
+        if let Some(headers) = input["headers"].as_object() {
+            for (name, value) in headers {
+                lines.push(format!("{name}: {}", value.as_str().unwrap_or_default()));
+            }
+        }
+        if let Some(body) = input.get("body") {
+            let body = serde_json::to_string_pretty(body).unwrap_or_default();
+            lines.push(String::new());
+            lines.push(redact(&body, &self.secret_values()));
+        }
+        Some(lines.join("\n"))
+    }
+
+    fn approval_scope(&self, input: &serde_json::Value) -> Option<&'static str> {
+        (!matches!(method(input).as_str(), "GET" | "HEAD")).then_some("write")
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let url = input
+                .get("url")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: url".into()))?;
+            let method = method(&input);
+            if !METHODS.contains(&method.as_str()) {
+                return Err(ToolError::InvalidInput(format!(
+                    "unsupported method '{method}'; use one of {}",
+                    METHODS.join(", ")
+                )));
+            }
+            let method = reqwest::Method::from_bytes(method.as_bytes())
+                .map_err(|e| ToolError::InvalidInput(format!("invalid method: {e}")))?;
+            let body = input.get("body").filter(|body| !body.is_null());
+            if body.is_some() && matches!(method, reqwest::Method::GET | reqwest::Method::HEAD) {
+                return Err(ToolError::InvalidInput(format!(
+                    "{method} requests cannot have a body"
+                )));
+            }
+
+            validate_domain(url, &self.allowed_domains)?;
+
+            let mut request = self
+                .client
+                .client()
+                .request(method.clone(), url)
+                .headers(input_headers(&input)?);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let request = request
+                .build()
+                .map_err(|e| ToolError::InvalidInput(format!("invalid request: {e}")))?;
+            let added = Mutex::new(Vec::new());
+            let response = self
+                .client
+                .send_following_redirects(request, |request| self.prepare(request, &added))
+                .await?;
+
+            let secrets = self.secret_values();
+            let status = response.status().as_u16();
+            let final_url = redact(response.url().as_str(), &secrets);
+            let mut headers = serde_json::Map::new();
+            for (name, value) in response.headers() {
+                if name == reqwest::header::SET_COOKIE {
+                    continue;
+                }
+                let value = redact(&String::from_utf8_lossy(value.as_bytes()), &secrets);
+                match headers.get_mut(name.as_str()) {
+                    Some(serde_json::Value::String(existing)) => {
+                        existing.push_str(", ");
+                        existing.push_str(&value);
+                    }
+                    _ => {
+                        headers.insert(name.to_string(), value.into());
+                    }
+                }
+            }
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let mime = mime_type(content_type.as_deref());
+
+            let mut result = serde_json::json!({
+                "url": final_url,
+                "status": status,
+                "headers": headers,
+                "body": null
+            });
+            if method == reqwest::Method::HEAD {
+                return Ok(result);
+            }
+            if mime.is_some() && classify(mime.as_deref(), &[]) == ContentKind::Binary {
+                result["error"] = "binary content is not returned".into();
+                return Ok(result);
+            }
+            let (bytes, truncated) = read_body(response, self.max_response_bytes).await?;
+            let kind = classify(mime.as_deref(), &bytes[..bytes.len().min(8192)]);
+            if kind == ContentKind::Binary {
+                result["error"] = "binary content is not returned".into();
+                return Ok(result);
+            }
+            let text = redact(
+                &decode_body(&bytes, content_type.as_deref(), kind),
+                &secrets,
+            );
+            result["body"] = match kind {
+                ContentKind::Json if !truncated => {
+                    serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+                }
+                _ => text.into(),
+            };
+            if truncated {
+                result["truncated"] = true.into();
+            }
+            Ok(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, http_response};
+
+    use std::collections::BTreeMap;
+
+    /// Answer every request on a local port with `status`, `headers` and
+    /// a body made by `respond` from the raw request text. Returns the
+    /// server's base URL.
+    async fn serve(status: &'static str, headers: String, respond: fn(&str) -> String) -> String {
+        let respond = move |request: &str| http_response(status, &headers, respond(request));
+        testutil::serve(respond).await.0
+    }
+
+    /// Echo the request back as text.
+    async fn serve_echo() -> String {
+        serve(
+            "200 OK",
+            "Content-Type: text/plain\r\n".into(),
+            str::to_string,
+        )
+        .await
+    }
+
+    fn skill(credentials: BTreeMap<String, HttpCredentialsConfig>) -> HttpRequestSkill {
+        HttpRequestSkill::new(&HttpRequestConfig {
+            allowed_domains: vec!["127.0.0.1".into(), "localhost".into()],
+            allowed_private_networks: vec!["127.0.0.1".into(), "::1".into()],
+            credentials,
+            max_response_bytes: None,
+            approval: None,
+            write_approval: None,
+            limits: Default::default(),
+        })
+    }
+
+    /// Credentials for 127.0.0.1 from the given token variables.
+    fn local_credentials(
+        token_env: &str,
+        key_env: &str,
+    ) -> BTreeMap<String, HttpCredentialsConfig> {
+        BTreeMap::from([(
+            "127.0.0.1".to_string(),
+            HttpCredentialsConfig {
+                bearer_token_env: Some(token_env.into()),
+                headers: BTreeMap::from([("X-Client".into(), "buddy".into())]),
+                headers_env: BTreeMap::from([("X-Api-Key".into(), key_env.into())]),
+            },
+        )])
+    }
+
+    #[tokio::test]
+    async fn sends_json_body_with_injected_and_redacted_credentials() {
+        // SAFETY: test-only, variables unique to this test.
+        unsafe {
+            std::env::set_var("BUDDY_TEST_HTTP_TOKEN_022", "tok-secret-1");
+            std::env::set_var("BUDDY_TEST_HTTP_KEY_022", "key-secret-2");
+        }
+        let base = serve_echo().await;
+        let skill = skill(local_credentials(
+            "BUDDY_TEST_HTTP_TOKEN_022",
+            "BUDDY_TEST_HTTP_KEY_022",
+        ));
+
+        let result = skill
+            .execute(serde_json::json!({
+                "method": "post",
+                "url": format!("{base}/api/issues"),
+                "headers": { "X-Trace": "t1" },
+                "body": { "title": "Broken build" }
+            }))
+            .await
+            .unwrap();
+        assert_eq!(result["status"], 200);
+        let echoed = result["body"].as_str().unwrap().to_ascii_lowercase();
+        assert!(echoed.starts_with("post /api/issues http/1.1"), "{echoed}");
+        assert!(
+            echoed.contains("authorization: bearer [redacted]"),
+            "{echoed}"
+        );
+        assert!(echoed.contains("x-api-key: [redacted]"), "{echoed}");
+        assert!(echoed.contains("x-client: buddy"), "{echoed}");
+        assert!(echoed.contains("x-trace: t1"), "{echoed}");
+        assert!(
+            echoed.contains("content-type: application/json"),
+            "{echoed}"
+        );
+        assert!(echoed.ends_with(r#"{"title":"broken build"}"#), "{echoed}");
+        assert!(!result.to_string().contains("secret"), "{result}");
+    }
+
+    #[tokio::test]
+    async fn json_responses_are_parsed() {
+        let base = serve(
+            "201 Created",
+            "Content-Type: application/json\r\nLink: <next>; rel=\"next\"\r\n".into(),
+            |_| r#"{"id":7}"#.to_string(),
+        )
+        .await;
+        let result = skill(BTreeMap::new())
+            .execute(serde_json::json!({ "method": "PUT", "url": base, "body": [1] }))
+            .await
+            .unwrap();
+        assert_eq!(result["status"], 201);
+        assert_eq!(result["body"], serde_json::json!({ "id": 7 }));
+        assert_eq!(result["headers"]["link"], "<next>; rel=\"next\"");
+    }
+
+    #[tokio::test]
+    async fn credentials_are_not_sent_across_origins() {
+        // SAFETY: test-only, variables unique to this test.
+        unsafe {
+            std::env::set_var("BUDDY_TEST_HTTP_TOKEN_022B", "tok-secret-3");
+            std::env::set_var("BUDDY_TEST_HTTP_KEY_022B", "key-secret-4");
+        }
+        let echo = serve_echo().await.replace("127.0.0.1", "localhost");
+        let redirect = format!("Location: {echo}/landed\r\n");
+        let base = serve("307 Temporary Redirect", redirect, |_| String::new()).await;
+        let skill = skill(local_credentials(
+            "BUDDY_TEST_HTTP_TOKEN_022B",
+            "BUDDY_TEST_HTTP_KEY_022B",
+        ));
+
+        let result = skill
+            .execute(serde_json::json!({ "method": "DELETE", "url": format!("{base}/item") }))
+            .await
+            .unwrap();
+        let echoed = result["body"].as_str().unwrap().to_ascii_lowercase();
+        assert!(echoed.starts_with("delete /landed http/1.1"), "{echoed}");
+        assert!(!echoed.contains("authorization"), "{echoed}");
+        assert!(!echoed.contains("x-api-key"), "{echoed}");
+        assert!(!echoed.contains("x-client"), "{echoed}");
+    }
+
+    #[tokio::test]
+    async fn model_headers_are_kept_unless_credentials_set_them() {
+        // SAFETY: test-only, variables unique to this test.
+        unsafe {
+            std::env::set_var("BUDDY_TEST_HTTP_TOKEN_022D", "tok-secret-6");
+            std::env::set_var("BUDDY_TEST_HTTP_KEY_022D", "key-secret-7");
+        }
+        // Credentials exist for 127.0.0.1 only; localhost has none.
+        let base = serve_echo().await;
+        let skill = skill(local_credentials(
+            "BUDDY_TEST_HTTP_TOKEN_022D",
+            "BUDDY_TEST_HTTP_KEY_022D",
+        ));
+
+        let result = skill
+            .execute(serde_json::json!({
+                "url": base.replace("127.0.0.1", "localhost"),
+                "headers": { "Authorization": "Bearer model-token" }
+            }))
+            .await
+            .unwrap();
+        let echoed = result["body"].as_str().unwrap().to_ascii_lowercase();
+        assert!(
+            echoed.contains("authorization: bearer model-token"),
+            "{echoed}"
+        );
+
+        let result = skill
+            .execute(serde_json::json!({
+                "url": base,
+                "headers": { "Authorization": "Bearer model-token" }
+            }))
+            .await;
+        match result {
+            Err(ToolError::InvalidInput(msg)) => {
+                assert!(msg.contains("header 'authorization' is set by"), "{msg}")
+            }
+            other => panic!("expected InvalidInput, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_are_refused() {
+        let skill = skill(local_credentials(
+            "BUDDY_TEST_HTTP_UNSET_TOKEN_022",
+            "BUDDY_TEST_HTTP_UNSET_KEY_022",
+        ));
+        for input in [
+            serde_json::json!({ "url": "http://127.0.0.1:9/", "body": {} }),
+            serde_json::json!({ "method": "TRACE", "url": "http://127.0.0.1:9/" }),
+            serde_json::json!({ "url": "http://127.0.0.1:9/", "headers": { "Host": "x" } }),
+        ] {
+            let result = skill.execute(input).await;
+            assert!(
+                matches!(result, Err(ToolError::InvalidInput(_))),
+                "{result:?}"
+            );
+        }
+        let result = skill
+            .execute(serde_json::json!({ "url": "https://example.com/" }))
+            .await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+
+        let base = serve_echo().await;
+        let result = skill.execute(serde_json::json!({ "url": base })).await;
+        match result {
+            Err(ToolError::ExecutionFailed(msg)) => {
+                assert!(msg.contains("BUDDY_TEST_HTTP_UNSET_KEY_022"), "{msg}")
+            }
+            other => panic!("expected ExecutionFailed, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn non_get_methods_have_the_write_scope() {
+        let skill = skill(BTreeMap::new());
+        for (input, scope) in [
+            (serde_json::json!({ "url": "u" }), None),
+            (serde_json::json!({ "method": "head", "url": "u" }), None),
+            (
+                serde_json::json!({ "method": "POST", "url": "u" }),
+                Some("write"),
+            ),
+            (
+                serde_json::json!({ "method": "delete", "url": "u" }),
+                Some("write"),
+            ),
+        ] {
+            assert_eq!(skill.approval_scope(&input), scope, "{input}");
+        }
+    }
+
+    #[test]
+    fn approval_preview_names_secrets_without_their_values() {
+        // SAFETY: test-only, variables unique to this test.
+        unsafe { std::env::set_var("BUDDY_TEST_HTTP_TOKEN_022C", "tok-secret-5") };
+        let skill = skill(local_credentials(
+            "BUDDY_TEST_HTTP_TOKEN_022C",
+            "BUDDY_TEST_HTTP_KEY_022C",
+        ));
+        let preview = skill
+            .approval_preview(&serde_json::json!({
+                "method": "PATCH",
+                "url": "http://127.0.0.1/api/jobs/1",
+                "body": { "state": "cancelled" }
+            }))
+            .unwrap();
+        assert_eq!(
+            preview,
+            "PATCH http://127.0.0.1/api/jobs/1\n\
+             Authorization: Bearer (from $BUDDY_TEST_HTTP_TOKEN_022C)\n\
+             X-Client: buddy\n\
+             X-Api-Key: (from $BUDDY_TEST_HTTP_KEY_022C)\n\
+             \n\
+             {\n  \"state\": \"cancelled\"\n}"
+        );
+
+        let preview = skill
+            .approval_preview(&serde_json::json!({ "url": "https://example.com/" }))
+            .unwrap();
+        assert!(preview.contains("This request will fail"), "{preview}");
+    }
+}
@@ -0,0 +1,197 @@
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::config::ReadFileConfig;
+
+use super::read_file::validate_path;
+use super::walk::{EntryKind, walk};
+use super::{Tool, ToolError};
+
+/// Depth of a recursive listing when the input does not say otherwise.
+const DEFAULT_RECURSIVE_DEPTH: usize = 3;
+/// Entries returned when the input does not say otherwise.
+const DEFAULT_MAX_ENTRIES: usize = 200;
+/// Upper bound on `max_entries`.
+const MAX_ENTRIES_LIMIT: usize = 1000;
+
+/// Skill that lists directories within the `read_file` sandbox.
+pub struct ListDirectorySkill {
+    allowed_directories: Vec<PathBuf>,
+}
+
+impl ListDirectorySkill {
+    pub fn new(config: &ReadFileConfig) -> Self {
+        Self {
+            allowed_directories: config
+                .allowed_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+        }
+    }
+}
+
+impl Tool for ListDirectorySkill {
+    fn name(&self) -> &str {
+        "list_directory"
+    }
+
+    fn description(&self) -> &str {
+        "List the entries of a directory in an allowed directory, optionally recursively"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Directory to list" },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "Also list subdirectories (default: false)"
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Levels to descend when recursive (default: 3)"
+                },
+                "max_entries": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_ENTRIES_LIMIT,
+                    "description": "Maximum entries to return (default: 200)"
+                },
+                "include_hidden": {
+                    "type": "boolean",
+                    "description": "Include entries whose names start with '.' (default: false)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let path = input
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: path".into()))?;
+            let recursive = input["recursive"].as_bool().unwrap_or(false);
+            let max_depth = if recursive {
+                input["max_depth"]
+                    .as_u64()
+                    .map_or(DEFAULT_RECURSIVE_DEPTH, |d| d as usize)
+            } else {
+                1
+            };
+            let max_entries = input["max_entries"]
+                .as_u64()
+                .map_or(DEFAULT_MAX_ENTRIES, |n| n as usize)
+                .min(MAX_ENTRIES_LIMIT);
+            let include_hidden = input["include_hidden"].as_bool().unwrap_or(false);
+
+            let dir = validate_path(path, &self.allowed_directories)?;
+            if !dir.is_dir() {
+                return Err(ToolError::InvalidInput(format!(
+                    "'{path}' is not a directory"
+                )));
+            }
+
+            let mut entries = Vec::new();
+            let mut truncated = false;
+            walk(&dir, max_depth, include_hidden, |entry| {
+                if entries.len() == max_entries {
+                    truncated = true;
+                    return ControlFlow::Break(());
+                }
+                let mut item = serde_json::json!({
+                    "path": entry.relative,
+                    "type": entry.kind.as_str(),
+                });
+                if entry.kind == EntryKind::File {
+                    item["size"] = entry.size.into();
+                }
+                entries.push(item);
+                ControlFlow::Continue(())
+            })?;
+
+            Ok(serde_json::json!({
+                "path": dir.to_string_lossy(),
+                "entries": entries,
+                "truncated": truncated
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_skill(dir: &std::path::Path) -> ListDirectorySkill {
+        ListDirectorySkill {
+            allowed_directories: vec![dir.to_path_buf()],
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_top_level_or_recursively_with_limits() {
+        let dir = std::env::temp_dir().join("buddy-test-list-dir");
+        std::fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+        std::fs::write(dir.join("a.txt"), "hello").unwrap();
+        std::fs::write(dir.join("sub/b.txt"), "").unwrap();
+        std::fs::write(dir.join("sub/deeper/c.txt"), "").unwrap();
+        let skill = make_skill(&dir);
+        let path = dir.to_str().unwrap();
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path }))
+            .await
+            .unwrap();
+        assert_eq!(
+            result["entries"],
+            serde_json::json!([
+                { "path": "a.txt", "type": "file", "size": 5 },
+                { "path": "sub", "type": "dir" }
+            ])
+        );
+        assert_eq!(result["truncated"], false);
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "recursive": true, "max_depth": 2 }))
+            .await
+            .unwrap();
+        let paths: Vec<_> = result["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["a.txt", "sub", "sub/b.txt", "sub/deeper"]);
+
+        let result = skill
+            .execute(serde_json::json!({ "path": path, "recursive": true, "max_entries": 2 }))
+            .await
+            .unwrap();
+        assert_eq!(result["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(result["truncated"], true);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn directory_outside_sandbox_is_forbidden() {
+        let dir = std::env::temp_dir().join("buddy-test-list-dir-sandbox");
+        std::fs::create_dir_all(&dir).unwrap();
+        let skill = make_skill(&dir);
+        let result = skill
+            .execute(serde_json::json!({ "path": format!("{}/..", dir.display()) }))
+            .await;
+        assert!(matches!(result, Err(ToolError::Forbidden(_))), "{result:?}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
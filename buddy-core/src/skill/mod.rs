@@ -1,7 +1,21 @@
+mod diff;
+pub mod edit_file;
 pub mod fetch_url;
+pub mod find_files;
+pub mod grep_files;
+mod html;
+pub mod http_request;
+pub mod list_directory;
 pub mod read_file;
 pub mod recall;
 pub mod remember;
+pub mod reminders;
+pub mod run_command;
+pub mod schema;
+pub mod ssrf;
+pub mod todos;
+mod walk;
+pub mod web_search;
 pub mod working_memory;
 pub mod write_file;
 
@@ -10,8 +24,10 @@ use std::future::Future;
 use std::path::{Component, Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::config::ToolsConfig;
+use crate::config::{ToolLimitsConfig, ToolsConfig};
+use crate::store::Store;
 use serde::{Deserialize, Serialize};
 
 /// Normalize a path by making it absolute and resolving `.` and `..` without
@@ -39,6 +55,23 @@ pub(crate) fn normalize_path(path: &Path) -> Result<PathBuf, ToolError> {
     Ok(components.iter().collect())
 }
 
+/// Whether `path` lies within one of `allowed_dirs`, compared against the
+/// canonicalized form of each directory.
+pub(crate) fn is_within_allowed(path: &Path, allowed_dirs: &[PathBuf]) -> Result<bool, ToolError> {
+    for dir in allowed_dirs {
+        let canonical_dir = std::fs::canonicalize(dir).map_err(|e| {
+            ToolError::ExecutionFailed(format!(
+                "cannot resolve allowed directory '{}': {e}",
+                dir.display()
+            ))
+        })?;
+        if path.starts_with(&canonical_dir) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 /// Declares how a skill interacts with the outside world.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -60,6 +93,8 @@ pub enum ToolError {
     Forbidden(String),
     /// The tool execution failed for an operational reason.
     ExecutionFailed(String),
+    /// The tool did not finish within its timeout.
+    Timeout(Duration),
 }
 
 impl std::fmt::Display for ToolError {
@@ -68,12 +103,18 @@ impl std::fmt::Display for ToolError {
             Self::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
             Self::Forbidden(msg) => write!(f, "forbidden: {msg}"),
             Self::ExecutionFailed(msg) => write!(f, "execution failed: {msg}"),
+            Self::Timeout(limit) => write!(f, "timed out after {}s", limit.as_secs_f64()),
         }
     }
 }
 
 impl std::error::Error for ToolError {}
 
+/// Map a `Store` error, which tools report as an execution failure.
+pub(crate) fn store_error(e: String) -> ToolError {
+    ToolError::ExecutionFailed(e)
+}
+
 /// A callable tool capability that can be exposed to LLM providers.
 ///
 /// Implementors must be `Send + Sync` so they can be stored in the registry
@@ -93,6 +134,26 @@ pub trait Tool: Send + Sync {
         PermissionLevel::ReadOnly
     }
 
+    /// A human-readable rendering of what a call would do, shown when asking
+    /// the user for approval in place of the raw arguments (e.g. a diff).
+    /// Defaults to `None`.
+    fn approval_preview(&self, _input: &serde_json::Value) -> Option<String> {
+        None
+    }
+
+    /// A narrower scope for approving this call, for tools whose calls
+    /// differ in risk. Approval policies and `once` approvals for a scoped
+    /// call are looked up under `<name>:<scope>` instead of the tool name.
+    /// Defaults to `None`.
+    fn approval_scope(&self, _input: &serde_json::Value) -> Option<&'static str> {
+        None
+    }
+
+    /// Called when a turn of `conversation_id` starts, before any of its
+    /// tool calls, to reset state kept for the turn. Defaults to doing
+    /// nothing.
+    fn begin_turn(&self, _conversation_id: &str) {}
+
     /// Execute the tool with the given input and return a result.
     fn execute(
         &self,
@@ -100,13 +161,96 @@ pub trait Tool: Send + Sync {
     ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>>;
 }
 
+/// Timeout for a tool call when the config does not say otherwise.
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest tool result passed back to the model when the config does not
+/// say otherwise.
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 32_000;
+/// Appended to a tool result that was cut short.
+pub const TRUNCATION_MARKER: &str = "... (truncated)";
+
+/// Limits applied to every call of a tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolLimits {
+    pub timeout: Duration,
+    pub max_result_bytes: usize,
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TOOL_TIMEOUT,
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+        }
+    }
+}
+
+impl ToolLimits {
+    /// These limits with the values set in `config` taking precedence.
+    pub fn overridden_by(self, config: &ToolLimitsConfig) -> Self {
+        Self {
+            timeout: config
+                .timeout_secs
+                .map_or(self.timeout, Duration::from_secs),
+            max_result_bytes: config.max_result_bytes.unwrap_or(self.max_result_bytes),
+        }
+    }
+}
+
+/// Cut `content` to at most `max_bytes` (on a char boundary), marking it
+/// with `TRUNCATION_MARKER` if anything was dropped.
+pub fn truncate_result(content: &str, max_bytes: usize) -> String {
+    if content.len() <= max_bytes {
+        return content.to_string();
+    }
+    let mut end = max_bytes;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{TRUNCATION_MARKER}", &content[..end])
+}
+
+/// What an approval prompt shows about a tool call: the tool's preview when
+/// it has one, cut to `max_bytes`, otherwise the pretty-printed arguments.
+pub fn approval_details(arguments: &str, preview: Option<&str>, max_bytes: usize) -> String {
+    if let Some(preview) = preview {
+        return truncate_result(preview, max_bytes);
+    }
+    serde_json::from_str::<serde_json::Value>(arguments)
+        .map(|v| serde_json::to_string_pretty(&v).unwrap_or_else(|_| arguments.to_string()))
+        .unwrap_or_else(|_| arguments.to_string())
+}
+
+/// Parse a model's tool-call arguments. Some models send an empty string
+/// for a call without arguments.
+fn parse_arguments(arguments: &str) -> Result<serde_json::Value, String> {
+    if arguments.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(arguments).map_err(|e| format!("not valid JSON ({e})"))
+}
+
+/// The error returned to the model when its arguments fail validation: one
+/// violation per line, so it can correct the call.
+fn invalid_arguments_message(name: &str, violations: &[schema::Violation]) -> String {
+    let mut message = format!("Error: invalid arguments for {name}:");
+    for violation in violations {
+        message.push_str(&format!("\n- {violation}"));
+    }
+    message.push_str("\nCall the tool again with arguments that match its schema.");
+    message
+}
+
 /// Registry of all available tools.
 ///
 /// Tools are registered at startup and looked up by name when the LLM
-/// requests a tool call.
+/// requests a tool call. Calls made through the registry are held to each
+/// tool's `ToolLimits`.
 #[derive(Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    limits: HashMap<String, ToolLimits>,
+    default_limits: ToolLimits,
 }
 
 impl ToolRegistry {
@@ -114,6 +258,78 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            limits: HashMap::new(),
+            default_limits: ToolLimits::default(),
+        }
+    }
+
+    /// Set the limits for tools without limits of their own.
+    pub fn set_default_limits(&mut self, limits: ToolLimits) {
+        self.default_limits = limits;
+    }
+
+    /// Set the limits for the tool called `name`.
+    pub fn set_limits(&mut self, name: &str, limits: ToolLimits) {
+        self.limits.insert(name.to_owned(), limits);
+    }
+
+    /// The limits that apply to the tool called `name`.
+    pub fn limits(&self, name: &str) -> ToolLimits {
+        self.limits
+            .get(name)
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// Run `tool` (registered as `name`), abandoning it once its timeout
+    /// has passed.
+    pub async fn run(
+        &self,
+        name: &str,
+        tool: &dyn Tool,
+        input: serde_json::Value,
+    ) -> Result<serde_json::Value, ToolError> {
+        let timeout = self.limits(name).timeout;
+        tokio::time::timeout(timeout, tool.execute(input))
+            .await
+            .unwrap_or(Err(ToolError::Timeout(timeout)))
+    }
+
+    /// Run the tool called `name` with the model's JSON `arguments` within
+    /// its limits, returning the content passed back to the model: the JSON
+    /// output, or an `Error: ...` message. Arguments that do not match the
+    /// tool's schema are reported back without running the tool. Content over
+    /// the tool's size cap is truncated.
+    pub async fn execute(&self, name: &str, arguments: &str, conversation_id: &str) -> String {
+        let Some(tool) = self.get(name) else {
+            return format!("Error: unknown tool '{name}'");
+        };
+        let mut input = match parse_arguments(arguments) {
+            Ok(input) => input,
+            Err(e) => return format!("Error: invalid arguments for {name}: {e}"),
+        };
+        let violations = schema::validate(&tool.input_schema(), &input);
+        if !violations.is_empty() {
+            return invalid_arguments_message(name, &violations);
+        }
+        // Inject conversation context so skills can access per-conversation state.
+        if let Some(obj) = input.as_object_mut() {
+            obj.insert(
+                "conversation_id".to_string(),
+                serde_json::Value::String(conversation_id.to_string()),
+            );
+        }
+        let content = match self.run(name, tool, input).await {
+            Ok(output) => serde_json::to_string(&output).unwrap_or_else(|_| "{}".to_string()),
+            Err(e) => format!("Error: {e}"),
+        };
+        truncate_result(&content, self.limits(name).max_result_bytes)
+    }
+
+    /// Tell every tool that a turn of `conversation_id` is starting.
+    pub fn begin_turn(&self, conversation_id: &str) {
+        for tool in self.tools.values() {
+            tool.begin_turn(conversation_id);
         }
     }
 
@@ -177,9 +393,19 @@ impl ToolRegistry {
 /// # Tool Registration Rules
 ///
 /// Tools that ALWAYS require config (need sandboxing):
-/// - `read_file` - requires allowed_directories in config
-/// - `write_file` - requires allowed_directories in config
-/// - `fetch_url` - requires allowed_domains in config
+/// - `read_file` - requires allowed_directories in config; also enables
+///   `list_directory`, `find_files` and `grep_files` over the same directories
+/// - `write_file` - requires allowed_directories in config; also enables
+///   `edit_file` over the same directories
+/// - `fetch_url` - requires allowed_domains in config; may also fetch the
+///   URLs `web_search` returned in the same turn
+/// - `run_command` - requires allowed_commands and allowed_directories in config
+/// - `http_request` - requires allowed_domains in config
+/// - `web_search` - requires a backend endpoint in config
+/// - `schedule_reminder` - requires its config section and a store; also
+///   enables `list_reminders` and `cancel_reminder`
+/// - `todos` - requires its config section and a store; enables `add_todo`,
+///   `list_todos`, `complete_todo` and `delete_todo`
 ///
 /// Tools that NEVER require config:
 /// - `memory_read` - per-conversation working memory, no sandboxing
@@ -187,18 +413,69 @@ impl ToolRegistry {
 pub fn build_tool_registry(
     config: &ToolsConfig,
     working_memory: Option<working_memory::WorkingMemoryMap>,
+    store: Option<&Store>,
 ) -> ToolRegistry {
     let mut registry = ToolRegistry::new();
+    let default_limits = ToolLimits::default().overridden_by(&config.limits);
+    registry.set_default_limits(default_limits);
 
     // Tools that require config (sandboxing)
     if let Some(ref cfg) = config.read_file {
+        let limits = default_limits.overridden_by(&cfg.limits);
         registry.register(Arc::new(read_file::ReadFileSkill::new(cfg)));
+        registry.register(Arc::new(list_directory::ListDirectorySkill::new(cfg)));
+        registry.register(Arc::new(find_files::FindFilesSkill::new(cfg)));
+        registry.register(Arc::new(grep_files::GrepFilesSkill::new(cfg)));
+        for name in ["read_file", "list_directory", "find_files", "grep_files"] {
+            registry.set_limits(name, limits);
+        }
     }
     if let Some(ref cfg) = config.write_file {
+        let limits = default_limits.overridden_by(&cfg.limits);
         registry.register(Arc::new(write_file::WriteFileSkill::new(cfg)));
+        registry.register(Arc::new(edit_file::EditFileSkill::new(cfg)));
+        registry.set_limits("write_file", limits);
+        registry.set_limits("edit_file", limits);
     }
+    let search_grants = web_search::SearchGrants::default();
     if let Some(ref cfg) = config.fetch_url {
-        registry.register(Arc::new(fetch_url::FetchUrlSkill::new(cfg)));
+        let mut skill = fetch_url::FetchUrlSkill::new(cfg);
+        if config.web_search.is_some() {
+            skill = skill.with_search_grants(search_grants.clone());
+        }
+        registry.register(Arc::new(skill));
+        registry.set_limits("fetch_url", default_limits.overridden_by(&cfg.limits));
+    }
+    if let Some(ref cfg) = config.run_command {
+        registry.register(Arc::new(run_command::RunCommandSkill::new(cfg)));
+        registry.set_limits("run_command", default_limits.overridden_by(&cfg.limits));
+    }
+    if let Some(ref cfg) = config.http_request {
+        registry.register(Arc::new(http_request::HttpRequestSkill::new(cfg)));
+        registry.set_limits("http_request", default_limits.overridden_by(&cfg.limits));
+    }
+    if let Some(ref cfg) = config.web_search {
+        registry.register(Arc::new(web_search::WebSearchSkill::new(cfg, search_grants)));
+        registry.set_limits("web_search", default_limits.overridden_by(&cfg.limits));
+    }
+    if let (Some(cfg), Some(store)) = (&config.schedule_reminder, store) {
+        let limits = default_limits.overridden_by(&cfg.limits);
+        registry.register(Arc::new(reminders::ScheduleReminderSkill::new(cfg, store.clone())));
+        registry.register(Arc::new(reminders::ListRemindersSkill::new(store.clone())));
+        registry.register(Arc::new(reminders::CancelReminderSkill::new(store.clone())));
+        for name in ["schedule_reminder", "list_reminders", "cancel_reminder"] {
+            registry.set_limits(name, limits);
+        }
+    }
+    if let (Some(cfg), Some(store)) = (&config.todos, store) {
+        let limits = default_limits.overridden_by(&cfg.limits);
+        registry.register(Arc::new(todos::AddTodoSkill::new(store.clone())));
+        registry.register(Arc::new(todos::ListTodosSkill::new(store.clone())));
+        registry.register(Arc::new(todos::CompleteTodoSkill::new(store.clone())));
+        registry.register(Arc::new(todos::DeleteTodoSkill::new(store.clone())));
+        for name in ["add_todo", "list_todos", "complete_todo", "delete_todo"] {
+            registry.set_limits(name, limits);
+        }
     }
 
     // Tools that don't require config (always available when working_memory is provided)
@@ -280,6 +557,138 @@ mod tests {
         assert!(err.to_string().contains("missing required field"));
     }
 
+    /// A tool that never finishes.
+    struct HangingTool;
+
+    impl Tool for HangingTool {
+        fn name(&self) -> &str {
+            "hang"
+        }
+        fn description(&self) -> &str {
+            "Never returns"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object" })
+        }
+        fn execute(
+            &self,
+            _input: serde_json::Value,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>>
+        {
+            Box::pin(std::future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn registry_execute_times_out_with_the_tool_limit() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(HangingTool));
+        registry.set_limits(
+            "hang",
+            ToolLimits {
+                timeout: Duration::from_millis(20),
+                ..ToolLimits::default()
+            },
+        );
+        let content = registry.execute("hang", "{}", "conv").await;
+        assert_eq!(content, "Error: timed out after 0.02s");
+        assert_eq!(
+            registry.execute("missing", "{}", "conv").await,
+            "Error: unknown tool 'missing'"
+        );
+    }
+
+    #[tokio::test]
+    async fn registry_execute_truncates_long_results() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(MockEchoSkill));
+        registry.set_default_limits(ToolLimits {
+            max_result_bytes: 20,
+            ..ToolLimits::default()
+        });
+        let arguments = serde_json::json!({ "value": "é".repeat(50) }).to_string();
+        let content = registry.execute("echo", &arguments, "conv").await;
+        assert_eq!(content, format!("{{\"echo\":\"{}{TRUNCATION_MARKER}", "é".repeat(5)));
+
+        let short = registry.execute("echo", r#"{"value":"hi"}"#, "conv").await;
+        assert_eq!(short, r#"{"echo":"hi"}"#);
+    }
+
+    #[test]
+    fn approval_details_prefer_the_preview_and_cap_it() {
+        let args = r#"{"path":"/tmp/a.txt"}"#;
+        assert_eq!(
+            approval_details(args, None, 800),
+            "{\n  \"path\": \"/tmp/a.txt\"\n}"
+        );
+        assert_eq!(approval_details("not json", None, 800), "not json");
+        assert_eq!(
+            approval_details(args, Some("-old\n+new\n"), 800),
+            "-old\n+new\n"
+        );
+
+        let long = "+line\n".repeat(1000);
+        let details = approval_details(args, Some(&long), 800);
+        assert_eq!(details.len(), 800 + TRUNCATION_MARKER.len());
+        assert!(details.ends_with(TRUNCATION_MARKER));
+    }
+
+    #[tokio::test]
+    async fn registry_execute_rejects_arguments_that_fail_the_schema() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(HangingTool));
+        registry.register(Arc::new(MockEchoSkill));
+
+        let content = registry.execute("echo", r#"{"value": 3}"#, "conv").await;
+        assert_eq!(
+            content,
+            "Error: invalid arguments for echo:\n\
+             - arguments.value: expected string, got integer\n\
+             Call the tool again with arguments that match its schema."
+        );
+        let content = registry.execute("echo", "{\"value\":", "conv").await;
+        assert!(content.starts_with("Error: invalid arguments for echo: not valid JSON"));
+        // An empty argument string counts as `{}`, which `hang` accepts; it
+        // then runs until its timeout.
+        registry.set_default_limits(ToolLimits {
+            timeout: Duration::from_millis(10),
+            ..ToolLimits::default()
+        });
+        let content = registry.execute("hang", "", "conv").await;
+        assert_eq!(content, "Error: timed out after 0.01s");
+    }
+
+    #[test]
+    fn build_tool_registry_resolves_limits_per_tool() {
+        use crate::config::FetchUrlConfig;
+
+        let config = ToolsConfig {
+            fetch_url: Some(FetchUrlConfig {
+                allowed_domains: vec!["example.com".into()],
+                allowed_private_networks: Vec::new(),
+                max_content_bytes: None,
+                max_download_bytes: None,
+                approval: None,
+                limits: ToolLimitsConfig {
+                    timeout_secs: Some(5),
+                    max_result_bytes: None,
+                },
+            }),
+            limits: ToolLimitsConfig {
+                timeout_secs: None,
+                max_result_bytes: Some(1000),
+            },
+            ..ToolsConfig::default()
+        };
+        let registry = build_tool_registry(&config, None, None);
+        let fetch = registry.limits("fetch_url");
+        assert_eq!(fetch.timeout, Duration::from_secs(5));
+        assert_eq!(fetch.max_result_bytes, 1000);
+        let other = registry.limits("memory_read");
+        assert_eq!(other.timeout, DEFAULT_TOOL_TIMEOUT);
+        assert_eq!(other.max_result_bytes, 1000);
+    }
+
     #[test]
     fn tool_error_display() {
         let e1 = ToolError::InvalidInput("bad".into());
@@ -290,12 +699,15 @@ mod tests {
 
         let e3 = ToolError::ExecutionFailed("boom".into());
         assert_eq!(e3.to_string(), "execution failed: boom");
+
+        let e4 = ToolError::Timeout(Duration::from_millis(1500));
+        assert_eq!(e4.to_string(), "timed out after 1.5s");
     }
 
     #[test]
     fn build_tool_registry_with_no_tools_is_empty() {
         let config = ToolsConfig::default();
-        let registry = build_tool_registry(&config, None);
+        let registry = build_tool_registry(&config, None, None);
         assert!(registry.is_empty());
         assert_eq!(registry.len(), 0);
     }
@@ -308,43 +720,103 @@ mod tests {
             read_file: Some(ReadFileConfig {
                 allowed_directories: vec!["/tmp".into()],
                 approval: None,
+                limits: Default::default(),
             }),
             write_file: None,
             fetch_url: None,
+            run_command: None,
+            http_request: None,
+            web_search: None,
+            schedule_reminder: None,
+            todos: None,
+            max_concurrency: None,
+            limits: Default::default(),
         };
-        let registry = build_tool_registry(&config, None);
-        assert_eq!(registry.len(), 1);
+        let registry = build_tool_registry(&config, None, None);
+        assert_eq!(registry.len(), 4);
         assert!(registry.get("read_file").is_some());
+        assert!(registry.get("list_directory").is_some());
+        assert!(registry.get("find_files").is_some());
+        assert!(registry.get("grep_files").is_some());
         assert!(registry.get("write_file").is_none());
+        assert!(registry.get("edit_file").is_none());
         assert!(registry.get("fetch_url").is_none());
     }
 
     #[test]
     fn build_tool_registry_with_all_tools() {
-        use crate::config::{FetchUrlConfig, ReadFileConfig, WriteFileConfig};
+        use crate::config::{
+            FetchUrlConfig, HttpRequestConfig, ReadFileConfig, ScheduleReminderConfig,
+            TodosConfig, WebSearchConfig, WriteFileConfig,
+        };
 
         let config = ToolsConfig {
             read_file: Some(ReadFileConfig {
                 allowed_directories: vec!["/tmp".into()],
                 approval: None,
+                limits: Default::default(),
             }),
             write_file: Some(WriteFileConfig {
                 allowed_directories: vec!["/tmp".into()],
                 approval: None,
+                limits: Default::default(),
             }),
             fetch_url: Some(FetchUrlConfig {
                 allowed_domains: vec!["example.com".into()],
+                allowed_private_networks: Vec::new(),
+                max_content_bytes: None,
+                max_download_bytes: None,
+                approval: None,
+                limits: Default::default(),
+            }),
+            run_command: None,
+            http_request: Some(HttpRequestConfig {
+                allowed_domains: vec!["api.example.com".into()],
+                allowed_private_networks: Vec::new(),
+                credentials: Default::default(),
+                max_response_bytes: None,
+                approval: None,
+                write_approval: None,
+                limits: Default::default(),
+            }),
+            web_search: Some(WebSearchConfig {
+                backend: Default::default(),
+                endpoint: "http://localhost:8888".into(),
+                max_results: None,
+                language: None,
+                categories: Vec::new(),
+                approval: None,
+                limits: Default::default(),
+            }),
+            schedule_reminder: Some(ScheduleReminderConfig {
+                max_per_conversation: None,
                 approval: None,
+                limits: Default::default(),
             }),
+            todos: Some(TodosConfig::default()),
+            max_concurrency: None,
+            limits: Default::default(),
         };
+        let store = Store::open_in_memory().unwrap();
         let registry = build_tool_registry(
             &config,
             Some(working_memory::new_working_memory_map()),
+            Some(&store),
         );
-        assert_eq!(registry.len(), 5);
+        assert_eq!(registry.len(), 18);
         assert!(registry.get("read_file").is_some());
         assert!(registry.get("write_file").is_some());
+        assert!(registry.get("edit_file").is_some());
         assert!(registry.get("fetch_url").is_some());
+        assert!(registry.get("http_request").is_some());
+        assert!(registry.get("web_search").is_some());
+        assert!(registry.get("schedule_reminder").is_some());
+        assert!(registry.get("list_reminders").is_some());
+        assert!(registry.get("cancel_reminder").is_some());
+        assert!(registry.get("add_todo").is_some());
+        assert!(registry.get("list_todos").is_some());
+        assert!(registry.get("complete_todo").is_some());
+        assert!(registry.get("delete_todo").is_some());
         assert!(registry.get("memory_read").is_some());
         assert!(registry.get("memory_write").is_some());
     }
@@ -352,7 +824,7 @@ mod tests {
     #[test]
     fn build_tool_registry_memory_tools_require_working_memory() {
         let config = ToolsConfig::default();
-        let registry = build_tool_registry(&config, None);
+        let registry = build_tool_registry(&config, None, None);
         assert!(registry.get("memory_read").is_none());
         assert!(registry.get("memory_write").is_none());
     }
@@ -436,10 +908,11 @@ impl Skill {
                     current_input = serde_json::json!({ "prompt": message });
                 }
                 InstructionStep::ToolCall { tool, input: tool_input } => {
-                    let tool = self.tool_registry.get(tool)
-                        .ok_or_else(|| SkillError::ToolNotFound(tool.to_string()))?;
+                    let name = tool;
+                    let tool = self.tool_registry.get(name)
+                        .ok_or_else(|| SkillError::ToolNotFound(name.to_string()))?;
                     let merged_input = merge_input(current_input, tool_input);
-                    current_input = tool.execute(merged_input).await
+                    current_input = self.tool_registry.run(name, tool, merged_input).await
                         .map_err(SkillError::ToolExecutionFailed)?;
                 }
                 InstructionStep::Validate { check: _, error_message } => {
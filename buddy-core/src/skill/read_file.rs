@@ -23,7 +23,7 @@ impl ReadFileSkill {
 ///
 /// 1. Normalize the path (resolve `..` without filesystem access) and reject if outside sandbox
 /// 2. Canonicalize the real path (resolves symlinks) and reject if outside sandbox
-fn validate_path(path: &str, allowed_dirs: &[PathBuf]) -> Result<PathBuf, ToolError> {
+pub(crate) fn validate_path(path: &str, allowed_dirs: &[PathBuf]) -> Result<PathBuf, ToolError> {
     // First pass: normalize and check (catches `../` traversal even for non-existent paths)
     let normalized = normalize_path(Path::new(path))?;
     let mut in_sandbox = false;
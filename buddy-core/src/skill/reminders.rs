@@ -0,0 +1,580 @@
+//! Reminders and scheduled prompts for Telegram and WhatsApp chats.
+//!
+//! `schedule_reminder` stores a job in the [`Store`] for the chat the
+//! conversation belongs to; the chat interface's scheduler delivers it (see
+//! [`crate::scheduler`]). `list_reminders` and `cancel_reminder` let the user
+//! manage the chat's jobs. Times are in the server's local time zone.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use super::{PermissionLevel, Tool, ToolError, store_error};
+use crate::config::ScheduleReminderConfig;
+use crate::scheduler;
+use crate::store::{Job, JobKind, NewJob, Store};
+
+/// Jobs a conversation may have scheduled when the config does not say.
+pub const DEFAULT_MAX_PER_CONVERSATION: usize = 25;
+
+/// Longest reminder text or prompt accepted.
+const MAX_TEXT_CHARS: u64 = 2000;
+
+/// Furthest ahead `in_minutes` may schedule: about five years.
+const MAX_IN_MINUTES: u64 = 60 * 24 * 366 * 5;
+
+/// Local date-time formats accepted for `at`, besides RFC 3339.
+const LOCAL_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// When a job runs: its first run and, for recurring jobs, the cron expression.
+struct When {
+    next_run_at: DateTime<Utc>,
+    schedule: Option<String>,
+}
+
+/// Work out when a `schedule_reminder` call runs from whichever of `at`,
+/// `in_minutes` and `cron` it sets.
+fn resolve_when(input: &serde_json::Value, now: DateTime<Local>) -> Result<When, ToolError> {
+    let at = input.get("at").and_then(|v| v.as_str());
+    let in_minutes = input.get("in_minutes").and_then(|v| v.as_u64());
+    let cron = input.get("cron").and_then(|v| v.as_str());
+    match (at, in_minutes, cron) {
+        (Some(at), None, None) => {
+            let time = parse_at(at.trim(), now)?;
+            if time <= now {
+                return Err(ToolError::InvalidInput(format!(
+                    "'{at}' is in the past (it is now {})",
+                    format_time(now.with_timezone(&Utc))
+                )));
+            }
+            Ok(When {
+                next_run_at: time.with_timezone(&Utc),
+                schedule: None,
+            })
+        }
+        (None, Some(minutes), None) => {
+            let next_run_at = Some(minutes)
+                .filter(|&m| m <= MAX_IN_MINUTES)
+                .and_then(|m| i64::try_from(m).ok())
+                .and_then(chrono::TimeDelta::try_minutes)
+                .and_then(|delta| now.with_timezone(&Utc).checked_add_signed(delta))
+                .ok_or_else(|| {
+                    ToolError::InvalidInput(format!("in_minutes must be at most {MAX_IN_MINUTES}"))
+                })?;
+            Ok(When {
+                next_run_at,
+                schedule: None,
+            })
+        }
+        (None, None, Some(cron)) => {
+            let cron = cron.split_whitespace().collect::<Vec<_>>().join(" ");
+            let next_run_at = scheduler::next_run(&cron, now.with_timezone(&Utc))
+                .map_err(|e| ToolError::InvalidInput(format!("invalid cron expression: {e}")))?;
+            Ok(When {
+                next_run_at,
+                schedule: Some(cron),
+            })
+        }
+        _ => Err(ToolError::InvalidInput(
+            "set exactly one of 'at', 'in_minutes' and 'cron'".into(),
+        )),
+    }
+}
+
+/// Parse `at`: an RFC 3339 time, a local date and time, or a local time of
+/// day meaning its next occurrence.
+fn parse_at(at: &str, now: DateTime<Local>) -> Result<DateTime<Local>, ToolError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(at) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(|| ToolError::InvalidInput(format!("'{at}' does not exist in local time")))
+    };
+    if let Some(naive) = LOCAL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(at, format).ok())
+    {
+        return local(naive);
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(at, "%H:%M") {
+        let today = local(now.date_naive().and_time(time))?;
+        if today > now {
+            return Ok(today);
+        }
+        return local((now.date_naive() + chrono::Duration::days(1)).and_time(time));
+    }
+    Err(ToolError::InvalidInput(format!(
+        "cannot parse '{at}'; use 'YYYY-MM-DD HH:MM' or 'HH:MM'"
+    )))
+}
+
+/// A job time as the user sees it: local time with its UTC offset.
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M (%:z)")
+        .to_string()
+}
+
+fn job_json(job: &Job) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "id": job.id,
+        "kind": job.kind,
+        "text": job.text,
+        "next_run_at": format_time(job.next_run_at),
+    });
+    if let Some(schedule) = &job.schedule {
+        value["cron"] = serde_json::json!(schedule);
+    }
+    value
+}
+
+fn conversation_id(input: &serde_json::Value) -> Result<&str, ToolError> {
+    input
+        .get("conversation_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::ExecutionFailed("missing conversation context".into()))
+}
+
+/// The channel and target that deliver into `conversation_id`'s chat.
+fn chat_target(store: &Store, conversation_id: &str) -> Result<(&'static str, String), ToolError> {
+    if let Some(chat_id) = store
+        .get_telegram_chat_for_conversation(conversation_id)
+        .map_err(store_error)?
+    {
+        return Ok(("telegram", chat_id.to_string()));
+    }
+    if let Some(phone) = store
+        .get_whatsapp_phone_for_conversation(conversation_id)
+        .map_err(store_error)?
+    {
+        return Ok(("whatsapp", phone));
+    }
+    Err(ToolError::Forbidden(
+        "reminders can only be scheduled from a Telegram or WhatsApp chat".into(),
+    ))
+}
+
+// ── schedule_reminder skill ────────────────────────────────────────────
+
+pub struct ScheduleReminderSkill {
+    store: Store,
+    max_per_conversation: usize,
+}
+
+impl ScheduleReminderSkill {
+    pub fn new(config: &ScheduleReminderConfig, store: Store) -> Self {
+        Self {
+            store,
+            max_per_conversation: config
+                .max_per_conversation
+                .unwrap_or(DEFAULT_MAX_PER_CONVERSATION),
+        }
+    }
+}
+
+impl Tool for ScheduleReminderSkill {
+    fn name(&self) -> &str {
+        "schedule_reminder"
+    }
+
+    fn description(&self) -> &str {
+        "Schedule a message to send to the user later in this chat, once or on a recurring cron \
+         schedule. With mode 'prompt' the text is instead run as a prompt at that time and the \
+         reply is sent. Times are in the server's local time zone; list_reminders reports the \
+         current time."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": MAX_TEXT_CHARS,
+                    "description": "Reminder to send, or the prompt to run in 'prompt' mode"
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["message", "prompt"],
+                    "description": "'message' sends the text (default); 'prompt' runs it and sends the reply"
+                },
+                "at": {
+                    "type": "string",
+                    "description": "When to run once: 'YYYY-MM-DD HH:MM', or 'HH:MM' for the next time the clock shows it"
+                },
+                "in_minutes": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_IN_MINUTES,
+                    "description": "Run once this many minutes from now"
+                },
+                "cron": {
+                    "type": "string",
+                    "description": "Run repeatedly on a five-field cron schedule (minute hour day month weekday), e.g. '0 8 * * 1-5'"
+                }
+            },
+            "required": ["text"]
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let text = input.get("text")?.as_str()?;
+        let when = resolve_when(input, Local::now()).ok()?;
+        let action = match input.get("mode").and_then(|v| v.as_str()) {
+            Some("prompt") => "Run prompt",
+            _ => "Send",
+        };
+        Some(match when.schedule {
+            Some(cron) => format!(
+                "{action}: {text}\nEvery: {cron} (next {})",
+                format_time(when.next_run_at)
+            ),
+            None => format!("{action}: {text}\nAt: {}", format_time(when.next_run_at)),
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let text = input
+                .get("text")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: text".into()))?;
+            let kind = match input.get("mode").and_then(|v| v.as_str()) {
+                Some("prompt") => JobKind::Prompt,
+                _ => JobKind::Message,
+            };
+            let conversation_id = conversation_id(&input)?;
+            let when = resolve_when(&input, Local::now())?;
+            let (channel, target) = chat_target(&self.store, conversation_id)?;
+
+            let scheduled = self.store.list_jobs(conversation_id).map_err(store_error)?;
+            if scheduled.len() >= self.max_per_conversation {
+                return Err(ToolError::Forbidden(format!(
+                    "this chat already has {} scheduled reminders; cancel one first",
+                    scheduled.len()
+                )));
+            }
+
+            let job = self
+                .store
+                .create_job(&NewJob {
+                    conversation_id,
+                    channel,
+                    target: &target,
+                    kind,
+                    text,
+                    schedule: when.schedule.as_deref(),
+                    next_run_at: when.next_run_at,
+                })
+                .map_err(store_error)?;
+            Ok(job_json(&job))
+        })
+    }
+}
+
+// ── list_reminders skill ───────────────────────────────────────────────
+
+pub struct ListRemindersSkill {
+    store: Store,
+}
+
+impl ListRemindersSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for ListRemindersSkill {
+    fn name(&self) -> &str {
+        "list_reminders"
+    }
+
+    fn description(&self) -> &str {
+        "List the reminders and scheduled prompts of this chat, soonest first, with the current \
+         local time."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let conversation_id = conversation_id(&input)?;
+            let jobs = self.store.list_jobs(conversation_id).map_err(store_error)?;
+            Ok(serde_json::json!({
+                "now": format_time(Utc::now()),
+                "reminders": jobs.iter().map(job_json).collect::<Vec<_>>()
+            }))
+        })
+    }
+}
+
+// ── cancel_reminder skill ──────────────────────────────────────────────
+
+pub struct CancelReminderSkill {
+    store: Store,
+}
+
+impl CancelReminderSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for CancelReminderSkill {
+    fn name(&self) -> &str {
+        "cancel_reminder"
+    }
+
+    fn description(&self) -> &str {
+        "Cancel a reminder or scheduled prompt of this chat by the id list_reminders returned."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "minLength": 1, "description": "Reminder id" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let id = input
+                .get("id")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: id".into()))?;
+            let conversation_id = conversation_id(&input)?;
+            // Only this chat's jobs can be cancelled from it.
+            let job = self
+                .store
+                .get_job(id)
+                .map_err(store_error)?
+                .filter(|job| job.conversation_id == conversation_id)
+                .ok_or_else(|| {
+                    ToolError::InvalidInput(format!("no reminder with id '{id}' in this chat"))
+                })?;
+            self.store.delete_job(&job.id).map_err(store_error)?;
+            Ok(serde_json::json!({ "status": "cancelled", "reminder": job_json(&job) }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ScheduleReminderConfig {
+        ScheduleReminderConfig {
+            max_per_conversation: Some(2),
+            approval: None,
+            limits: Default::default(),
+        }
+    }
+
+    fn telegram_chat(store: &Store, chat_id: i64) -> String {
+        let conv = store
+            .create_conversation_with_source("Chat", "telegram")
+            .unwrap();
+        store.set_telegram_chat_mapping(chat_id, &conv.id).unwrap();
+        conv.id
+    }
+
+    fn local(s: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn when_accepts_one_of_at_in_minutes_or_cron() {
+        let now = local("2026-10-17 10:00");
+        let when = |input: serde_json::Value| resolve_when(&input, now);
+
+        let at = when(serde_json::json!({ "at": "2026-10-18 09:30" })).unwrap();
+        assert_eq!(at.next_run_at, local("2026-10-18 09:30"));
+        assert_eq!(at.schedule, None);
+        // A bare time of day is its next occurrence.
+        let later = when(serde_json::json!({ "at": "11:15" })).unwrap();
+        assert_eq!(later.next_run_at, local("2026-10-17 11:15"));
+        let tomorrow = when(serde_json::json!({ "at": "09:00" })).unwrap();
+        assert_eq!(tomorrow.next_run_at, local("2026-10-18 09:00"));
+
+        let soon = when(serde_json::json!({ "in_minutes": 90 })).unwrap();
+        assert_eq!(soon.next_run_at, local("2026-10-17 11:30"));
+
+        let daily = when(serde_json::json!({ "cron": " 0  8 * * * " })).unwrap();
+        assert_eq!(daily.next_run_at, local("2026-10-18 08:00"));
+        assert_eq!(daily.schedule.as_deref(), Some("0 8 * * *"));
+
+        for (input, error) in [
+            (serde_json::json!({}), "exactly one of"),
+            (
+                serde_json::json!({ "at": "09:00", "in_minutes": 5 }),
+                "exactly one of",
+            ),
+            (
+                serde_json::json!({ "at": "2026-10-16 09:00" }),
+                "is in the past",
+            ),
+            (
+                serde_json::json!({ "at": "tomorrow" }),
+                "cannot parse 'tomorrow'",
+            ),
+            (
+                serde_json::json!({ "cron": "0 25 * * *" }),
+                "hour '25' is outside 0-23",
+            ),
+            (
+                serde_json::json!({ "in_minutes": u64::MAX }),
+                "in_minutes must be at most",
+            ),
+            (
+                serde_json::json!({ "in_minutes": MAX_IN_MINUTES + 1 }),
+                "in_minutes must be at most",
+            ),
+        ] {
+            let err = when(input).err().unwrap().to_string();
+            assert!(err.contains(error), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn huge_in_minutes_is_rejected_without_panicking() {
+        let store = Store::open_in_memory().unwrap();
+        let chat = telegram_chat(&store, 42);
+        let schedule = ScheduleReminderSkill::new(&config(), store);
+        let input = serde_json::json!({
+            "text": "Far future",
+            "in_minutes": i64::MAX as u64 / 2,
+            "conversation_id": chat
+        });
+
+        assert_eq!(schedule.approval_preview(&input), None);
+        let err = schedule.execute(input).await.unwrap_err();
+        assert!(matches!(err, ToolError::InvalidInput(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn reminders_are_scheduled_listed_and_cancelled_per_chat() {
+        let store = Store::open_in_memory().unwrap();
+        let chat = telegram_chat(&store, 42);
+        let schedule = ScheduleReminderSkill::new(&config(), store.clone());
+        let list = ListRemindersSkill::new(store.clone());
+        let cancel = CancelReminderSkill::new(store.clone());
+
+        let daily = schedule
+            .execute(serde_json::json!({
+                "text": "Summarize my inbox",
+                "mode": "prompt",
+                "cron": "0 8 * * *",
+                "conversation_id": chat
+            }))
+            .await
+            .unwrap();
+        assert_eq!(daily["kind"], "prompt");
+        assert_eq!(daily["cron"], "0 8 * * *");
+        let id = daily["id"].as_str().unwrap();
+        let job = store.get_job(id).unwrap().unwrap();
+        assert_eq!(
+            (job.channel.as_str(), job.target.as_str()),
+            ("telegram", "42")
+        );
+        schedule
+            .execute(serde_json::json!({
+                "text": "Call mom",
+                "in_minutes": 30,
+                "conversation_id": chat
+            }))
+            .await
+            .unwrap();
+
+        // The per-chat limit is enforced.
+        let err = schedule
+            .execute(serde_json::json!({
+                "text": "One more",
+                "in_minutes": 5,
+                "conversation_id": chat
+            }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already has 2"), "{err}");
+
+        let listed = list
+            .execute(serde_json::json!({ "conversation_id": chat }))
+            .await
+            .unwrap();
+        let texts: Vec<_> = listed["reminders"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, ["Call mom", "Summarize my inbox"]);
+
+        // Another conversation can neither see nor cancel them.
+        let other = telegram_chat(&store, 43);
+        let err = cancel
+            .execute(serde_json::json!({ "id": id, "conversation_id": other }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no reminder"), "{err}");
+
+        cancel
+            .execute(serde_json::json!({ "id": id, "conversation_id": chat }))
+            .await
+            .unwrap();
+        assert!(store.get_job(id).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn web_conversations_cannot_schedule() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Web chat").unwrap();
+        let schedule = ScheduleReminderSkill::new(&config(), store.clone());
+        let err = schedule
+            .execute(serde_json::json!({
+                "text": "Call mom",
+                "in_minutes": 30,
+                "conversation_id": conv.id
+            }))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::Forbidden(_)), "{err}");
+        assert!(store.list_jobs(&conv.id).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::config::RunCommandConfig;
+
+use super::{
+    PermissionLevel, TRUNCATION_MARKER, Tool, ToolError, is_within_allowed, normalize_path,
+};
+
+/// Bytes of stdout and of stderr kept when the config does not say otherwise.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 16_000;
+
+/// Environment variables every command gets, if they are set for buddy.
+const BASE_ENV: &[&str] = &["PATH", "HOME", "LANG", "TERM"];
+
+/// Skill that runs allowlisted executables in sandboxed directories.
+///
+/// Commands run without a shell, with a scrubbed environment and no stdin.
+/// The child is killed if the call is dropped, e.g. when the registry's
+/// timeout for the tool expires.
+pub struct RunCommandSkill {
+    allowed_commands: Vec<String>,
+    allowed_directories: Vec<PathBuf>,
+    pass_env: Vec<String>,
+    max_output_bytes: usize,
+    description: String,
+}
+
+impl RunCommandSkill {
+    pub fn new(config: &RunCommandConfig) -> Self {
+        Self {
+            allowed_commands: config.allowed_commands.clone(),
+            allowed_directories: config
+                .allowed_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            pass_env: config.pass_env.clone(),
+            max_output_bytes: config.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+            description: format!(
+                "Run a command (without a shell) in an allowed directory and return its exit \
+                 code and output. Allowed commands: {}",
+                config.allowed_commands.join(", ")
+            ),
+        }
+    }
+}
+
+/// Validate that a working directory is within an allowed directory.
+///
+/// 1. Normalize the path (resolve `..` without filesystem access)
+/// 2. Check against canonicalized allowed directories
+/// 3. Canonicalize the directory to catch symlink attacks, re-verify
+fn validate_working_dir(dir: &str, allowed_dirs: &[PathBuf]) -> Result<PathBuf, ToolError> {
+    let normalized = normalize_path(Path::new(dir))?;
+    if !is_within_allowed(&normalized, allowed_dirs)? {
+        return Err(ToolError::Forbidden(format!(
+            "directory '{dir}' is outside allowed directories"
+        )));
+    }
+
+    let canonical = std::fs::canonicalize(&normalized).map_err(|e| {
+        ToolError::InvalidInput(format!("cannot resolve working directory '{dir}': {e}"))
+    })?;
+    if !canonical.is_dir() {
+        return Err(ToolError::InvalidInput(format!(
+            "'{dir}' is not a directory"
+        )));
+    }
+    if !is_within_allowed(&canonical, allowed_dirs)? {
+        return Err(ToolError::Forbidden(format!(
+            "directory '{dir}' resolves outside allowed directories"
+        )));
+    }
+    Ok(canonical)
+}
+
+/// Read `reader` to the end, keeping the first `cap` bytes. The rest is
+/// drained so the child never blocks on a full pipe.
+async fn read_capped(mut reader: impl AsyncRead + Unpin, cap: usize) -> std::io::Result<String> {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        let room = cap.saturating_sub(kept.len());
+        kept.extend_from_slice(&buf[..n.min(room)]);
+        truncated |= n > room;
+    }
+    let mut text = String::from_utf8_lossy(&kept).into_owned();
+    if truncated {
+        text.push_str(TRUNCATION_MARKER);
+    }
+    Ok(text)
+}
+
+impl Tool for RunCommandSkill {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string", "description": "Executable to run, e.g. git" },
+                "args": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Arguments, one per item (no shell quoting or expansion)"
+                },
+                "working_directory": {
+                    "type": "string",
+                    "description": "Directory to run in (defaults to the first allowed directory)"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let command = input
+                .get("command")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: command".into()))?;
+            let args: Vec<&str> = input
+                .get("args")
+                .and_then(|v| v.as_array())
+                .map(|args| args.iter().filter_map(|a| a.as_str()).collect())
+                .unwrap_or_default();
+
+            if !self.allowed_commands.iter().any(|c| c == command) {
+                return Err(ToolError::Forbidden(format!(
+                    "command '{command}' is not in the allowlist"
+                )));
+            }
+
+            let dir = match input.get("working_directory").and_then(|v| v.as_str()) {
+                Some(dir) => dir.to_string(),
+                None => self
+                    .allowed_directories
+                    .first()
+                    .ok_or_else(|| {
+                        ToolError::Forbidden("no allowed directories configured".into())
+                    })?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            let dir = validate_working_dir(&dir, &self.allowed_directories)?;
+
+            let env = BASE_ENV
+                .iter()
+                .copied()
+                .chain(self.pass_env.iter().map(String::as_str))
+                .filter_map(|name| std::env::var_os(name).map(|value| (name, value)));
+
+            let mut child = tokio::process::Command::new(command)
+                .args(&args)
+                .current_dir(&dir)
+                .env_clear()
+                .envs(env)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| {
+                    ToolError::ExecutionFailed(format!("failed to start '{command}': {e}"))
+                })?;
+
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            let (stdout, stderr, status) = tokio::try_join!(
+                read_capped(stdout, self.max_output_bytes),
+                read_capped(stderr, self.max_output_bytes),
+                child.wait(),
+            )
+            .map_err(|e| ToolError::ExecutionFailed(format!("failed to run '{command}': {e}")))?;
+
+            Ok(serde_json::json!({
+                "exit_code": status.code(),
+                "stdout": stdout,
+                "stderr": stderr
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_skill(commands: &[&str], dir: &Path) -> RunCommandSkill {
+        RunCommandSkill::new(&RunCommandConfig {
+            allowed_commands: commands.iter().map(|c| c.to_string()).collect(),
+            allowed_directories: vec![dir.to_str().unwrap().to_string()],
+            pass_env: Vec::new(),
+            max_output_bytes: Some(100),
+            approval: None,
+            limits: Default::default(),
+        })
+    }
+
+    fn sandbox(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn runs_allowed_command_in_working_directory() {
+        let dir = sandbox("buddy-test-run-command");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let skill = make_skill(&["echo", "pwd"], &dir);
+
+        let result = skill
+            .execute(serde_json::json!({ "command": "echo", "args": ["hello", "$HOME"] }))
+            .await
+            .unwrap();
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["stdout"], "hello $HOME\n");
+
+        let result = skill
+            .execute(serde_json::json!({
+                "command": "pwd",
+                "working_directory": dir.join("sub").to_str().unwrap()
+            }))
+            .await
+            .unwrap();
+        assert_eq!(result["stdout"], format!("{}\n", dir.join("sub").display()));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn reports_exit_code_and_stderr() {
+        let dir = sandbox("buddy-test-run-command-fail");
+        let skill = make_skill(&["ls"], &dir);
+        let result = skill
+            .execute(serde_json::json!({ "command": "ls", "args": ["missing-file"] }))
+            .await
+            .unwrap();
+        assert_ne!(result["exit_code"], 0);
+        assert!(result["stderr"].as_str().unwrap().contains("missing-file"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn command_not_in_allowlist_is_forbidden() {
+        let dir = sandbox("buddy-test-run-command-allowlist");
+        let skill = make_skill(&["echo"], &dir);
+        for command in ["rm", "/bin/echo", "sh"] {
+            let result = skill
+                .execute(serde_json::json!({ "command": command, "args": ["x"] }))
+                .await;
+            assert!(
+                matches!(result, Err(ToolError::Forbidden(_))),
+                "{command}: {result:?}"
+            );
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn working_directory_outside_sandbox_is_forbidden() {
+        let dir = sandbox("buddy-test-run-command-sandbox");
+        let skill = make_skill(&["pwd"], &dir);
+        for outside in ["/".to_string(), format!("{}/..", dir.display())] {
+            let result = skill
+                .execute(serde_json::json!({ "command": "pwd", "working_directory": outside }))
+                .await;
+            assert!(
+                matches!(result, Err(ToolError::Forbidden(_))),
+                "{outside}: {result:?}"
+            );
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn environment_is_scrubbed() {
+        let dir = sandbox("buddy-test-run-command-env");
+        let skill = make_skill(&["env"], &dir);
+        let result = skill
+            .execute(serde_json::json!({ "command": "env" }))
+            .await
+            .unwrap();
+        let names: Vec<&str> = result["stdout"]
+            .as_str()
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split('=').next())
+            .collect();
+        assert!(
+            names.iter().all(|name| BASE_ENV.contains(name)),
+            "{names:?}"
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn output_is_capped() {
+        let dir = sandbox("buddy-test-run-command-cap");
+        let skill = make_skill(&["seq"], &dir);
+        let result = skill
+            .execute(serde_json::json!({ "command": "seq", "args": ["1", "100000"] }))
+            .await
+            .unwrap();
+        assert_eq!(result["exit_code"], 0);
+        let stdout = result["stdout"].as_str().unwrap();
+        assert_eq!(stdout.len(), 100 + TRUNCATION_MARKER.len());
+        assert!(stdout.starts_with("1\n2\n3\n"));
+        assert!(stdout.ends_with(TRUNCATION_MARKER));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,234 @@
+//! Validation of tool arguments against a tool's `input_schema()`.
+//!
+//! Covers the subset of JSON Schema that tool schemas use: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`, and
+//! the length and range bounds. Other keywords are ignored.
+
+use serde_json::Value;
+
+/// One way in which a value does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Where the violation is, e.g. `arguments.path` or `arguments.tags[2]`.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Check `value` against `schema`, returning every violation found.
+pub fn validate(schema: &Value, value: &Value) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    check(schema, value, "arguments", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, path: &str, out: &mut Vec<Violation>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let mut fail = |message: String| {
+        out.push(Violation {
+            path: path.to_string(),
+            message,
+        })
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            fail(format!(
+                "expected {}, got {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+            // Nothing below is meaningful for a value of the wrong type.
+            return;
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        let options: Vec<String> = options.iter().map(Value::to_string).collect();
+        fail(format!("must be one of {}", options.join(", ")));
+    }
+    if let Some(expected) = schema.get("const")
+        && expected != value
+    {
+        fail(format!("must be {expected}"));
+    }
+
+    match value {
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+                && len < min
+            {
+                fail(format!("must be at least {min} characters long"));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+                && len > max
+            {
+                fail(format!("must be at most {max} characters long"));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && n < min
+            {
+                fail(format!("must be at least {min}"));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && n > max
+            {
+                fail(format!("must be at most {max}"));
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                && len < min
+            {
+                fail(format!("must have at least {min} items"));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+                && len > max
+            {
+                fail(format!("must have at most {max} items"));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{path}[{i}]"), out);
+                }
+            }
+        }
+        Value::Object(fields) => {
+            for name in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !fields.contains_key(name) {
+                    fail(format!("missing required property '{name}'"));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in fields {
+                let field_path = format!("{path}.{name}");
+                match properties.and_then(|p| p.get(name)) {
+                    Some(field_schema) => check(field_schema, field, &field_path, out),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => out.push(Violation {
+                            path: field_path,
+                            message: "is not an allowed property".to_string(),
+                        }),
+                        Some(extra @ Value::Object(_)) => check(extra, field, &field_path, out),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        // An unknown type name should not make every value invalid.
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(schema: Value, value: Value) -> Vec<String> {
+        validate(&schema, &value)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn valid_arguments_have_no_violations() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "minLength": 1 },
+                "limit": { "type": "integer", "minimum": 1 },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["path"]
+        });
+        let value = json!({ "path": "a.txt", "limit": 3, "tags": ["x"], "extra": true });
+        assert!(validate(&schema, &value).is_empty());
+    }
+
+    #[test]
+    fn reports_every_violation_with_its_path() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["set", "delete"] },
+                "limit": { "type": "integer", "maximum": 10 },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["action", "key"],
+            "additionalProperties": false
+        });
+        let value = json!({ "action": "drop", "limit": 2.5, "tags": ["a", 1], "other": 1 });
+        assert_eq!(
+            messages(schema, value),
+            vec![
+                "arguments: missing required property 'key'",
+                r#"arguments.action: must be one of "set", "delete""#,
+                "arguments.limit: expected integer, got number",
+                "arguments.other: is not an allowed property",
+                "arguments.tags[1]: expected string, got integer",
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_root_type_stops_further_checks() {
+        let schema = json!({ "type": "object", "required": ["path"] });
+        assert_eq!(
+            messages(schema, json!("a.txt")),
+            vec!["arguments: expected object, got string"]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Guards against server-side request forgery for the network tools.
+//!
+//! An allowlisted domain is not enough on its own: its DNS can point at
+//! `127.0.0.1`, a `*` entry admits `169.254.169.254`, and a redirect can
+//! lead anywhere. So requests go through a client that resolves names
+//! itself and refuses private, loopback and link-local addresses unless
+//! they are explicitly allowed. The addresses it vets are the ones the
+//! connection uses, so a second lookup cannot swap in another address.
+//! Redirects are not followed by the client; callers follow them with
+//! `send_following_redirects`, which re-checks every hop.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use url::{Host, Url};
+
+use super::ToolError;
+
+/// Redirects followed before a request is given up.
+const MAX_REDIRECTS: usize = 10;
+
+/// An IP network such as `192.168.1.0/24`; a bare address is a network of
+/// one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{s}' is not an IP address or network"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&p| p <= max)
+                .ok_or_else(|| format!("'{s}' has an invalid prefix length (0-{max})"))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parse the configured networks, skipping (and reporting) invalid ones.
+pub(crate) fn parse_networks(networks: &[String]) -> Vec<IpNetwork> {
+    networks
+        .iter()
+        .filter_map(|network| match network.parse() {
+            Ok(network) => Some(network),
+            Err(e) => {
+                eprintln!("warning: ignoring allowed private network: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// An IPv4 address embedded in an IPv6 one (`::ffff:a.b.c.d`) is judged as
+/// the IPv4 address.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Whether `ip` is not a public internet address: private, loopback,
+/// link-local, shared (CGNAT), multicast, reserved or unspecified.
+pub(crate) fn is_private(ip: IpAddr) -> bool {
+    match canonical(ip) {
+        IpAddr::V4(v4) => is_private_v4(v4),
+        IpAddr::V6(v6) => is_private_v6(v6),
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64) // 100.64.0.0/10
+        || (a == 192 && b == 0 && c == 0) // 192.0.0.0/24
+        || (a == 198 && (b & 0xfe) == 18) // 198.18.0.0/15
+        || a >= 240
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00 // fc00::/7 unique local
+        || (first & 0xffc0) == 0xfe80 // fe80::/10 link-local
+        || (first & 0xffc0) == 0xfec0 // fec0::/10 site-local
+        || (first == 0x64 && ip.segments()[1] == 0xff9b) // 64:ff9b::/96 NAT64
+        || first == 0x2002 // 6to4
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8) // documentation
+}
+
+/// Refuse `ip` if it is private and not in `allowed`.
+fn check_ip(host: &str, ip: IpAddr, allowed: &[IpNetwork]) -> Result<(), String> {
+    if is_private(ip) && !allowed.iter().any(|net| net.contains(ip)) {
+        return Err(if host == ip.to_string() {
+            format!("{ip} is a private or local address")
+        } else {
+            format!("'{host}' resolves to {ip}, a private or local address")
+        });
+    }
+    Ok(())
+}
+
+/// A request refused because of where it would connect.
+#[derive(Debug)]
+struct BlockedAddress(String);
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+/// DNS resolver that refuses names resolving to blocked addresses. Any
+/// blocked address fails the lookup, so a name cannot mix a public
+/// address with a private one.
+struct GuardedResolver {
+    allowed_networks: Arc<Vec<IpNetwork>>,
+}
+
+impl reqwest::dns::Resolve for GuardedResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let allowed = self.allowed_networks.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            for addr in &addrs {
+                check_ip(host, addr.ip(), &allowed).map_err(BlockedAddress)?;
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// An HTTP client for user-supplied URLs.
+///
+/// It connects only to addresses its resolver has vetted, never follows
+/// redirects itself and ignores proxy settings, since a proxy would do the
+/// resolving out of its sight.
+pub(crate) struct GuardedClient {
+    client: reqwest::Client,
+    allowed_networks: Arc<Vec<IpNetwork>>,
+}
+
+impl GuardedClient {
+    pub(crate) fn new(timeout: Duration, allowed_networks: Vec<IpNetwork>) -> Self {
+        let allowed_networks = Arc::new(allowed_networks);
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(GuardedResolver {
+                allowed_networks: allowed_networks.clone(),
+            }))
+            .build()
+            .expect("failed to build HTTP client");
+        Self {
+            client,
+            allowed_networks,
+        }
+    }
+
+    /// The underlying client, for building requests.
+    pub(crate) fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Check a URL before connecting: only http(s), and an IP literal
+    /// host must be allowed. Names are checked by the resolver.
+    fn check_url(&self, url: &Url) -> Result<(), ToolError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ToolError::InvalidInput(format!(
+                "unsupported URL scheme '{}'",
+                url.scheme()
+            )));
+        }
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            Some(Host::Domain(_)) => return Ok(()),
+            None => return Err(ToolError::InvalidInput("URL has no host".into())),
+        };
+        check_ip(&ip.to_string(), ip, &self.allowed_networks).map_err(ToolError::Forbidden)
+    }
+
+    /// Send `request`, following redirects. `prepare` vets each request
+    /// (the first included) before it is sent, e.g. against a domain
+    /// allowlist, and may adjust it, e.g. to add headers for its host.
+    /// Redirects that change the method (301/302/303 after a non-GET
+    /// request) continue as a body-less GET, and credentials are not sent
+    /// on to another origin.
+    pub(crate) async fn send_following_redirects(
+        &self,
+        mut request: reqwest::Request,
+        prepare: impl Fn(&mut reqwest::Request) -> Result<(), ToolError>,
+    ) -> Result<reqwest::Response, ToolError> {
+        for _ in 0..=MAX_REDIRECTS {
+            prepare(&mut request)?;
+            self.check_url(request.url())?;
+            let next = request.try_clone();
+            let response = self.client.execute(request).await.map_err(request_error)?;
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok());
+            let (Some(location), true) = (location, status.is_redirection()) else {
+                return Ok(response);
+            };
+            let url = response.url().join(location).map_err(|e| {
+                ToolError::ExecutionFailed(format!("invalid redirect location '{location}': {e}"))
+            })?;
+            let keeps_method = matches!(status.as_u16(), 307 | 308);
+            let same_origin = url.origin() == response.url().origin();
+            request = match next {
+                Some(mut next) if keeps_method || next.method() == reqwest::Method::GET => {
+                    if !same_origin {
+                        let headers = next.headers_mut();
+                        for name in [
+                            reqwest::header::AUTHORIZATION,
+                            reqwest::header::COOKIE,
+                            reqwest::header::PROXY_AUTHORIZATION,
+                        ] {
+                            headers.remove(name);
+                        }
+                    }
+                    *next.url_mut() = url;
+                    next
+                }
+                _ if keeps_method => {
+                    return Err(ToolError::ExecutionFailed(
+                        "cannot repeat a streamed request body for a redirect".into(),
+                    ));
+                }
+                _ => reqwest::Request::new(reqwest::Method::GET, url),
+            };
+        }
+        Err(ToolError::ExecutionFailed(format!(
+            "too many redirects (more than {MAX_REDIRECTS})"
+        )))
+    }
+}
+
+/// Turn a request failure into a tool error, surfacing blocked addresses
+/// as `Forbidden`.
+fn request_error(e: reqwest::Error) -> ToolError {
+    let mut source = std::error::Error::source(&e);
+    while let Some(err) = source {
+        if let Some(blocked) = err.downcast_ref::<BlockedAddress>() {
+            return ToolError::Forbidden(blocked.0.clone());
+        }
+        source = err.source();
+    }
+    ToolError::ExecutionFailed(format!("HTTP request failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_and_local_addresses_are_recognised() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.5.4",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd12:3456::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(is_private(ip.parse().unwrap()), "{ip} should be private");
+        }
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(!is_private(ip.parse().unwrap()), "{ip} should be public");
+        }
+    }
+
+    #[test]
+    fn networks_parse_and_match() {
+        let net: IpNetwork = "192.168.1.0/24".parse().unwrap();
+        assert!(net.contains("192.168.1.77".parse().unwrap()));
+        assert!(!net.contains("192.168.2.1".parse().unwrap()));
+        assert!(net.contains("::ffff:192.168.1.5".parse().unwrap()));
+
+        let single: IpNetwork = "127.0.0.1".parse().unwrap();
+        assert!(single.contains("127.0.0.1".parse().unwrap()));
+        assert!(!single.contains("127.0.0.2".parse().unwrap()));
+
+        let v6: IpNetwork = "fd00::/8".parse().unwrap();
+        assert!(v6.contains("fd12::1".parse().unwrap()));
+        assert!(!v6.contains("fe80::1".parse().unwrap()));
+
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains("10.0.0.1".parse().unwrap()));
+
+        for invalid in ["300.1.1.1/8", "10.0.0.0/33", "localhost", "10.0.0.0/x"] {
+            assert!(invalid.parse::<IpNetwork>().is_err(), "{invalid}");
+        }
+    }
+}
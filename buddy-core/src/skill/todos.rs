@@ -0,0 +1,446 @@
+//! Todo lists kept in the [`Store`].
+//!
+//! Lists are not tied to a conversation: the web UI, Telegram and WhatsApp
+//! all read and change the same ones, and the REST API exposes them too.
+//! Lists are created by adding their first item. Due dates are plain dates,
+//! compared against the server's local date.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use chrono::{Local, NaiveDate};
+
+use super::{PermissionLevel, Tool, ToolError, store_error};
+use crate::store::{Store, TodoItem, TodoUpdate};
+
+/// List that items are added to when the model does not name one.
+pub const DEFAULT_LIST: &str = "todo";
+
+/// Longest item text accepted.
+pub const MAX_TEXT_CHARS: u64 = 500;
+
+/// Longest list name accepted.
+pub const MAX_LIST_CHARS: u64 = 100;
+
+fn optional_str<'a>(input: &'a serde_json::Value, field: &str) -> Option<&'a str> {
+    input
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn required_str<'a>(input: &'a serde_json::Value, field: &str) -> Result<&'a str, ToolError> {
+    optional_str(input, field)
+        .ok_or_else(|| ToolError::InvalidInput(format!("missing required field: {field}")))
+}
+
+fn parse_due_date(due: &str) -> Result<NaiveDate, ToolError> {
+    NaiveDate::parse_from_str(due, "%Y-%m-%d").map_err(|_| {
+        ToolError::InvalidInput(format!("cannot parse due date '{due}'; use YYYY-MM-DD"))
+    })
+}
+
+/// An item as the model sees it, flagged when it is open and past due.
+fn item_json(item: &TodoItem, today: NaiveDate) -> serde_json::Value {
+    let mut value = serde_json::to_value(item).unwrap_or_default();
+    if item.completed_at.is_none() && item.due_date.is_some_and(|due| due < today) {
+        value["overdue"] = serde_json::json!(true);
+    }
+    value
+}
+
+// ── add_todo skill ─────────────────────────────────────────────────────
+
+pub struct AddTodoSkill {
+    store: Store,
+}
+
+impl AddTodoSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for AddTodoSkill {
+    fn name(&self) -> &str {
+        "add_todo"
+    }
+
+    fn description(&self) -> &str {
+        "Add an item to one of the user's todo lists, creating the list if needed. The lists are \
+         shared across every chat and the web UI."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": MAX_TEXT_CHARS,
+                    "description": "What needs doing"
+                },
+                "list": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": MAX_LIST_CHARS,
+                    "description": format!("List name, e.g. 'groceries' (default '{DEFAULT_LIST}')")
+                },
+                "due_date": {
+                    "type": "string",
+                    "description": "Due date as YYYY-MM-DD"
+                },
+                "notes": {
+                    "type": "string",
+                    "description": "Extra details"
+                }
+            },
+            "required": ["text"]
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let text = optional_str(input, "text")?;
+        let list = optional_str(input, "list").unwrap_or(DEFAULT_LIST);
+        Some(match optional_str(input, "due_date") {
+            Some(due) => format!("Add to {list}: {text}\nDue: {due}"),
+            None => format!("Add to {list}: {text}"),
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let text = required_str(&input, "text")?;
+            let list = optional_str(&input, "list").unwrap_or(DEFAULT_LIST);
+            let due_date = optional_str(&input, "due_date")
+                .map(parse_due_date)
+                .transpose()?;
+            let notes = optional_str(&input, "notes");
+            let item = self
+                .store
+                .add_todo(list, text, notes, due_date)
+                .map_err(store_error)?;
+            Ok(item_json(&item, Local::now().date_naive()))
+        })
+    }
+}
+
+// ── list_todos skill ───────────────────────────────────────────────────
+
+pub struct ListTodosSkill {
+    store: Store,
+}
+
+impl ListTodosSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for ListTodosSkill {
+    fn name(&self) -> &str {
+        "list_todos"
+    }
+
+    fn description(&self) -> &str {
+        "List the user's todo lists and their open items, soonest due first, with today's date. \
+         Item ids are needed to complete or delete items."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "list": {
+                    "type": "string",
+                    "description": "Only this list (default: all lists)"
+                },
+                "include_completed": {
+                    "type": "boolean",
+                    "description": "Also list completed items (default false)"
+                }
+            }
+        })
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let list = optional_str(&input, "list");
+            let include_completed = input
+                .get("include_completed")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let lists = self
+                .store
+                .list_todos(list, include_completed)
+                .map_err(store_error)?;
+            if let Some(name) = list
+                && lists.is_empty()
+            {
+                return Err(ToolError::InvalidInput(format!(
+                    "no todo list named '{name}'"
+                )));
+            }
+            let today = Local::now().date_naive();
+            let lists: Vec<_> = lists
+                .iter()
+                .map(|list| {
+                    serde_json::json!({
+                        "name": list.name,
+                        "items": list.items.iter().map(|i| item_json(i, today)).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            Ok(serde_json::json!({ "today": today.to_string(), "lists": lists }))
+        })
+    }
+}
+
+// ── complete_todo skill ────────────────────────────────────────────────
+
+pub struct CompleteTodoSkill {
+    store: Store,
+}
+
+impl CompleteTodoSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for CompleteTodoSkill {
+    fn name(&self) -> &str {
+        "complete_todo"
+    }
+
+    fn description(&self) -> &str {
+        "Mark a todo item done by the id list_todos returned, or reopen it with completed=false."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "minLength": 1, "description": "Item id" },
+                "completed": {
+                    "type": "boolean",
+                    "description": "false reopens the item (default true)"
+                }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let item = self.store.get_todo(optional_str(input, "id")?).ok()??;
+        let action = match input.get("completed").and_then(|v| v.as_bool()) {
+            Some(false) => "Reopen",
+            _ => "Complete",
+        };
+        Some(format!("{action} ({}): {}", item.list, item.text))
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let id = required_str(&input, "id")?;
+            let completed = input
+                .get("completed")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            let update = TodoUpdate {
+                completed: Some(completed),
+                ..Default::default()
+            };
+            let item = self
+                .store
+                .update_todo(id, &update)
+                .map_err(store_error)?
+                .ok_or_else(|| ToolError::InvalidInput(format!("no todo item with id '{id}'")))?;
+            Ok(item_json(&item, Local::now().date_naive()))
+        })
+    }
+}
+
+// ── delete_todo skill ──────────────────────────────────────────────────
+
+pub struct DeleteTodoSkill {
+    store: Store,
+}
+
+impl DeleteTodoSkill {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Tool for DeleteTodoSkill {
+    fn name(&self) -> &str {
+        "delete_todo"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a todo item by id, or a whole list with all its items by name. Prefer \
+         complete_todo for items that were done."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Mutating
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Item to delete" },
+                "list": { "type": "string", "description": "List to delete with all its items" }
+            }
+        })
+    }
+
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        match (optional_str(input, "id"), optional_str(input, "list")) {
+            (Some(id), None) => {
+                let item = self.store.get_todo(id).ok()??;
+                Some(format!("Delete ({}): {}", item.list, item.text))
+            }
+            (None, Some(list)) => Some(format!("Delete list '{list}' and all its items")),
+            _ => None,
+        }
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            match (optional_str(&input, "id"), optional_str(&input, "list")) {
+                (Some(id), None) => {
+                    if !self.store.delete_todo(id).map_err(store_error)? {
+                        return Err(ToolError::InvalidInput(format!(
+                            "no todo item with id '{id}'"
+                        )));
+                    }
+                    Ok(serde_json::json!({ "status": "deleted", "id": id }))
+                }
+                (None, Some(list)) => {
+                    if !self.store.delete_todo_list(list).map_err(store_error)? {
+                        return Err(ToolError::InvalidInput(format!(
+                            "no todo list named '{list}'"
+                        )));
+                    }
+                    Ok(serde_json::json!({ "status": "deleted", "list": list }))
+                }
+                _ => Err(ToolError::InvalidInput(
+                    "set exactly one of 'id' and 'list'".into(),
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn todos_are_added_listed_completed_and_deleted() {
+        let store = Store::open_in_memory().unwrap();
+        let add = AddTodoSkill::new(store.clone());
+        let list = ListTodosSkill::new(store.clone());
+        let complete = CompleteTodoSkill::new(store.clone());
+        let delete = DeleteTodoSkill::new(store.clone());
+
+        let taxes = add
+            .execute(serde_json::json!({ "text": "File taxes", "due_date": "2000-04-15" }))
+            .await
+            .unwrap();
+        assert_eq!(taxes["list"], DEFAULT_LIST);
+        assert_eq!(taxes["overdue"], true);
+        let milk = add
+            .execute(serde_json::json!({ "text": "Milk", "list": "Groceries" }))
+            .await
+            .unwrap();
+        assert!(milk.get("overdue").is_none());
+
+        let milk_id = milk["id"].as_str().unwrap();
+        let done = complete
+            .execute(serde_json::json!({ "id": milk_id }))
+            .await
+            .unwrap();
+        assert!(done["completed_at"].is_string());
+
+        // Completed items are hidden unless asked for.
+        let listed = list.execute(serde_json::json!({})).await.unwrap();
+        assert_eq!(listed["today"], Local::now().date_naive().to_string());
+        let lists = listed["lists"].as_array().unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0]["name"], "Groceries");
+        assert!(lists[0]["items"].as_array().unwrap().is_empty());
+        assert_eq!(lists[1]["items"][0]["text"], "File taxes");
+        let groceries = list
+            .execute(serde_json::json!({ "list": "groceries", "include_completed": true }))
+            .await
+            .unwrap();
+        assert_eq!(groceries["lists"][0]["items"][0]["text"], "Milk");
+
+        delete
+            .execute(serde_json::json!({ "list": "Groceries" }))
+            .await
+            .unwrap();
+        let err = delete
+            .execute(serde_json::json!({ "id": milk_id }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no todo item"), "{err}");
+        let err = list
+            .execute(serde_json::json!({ "list": "Groceries" }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no todo list named"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn invalid_input_is_rejected() {
+        let store = Store::open_in_memory().unwrap();
+        let add = AddTodoSkill::new(store.clone());
+        let delete = DeleteTodoSkill::new(store.clone());
+
+        for (input, error) in [
+            (
+                serde_json::json!({ "text": "  " }),
+                "missing required field: text",
+            ),
+            (
+                serde_json::json!({ "text": "Milk", "due_date": "next week" }),
+                "cannot parse due date 'next week'",
+            ),
+        ] {
+            let err = add.execute(input).await.unwrap_err().to_string();
+            assert!(err.contains(error), "{err}");
+        }
+        let err = delete
+            .execute(serde_json::json!({ "id": "x", "list": "y" }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("exactly one of"), "{err}");
+    }
+}
@@ -0,0 +1,235 @@
+//! Directory traversal and glob matching shared by the file search tools.
+//!
+//! The walk never follows symlinks: they are reported as entries but not
+//! descended into or read, so a link inside an allowed directory cannot lead
+//! the tools outside it.
+
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use super::ToolError;
+
+/// Entries a recursive search visits before it stops and reports its
+/// results as truncated, so searching a huge tree still ends quickly.
+pub(crate) const MAX_VISITED_ENTRIES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl EntryKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Dir => "dir",
+            Self::Symlink => "symlink",
+        }
+    }
+}
+
+/// An entry found by `walk`.
+pub(crate) struct Entry {
+    /// Path relative to the walk's root, with `/` separators.
+    pub relative: String,
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// Size in bytes (files only).
+    pub size: u64,
+}
+
+/// Visit the entries below `root` a directory at a time, each in name
+/// order, descending at most `max_depth` levels (1 lists only `root`'s own
+/// entries). Hidden entries (names starting with `.`) are skipped unless
+/// `include_hidden` is set, and so are unreadable subdirectories. The walk
+/// stops early when `visit` returns `ControlFlow::Break`.
+pub(crate) fn walk(
+    root: &Path,
+    max_depth: usize,
+    include_hidden: bool,
+    mut visit: impl FnMut(Entry) -> ControlFlow<()>,
+) -> Result<(), ToolError> {
+    let mut stack = vec![(root.to_path_buf(), String::new(), 1)];
+    let mut first = true;
+    while let Some((dir, prefix, depth)) = stack.pop() {
+        let read = match std::fs::read_dir(&dir) {
+            Ok(read) => read,
+            // Only the root itself must be readable.
+            Err(e) if first => {
+                return Err(ToolError::ExecutionFailed(format!(
+                    "cannot read directory '{}': {e}",
+                    dir.display()
+                )));
+            }
+            Err(_) => continue,
+        };
+        first = false;
+
+        let mut entries: Vec<_> = read.filter_map(Result::ok).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !include_hidden && name.starts_with('.') {
+                continue;
+            }
+            // `DirEntry::metadata` does not traverse symlinks.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let kind = if metadata.is_symlink() {
+                EntryKind::Symlink
+            } else if metadata.is_dir() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            let relative = format!("{prefix}{name}");
+            if kind == EntryKind::Dir && depth < max_depth {
+                subdirs.push((entry.path(), format!("{relative}/"), depth + 1));
+            }
+            let found = Entry {
+                relative,
+                path: entry.path(),
+                kind,
+                size: if kind == EntryKind::File {
+                    metadata.len()
+                } else {
+                    0
+                },
+            };
+            if visit(found).is_break() {
+                return Ok(());
+            }
+        }
+        // Reversed so the stack pops them in name order.
+        stack.extend(subdirs.into_iter().rev());
+    }
+    Ok(())
+}
+
+/// Compile a glob pattern into a regex over `/`-separated relative paths.
+///
+/// Supports `*` (within one path segment), `**` (across segments), `?`,
+/// character classes (`[abc]`, `[!abc]`) and alternatives (`{rs,toml}`).
+pub(crate) fn glob_to_regex(pattern: &str) -> Result<Regex, ToolError> {
+    let invalid =
+        |reason: &str| ToolError::InvalidInput(format!("invalid glob '{pattern}': {reason}"));
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut in_braces = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                re.push('[');
+                if matches!(chars.peek(), Some('!') | Some('^')) {
+                    chars.next();
+                    re.push('^');
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => re.push_str("\\\\"),
+                        Some(c) => re.push(c),
+                        None => return Err(invalid("unclosed '['")),
+                    }
+                }
+                re.push(']');
+            }
+            '{' if !in_braces => {
+                in_braces = true;
+                re.push_str("(?:");
+            }
+            ',' if in_braces => re.push('|'),
+            '}' if in_braces => {
+                in_braces = false;
+                re.push(')');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if in_braces {
+        return Err(invalid("unclosed '{'"));
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| invalid(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_segments_and_alternatives() {
+        let re = glob_to_regex("src/**/*.{rs,toml}").unwrap();
+        assert!(re.is_match("src/main.rs"));
+        assert!(re.is_match("src/a/b/Cargo.toml"));
+        assert!(!re.is_match("src/main.rs.bak"));
+        assert!(!re.is_match("tests/main.rs"));
+
+        let re = glob_to_regex("file?.[!c]*").unwrap();
+        assert!(re.is_match("file1.txt"));
+        assert!(!re.is_match("file1.c"));
+        assert!(!re.is_match("dir/file1.txt"));
+
+        assert!(glob_to_regex("a[bc").is_err());
+        assert!(glob_to_regex("a{b,c").is_err());
+    }
+
+    #[test]
+    fn walk_lists_in_order_without_following_symlinks() {
+        let root = std::env::temp_dir().join("buddy-test-walk");
+        let outside = std::env::temp_dir().join("buddy-test-walk-outside");
+        std::fs::create_dir_all(root.join("b/c")).unwrap();
+        std::fs::create_dir_all(root.join(".hidden")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("a.txt"), "abc").unwrap();
+        std::fs::write(root.join("b/c/d.txt"), "").unwrap();
+        std::fs::write(outside.join("secret.txt"), "").unwrap();
+        let _ = std::fs::remove_file(root.join("link"));
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+        let mut seen = Vec::new();
+        walk(&root, usize::MAX, false, |entry| {
+            seen.push(format!("{} {}", entry.relative, entry.kind.as_str()));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(
+            seen,
+            vec![
+                "a.txt file",
+                "b dir",
+                "link symlink",
+                "b/c dir",
+                "b/c/d.txt file"
+            ]
+        );
+
+        let mut seen = Vec::new();
+        walk(&root, 1, true, |entry| {
+            seen.push(entry.relative);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(seen, vec![".hidden", "a.txt", "b", "link"]);
+
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::remove_dir_all(&outside).ok();
+    }
+}
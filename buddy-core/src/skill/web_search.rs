@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::{SearchBackendKind, WebSearchConfig};
+
+use super::{PermissionLevel, Tool, ToolError};
+
+/// Results returned when neither the input nor the config says otherwise.
+pub const DEFAULT_MAX_RESULTS: usize = 8;
+/// Upper bound on `max_results`.
+const MAX_RESULTS_LIMIT: usize = 20;
+/// Snippets are cut to this many characters.
+const MAX_SNIPPET_CHARS: usize = 300;
+
+/// One search hit, normalized across backends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// A search engine `web_search` can query.
+pub trait SearchBackend: Send + Sync {
+    /// Search for `query`, returning at most `max_results` hits, best first.
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        max_results: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, ToolError>> + Send + 'a>>;
+}
+
+/// Build the backend selected by `config`.
+pub fn build_backend(config: &WebSearchConfig) -> Box<dyn SearchBackend> {
+    match config.backend {
+        SearchBackendKind::Searxng => Box::new(SearxngBackend::new(config)),
+    }
+}
+
+/// URLs returned by `web_search`, which `fetch_url` may then fetch in the
+/// same turn of the same conversation even when their domain is not in its
+/// allowlist. Cleared when the conversation's next turn starts.
+#[derive(Debug, Clone, Default)]
+pub struct SearchGrants(Arc<Mutex<HashMap<String, HashSet<String>>>>);
+
+impl SearchGrants {
+    /// Let `conversation_id` fetch `urls` for the rest of the turn.
+    pub fn grant<'a>(&self, conversation_id: &str, urls: impl IntoIterator<Item = &'a str>) {
+        let mut grants = self.0.lock().unwrap();
+        let granted = grants.entry(conversation_id.to_string()).or_default();
+        granted.extend(urls.into_iter().filter_map(grant_key));
+    }
+
+    /// Whether `url` was returned by a search in `conversation_id`'s turn.
+    pub fn is_granted(&self, conversation_id: &str, url: &str) -> bool {
+        let Some(key) = grant_key(url) else {
+            return false;
+        };
+        self.0
+            .lock()
+            .unwrap()
+            .get(conversation_id)
+            .is_some_and(|granted| granted.contains(&key))
+    }
+
+    /// Drop every grant of `conversation_id`.
+    pub fn clear(&self, conversation_id: &str) {
+        self.0.lock().unwrap().remove(conversation_id);
+    }
+}
+
+/// The form a URL is granted and looked up in: parsed, without fragment.
+fn grant_key(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    url.set_fragment(None);
+    Some(url.into())
+}
+
+/// Collapse whitespace and cut `text` to `MAX_SNIPPET_CHARS`.
+fn normalize_snippet(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_SNIPPET_CHARS {
+        return text;
+    }
+    let mut cut: String = text.chars().take(MAX_SNIPPET_CHARS).collect();
+    cut.push_str("...");
+    cut
+}
+
+/// Backend for a self-hosted SearxNG instance, through its JSON API
+/// (`/search?format=json`).
+pub struct SearxngBackend {
+    endpoint: String,
+    language: Option<String>,
+    categories: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl SearxngBackend {
+    pub fn new(config: &WebSearchConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .expect("failed to build HTTP client");
+
+        Self {
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            language: config.language.clone(),
+            categories: config.categories.clone(),
+            client,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl SearchBackend for SearxngBackend {
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        max_results: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, ToolError>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut params = vec![("q", query.to_string()), ("format", "json".to_string())];
+            if let Some(language) = &self.language {
+                params.push(("language", language.clone()));
+            }
+            if !self.categories.is_empty() {
+                params.push(("categories", self.categories.join(",")));
+            }
+            let response = self
+                .client
+                .get(format!("{}/search", self.endpoint))
+                .query(&params)
+                .send()
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(format!("search request failed: {e}")))?;
+            let status = response.status();
+            if status == reqwest::StatusCode::FORBIDDEN {
+                return Err(ToolError::ExecutionFailed(
+                    "SearxNG refused the JSON format; enable `json` under `search.formats` in \
+                     its settings.yml"
+                        .into(),
+                ));
+            }
+            if !status.is_success() {
+                return Err(ToolError::ExecutionFailed(format!(
+                    "search backend returned {status}"
+                )));
+            }
+            let body: SearxngResponse = response
+                .json()
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(format!("invalid search response: {e}")))?;
+
+            let mut seen = HashSet::new();
+            Ok(body
+                .results
+                .into_iter()
+                .filter(|r| r.url.starts_with("http://") || r.url.starts_with("https://"))
+                .filter(|r| seen.insert(r.url.clone()))
+                .take(max_results)
+                .map(|r| SearchResult {
+                    title: normalize_snippet(&r.title),
+                    url: r.url,
+                    snippet: normalize_snippet(&r.content),
+                })
+                .collect())
+        })
+    }
+}
+
+/// Skill that searches the web through a configured backend.
+///
+/// Every URL it returns is granted to `fetch_url` for the rest of the turn
+/// (see `SearchGrants`).
+pub struct WebSearchSkill {
+    backend: Box<dyn SearchBackend>,
+    grants: SearchGrants,
+    max_results: usize,
+}
+
+impl WebSearchSkill {
+    pub fn new(config: &WebSearchConfig, grants: SearchGrants) -> Self {
+        Self::with_backend(build_backend(config), grants, config.max_results)
+    }
+
+    /// A skill over any backend; `max_results` defaults to
+    /// `DEFAULT_MAX_RESULTS`.
+    pub fn with_backend(
+        backend: Box<dyn SearchBackend>,
+        grants: SearchGrants,
+        max_results: Option<usize>,
+    ) -> Self {
+        Self {
+            backend,
+            grants,
+            max_results: max_results
+                .unwrap_or(DEFAULT_MAX_RESULTS)
+                .clamp(1, MAX_RESULTS_LIMIT),
+        }
+    }
+}
+
+impl Tool for WebSearchSkill {
+    fn name(&self) -> &str {
+        "web_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the web, returning the title, URL and a snippet of each result. The result \
+         URLs can be read with fetch_url in the same turn."
+    }
+
+    fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::Network
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "minLength": 1, "description": "Search query" },
+                "max_results": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_RESULTS_LIMIT,
+                    "description": format!("Maximum results to return (default: {})", self.max_results)
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn begin_turn(&self, conversation_id: &str) {
+        self.grants.clear(conversation_id);
+    }
+
+    fn execute(
+        &self,
+        input: serde_json::Value,
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, ToolError>> + Send + '_>> {
+        Box::pin(async move {
+            let query = input
+                .get("query")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|q| !q.is_empty())
+                .ok_or_else(|| ToolError::InvalidInput("missing required field: query".into()))?;
+            let max_results = input["max_results"]
+                .as_u64()
+                .map_or(self.max_results, |n| n as usize)
+                .clamp(1, MAX_RESULTS_LIMIT);
+            let conversation_id = input["conversation_id"].as_str().unwrap_or_default();
+
+            let results = self.backend.search(query, max_results).await?;
+            self.grants
+                .grant(conversation_id, results.iter().map(|r| r.url.as_str()));
+
+            Ok(serde_json::json!({
+                "query": query,
+                "results": results
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{http_response, serve};
+
+    struct FixedBackend(Vec<SearchResult>);
+
+    impl SearchBackend for FixedBackend {
+        fn search<'a>(
+            &'a self,
+            _query: &'a str,
+            max_results: usize,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, ToolError>> + Send + 'a>>
+        {
+            Box::pin(async move { Ok(self.0.iter().take(max_results).cloned().collect()) })
+        }
+    }
+
+    fn result(url: &str) -> SearchResult {
+        SearchResult {
+            title: "Title".into(),
+            url: url.into(),
+            snippet: "Snippet".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn results_are_granted_to_the_conversation_until_its_next_turn() {
+        let grants = SearchGrants::default();
+        let skill = WebSearchSkill::with_backend(
+            Box::new(FixedBackend(vec![
+                result("https://docs.rs/regex#syntax"),
+                result("https://blog.example.org/post"),
+            ])),
+            grants.clone(),
+            Some(1),
+        );
+
+        let output = skill
+            .execute(serde_json::json!({ "query": "regex", "conversation_id": "c1" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            output["results"],
+            serde_json::json!([
+                { "title": "Title", "url": "https://docs.rs/regex#syntax", "snippet": "Snippet" }
+            ])
+        );
+        assert!(grants.is_granted("c1", "https://docs.rs/regex"));
+        assert!(!grants.is_granted("c1", "https://blog.example.org/post"));
+        assert!(!grants.is_granted("c2", "https://docs.rs/regex"));
+
+        skill.begin_turn("c1");
+        assert!(!grants.is_granted("c1", "https://docs.rs/regex"));
+    }
+
+    #[tokio::test]
+    async fn searxng_results_are_normalized() {
+        let body = serde_json::json!({
+            "query": "rust",
+            "results": [
+                { "url": "https://www.rust-lang.org/", "title": "Rust",
+                  "content": "  A language\n empowering   everyone ", "engine": "ddg" },
+                { "url": "https://www.rust-lang.org/", "title": "Rust (dup)", "content": "" },
+                { "url": "ftp://old.example.com/", "title": "FTP", "content": "" },
+                { "url": "https://doc.rust-lang.org/book/", "title": "The Book" }
+            ]
+        })
+        .to_string();
+        let response = http_response("200 OK", "Content-Type: application/json\r\n", body);
+        let (base, mut requests) = serve(move |_| response.clone()).await;
+
+        let backend = SearxngBackend::new(&WebSearchConfig {
+            backend: SearchBackendKind::Searxng,
+            endpoint: format!("{base}/"),
+            max_results: None,
+            language: Some("en".into()),
+            categories: vec!["general".into(), "it".into()],
+            approval: None,
+            limits: Default::default(),
+        });
+        let results = backend.search("rust lang", 5).await.unwrap();
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    title: "Rust".into(),
+                    url: "https://www.rust-lang.org/".into(),
+                    snippet: "A language empowering everyone".into(),
+                },
+                SearchResult {
+                    title: "The Book".into(),
+                    url: "https://doc.rust-lang.org/book/".into(),
+                    snippet: String::new(),
+                },
+            ]
+        );
+        let request = requests.recv().await.unwrap();
+        assert!(
+            request.starts_with(
+                "GET /search?q=rust+lang&format=json&language=en&categories=general%2Cit "
+            ),
+            "{request}"
+        );
+    }
+}
@@ -4,7 +4,8 @@ use std::pin::Pin;
 
 use crate::config::WriteFileConfig;
 
-use super::{PermissionLevel, Tool, ToolError, normalize_path};
+use super::diff::unified_diff;
+use super::{PermissionLevel, Tool, ToolError, is_within_allowed, normalize_path};
 
 /// Skill that writes file contents to sandboxed directories.
 pub struct WriteFileSkill {
@@ -32,21 +33,7 @@ fn validate_write_path(
     let normalized = normalize_path(Path::new(path))?;
 
     // First pass: check normalized path against allowed dirs
-    let mut allowed = false;
-    for dir in allowed_dirs {
-        let canonical_dir = std::fs::canonicalize(dir).map_err(|e| {
-            ToolError::ExecutionFailed(format!(
-                "cannot resolve allowed directory '{}': {e}",
-                dir.display()
-            ))
-        })?;
-        if normalized.starts_with(&canonical_dir) {
-            allowed = true;
-            break;
-        }
-    }
-
-    if !allowed {
+    if !is_within_allowed(&normalized, allowed_dirs)? {
         return Err(ToolError::Forbidden(format!(
             "path '{path}' is outside allowed directories"
         )));
@@ -71,21 +58,7 @@ fn validate_write_path(
             .ok_or_else(|| ToolError::InvalidInput("path has no file name".into()))?,
     );
 
-    let mut verified = false;
-    for dir in allowed_dirs {
-        let canonical_dir = std::fs::canonicalize(dir).map_err(|e| {
-            ToolError::ExecutionFailed(format!(
-                "cannot resolve allowed directory '{}': {e}",
-                dir.display()
-            ))
-        })?;
-        if final_path.starts_with(&canonical_dir) {
-            verified = true;
-            break;
-        }
-    }
-
-    if !verified {
+    if !is_within_allowed(&final_path, allowed_dirs)? {
         return Err(ToolError::Forbidden(format!(
             "path '{path}' resolves outside allowed directories"
         )));
@@ -118,6 +91,28 @@ impl Tool for WriteFileSkill {
         })
     }
 
+    fn approval_preview(&self, input: &serde_json::Value) -> Option<String> {
+        let path = input.get("path")?.as_str()?;
+        let content = input.get("content")?.as_str()?;
+        // Only look at files the write itself would be allowed to touch.
+        let normalized = normalize_path(Path::new(path)).ok()?;
+        if !is_within_allowed(&normalized, &self.allowed_directories).ok()? {
+            return None;
+        }
+        let existing = std::fs::canonicalize(&normalized)
+            .ok()
+            .filter(|canonical| {
+                is_within_allowed(canonical, &self.allowed_directories).unwrap_or(false)
+            })
+            .and_then(|canonical| std::fs::read_to_string(canonical).ok());
+        let diff = unified_diff(path, existing.as_deref().unwrap_or_default(), content);
+        Some(match existing {
+            None => format!("New file\n{diff}"),
+            Some(_) if diff.is_empty() => "(no changes)".to_string(),
+            Some(_) => diff,
+        })
+    }
+
     fn execute(
         &self,
         input: serde_json::Value,
@@ -221,6 +216,42 @@ mod tests {
         std::fs::remove_dir_all(&allowed).ok();
     }
 
+    #[test]
+    fn approval_preview_diffs_against_the_existing_file() {
+        let dir = std::env::temp_dir().join("buddy-test-write-preview");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "keep\nold\n").unwrap();
+        let path = file.to_str().unwrap();
+        let skill = make_skill(&[dir.to_str().unwrap()]);
+
+        let preview = skill
+            .approval_preview(&serde_json::json!({ "path": path, "content": "keep\nnew\n" }))
+            .unwrap();
+        assert_eq!(
+            preview,
+            format!("--- {path}\n+++ {path}\n@@ -1,2 +1,2 @@\n keep\n-old\n+new\n")
+        );
+
+        let new_file = dir.join("fresh.txt");
+        let preview = skill
+            .approval_preview(&serde_json::json!({
+                "path": new_file.to_str().unwrap(),
+                "content": "hi\n"
+            }))
+            .unwrap();
+        assert!(preview.starts_with("New file\n"), "{preview}");
+        assert!(preview.ends_with("@@ -0,0 +1,1 @@\n+hi\n"), "{preview}");
+
+        let outside = skill.approval_preview(&serde_json::json!({
+            "path": "/etc/hostname",
+            "content": "x"
+        }));
+        assert_eq!(outside, None);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[tokio::test]
     async fn write_file_with_traversal_is_forbidden() {
         let allowed = std::env::temp_dir().join("buddy-test-write-traversal");
@@ -12,10 +12,11 @@ use std::time::Duration;
 
 use tokio::sync::{oneshot, Mutex};
 
-use crate::config::{ApprovalPolicy, Config};
+use crate::config::{ApprovalPolicy, Config, MemoryConfig};
 use crate::embedding::Embedder;
+use crate::engine::{CancelToken, Engine};
 use crate::memory::VectorStore;
-use crate::provider::{AnyProvider, ProviderChain};
+use crate::provider::{AnyProvider, Provider, ProviderChain};
 use crate::reload;
 use crate::skill::working_memory::WorkingMemoryMap;
 use crate::skill::{SkillRegistry, ToolRegistry};
@@ -33,6 +34,14 @@ pub fn new_pending_approvals() -> PendingApprovals {
     Arc::new(Mutex::new(HashMap::new()))
 }
 
+/// Cancellation handles for in-flight chat turns, keyed by conversation ID.
+pub type ActiveGenerations = Arc<Mutex<HashMap<String, CancelToken>>>;
+
+/// Create a new empty `ActiveGenerations` map.
+pub fn new_active_generations() -> ActiveGenerations {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
 /// Shared application state.
 ///
 /// Fields wrapped in `ArcSwap` are hot-reloadable: they can be atomically
@@ -58,6 +67,7 @@ pub struct AppState<P> {
     pub memory_config: arc_swap::ArcSwap<crate::config::MemoryConfig>,
     pub warnings: SharedWarnings,
     pub pending_approvals: PendingApprovals,
+    pub active_generations: ActiveGenerations,
     pub conversation_approvals: ConversationApprovals,
     pub approval_overrides: arc_swap::ArcSwap<HashMap<String, ApprovalPolicy>>,
     pub approval_timeout: Duration,
@@ -71,6 +81,62 @@ pub struct AppState<P> {
     pub telegram_process: ChildProcessHandle,
 }
 
+impl<P: Provider> AppState<P> {
+    /// Load the hot-reloadable components an engine needs, so a config
+    /// reload does not affect a request already in flight.
+    pub fn engine_snapshot(&self) -> EngineSnapshot<P> {
+        let config = self.config.read().unwrap();
+        EngineSnapshot {
+            provider: self.provider.load_full(),
+            registry: self.registry.load_full(),
+            skill_registry: self.skill_registry.load_full(),
+            approval_overrides: self.approval_overrides.load_full(),
+            embedder: self.embedder.load_full(),
+            vector_store: self.vector_store.load_full(),
+            memory_config: self.memory_config.load_full(),
+            persist_reasoning: config.chat.persist_reasoning,
+            tool_concurrency: config.tools.max_concurrency(),
+        }
+    }
+}
+
+/// Components loaded from an [`AppState`] for the duration of one request.
+pub struct EngineSnapshot<P> {
+    provider: Arc<P>,
+    registry: Arc<ToolRegistry>,
+    skill_registry: Arc<SkillRegistry>,
+    approval_overrides: Arc<HashMap<String, ApprovalPolicy>>,
+    embedder: Arc<Option<Arc<dyn Embedder>>>,
+    vector_store: Arc<Option<Arc<dyn VectorStore>>>,
+    memory_config: Arc<MemoryConfig>,
+    persist_reasoning: bool,
+    tool_concurrency: usize,
+}
+
+impl<P: Provider> EngineSnapshot<P> {
+    /// An engine with working memory, long-term memory and summarization,
+    /// as the chat interfaces use it.
+    pub fn engine<'a>(&'a self, state: &'a AppState<P>) -> Engine<'a, P> {
+        Engine::new(
+            &state.store,
+            &*self.provider,
+            &self.registry,
+            &self.skill_registry,
+            &self.approval_overrides,
+            &state.conversation_approvals,
+        )
+        .with_working_memory(&state.working_memory)
+        .with_long_term_memory(
+            (*self.embedder).as_deref(),
+            (*self.vector_store).as_deref(),
+            &self.memory_config,
+        )
+        .with_summarization(&self.memory_config)
+        .with_reasoning_persistence(self.persist_reasoning)
+        .with_tool_concurrency(self.tool_concurrency)
+    }
+}
+
 impl AppState<ProviderChain<AnyProvider>> {
     /// Construct a new `AppState` from a parsed config and config file path.
     ///
@@ -91,8 +157,13 @@ impl AppState<ProviderChain<AnyProvider>> {
 
         let vector_store = reload::build_vector_store(&embedder).map_err(|e| e.to_string())?;
 
-        let registry =
-            reload::build_tool_registry(&config, working_memory.clone(), &embedder, &vector_store);
+        let registry = reload::build_tool_registry(
+            &config,
+            &store,
+            working_memory.clone(),
+            &embedder,
+            &vector_store,
+        );
 
         let skill_registry =
             reload::build_skill_registry(Arc::new(registry.clone()), &embedder, &vector_store);
@@ -114,6 +185,7 @@ impl AppState<ProviderChain<AnyProvider>> {
             memory_config: arc_swap::ArcSwap::from_pointee(config.memory.clone()),
             warnings,
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(approval_overrides),
             approval_timeout: Duration::from_secs(60),
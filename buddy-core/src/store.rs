@@ -1,12 +1,17 @@
+use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use crate::types::{Message, MessageContent, Role};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
 use rusqlite::{params, Connection};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-/// A conversation with all its messages.
+/// A conversation with the messages on its active branch.
+///
+/// Messages form a tree: regenerating a reply or editing an earlier message
+/// starts a new branch from that point. `messages` is the path from the root
+/// to the active leaf, which is what providers see as history.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Conversation {
     pub id: String,
@@ -17,6 +22,17 @@ pub struct Conversation {
     pub messages: Vec<Message>,
 }
 
+/// A stored message with its place in the conversation tree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageNode {
+    pub id: String,
+    /// `None` for a message that starts the conversation (or a branch of it
+    /// that replaces the first message).
+    pub parent_id: Option<String>,
+    #[serde(flatten)]
+    pub message: Message,
+}
+
 /// A lightweight summary for listing conversations.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConversationSummary {
@@ -25,14 +41,178 @@ pub struct ConversationSummary {
     pub source: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Messages on the active branch.
     pub message_count: i64,
 }
 
+/// A rolling summary of a conversation's older messages.
+///
+/// `message_count` is the number of leading messages of the active path the
+/// summary covers; those messages are replaced by the summary in provider
+/// context but remain in the database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RollingSummary {
+    pub summary: String,
+    pub message_count: usize,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Largest image accepted from a chat interface, in bytes.
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Binary content referenced by a `MessageContent::Image`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Token usage of the completion that produced a message.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageUsage {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// How usage totals are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGrouping {
+    Conversation,
+    Provider,
+    Model,
+    Day,
+}
+
+/// Summed token usage for one group key and model.
+///
+/// Rows are always split by provider and model so callers can price them.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UsageTotal {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// What a scheduled job sends when it is due.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    /// Send `text` as a reminder.
+    Message,
+    /// Run `text` as a prompt through the provider and send the reply.
+    Prompt,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Prompt => "prompt",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "prompt" => Self::Prompt,
+            _ => Self::Message,
+        }
+    }
+}
+
+/// A scheduled message or prompt, delivered into the chat it was created in.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Job {
+    pub id: String,
+    pub conversation_id: String,
+    /// Interface that delivers the job: `"telegram"` or `"whatsapp"`.
+    pub channel: String,
+    /// Telegram chat id or WhatsApp phone number.
+    pub target: String,
+    pub kind: JobKind,
+    pub text: String,
+    /// Cron expression for recurring jobs; `None` for one-shot jobs.
+    pub schedule: Option<String>,
+    pub next_run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Failed deliveries of the current run so far.
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// A job to schedule with [`Store::create_job`].
+pub struct NewJob<'a> {
+    pub conversation_id: &'a str,
+    pub channel: &'a str,
+    pub target: &'a str,
+    pub kind: JobKind,
+    pub text: &'a str,
+    pub schedule: Option<&'a str>,
+    pub next_run_at: DateTime<Utc>,
+}
+
+/// An item on a todo list.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TodoItem {
+    pub id: String,
+    /// Name of the list the item is on.
+    pub list: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<NaiveDate>,
+    /// When the item was completed; `None` while it is open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A named todo list with its items. Lists are not tied to a conversation,
+/// so every interface sees the same ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TodoList {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub items: Vec<TodoItem>,
+}
+
+/// Changes to a todo item for [`Store::update_todo`]. Unset fields are left
+/// as they are; `notes` and `due_date` are cleared by an explicit `null`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct TodoUpdate {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub due_date: Option<Option<NaiveDate>>,
+    #[serde(default)]
+    pub completed: Option<bool>,
+}
+
+/// Deserialize a present field (even `null`) as `Some`, so a missing field
+/// can be told apart from one set to `null`.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
 /// SQLite-backed conversation store.
 ///
-/// Wraps a `Connection` in a `Mutex` so it is `Send + Sync`.
+/// Wraps a `Connection` in a `Mutex` so it is `Send + Sync`. Clones share
+/// the same connection.
+#[derive(Clone)]
 pub struct Store {
-    conn: Mutex<Connection>,
+    conn: Arc<Mutex<Connection>>,
 }
 
 impl Store {
@@ -41,7 +221,7 @@ impl Store {
         let conn = Connection::open(path)
             .map_err(|e| format!("failed to open database '{}': {e}", path.display()))?;
         let store = Self {
-            conn: Mutex::new(conn),
+            conn: Arc::new(Mutex::new(conn)),
         };
         store.migrate()?;
         Ok(store)
@@ -54,7 +234,7 @@ impl Store {
         let conn = Connection::open_in_memory()
             .map_err(|e| format!("failed to open in-memory database: {e}"))?;
         let store = Self {
-            conn: Mutex::new(conn),
+            conn: Arc::new(Mutex::new(conn)),
         };
         store.migrate()?;
         Ok(store)
@@ -100,19 +280,99 @@ impl Store {
                 phone TEXT PRIMARY KEY,
                 conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE
             );
+
+            CREATE TABLE IF NOT EXISTS conversation_summaries (
+                conversation_id TEXT PRIMARY KEY REFERENCES conversations(id) ON DELETE CASCADE,
+                summary TEXT NOT NULL,
+                message_count INTEGER NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS summary_usage (
+                conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                usage_provider TEXT NOT NULL,
+                usage_model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                timestamp TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS blobs (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                mime_type TEXT NOT NULL,
+                data BLOB NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS jobs (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                channel TEXT NOT NULL,
+                target TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                text TEXT NOT NULL,
+                schedule TEXT,
+                next_run_at TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(channel, next_run_at);
+
+            CREATE TABLE IF NOT EXISTS todo_lists (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS todo_items (
+                id TEXT PRIMARY KEY,
+                list_id TEXT NOT NULL REFERENCES todo_lists(id) ON DELETE CASCADE,
+                text TEXT NOT NULL,
+                notes TEXT,
+                due_date TEXT,
+                completed_at TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_todo_items_list ON todo_items(list_id);
             ",
         )
         .map_err(|e| format!("migration failed: {e}"))?;
 
-        // Idempotent column addition — ALTER TABLE ADD COLUMN fails if the
-        // column already exists, so we ignore that specific error.
-        match conn.execute(
+        for statement in [
             "ALTER TABLE conversations ADD COLUMN source TEXT NOT NULL DEFAULT 'web'",
-            [],
-        ) {
-            Ok(_) => {}
-            Err(e) if e.to_string().contains("duplicate column name") => {}
-            Err(e) => return Err(format!("migration failed: {e}")),
+            "ALTER TABLE messages ADD COLUMN usage_provider TEXT",
+            "ALTER TABLE messages ADD COLUMN usage_model TEXT",
+            "ALTER TABLE messages ADD COLUMN prompt_tokens INTEGER",
+            "ALTER TABLE messages ADD COLUMN completion_tokens INTEGER",
+            "ALTER TABLE jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0",
+        ] {
+            add_column(&conn, statement)?;
+        }
+
+        // Conversations stored before branching existed are linear: each
+        // message's parent is its predecessor and the last one is the leaf.
+        if add_column(&conn, "ALTER TABLE messages ADD COLUMN parent_id TEXT")? {
+            conn.execute(
+                "UPDATE messages SET parent_id = (
+                     SELECT p.id FROM messages p
+                     WHERE p.conversation_id = messages.conversation_id
+                       AND p.sort_order < messages.sort_order
+                     ORDER BY p.sort_order DESC LIMIT 1)",
+                [],
+            )
+            .map_err(|e| format!("migration failed: {e}"))?;
+        }
+        if add_column(&conn, "ALTER TABLE conversations ADD COLUMN active_leaf_id TEXT")? {
+            conn.execute(
+                "UPDATE conversations SET active_leaf_id = (
+                     SELECT m.id FROM messages m
+                     WHERE m.conversation_id = conversations.id
+                     ORDER BY m.sort_order DESC LIMIT 1)",
+                [],
+            )
+            .map_err(|e| format!("migration failed: {e}"))?;
         }
 
         Ok(())
@@ -183,7 +443,12 @@ impl Store {
         let mut stmt = conn
             .prepare(
                 "SELECT c.id, c.title, c.source, c.created_at, c.updated_at,
-                        (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id) as msg_count
+                        (WITH RECURSIVE path(id, parent_id) AS (
+                             SELECT id, parent_id FROM messages WHERE id = c.active_leaf_id
+                             UNION ALL
+                             SELECT m.id, m.parent_id FROM messages m
+                             JOIN path p ON m.id = p.parent_id)
+                         SELECT COUNT(*) FROM path) as msg_count
                  FROM conversations c
                  ORDER BY c.updated_at DESC",
             )
@@ -245,43 +510,94 @@ impl Store {
             return Ok(None);
         };
 
-        // Fetch messages.
-        let mut msg_stmt = conn
-            .prepare(
-                "SELECT role, content_type, content_json, timestamp
-                 FROM messages
-                 WHERE conversation_id = ?1
-                 ORDER BY sort_order ASC",
-            )
-            .map_err(|e| format!("failed to prepare messages query: {e}"))?;
-
-        let msg_rows = msg_stmt
-            .query_map(params![id], |row| {
-                let role_str: String = row.get(0)?;
-                let _content_type: String = row.get(1)?;
-                let content_json: String = row.get(2)?;
-                let ts_str: String = row.get(3)?;
-                Ok((role_str, content_json, ts_str))
-            })
-            .map_err(|e| format!("failed to query messages: {e}"))?;
-
-        for row in msg_rows {
-            let (role_str, content_json, ts_str) =
-                row.map_err(|e| format!("failed to read message row: {e}"))?;
-            let role: Role = serde_json::from_str(&format!("\"{role_str}\""))
-                .map_err(|e| format!("invalid role '{role_str}': {e}"))?;
-            let content: MessageContent = serde_json::from_str(&content_json)
-                .map_err(|e| format!("invalid message content: {e}"))?;
-            conv.messages.push(Message {
-                role,
-                content,
-                timestamp: parse_datetime(&ts_str),
-            });
-        }
+        let nodes = load_message_nodes(&conn, id)?;
+        let leaf = active_leaf(&conn, id)?;
+        conv.messages = path_to(&nodes, leaf.as_deref())
+            .into_iter()
+            .map(|node| node.message.clone())
+            .collect();
 
         Ok(Some(conv))
     }
 
+    /// The messages on a conversation's active branch, root first, with their
+    /// IDs. `None` if the conversation does not exist.
+    pub fn get_active_path(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<Vec<MessageNode>>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        if !conversation_exists(&conn, conversation_id)? {
+            return Ok(None);
+        }
+        let nodes = load_message_nodes(&conn, conversation_id)?;
+        let leaf = active_leaf(&conn, conversation_id)?;
+        Ok(Some(path_to(&nodes, leaf.as_deref()).into_iter().cloned().collect()))
+    }
+
+    /// Every message of a conversation across all branches, in the order
+    /// they were stored. `None` if the conversation does not exist.
+    pub fn get_message_tree(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<Vec<MessageNode>>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        if !conversation_exists(&conn, conversation_id)? {
+            return Ok(None);
+        }
+        load_message_nodes(&conn, conversation_id).map(Some)
+    }
+
+    /// Move the active branch so it ends at `message_id` (or before the first
+    /// message when `None`). The next appended message starts a new branch
+    /// from there; the messages after it stay in the tree.
+    pub fn rewind_to(&self, conversation_id: &str, message_id: Option<&str>) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let nodes = load_message_nodes(&conn, conversation_id)?;
+        if let Some(id) = message_id
+            && !nodes.iter().any(|n| n.id == id)
+        {
+            return Err(format!("message '{id}' not found in conversation '{conversation_id}'"));
+        }
+        set_active_leaf(&conn, conversation_id, &nodes, message_id)
+    }
+
+    /// Make the branch through `message_id` active, following the most
+    /// recent reply at each step down to a leaf.
+    pub fn switch_branch(&self, conversation_id: &str, message_id: &str) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let nodes = load_message_nodes(&conn, conversation_id)?;
+        if !nodes.iter().any(|n| n.id == message_id) {
+            return Err(format!(
+                "message '{message_id}' not found in conversation '{conversation_id}'"
+            ));
+        }
+        // Nodes are in storage order, so the last child seen is the newest.
+        let mut newest_child: HashMap<&str, &str> = HashMap::new();
+        for node in &nodes {
+            if let Some(parent) = &node.parent_id {
+                newest_child.insert(parent, &node.id);
+            }
+        }
+        let mut leaf = message_id;
+        while let Some(child) = newest_child.get(leaf) {
+            leaf = child;
+        }
+        set_active_leaf(&conn, conversation_id, &nodes, Some(leaf))
+    }
+
     /// Delete a conversation and all its messages (via ON DELETE CASCADE).
     /// Returns `true` if a conversation was deleted, `false` if it didn't exist.
     pub fn delete_conversation(&self, id: &str) -> Result<bool, String> {
@@ -298,8 +614,19 @@ impl Store {
         Ok(rows > 0)
     }
 
-    /// Append a single message to a conversation.
+    /// Append a single message to the end of a conversation's active branch.
     pub fn append_message(&self, conversation_id: &str, message: &Message) -> Result<(), String> {
+        self.append_message_with_usage(conversation_id, message, None)
+    }
+
+    /// Append a message along with the token usage of the completion that
+    /// produced it.
+    pub fn append_message_with_usage(
+        &self,
+        conversation_id: &str,
+        message: &Message,
+        usage: Option<&MessageUsage>,
+    ) -> Result<(), String> {
         let conn = self
             .conn
             .lock()
@@ -315,102 +642,627 @@ impl Store {
             .map_err(|e| format!("failed to get next sort_order: {e}"))?;
 
         let msg_id = uuid::Uuid::new_v4().to_string();
+        let parent_id = active_leaf(&conn, conversation_id)?;
         let role_str = serialize_role(&message.role);
         let (content_type, content_json) = serialize_content(&message.content);
         let ts_str = message.timestamp.to_rfc3339();
 
         conn.execute(
-            "INSERT INTO messages (id, conversation_id, role, content_type, content_json, timestamp, sort_order)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![msg_id, conversation_id, role_str, content_type, content_json, ts_str, sort_order],
+            "INSERT INTO messages (id, conversation_id, role, content_type, content_json, timestamp, sort_order,
+                                   usage_provider, usage_model, prompt_tokens, completion_tokens,
+                                   parent_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                msg_id,
+                conversation_id,
+                role_str,
+                content_type,
+                content_json,
+                ts_str,
+                sort_order,
+                usage.map(|u| &u.provider),
+                usage.map(|u| &u.model),
+                usage.map(|u| u.prompt_tokens),
+                usage.map(|u| u.completion_tokens),
+                parent_id,
+            ],
         )
         .map_err(|e| format!("failed to insert message: {e}"))?;
 
-        // Update the conversation's updated_at.
+        // The new message becomes the leaf; also bump updated_at.
         let now_str = Utc::now().to_rfc3339();
         conn.execute(
-            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
-            params![now_str, conversation_id],
+            "UPDATE conversations SET updated_at = ?1, active_leaf_id = ?2 WHERE id = ?3",
+            params![now_str, msg_id, conversation_id],
+        )
+        .map_err(|e| format!("failed to update conversation timestamp: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Look up the conversation ID for a Telegram chat.
+    pub fn get_conversation_id_for_telegram_chat(
+        &self,
+        chat_id: i64,
+    ) -> Result<Option<String>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let result = conn
+            .query_row(
+                "SELECT conversation_id FROM telegram_chats WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("failed to look up telegram chat: {e}"))?;
+        Ok(result)
+    }
+
+    /// Map a Telegram chat to a buddy conversation.
+    pub fn set_telegram_chat_mapping(
+        &self,
+        chat_id: i64,
+        conversation_id: &str,
+    ) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO telegram_chats (chat_id, conversation_id) VALUES (?1, ?2)",
+            params![chat_id, conversation_id],
+        )
+        .map_err(|e| format!("failed to set telegram chat mapping: {e}"))?;
+        Ok(())
+    }
+
+    /// Look up the conversation ID for a WhatsApp phone number.
+    pub fn get_conversation_id_for_whatsapp_phone(
+        &self,
+        phone: &str,
+    ) -> Result<Option<String>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let result = conn
+            .query_row(
+                "SELECT conversation_id FROM whatsapp_chats WHERE phone = ?1",
+                params![phone],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("failed to look up whatsapp chat: {e}"))?;
+        Ok(result)
+    }
+
+    /// Map a WhatsApp phone number to a buddy conversation.
+    pub fn set_whatsapp_chat_mapping(
+        &self,
+        phone: &str,
+        conversation_id: &str,
+    ) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO whatsapp_chats (phone, conversation_id) VALUES (?1, ?2)",
+            params![phone, conversation_id],
+        )
+        .map_err(|e| format!("failed to set whatsapp chat mapping: {e}"))?;
+        Ok(())
+    }
+
+    /// Look up the Telegram chat a conversation belongs to.
+    pub fn get_telegram_chat_for_conversation(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<i64>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.query_row(
+            "SELECT chat_id FROM telegram_chats WHERE conversation_id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("failed to look up telegram chat: {e}"))
+    }
+
+    /// Look up the WhatsApp phone number a conversation belongs to.
+    pub fn get_whatsapp_phone_for_conversation(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<String>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.query_row(
+            "SELECT phone FROM whatsapp_chats WHERE conversation_id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("failed to look up whatsapp chat: {e}"))
+    }
+
+    /// Schedule a job. Jobs are deleted along with their conversation.
+    pub fn create_job(&self, job: &NewJob<'_>) -> Result<Job, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        conn.execute(
+            "INSERT INTO jobs
+                 (id, conversation_id, channel, target, kind, text, schedule,
+                  next_run_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                job.conversation_id,
+                job.channel,
+                job.target,
+                job.kind.as_str(),
+                job.text,
+                job.schedule,
+                job_time(&job.next_run_at),
+                job_time(&created_at),
+            ],
+        )
+        .map_err(|e| format!("failed to create job: {e}"))?;
+        Ok(Job {
+            id,
+            conversation_id: job.conversation_id.to_string(),
+            channel: job.channel.to_string(),
+            target: job.target.to_string(),
+            kind: job.kind,
+            text: job.text.to_string(),
+            schedule: job.schedule.map(str::to_string),
+            next_run_at: parse_datetime(&job_time(&job.next_run_at)),
+            created_at: parse_datetime(&job_time(&created_at)),
+            attempts: 0,
+        })
+    }
+
+    /// Load a job by id.
+    pub fn get_job(&self, id: &str) -> Result<Option<Job>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.query_row(
+            &format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = ?1"),
+            params![id],
+            job_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("failed to get job: {e}"))
+    }
+
+    /// List a conversation's jobs, soonest first.
+    pub fn list_jobs(&self, conversation_id: &str) -> Result<Vec<Job>, String> {
+        self.query_jobs(
+            &format!(
+                "SELECT {JOB_COLUMNS} FROM jobs WHERE conversation_id = ?1
+                 ORDER BY next_run_at, created_at"
+            ),
+            params![conversation_id],
+        )
+    }
+
+    /// List the jobs of `channel` that are due at `now`, oldest first.
+    pub fn due_jobs(&self, channel: &str, now: DateTime<Utc>) -> Result<Vec<Job>, String> {
+        self.query_jobs(
+            &format!(
+                "SELECT {JOB_COLUMNS} FROM jobs WHERE channel = ?1 AND next_run_at <= ?2
+                 ORDER BY next_run_at, created_at"
+            ),
+            params![channel, job_time(&now)],
+        )
+    }
+
+    fn query_jobs(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Job>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let mut stmt = conn
+            .prepare(sql)
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(params, job_from_row)
+            .map_err(|e| format!("failed to query jobs: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to read job row: {e}"))
+    }
+
+    /// Move a recurring job to its next run.
+    pub fn reschedule_job(&self, id: &str, next_run_at: DateTime<Utc>) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.execute(
+            "UPDATE jobs SET next_run_at = ?1, attempts = 0 WHERE id = ?2",
+            params![job_time(&next_run_at), id],
+        )
+        .map_err(|e| format!("failed to reschedule job: {e}"))?;
+        Ok(())
+    }
+
+    /// Count a failed delivery of a job and try it again at `retry_at`.
+    pub fn retry_job(&self, id: &str, retry_at: DateTime<Utc>) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.execute(
+            "UPDATE jobs SET next_run_at = ?1, attempts = attempts + 1 WHERE id = ?2",
+            params![job_time(&retry_at), id],
+        )
+        .map_err(|e| format!("failed to retry job: {e}"))?;
+        Ok(())
+    }
+
+    /// Add an item to the todo list called `list` (matched case-insensitively),
+    /// creating the list if it does not exist yet.
+    pub fn add_todo(
+        &self,
+        list: &str,
+        text: &str,
+        notes: Option<&str>,
+        due_date: Option<NaiveDate>,
+    ) -> Result<TodoItem, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO todo_lists (id, name, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO NOTHING",
+            params![uuid::Uuid::new_v4().to_string(), list, now],
+        )
+        .map_err(|e| format!("failed to create todo list: {e}"))?;
+        let list_id: String = conn
+            .query_row(
+                "SELECT id FROM todo_lists WHERE name = ?1",
+                params![list],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("failed to look up todo list: {e}"))?;
+        let id = uuid::Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO todo_items (id, list_id, text, notes, due_date, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, list_id, text, notes, due_date.map(|d| d.to_string()), now],
+        )
+        .map_err(|e| format!("failed to add todo item: {e}"))?;
+        todo_by_id(&conn, &id)?.ok_or_else(|| format!("todo item '{id}' vanished"))
+    }
+
+    /// List todo lists by name with their items: open items first, then by
+    /// due date (undated last) and age. Only the list called `list` when it
+    /// is set; completed items only with `include_completed`.
+    pub fn list_todos(
+        &self,
+        list: Option<&str>,
+        include_completed: bool,
+    ) -> Result<Vec<TodoList>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, created_at FROM todo_lists
+                 WHERE ?1 IS NULL OR name = ?1 ORDER BY name",
+            )
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let mut lists = stmt
+            .query_map(params![list], |row| {
+                let created_at: String = row.get(2)?;
+                Ok(TodoList {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: parse_datetime(&created_at),
+                    items: Vec::new(),
+                })
+            })
+            .map_err(|e| format!("failed to query todo lists: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to read todo list row: {e}"))?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TODO_COLUMNS}, i.list_id FROM todo_items i
+                 JOIN todo_lists l ON l.id = i.list_id
+                 WHERE (?1 IS NULL OR l.name = ?1) AND (?2 OR i.completed_at IS NULL)
+                 ORDER BY i.completed_at IS NOT NULL, i.due_date IS NULL, i.due_date,
+                          i.created_at"
+            ))
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(params![list, include_completed], |row| {
+                Ok((todo_from_row(row)?, row.get::<_, String>(7)?))
+            })
+            .map_err(|e| format!("failed to query todo items: {e}"))?;
+        for row in rows {
+            let (item, list_id) = row.map_err(|e| format!("failed to read todo row: {e}"))?;
+            if let Some(list) = lists.iter_mut().find(|l| l.id == list_id) {
+                list.items.push(item);
+            }
+        }
+        Ok(lists)
+    }
+
+    /// Load a todo item by id.
+    pub fn get_todo(&self, id: &str) -> Result<Option<TodoItem>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        todo_by_id(&conn, id)
+    }
+
+    /// Apply `update` to a todo item and return it, or `None` if there is no
+    /// such item. Completing an item that is already complete keeps its
+    /// original completion time.
+    pub fn update_todo(&self, id: &str, update: &TodoUpdate) -> Result<Option<TodoItem>, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let Some(mut item) = todo_by_id(&conn, id)? else {
+            return Ok(None);
+        };
+        if let Some(text) = &update.text {
+            item.text = text.clone();
+        }
+        if let Some(notes) = &update.notes {
+            item.notes = notes.clone();
+        }
+        if let Some(due_date) = update.due_date {
+            item.due_date = due_date;
+        }
+        match update.completed {
+            Some(true) if item.completed_at.is_none() => item.completed_at = Some(Utc::now()),
+            Some(false) => item.completed_at = None,
+            _ => {}
+        }
+        conn.execute(
+            "UPDATE todo_items SET text = ?1, notes = ?2, due_date = ?3, completed_at = ?4
+             WHERE id = ?5",
+            params![
+                item.text,
+                item.notes,
+                item.due_date.map(|d| d.to_string()),
+                item.completed_at.map(|t| t.to_rfc3339()),
+                id
+            ],
+        )
+        .map_err(|e| format!("failed to update todo item: {e}"))?;
+        Ok(Some(item))
+    }
+
+    /// Delete a todo item. Returns `false` if it did not exist.
+    pub fn delete_todo(&self, id: &str) -> Result<bool, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let deleted = conn
+            .execute("DELETE FROM todo_items WHERE id = ?1", params![id])
+            .map_err(|e| format!("failed to delete todo item: {e}"))?;
+        Ok(deleted > 0)
+    }
+
+    /// Delete the todo list called `name` (matched case-insensitively) with
+    /// all of its items. Returns `false` if there is no such list.
+    pub fn delete_todo_list(&self, name: &str) -> Result<bool, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let deleted = conn
+            .execute("DELETE FROM todo_lists WHERE name = ?1", params![name])
+            .map_err(|e| format!("failed to delete todo list: {e}"))?;
+        Ok(deleted > 0)
+    }
+
+    /// Delete a job. Returns `false` if it did not exist.
+    pub fn delete_job(&self, id: &str) -> Result<bool, String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let deleted = conn
+            .execute("DELETE FROM jobs WHERE id = ?1", params![id])
+            .map_err(|e| format!("failed to delete job: {e}"))?;
+        Ok(deleted > 0)
+    }
+
+    /// Record the token usage of a completion that summarized part of a
+    /// conversation. It counts towards [`Store::usage_totals`] like the usage
+    /// of the conversation's messages.
+    pub fn record_summary_usage(
+        &self,
+        conversation_id: &str,
+        usage: &MessageUsage,
+    ) -> Result<(), String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT INTO summary_usage (conversation_id, usage_provider, usage_model,
+                                        prompt_tokens, completion_tokens, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                conversation_id,
+                usage.provider,
+                usage.model,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                Utc::now().to_rfc3339()
+            ],
         )
-        .map_err(|e| format!("failed to update conversation timestamp: {e}"))?;
-
+        .map_err(|e| format!("failed to record summary usage: {e}"))?;
         Ok(())
     }
 
-    /// Look up the conversation ID for a Telegram chat.
-    pub fn get_conversation_id_for_telegram_chat(
+    /// Sum recorded token usage, grouped by `grouping` and split by provider
+    /// and model. Includes the usage of conversation summaries. Restricted to
+    /// one conversation when `conversation_id` is set. Days are UTC dates
+    /// (`YYYY-MM-DD`).
+    pub fn usage_totals(
         &self,
-        chat_id: i64,
-    ) -> Result<Option<String>, String> {
+        grouping: UsageGrouping,
+        conversation_id: Option<&str>,
+    ) -> Result<Vec<UsageTotal>, String> {
+        let key = match grouping {
+            UsageGrouping::Conversation => "conversation_id",
+            UsageGrouping::Provider => "usage_provider",
+            UsageGrouping::Model => "usage_model",
+            UsageGrouping::Day => "substr(timestamp, 1, 10)",
+        };
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| "database lock poisoned".to_string())?;
+        let sql = format!(
+            "SELECT {key}, usage_provider, usage_model,
+                    SUM(prompt_tokens), SUM(completion_tokens)
+             FROM (
+                 SELECT conversation_id, usage_provider, usage_model, prompt_tokens,
+                        completion_tokens, timestamp
+                 FROM messages WHERE prompt_tokens IS NOT NULL
+                 UNION ALL
+                 SELECT conversation_id, usage_provider, usage_model, prompt_tokens,
+                        completion_tokens, timestamp
+                 FROM summary_usage
+             )
+             WHERE ?1 IS NULL OR conversation_id = ?1
+             GROUP BY 1, 2, 3
+             ORDER BY 1, 2, 3"
+        );
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| format!("failed to prepare usage query: {e}"))?;
+        let rows = stmt
+            .query_map(params![conversation_id], |row| {
+                Ok(UsageTotal {
+                    key: row.get(0)?,
+                    provider: row.get(1)?,
+                    model: row.get(2)?,
+                    prompt_tokens: row.get::<_, i64>(3)? as u64,
+                    completion_tokens: row.get::<_, i64>(4)? as u64,
+                })
+            })
+            .map_err(|e| format!("failed to query usage: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to read usage row: {e}"))
+    }
+
+    /// Get the rolling summary for a conversation, if one has been generated.
+    pub fn get_rolling_summary(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<RollingSummary>, String> {
         let conn = self
             .conn
             .lock()
             .map_err(|_| "database lock poisoned".to_string())?;
         let result = conn
             .query_row(
-                "SELECT conversation_id FROM telegram_chats WHERE chat_id = ?1",
-                params![chat_id],
-                |row| row.get(0),
+                "SELECT summary, message_count, updated_at FROM conversation_summaries
+                 WHERE conversation_id = ?1",
+                params![conversation_id],
+                |row| {
+                    let message_count: i64 = row.get(1)?;
+                    let updated_str: String = row.get(2)?;
+                    Ok(RollingSummary {
+                        summary: row.get(0)?,
+                        message_count: message_count as usize,
+                        updated_at: parse_datetime(&updated_str),
+                    })
+                },
             )
             .optional()
-            .map_err(|e| format!("failed to look up telegram chat: {e}"))?;
+            .map_err(|e| format!("failed to get rolling summary: {e}"))?;
         Ok(result)
     }
 
-    /// Map a Telegram chat to a buddy conversation.
-    pub fn set_telegram_chat_mapping(
+    /// Store (or replace) the rolling summary for a conversation.
+    pub fn set_rolling_summary(
         &self,
-        chat_id: i64,
         conversation_id: &str,
+        summary: &str,
+        message_count: usize,
     ) -> Result<(), String> {
         let conn = self
             .conn
             .lock()
             .map_err(|_| "database lock poisoned".to_string())?;
+        let now_str = Utc::now().to_rfc3339();
         conn.execute(
-            "INSERT OR REPLACE INTO telegram_chats (chat_id, conversation_id) VALUES (?1, ?2)",
-            params![chat_id, conversation_id],
+            "INSERT OR REPLACE INTO conversation_summaries
+                 (conversation_id, summary, message_count, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![conversation_id, summary, message_count as i64, now_str],
         )
-        .map_err(|e| format!("failed to set telegram chat mapping: {e}"))?;
+        .map_err(|e| format!("failed to set rolling summary: {e}"))?;
         Ok(())
     }
 
-    /// Look up the conversation ID for a WhatsApp phone number.
-    pub fn get_conversation_id_for_whatsapp_phone(
+    /// Store binary content (such as an uploaded image) for a conversation
+    /// and return its id. Blobs are deleted along with the conversation.
+    pub fn put_blob(
         &self,
-        phone: &str,
-    ) -> Result<Option<String>, String> {
+        conversation_id: &str,
+        mime_type: &str,
+        data: &[u8],
+    ) -> Result<String, String> {
         let conn = self
             .conn
             .lock()
             .map_err(|_| "database lock poisoned".to_string())?;
-        let result = conn
-            .query_row(
-                "SELECT conversation_id FROM whatsapp_chats WHERE phone = ?1",
-                params![phone],
-                |row| row.get(0),
-            )
-            .optional()
-            .map_err(|e| format!("failed to look up whatsapp chat: {e}"))?;
-        Ok(result)
+        let id = uuid::Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO blobs (id, conversation_id, mime_type, data, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, conversation_id, mime_type, data, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("failed to store blob: {e}"))?;
+        Ok(id)
     }
 
-    /// Map a WhatsApp phone number to a buddy conversation.
-    pub fn set_whatsapp_chat_mapping(
-        &self,
-        phone: &str,
-        conversation_id: &str,
-    ) -> Result<(), String> {
+    /// Load a blob by id.
+    pub fn get_blob(&self, id: &str) -> Result<Option<Blob>, String> {
         let conn = self
             .conn
             .lock()
             .map_err(|_| "database lock poisoned".to_string())?;
-        conn.execute(
-            "INSERT OR REPLACE INTO whatsapp_chats (phone, conversation_id) VALUES (?1, ?2)",
-            params![phone, conversation_id],
+        conn.query_row(
+            "SELECT mime_type, data FROM blobs WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Blob {
+                    mime_type: row.get(0)?,
+                    data: row.get(1)?,
+                })
+            },
         )
-        .map_err(|e| format!("failed to set whatsapp chat mapping: {e}"))?;
-        Ok(())
+        .optional()
+        .map_err(|e| format!("failed to get blob: {e}"))
     }
 
     /// Update a conversation's title.
@@ -431,6 +1283,189 @@ impl Store {
 
 // ── Helpers ─────────────────────────────────────────────────────────────
 
+/// Run an `ALTER TABLE ... ADD COLUMN`, returning `false` if the column
+/// already exists (SQLite has no `ADD COLUMN IF NOT EXISTS`).
+fn add_column(conn: &Connection, statement: &str) -> Result<bool, String> {
+    match conn.execute(statement, []) {
+        Ok(_) => Ok(true),
+        Err(e) if e.to_string().contains("duplicate column name") => Ok(false),
+        Err(e) => Err(format!("migration failed: {e}")),
+    }
+}
+
+fn conversation_exists(conn: &Connection, conversation_id: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT 1 FROM conversations WHERE id = ?1",
+        params![conversation_id],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|row| row.is_some())
+    .map_err(|e| format!("failed to get conversation: {e}"))
+}
+
+/// The last message on a conversation's active branch.
+fn active_leaf(conn: &Connection, conversation_id: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT active_leaf_id FROM conversations WHERE id = ?1",
+        params![conversation_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+    .map_err(|e| format!("failed to get active branch: {e}"))
+}
+
+/// Point the active branch at `leaf`, dropping the rolling summary if it
+/// covers messages that are no longer on the branch.
+fn set_active_leaf(
+    conn: &Connection,
+    conversation_id: &str,
+    nodes: &[MessageNode],
+    leaf: Option<&str>,
+) -> Result<(), String> {
+    let old_leaf = active_leaf(conn, conversation_id)?;
+    let old_path = path_to(nodes, old_leaf.as_deref());
+    let new_path = path_to(nodes, leaf);
+    let shared = old_path
+        .iter()
+        .zip(&new_path)
+        .take_while(|(a, b)| a.id == b.id)
+        .count();
+    conn.execute(
+        "DELETE FROM conversation_summaries WHERE conversation_id = ?1 AND message_count > ?2",
+        params![conversation_id, shared as i64],
+    )
+    .map_err(|e| format!("failed to invalidate rolling summary: {e}"))?;
+    conn.execute(
+        "UPDATE conversations SET active_leaf_id = ?1, updated_at = ?2 WHERE id = ?3",
+        params![leaf, Utc::now().to_rfc3339(), conversation_id],
+    )
+    .map_err(|e| format!("failed to set active branch: {e}"))?;
+    Ok(())
+}
+
+/// Load every message of a conversation in `sort_order`.
+fn load_message_nodes(
+    conn: &Connection,
+    conversation_id: &str,
+) -> Result<Vec<MessageNode>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, parent_id, role, content_json, timestamp
+             FROM messages
+             WHERE conversation_id = ?1
+             ORDER BY sort_order ASC",
+        )
+        .map_err(|e| format!("failed to prepare messages query: {e}"))?;
+
+    let rows = stmt
+        .query_map(params![conversation_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(|e| format!("failed to query messages: {e}"))?;
+
+    let mut nodes = Vec::new();
+    for row in rows {
+        let (id, parent_id, role_str, content_json, ts_str) =
+            row.map_err(|e| format!("failed to read message row: {e}"))?;
+        let role: Role = serde_json::from_str(&format!("\"{role_str}\""))
+            .map_err(|e| format!("invalid role '{role_str}': {e}"))?;
+        let content: MessageContent = serde_json::from_str(&content_json)
+            .map_err(|e| format!("invalid message content: {e}"))?;
+        nodes.push(MessageNode {
+            id,
+            parent_id,
+            message: Message {
+                role,
+                content,
+                timestamp: parse_datetime(&ts_str),
+            },
+        });
+    }
+    Ok(nodes)
+}
+
+/// The chain of messages from the root down to `leaf`.
+fn path_to<'n>(nodes: &'n [MessageNode], leaf: Option<&str>) -> Vec<&'n MessageNode> {
+    let by_id: HashMap<&str, &MessageNode> = nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut path = Vec::new();
+    let mut next = leaf;
+    while let Some(node) = next.and_then(|id| by_id.get(id)) {
+        path.push(*node);
+        next = node.parent_id.as_deref();
+    }
+    path.reverse();
+    path
+}
+
+/// Columns of the `jobs` table, in the order `job_from_row` reads them.
+const JOB_COLUMNS: &str =
+    "id, conversation_id, channel, target, kind, text, schedule, next_run_at, created_at, attempts";
+
+fn job_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Job> {
+    let kind: String = row.get(4)?;
+    let next_run_at: String = row.get(7)?;
+    let created_at: String = row.get(8)?;
+    Ok(Job {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        channel: row.get(2)?,
+        target: row.get(3)?,
+        kind: JobKind::parse(&kind),
+        text: row.get(5)?,
+        schedule: row.get(6)?,
+        next_run_at: parse_datetime(&next_run_at),
+        created_at: parse_datetime(&created_at),
+        attempts: row.get(9)?,
+    })
+}
+
+/// Columns of a todo item joined with its list, in the order `todo_from_row`
+/// reads them.
+const TODO_COLUMNS: &str =
+    "i.id, l.name, i.text, i.notes, i.due_date, i.completed_at, i.created_at";
+
+fn todo_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TodoItem> {
+    let due_date: Option<String> = row.get(4)?;
+    let completed_at: Option<String> = row.get(5)?;
+    let created_at: String = row.get(6)?;
+    Ok(TodoItem {
+        id: row.get(0)?,
+        list: row.get(1)?,
+        text: row.get(2)?,
+        notes: row.get(3)?,
+        due_date: due_date.and_then(|d| d.parse().ok()),
+        completed_at: completed_at.as_deref().map(parse_datetime),
+        created_at: parse_datetime(&created_at),
+    })
+}
+
+fn todo_by_id(conn: &Connection, id: &str) -> Result<Option<TodoItem>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {TODO_COLUMNS} FROM todo_items i
+             JOIN todo_lists l ON l.id = i.list_id WHERE i.id = ?1"
+        ),
+        params![id],
+        todo_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("failed to get todo item: {e}"))
+}
+
+/// Format a job time with whole seconds in UTC, so stored times compare
+/// correctly as strings.
+fn job_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
 /// Parse an RFC 3339 datetime string, falling back to epoch on failure.
 fn parse_datetime(s: &str) -> DateTime<Utc> {
     DateTime::parse_from_rfc3339(s)
@@ -453,6 +1488,25 @@ fn serialize_content(content: &MessageContent) -> (String, String) {
         MessageContent::Text { .. } => "text",
         MessageContent::ToolCall { .. } => "tool_call",
         MessageContent::ToolResult { .. } => "tool_result",
+        MessageContent::Image { .. } => "image",
+        MessageContent::Reasoning { .. } => "reasoning",
+    };
+    // Image bytes live in the blob table; only the reference is stored.
+    let stripped;
+    let content = match content {
+        MessageContent::Image {
+            blob_id,
+            mime_type,
+            data: Some(_),
+        } => {
+            stripped = MessageContent::Image {
+                blob_id: blob_id.clone(),
+                mime_type: mime_type.clone(),
+                data: None,
+            };
+            &stripped
+        }
+        _ => content,
     };
     // MessageContent is a known enum; serialization failure indicates a code bug
     let json = serde_json::to_string(content).expect("MessageContent should always serialize");
@@ -877,6 +1931,409 @@ mod tests {
         // Set mapping.
         store.set_telegram_chat_mapping(12345, &conv.id).unwrap();
         let found = store.get_conversation_id_for_telegram_chat(12345).unwrap();
-        assert_eq!(found, Some(conv.id));
+        assert_eq!(found, Some(conv.id.clone()));
+        assert_eq!(
+            store.get_telegram_chat_for_conversation(&conv.id).unwrap(),
+            Some(12345)
+        );
+    }
+
+    // ── Test: scheduled jobs ────────────────────────────────────────────
+
+    #[test]
+    fn jobs_survive_reopen_and_become_due() {
+        let path = temp_db_path("jobs");
+        let now = Utc::now();
+        let (conv_id, job) = {
+            let store = Store::open(&path).unwrap();
+            let conv = store.create_conversation_with_source("Chat", "telegram").unwrap();
+            let job = store
+                .create_job(&NewJob {
+                    conversation_id: &conv.id,
+                    channel: "telegram",
+                    target: "42",
+                    kind: JobKind::Prompt,
+                    text: "Summarize the news",
+                    schedule: Some("0 8 * * *"),
+                    next_run_at: now + chrono::Duration::minutes(5),
+                })
+                .unwrap();
+            (conv.id, job)
+        };
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(store.list_jobs(&conv_id).unwrap(), vec![job.clone()]);
+        assert_eq!(store.get_job(&job.id).unwrap(), Some(job.clone()));
+        assert!(store.due_jobs("telegram", now).unwrap().is_empty());
+
+        let later = now + chrono::Duration::minutes(10);
+        assert!(store.due_jobs("whatsapp", later).unwrap().is_empty());
+        assert_eq!(store.due_jobs("telegram", later).unwrap(), vec![job.clone()]);
+
+        let next = now + chrono::Duration::days(1);
+        store.reschedule_job(&job.id, next).unwrap();
+        assert!(store.due_jobs("telegram", later).unwrap().is_empty());
+
+        assert!(store.delete_job(&job.id).unwrap());
+        assert!(!store.delete_job(&job.id).unwrap());
+        assert!(store.list_jobs(&conv_id).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn jobs_are_deleted_with_their_conversation() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation_with_source("Chat", "whatsapp").unwrap();
+        store.set_whatsapp_chat_mapping("15551234567", &conv.id).unwrap();
+        assert_eq!(
+            store.get_whatsapp_phone_for_conversation(&conv.id).unwrap(),
+            Some("15551234567".to_string())
+        );
+        store
+            .create_job(&NewJob {
+                conversation_id: &conv.id,
+                channel: "whatsapp",
+                target: "15551234567",
+                kind: JobKind::Message,
+                text: "Stand up",
+                schedule: None,
+                next_run_at: Utc::now(),
+            })
+            .unwrap();
+
+        store.delete_conversation(&conv.id).unwrap();
+        assert!(store.list_jobs(&conv.id).unwrap().is_empty());
+    }
+
+    // ── Test: todo lists ────────────────────────────────────────────────
+
+    #[test]
+    fn todos_are_grouped_by_list_and_ordered() {
+        let store = Store::open_in_memory().unwrap();
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let milk = store.add_todo("Groceries", "milk", None, None).unwrap();
+        let eggs = store
+            .add_todo("groceries", "eggs", Some("free range"), Some(date("2026-10-20")))
+            .unwrap();
+        let taxes = store
+            .add_todo("Home", "taxes", None, Some(date("2026-10-18")))
+            .unwrap();
+        // The list name is matched case-insensitively and keeps its first spelling.
+        assert_eq!(eggs.list, "Groceries");
+        assert_eq!(eggs.notes.as_deref(), Some("free range"));
+
+        let done = TodoUpdate {
+            completed: Some(true),
+            ..Default::default()
+        };
+        let eggs_done = store.update_todo(&eggs.id, &done).unwrap().unwrap();
+        let completed_at = eggs_done.completed_at.unwrap();
+
+        let lists = store.list_todos(None, false).unwrap();
+        let names: Vec<_> = lists.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["Groceries", "Home"]);
+        assert_eq!(lists[0].items, [milk]);
+        assert_eq!(lists[1].items, [taxes]);
+
+        // Completed items come last; completing again keeps the first time.
+        store.update_todo(&eggs.id, &done).unwrap();
+        let lists = store.list_todos(Some("GROCERIES"), true).unwrap();
+        assert_eq!(lists.len(), 1);
+        let texts: Vec<_> = lists[0].items.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, ["milk", "eggs"]);
+        assert_eq!(lists[0].items[1].completed_at, Some(completed_at));
+
+        let reopened = store
+            .update_todo(
+                &eggs.id,
+                &TodoUpdate {
+                    completed: Some(false),
+                    notes: Some(None),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(reopened.completed_at, None);
+        assert_eq!(reopened.notes, None);
+        assert_eq!(reopened.due_date, Some(date("2026-10-20")));
+        assert_eq!(store.get_todo(&eggs.id).unwrap(), Some(reopened));
+        assert!(store.update_todo("missing", &done).unwrap().is_none());
+    }
+
+    #[test]
+    fn deleting_a_todo_list_removes_its_items() {
+        let store = Store::open_in_memory().unwrap();
+        let milk = store.add_todo("Groceries", "milk", None, None).unwrap();
+        let eggs = store.add_todo("Groceries", "eggs", None, None).unwrap();
+
+        assert!(store.delete_todo(&milk.id).unwrap());
+        assert!(!store.delete_todo(&milk.id).unwrap());
+        assert!(store.delete_todo_list("groceries").unwrap());
+        assert!(!store.delete_todo_list("groceries").unwrap());
+        assert!(store.get_todo(&eggs.id).unwrap().is_none());
+        assert!(store.list_todos(None, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn todo_update_tells_missing_fields_from_null() {
+        let update: TodoUpdate =
+            serde_json::from_str(r#"{"due_date": null, "notes": "call first"}"#).unwrap();
+        assert_eq!(
+            update,
+            TodoUpdate {
+                text: None,
+                notes: Some(Some("call first".into())),
+                due_date: Some(None),
+                completed: None,
+            }
+        );
+    }
+
+    // ── Test: rolling summaries ─────────────────────────────────────────
+
+    #[test]
+    fn rolling_summary_round_trip_and_replace() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+
+        assert!(store.get_rolling_summary(&conv.id).unwrap().is_none());
+
+        store.set_rolling_summary(&conv.id, "first", 4).unwrap();
+        store.set_rolling_summary(&conv.id, "second", 10).unwrap();
+        let summary = store.get_rolling_summary(&conv.id).unwrap().unwrap();
+        assert_eq!(summary.summary, "second");
+        assert_eq!(summary.message_count, 10);
+    }
+
+    #[test]
+    fn rolling_summary_deleted_with_conversation() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        store.set_rolling_summary(&conv.id, "summary", 2).unwrap();
+
+        store.delete_conversation(&conv.id).unwrap();
+        assert!(store.get_rolling_summary(&conv.id).unwrap().is_none());
+    }
+
+    // ── Test: blobs ─────────────────────────────────────────────────────
+
+    #[test]
+    fn image_messages_store_only_the_blob_reference() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        let blob_id = store.put_blob(&conv.id, "image/png", &[1, 2, 3]).unwrap();
+
+        let msg = Message {
+            role: Role::User,
+            content: MessageContent::Image {
+                blob_id: blob_id.clone(),
+                mime_type: "image/png".into(),
+                data: Some("AQID".into()),
+            },
+            timestamp: Utc::now(),
+        };
+        store.append_message(&conv.id, &msg).unwrap();
+
+        let loaded = store.get_conversation(&conv.id).unwrap().unwrap();
+        assert_eq!(
+            loaded.messages[0].content,
+            MessageContent::Image {
+                blob_id: blob_id.clone(),
+                mime_type: "image/png".into(),
+                data: None,
+            }
+        );
+        let blob = store.get_blob(&blob_id).unwrap().unwrap();
+        assert_eq!(blob.mime_type, "image/png");
+        assert_eq!(blob.data, vec![1, 2, 3]);
+
+        store.delete_conversation(&conv.id).unwrap();
+        assert!(store.get_blob(&blob_id).unwrap().is_none());
+    }
+
+    // ── Test: token usage ───────────────────────────────────────────────
+
+    fn usage(provider: &str, model: &str, prompt: u32, completion: u32) -> MessageUsage {
+        MessageUsage {
+            provider: provider.into(),
+            model: model.into(),
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+        }
+    }
+
+    fn assistant(text: &str) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: MessageContent::Text { text: text.into() },
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn usage_totals_grouped_by_conversation_and_model() {
+        let store = Store::open_in_memory().unwrap();
+        let c1 = store.create_conversation("One").unwrap();
+        let c2 = store.create_conversation("Two").unwrap();
+
+        store.append_message(&c1.id, &assistant("no usage")).unwrap();
+        store
+            .append_message_with_usage(&c1.id, &assistant("a"), Some(&usage("openai", "gpt-4o", 100, 10)))
+            .unwrap();
+        store
+            .append_message_with_usage(&c1.id, &assistant("b"), Some(&usage("openai", "gpt-4o", 50, 5)))
+            .unwrap();
+        store
+            .append_message_with_usage(&c2.id, &assistant("c"), Some(&usage("ollama", "llama3", 7, 3)))
+            .unwrap();
+
+        let totals = store.usage_totals(UsageGrouping::Provider, None).unwrap();
+        assert_eq!(totals.len(), 2);
+        let openai = totals.iter().find(|t| t.key == "openai").unwrap();
+        assert_eq!((openai.prompt_tokens, openai.completion_tokens), (150, 15));
+        assert_eq!(openai.model, "gpt-4o");
+
+        let one = store
+            .usage_totals(UsageGrouping::Conversation, Some(&c1.id))
+            .unwrap();
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].key, c1.id);
+        assert_eq!(one[0].prompt_tokens, 150);
+    }
+
+    #[test]
+    fn usage_totals_grouped_by_day() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        let mut msg = assistant("x");
+        msg.timestamp = DateTime::parse_from_rfc3339("2025-03-04T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        store
+            .append_message_with_usage(&conv.id, &msg, Some(&usage("openai", "gpt-4o", 1, 2)))
+            .unwrap();
+
+        let totals = store.usage_totals(UsageGrouping::Day, None).unwrap();
+        assert_eq!(totals[0].key, "2025-03-04");
+    }
+
+    // ── Test: Conversation tree ─────────────────────────────────────────
+
+    fn user(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            content: MessageContent::Text { text: text.into() },
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn path_texts(store: &Store, conversation_id: &str) -> Vec<String> {
+        store
+            .get_conversation(conversation_id)
+            .unwrap()
+            .unwrap()
+            .messages
+            .into_iter()
+            .map(|m| match m.content {
+                MessageContent::Text { text } => text,
+                other => panic!("unexpected content {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rewinding_starts_a_branch_and_switching_restores_the_old_one() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        store.append_message(&conv.id, &user("question")).unwrap();
+        store.append_message(&conv.id, &assistant("first answer")).unwrap();
+
+        let path = store.get_active_path(&conv.id).unwrap().unwrap();
+        assert_eq!(path[1].parent_id.as_deref(), Some(path[0].id.as_str()));
+
+        // Regenerate: rewind to the question and append a new answer.
+        store.rewind_to(&conv.id, Some(&path[0].id)).unwrap();
+        store.append_message(&conv.id, &assistant("second answer")).unwrap();
+        assert_eq!(path_texts(&store, &conv.id), vec!["question", "second answer"]);
+
+        let tree = store.get_message_tree(&conv.id).unwrap().unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[2].parent_id.as_deref(), Some(path[0].id.as_str()));
+
+        store.switch_branch(&conv.id, &path[1].id).unwrap();
+        assert_eq!(path_texts(&store, &conv.id), vec!["question", "first answer"]);
+        // Switching to the shared root follows the newest reply.
+        store.switch_branch(&conv.id, &path[0].id).unwrap();
+        assert_eq!(path_texts(&store, &conv.id), vec!["question", "second answer"]);
+
+        assert!(store.switch_branch(&conv.id, "missing").is_err());
+        assert!(store.get_message_tree("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn editing_the_first_message_starts_a_new_root() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        store.append_message(&conv.id, &user("typo")).unwrap();
+        store.append_message(&conv.id, &assistant("huh?")).unwrap();
+
+        store.rewind_to(&conv.id, None).unwrap();
+        store.append_message(&conv.id, &user("fixed")).unwrap();
+        assert_eq!(path_texts(&store, &conv.id), vec!["fixed"]);
+        assert_eq!(store.get_message_tree(&conv.id).unwrap().unwrap().len(), 3);
+        assert_eq!(store.list_conversations().unwrap()[0].message_count, 1);
+
+        store.rewind_to(&conv.id, None).unwrap();
+        assert_eq!(store.list_conversations().unwrap()[0].message_count, 0);
+    }
+
+    #[test]
+    fn leaving_the_summarized_prefix_drops_the_rolling_summary() {
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation("Test").unwrap();
+        for text in ["a", "b", "c", "d"] {
+            store.append_message(&conv.id, &user(text)).unwrap();
+        }
+        let path = store.get_active_path(&conv.id).unwrap().unwrap();
+        store.set_rolling_summary(&conv.id, "a and b", 2).unwrap();
+
+        // Branching after the summarized messages keeps the summary.
+        store.rewind_to(&conv.id, Some(&path[2].id)).unwrap();
+        assert!(store.get_rolling_summary(&conv.id).unwrap().is_some());
+
+        store.rewind_to(&conv.id, Some(&path[0].id)).unwrap();
+        assert!(store.get_rolling_summary(&conv.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn linear_history_from_before_branching_is_linked_on_migration() {
+        let path = temp_db_path("tree-migration");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE conversations (id TEXT PRIMARY KEY, title TEXT NOT NULL,
+                     created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+                 CREATE TABLE messages (id TEXT PRIMARY KEY, conversation_id TEXT NOT NULL,
+                     role TEXT NOT NULL, content_type TEXT NOT NULL, content_json TEXT NOT NULL,
+                     timestamp TEXT NOT NULL, sort_order INTEGER NOT NULL);
+                 INSERT INTO conversations VALUES ('c', 'Old', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                 INSERT INTO messages VALUES ('m1', 'c', 'user', 'text',
+                     '{\"type\":\"text\",\"text\":\"hi\"}', '2024-01-01T00:00:00Z', 0);
+                 INSERT INTO messages VALUES ('m2', 'c', 'assistant', 'text',
+                     '{\"type\":\"text\",\"text\":\"hello\"}', '2024-01-01T00:00:01Z', 1);",
+            )
+            .unwrap();
+        }
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(path_texts(&store, "c"), vec!["hi", "hello"]);
+        store.append_message("c", &user("again")).unwrap();
+        let nodes = store.get_active_path("c").unwrap().unwrap();
+        assert_eq!(nodes[1].parent_id.as_deref(), Some("m1"));
+        assert_eq!(nodes[2].parent_id.as_deref(), Some("m2"));
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
     }
 }
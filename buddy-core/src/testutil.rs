@@ -182,10 +182,50 @@ impl Provider for MockProvider {
     }
 }
 
+/// A mock provider that returns fixed text tokens and records the messages
+/// it was called with.
+pub struct RecordingProvider {
+    pub tokens: Vec<String>,
+    calls: Mutex<Vec<Vec<Message>>>,
+}
+
+impl RecordingProvider {
+    pub fn new(tokens: Vec<String>) -> Self {
+        Self {
+            tokens,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Messages passed to the most recent `complete` call.
+    pub fn last_messages(&self) -> Vec<Message> {
+        self.calls.lock().unwrap().last().cloned().unwrap_or_default()
+    }
+}
+
+impl Provider for RecordingProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        _tools: Option<Vec<serde_json::Value>>,
+    ) -> Result<TokenStream, ProviderError> {
+        self.calls.lock().unwrap().push(messages);
+        let tokens = self.tokens.clone();
+        let stream = async_stream::try_stream! {
+            for text in tokens {
+                yield Token::Text { text };
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+}
+
 /// Responses the sequenced provider can return.
 pub enum MockResponse {
     Text(Vec<String>),
     ToolCalls(Vec<(String, String, String)>),
+    /// Arbitrary tokens, e.g. text followed by `Token::Usage`.
+    Tokens(Vec<Token>),
 }
 
 /// A mock provider that returns different responses per call.
@@ -233,6 +273,14 @@ impl Provider for SequencedProvider {
                 };
                 Ok(Box::pin(stream))
             }
+            MockResponse::Tokens(tokens) => {
+                let stream = async_stream::try_stream! {
+                    for token in tokens {
+                        yield token;
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
         }
     }
 }
@@ -379,3 +427,67 @@ impl crate::embedding::Embedder for WrongDimensionEmbedder {
 }
 
 
+
+// ── HTTP server ─────────────────────────────────────────────────────────
+
+/// Answer every request on a local port with the bytes `respond` builds from
+/// the raw request text. Each request is read in full (the head, then as much
+/// body as Content-Length says) and also sent on the returned channel, which
+/// callers that don't inspect requests can drop. Returns the server's base
+/// URL, e.g. `http://127.0.0.1:4321`, and that channel.
+pub async fn serve<F>(respond: F) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>)
+where
+    F: Fn(&str) -> Vec<u8> + Send + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (sent, received) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = stream.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|l| {
+                            let (k, v) = l.split_once(':')?;
+                            k.eq_ignore_ascii_case("content-length")
+                                .then(|| v.trim().parse::<usize>().ok())
+                                .flatten()
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let request = String::from_utf8_lossy(&request).into_owned();
+            let response = respond(&request);
+            let _ = sent.send(request);
+            let _ = stream.write_all(&response).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    (base, received)
+}
+
+/// An HTTP/1.1 response with Content-Length and `Connection: close` set.
+/// `headers` holds any other header lines, each ending in CRLF.
+pub fn http_response(status: &str, headers: &str, body: impl AsRef<[u8]>) -> Vec<u8> {
+    let body = body.as_ref();
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
@@ -26,6 +26,21 @@ pub enum MessageContent {
         name: String,
         content: String,
     },
+    /// An image attached by the user. The bytes live in the store's blob
+    /// table under `blob_id`; `data` carries them base64-encoded only while a
+    /// request is being built and is never persisted.
+    Image {
+        #[serde(default)]
+        blob_id: String,
+        mime_type: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<String>,
+    },
+    /// The model's reasoning ("thinking") before its reply, kept separate
+    /// from the answer text.
+    Reasoning {
+        text: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -119,4 +134,20 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn image_omits_data_when_absent() {
+        let content = MessageContent::Image {
+            blob_id: "blob-1".into(),
+            mime_type: "image/png".into(),
+            data: None,
+        };
+        let json = serde_json::to_string(&content).expect("serialize");
+        assert_eq!(
+            json,
+            r#"{"type":"image","blob_id":"blob-1","mime_type":"image/png"}"#
+        );
+        let deserialized: MessageContent = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(deserialized, content);
+    }
 }
@@ -1,6 +1,5 @@
 //! Chat streaming endpoint and tool-call loop.
 
-use std::collections::HashMap;
 use std::convert::Infallible;
 use std::sync::Arc;
 
@@ -9,20 +8,21 @@ use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::sse::{Event, Sse};
 use axum::Json;
-use chrono::Utc;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use futures_core::Stream;
 use futures_util::StreamExt;
 use tokio::sync::oneshot;
 
-use super::{ApiError, AppState, ApproveRequest, ChatEvent, ChatRequest, MemorySnippet};
-use buddy_core::config::ApprovalPolicy;
+use super::{
+    ApiError, AppState, ApproveRequest, ChatEvent, ChatRequest, EditMessageRequest, MemorySnippet,
+    RegenerateRequest, bad_request, internal_error, not_found_error,
+};
+use buddy_core::engine::{ApprovalRequest, CancelToken, Engine, EngineEvent};
 use buddy_core::types::{Message, MessageContent, Role};
-use buddy_core::provider::{Provider, Token};
+use buddy_core::provider::Provider;
 use buddy_core::skill::PermissionLevel;
-use buddy_core::store::title_from_message;
-
-/// Maximum number of tool-call loop iterations before aborting.
-pub(super) const MAX_TOOL_ITERATIONS: usize = 10;
+use buddy_core::store::{MAX_IMAGE_BYTES, MessageNode, title_from_message};
 
 /// `POST /api/chat` — accepts a `ChatRequest` and streams `ChatEvent` frames via SSE.
 ///
@@ -32,6 +32,9 @@ pub(super) const MAX_TOOL_ITERATIONS: usize = 10;
 ///
 /// If `conversation_id` is provided, loads history from that conversation.
 /// If omitted/null, auto-creates a new conversation.
+///
+/// The turn is cancelled when the client disconnects or when
+/// `POST /api/chat/{conversation_id}/cancel` is called.
 pub async fn chat_handler<P: Provider + 'static>(
     State(state): State<Arc<AppState<P>>>,
     body: Bytes,
@@ -45,6 +48,7 @@ pub async fn chat_handler<P: Provider + 'static>(
             }),
         )
     })?;
+    let images = decode_images(&state, &request.messages)?;
 
     // Resolve or create the conversation, loading existing messages when continuing.
     let (conversation_id, existing_messages) = match &request.conversation_id {
@@ -96,35 +100,165 @@ pub async fn chat_handler<P: Provider + 'static>(
         }
     };
 
+    // Move inline image data into the blob store; only references are kept.
+    let mut new_messages = request.messages;
+    for (message, bytes) in new_messages.iter_mut().zip(images) {
+        if let (
+            Some(bytes),
+            MessageContent::Image {
+                blob_id,
+                mime_type,
+                data,
+            },
+        ) = (bytes, &mut message.content)
+        {
+            *blob_id = state
+                .store
+                .put_blob(&conversation_id, mime_type, &bytes)
+                .map_err(internal_error)?;
+            *data = None;
+        }
+    }
+
     // Combine existing history with new messages for provider context.
-    let new_messages = request.messages;
     let mut all_messages = existing_messages;
     let persist_from = all_messages.len();
     all_messages.extend(new_messages);
 
-    let tools = {
-        let registry = state.registry.load();
-        let skill_registry = state.skill_registry.load();
-        let mut defs = registry.tool_definitions();
-        defs.extend(skill_registry.tool_definitions());
-        if defs.is_empty() {
-            None
-        } else {
-            Some(defs)
+    let disable_memory = request.disable_memory;
+    Ok(stream_turn(state, conversation_id, all_messages, persist_from, disable_memory).await)
+}
+
+/// `POST /api/conversations/{id}/messages/{message_id}/regenerate` — answer
+/// the user message again on a new branch, streaming `ChatEvent` frames.
+///
+/// `message_id` may be the user message or any message of the reply to it.
+/// The previous reply stays in the tree.
+pub async fn regenerate_message<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path((conversation_id, message_id)): Path<(String, String)>,
+    body: Bytes,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ApiError>)> {
+    let request: RegenerateRequest = if body.is_empty() {
+        RegenerateRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| bad_request(format!("invalid request body: {e}")))?
+    };
+    let tree = load_tree(&state, &conversation_id)?;
+
+    // Walk up to the user message the reply answers.
+    let mut current = tree.iter().find(|node| node.id == message_id);
+    if current.is_none() {
+        return Err(not_found_error(format!("message '{message_id}' not found")));
+    }
+    let prompt = loop {
+        match current {
+            Some(node) if is_user_text(&node.message) => break node,
+            Some(node) => {
+                current = node
+                    .parent_id
+                    .as_deref()
+                    .and_then(|parent| tree.iter().find(|n| n.id == parent));
+            }
+            None => {
+                return Err(bad_request(format!(
+                    "message '{message_id}' has no user message to answer"
+                )));
+            }
         }
     };
 
+    state
+        .store
+        .rewind_to(&conversation_id, Some(&prompt.id))
+        .map_err(internal_error)?;
+    let messages = load_messages(&state, &conversation_id)?;
+    let persist_from = messages.len();
+    Ok(stream_turn(state, conversation_id, messages, persist_from, request.disable_memory).await)
+}
+
+/// `POST /api/conversations/{id}/messages/{message_id}/edit` — replace a user
+/// message with new text on a new branch and stream the reply to it.
+///
+/// The original message and everything after it stay in the tree.
+pub async fn edit_message<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path((conversation_id, message_id)): Path<(String, String)>,
+    body: Bytes,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ApiError>)> {
+    let request: EditMessageRequest = serde_json::from_slice(&body)
+        .map_err(|e| bad_request(format!("invalid request body: {e}")))?;
+    let tree = load_tree(&state, &conversation_id)?;
+    let Some(original) = tree.iter().find(|node| node.id == message_id) else {
+        return Err(not_found_error(format!("message '{message_id}' not found")));
+    };
+    if !is_user_text(&original.message) {
+        return Err(bad_request(format!("message '{message_id}' is not a user text message")));
+    }
+
+    state
+        .store
+        .rewind_to(&conversation_id, original.parent_id.as_deref())
+        .map_err(internal_error)?;
+    let mut messages = load_messages(&state, &conversation_id)?;
+    let persist_from = messages.len();
+    messages.push(Message {
+        role: Role::User,
+        content: MessageContent::Text { text: request.text },
+        timestamp: chrono::Utc::now(),
+    });
+    Ok(stream_turn(state, conversation_id, messages, persist_from, request.disable_memory).await)
+}
+
+/// Run a turn in the background and stream its events to the client.
+///
+/// `messages[persist_from..]` are new and get persisted before the engine
+/// runs. The turn is registered in `active_generations` so it can be
+/// cancelled, and is cancelled if the client disconnects.
+async fn stream_turn<P: Provider + 'static>(
+    state: Arc<AppState<P>>,
+    conversation_id: String,
+    all_messages: Vec<Message>,
+    persist_from: usize,
+    disable_memory: bool,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     // Channel for streaming events to the client.
     let (tx, mut rx) = tokio::sync::mpsc::channel::<ChatEvent>(64);
 
+    let cancel = CancelToken::new();
+    state
+        .active_generations
+        .lock()
+        .await
+        .insert(conversation_id.clone(), cancel.clone());
+
     let conv_id = conversation_id.clone();
-    let disable_memory = request.disable_memory;
+    let turn_cancel = cancel.clone();
     tokio::spawn(async move {
-        run_tool_loop(state, conv_id, all_messages, persist_from, tools, tx, disable_memory).await;
+        run_tool_loop(
+            state.clone(),
+            conv_id.clone(),
+            all_messages,
+            persist_from,
+            tx,
+            disable_memory,
+            &turn_cancel,
+        )
+        .await;
+        // Only forget the handle if a newer turn has not replaced it.
+        let mut active = state.active_generations.lock().await;
+        if active.get(&conv_id).is_some_and(|c| c.same_as(&turn_cancel)) {
+            active.remove(&conv_id);
+        }
     });
 
     let conv_id_for_meta = conversation_id;
+    // Dropped with the SSE stream when the client disconnects, cancelling the turn.
+    let cancel_on_drop = CancelOnDrop(cancel);
     let events = async_stream::stream! {
+        let _cancel_on_drop = cancel_on_drop;
+
         // Emit ConversationMeta as the first event.
         yield Ok::<_, Infallible>(
             Event::default().data(serde_json::to_string(&ChatEvent::ConversationMeta {
@@ -143,7 +277,30 @@ pub async fn chat_handler<P: Provider + 'static>(
         }
     };
 
-    Ok(Sse::new(events))
+    Sse::new(events)
+}
+
+/// `POST /api/chat/{conversation_id}/cancel` — stop the turn in progress.
+///
+/// The provider stream or pending approval is abandoned; the partial reply
+/// and the cancellation are persisted by the engine.
+pub async fn cancel_handler<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(conversation_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    match state.active_generations.lock().await.get(&conversation_id) {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(StatusCode::OK)
+        }
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                code: "not_found".into(),
+                message: format!("no generation in progress for conversation '{conversation_id}'"),
+            }),
+        )),
+    }
 }
 
 /// `POST /api/chat/{conversation_id}/approve` — approve or deny a pending skill execution.
@@ -174,6 +331,90 @@ pub async fn approve_handler<P: Provider + 'static>(
 
 // ── Internal helpers ────────────────────────────────────────────────────
 
+/// The decoded bytes of each message's inline image; `None` for messages
+/// without one and for images that refer to a stored blob.
+type DecodedImages = Vec<Option<Vec<u8>>>;
+
+/// Decode the inline images of a chat request, indexed like `messages`.
+///
+/// An image must carry base64 `data` with an `image/*` MIME type within
+/// `MAX_IMAGE_BYTES`, or refer to a blob that is already stored.
+fn decode_images<P: Provider>(
+    state: &AppState<P>,
+    messages: &[Message],
+) -> Result<DecodedImages, (StatusCode, Json<ApiError>)> {
+    messages
+        .iter()
+        .map(|message| {
+            let MessageContent::Image {
+                blob_id,
+                mime_type,
+                data,
+            } = &message.content
+            else {
+                return Ok(None);
+            };
+            let Some(data) = data else {
+                return match state.store.get_blob(blob_id).map_err(internal_error)? {
+                    Some(_) => Ok(None),
+                    None => Err(bad_request(format!("image blob '{blob_id}' not found"))),
+                };
+            };
+            if !mime_type.starts_with("image/") {
+                return Err(bad_request(format!("unsupported image type '{mime_type}'")));
+            }
+            let bytes = BASE64
+                .decode(data)
+                .map_err(|e| bad_request(format!("invalid image data: {e}")))?;
+            if bytes.len() > MAX_IMAGE_BYTES {
+                return Err(bad_request(format!(
+                    "image exceeds the {} MB limit",
+                    MAX_IMAGE_BYTES / (1024 * 1024)
+                )));
+            }
+            Ok(Some(bytes))
+        })
+        .collect()
+}
+
+fn is_user_text(message: &Message) -> bool {
+    message.role == Role::User && matches!(message.content, MessageContent::Text { .. })
+}
+
+/// All messages of a conversation, or a 404 if it does not exist.
+fn load_tree<P: Provider>(
+    state: &AppState<P>,
+    conversation_id: &str,
+) -> Result<Vec<MessageNode>, (StatusCode, Json<ApiError>)> {
+    state
+        .store
+        .get_message_tree(conversation_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found_error(format!("conversation '{conversation_id}' not found")))
+}
+
+/// The messages on a conversation's active branch.
+fn load_messages<P: Provider>(
+    state: &AppState<P>,
+    conversation_id: &str,
+) -> Result<Vec<Message>, (StatusCode, Json<ApiError>)> {
+    state
+        .store
+        .get_conversation(conversation_id)
+        .map_err(internal_error)?
+        .map(|conv| conv.messages)
+        .ok_or_else(|| not_found_error(format!("conversation '{conversation_id}' not found")))
+}
+
+/// Cancels the turn when the SSE stream holding it is dropped.
+struct CancelOnDrop(CancelToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
 /// Persist a message to the store, logging errors without crashing.
 fn persist_message(store: &buddy_core::store::Store, conversation_id: &str, message: &Message) {
     if let Err(e) = store.append_message(conversation_id, message) {
@@ -181,39 +422,16 @@ fn persist_message(store: &buddy_core::store::Store, conversation_id: &str, mess
     }
 }
 
-/// Check whether a skill execution should proceed, applying the approval policy.
+/// Ask the web client to approve a skill execution.
 ///
-/// Returns `true` if the skill is approved (by policy, prior approval, or user action),
-/// `false` if denied or timed out.
-async fn check_approval<P: Provider>(
+/// Sends an `ApprovalRequest` event and waits for `approve_handler` to resolve
+/// it. Returns `false` if denied, timed out or cancelled.
+async fn request_approval<P: Provider>(
     state: &Arc<AppState<P>>,
-    approval_overrides: &HashMap<String, ApprovalPolicy>,
     tx: &tokio::sync::mpsc::Sender<ChatEvent>,
-    conversation_id: &str,
-    skill_name: &str,
-    arguments: &str,
-    permission_level: PermissionLevel,
+    request: &ApprovalRequest,
+    cancel: &CancelToken,
 ) -> bool {
-    // Resolve effective policy: config override, or default Always for non-ReadOnly.
-    let policy = approval_overrides
-        .get(skill_name)
-        .copied()
-        .unwrap_or(ApprovalPolicy::Always);
-
-    match policy {
-        ApprovalPolicy::Trust => return true,
-        ApprovalPolicy::Once => {
-            let approvals = state.conversation_approvals.lock().await;
-            if let Some(skills) = approvals.get(conversation_id) {
-                if skills.contains(skill_name) {
-                    return true;
-                }
-            }
-        }
-        ApprovalPolicy::Always => {}
-    }
-
-    // Need to ask the user.
     let approval_id = uuid::Uuid::new_v4().to_string();
     let (sender, receiver) = oneshot::channel::<bool>();
 
@@ -222,10 +440,10 @@ async fn check_approval<P: Provider>(
         pending.insert(approval_id.clone(), sender);
     }
 
-    let args_value: serde_json::Value = serde_json::from_str(arguments)
+    let args_value: serde_json::Value = serde_json::from_str(&request.arguments)
         .unwrap_or_else(|_| serde_json::json!({}));
 
-    let perm_str = match permission_level {
+    let perm_str = match request.permission_level {
         PermissionLevel::ReadOnly => "read_only",
         PermissionLevel::Mutating => "mutating",
         PermissionLevel::Network => "network",
@@ -234,13 +452,17 @@ async fn check_approval<P: Provider>(
     let _ = tx
         .send(ChatEvent::ApprovalRequest {
             id: approval_id.clone(),
-            skill_name: skill_name.to_string(),
+            skill_name: request.skill_name.clone(),
             arguments: args_value,
+            preview: request.preview.clone(),
             permission_level: perm_str.to_string(),
         })
         .await;
 
-    let result = tokio::time::timeout(state.approval_timeout, receiver).await;
+    let result = tokio::select! {
+        result = tokio::time::timeout(state.approval_timeout, receiver) => Some(result),
+        () = cancel.cancelled() => None,
+    };
 
     // Cleanup pending entry regardless of outcome.
     {
@@ -248,39 +470,22 @@ async fn check_approval<P: Provider>(
         pending.remove(&approval_id);
     }
 
-    match result {
-        Ok(Ok(true)) => {
-            // Record for `once` policy.
-            if policy == ApprovalPolicy::Once {
-                let mut approvals = state.conversation_approvals.lock().await;
-                approvals
-                    .entry(conversation_id.to_string())
-                    .or_default()
-                    .insert(skill_name.to_string());
-            }
-            true
-        }
-        _ => false,
-    }
+    matches!(result, Some(Ok(Ok(true))))
 }
 
-/// Run the tool-call loop, sending `ChatEvent`s through `tx`.
+/// Run one conversation turn on the shared engine, rendering its events as
+/// `ChatEvent`s through `tx`.
 ///
-/// 1. Send messages + tool definitions to the provider.
-/// 2. If the provider yields tool calls: execute them via the `ToolRegistry`,
-///    append `ToolCall` and `ToolResult` messages, and call the provider again.
-/// 3. Repeat until the provider returns only text (no tool calls).
-/// 4. Text deltas are streamed to the client as `TokenDelta` events.
-/// 5. Stops after `MAX_TOOL_ITERATIONS` to prevent runaway loops.
-/// 6. All messages (user, assistant, tool calls, tool results) are persisted.
+/// New incoming messages (from `persist_from` on) are persisted first, then
+/// current warnings are emitted, then the engine runs the tool-call loop.
 async fn run_tool_loop<P: Provider>(
     state: Arc<AppState<P>>,
     conversation_id: String,
-    mut messages: Vec<Message>,
+    messages: Vec<Message>,
     persist_from: usize,
-    tools: Option<Vec<serde_json::Value>>,
     tx: tokio::sync::mpsc::Sender<ChatEvent>,
     disable_memory: bool,
+    cancel: &CancelToken,
 ) {
     // Persist only new incoming messages (existing ones are already in the DB).
     for msg in &messages[persist_from..] {
@@ -301,253 +506,74 @@ async fn run_tool_loop<P: Provider>(
     let embedder = state.embedder.load();
     let vector_store = state.vector_store.load();
     let registry = state.registry.load();
+    let skill_registry = state.skill_registry.load();
     let provider = state.provider.load();
     let approval_overrides = state.approval_overrides.load();
+    let (persist_reasoning, tool_concurrency) = {
+        let config = state.config.read().unwrap();
+        (config.chat.persist_reasoning, config.tools.max_concurrency())
+    };
 
-    // Automatic context retrieval: search long-term memory for relevant memories.
-    let mut recalled_context: Option<String> = None;
-    if memory_config.auto_retrieve
-        && !disable_memory
-        && embedder.is_some()
-        && vector_store.is_some()
-    {
-        // Find the latest user message text.
-        let latest_user_text = messages
-            .iter()
-            .rev()
-            .find_map(|m| match (&m.role, &m.content) {
-                (Role::User, MessageContent::Text { text }) => Some(text.as_str()),
-                _ => None,
-            });
-
-        if let Some(query_text) = latest_user_text {
-            let emb = (**embedder).as_ref().unwrap();
-            let vs = (**vector_store).as_ref().unwrap();
-
-            if let Ok(embeddings) = emb.embed(&[query_text]) {
-                if let Some(embedding) = embeddings.into_iter().next() {
-                    if let Ok(results) = vs.search(&embedding, memory_config.auto_retrieve_limit) {
-                        let threshold = memory_config.similarity_threshold;
-                        let relevant: Vec<_> = results
-                            .into_iter()
-                            .filter(|r| r.score >= threshold)
-                            .collect();
-
-                        if !relevant.is_empty() {
-                            // Build system prompt section.
-                            let mut context_lines = vec!["## Recalled Memories".to_string()];
-                            let mut snippets = Vec::new();
-                            for r in &relevant {
-                                let category = r.metadata.get("category")
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s.to_string());
-                                let cat_label = category.as_deref().unwrap_or("general");
-                                context_lines.push(format!(
-                                    "- \"{}\" ({}, relevance: {:.2})",
-                                    r.source_text, cat_label, r.score
-                                ));
-                                snippets.push(MemorySnippet {
-                                    text: r.source_text.clone(),
-                                    category,
-                                    score: r.score,
-                                });
-                            }
-
-                            recalled_context = Some(context_lines.join("\n"));
-                            let _ = tx.send(ChatEvent::MemoryContext { memories: snippets }).await;
-                        }
-                    }
-                }
-            }
-        }
+    let mut engine = Engine::new(
+        &state.store,
+        &**provider,
+        &registry,
+        &skill_registry,
+        &approval_overrides,
+        &state.conversation_approvals,
+    )
+    .with_working_memory(&state.working_memory)
+    .with_summarization(&memory_config)
+    .with_reasoning_persistence(persist_reasoning)
+    .with_tool_concurrency(tool_concurrency)
+    .with_cancellation(cancel);
+    if !disable_memory {
+        engine = engine.with_long_term_memory(
+            (**embedder).as_deref(),
+            (**vector_store).as_deref(),
+            &memory_config,
+        );
     }
 
-    for _iteration in 0..MAX_TOOL_ITERATIONS {
-        // Inject recalled long-term memories and working memory as system context.
-        let mut provider_messages = messages.clone();
-        if let Some(ctx) = &recalled_context {
-            provider_messages.insert(
-                0,
-                Message {
-                    role: Role::System,
-                    content: MessageContent::Text { text: ctx.clone() },
-                    timestamp: Utc::now(),
-                },
-            );
-        }
-        {
-            let wm_map = state.working_memory.lock().unwrap();
-            if let Some(wm) = wm_map.get(&conversation_id) {
-                if !wm.is_empty() {
-                    provider_messages.insert(
-                        0,
-                        Message {
-                            role: Role::System,
-                            content: MessageContent::Text {
-                                text: format!(
-                                    "[Working Memory]\n{}",
-                                    wm.to_context_string()
-                                ),
-                            },
-                            timestamp: Utc::now(),
-                        },
-                    );
-                }
+    let events = engine.run(&conversation_id, messages);
+    tokio::pin!(events);
+    while let Some(event) = events.next().await {
+        let chat_event = match event {
+            EngineEvent::MemoryContext { memories } => ChatEvent::MemoryContext {
+                memories: memories
+                    .into_iter()
+                    .map(|m| MemorySnippet {
+                        text: m.text,
+                        category: m.category,
+                        score: m.score,
+                    })
+                    .collect(),
+            },
+            EngineEvent::TextDelta { text } => ChatEvent::TokenDelta { content: text },
+            EngineEvent::ReasoningDelta { text } => ChatEvent::ReasoningDelta { content: text },
+            EngineEvent::Warning { message } => ChatEvent::Warning { message },
+            EngineEvent::StreamReset { message } => ChatEvent::StreamReset { message },
+            EngineEvent::ToolCallStart { id, name, arguments } => {
+                ChatEvent::ToolCallStart { id, name, arguments }
             }
-        }
-
-        // Call the provider.
-        let token_stream = match provider.complete(provider_messages, tools.clone()).await {
-            Ok(s) => s,
-            Err(e) => {
-                let _ = tx.send(ChatEvent::Error { message: e.to_string() }).await;
-                let _ = tx.send(ChatEvent::Done).await;
-                return;
+            EngineEvent::ToolCallResult { id, content, .. } => {
+                ChatEvent::ToolCallResult { id, content }
             }
-        };
-
-        // Consume the stream, collecting text and tool calls.
-        let mut tool_calls: Vec<(String, String, String)> = Vec::new();
-        let mut full_text = String::new();
-
-        tokio::pin!(token_stream);
-        while let Some(result) = token_stream.next().await {
-            match result {
-                Ok(Token::Text { text }) => {
-                    full_text.push_str(&text);
-                    // Stream text deltas immediately.
-                    let _ = tx
-                        .send(ChatEvent::TokenDelta {
-                            content: text,
-                        })
-                        .await;
-                }
-                Ok(Token::Warning { message }) => {
-                    let _ = tx.send(ChatEvent::Warning { message }).await;
-                }
-                Ok(Token::ToolCall {
-                    id,
-                    name,
-                    arguments,
-                }) => {
-                    tool_calls.push((id, name, arguments));
-                }
-                Err(e) => {
-                    let _ = tx
-                        .send(ChatEvent::Error {
-                            message: e.to_string(),
-                        })
-                        .await;
-                    let _ = tx.send(ChatEvent::Done).await;
-                    return;
-                }
+            EngineEvent::ApprovalNeeded(request) => {
+                let approved = request_approval(&state, &tx, &request, cancel).await;
+                request.respond(approved);
+                continue;
             }
-        }
-
-        if tool_calls.is_empty() {
-            // Final text response — persist and done.
-            if !full_text.is_empty() {
-                let assistant_msg = Message {
-                    role: Role::Assistant,
-                    content: MessageContent::Text { text: full_text },
-                    timestamp: Utc::now(),
-                };
-                persist_message(&state.store, &conversation_id, &assistant_msg);
+            EngineEvent::Error(e) => {
+                let _ = tx.send(ChatEvent::Error { message: e.to_string() }).await;
+                ChatEvent::Done
             }
-            let _ = tx.send(ChatEvent::Done).await;
-            return;
-        }
-
-        // Execute each tool call.
-        for (id, name, arguments) in &tool_calls {
-            // Notify the client that a tool call is starting.
-            let _ = tx
-                .send(ChatEvent::ToolCallStart {
-                    id: id.clone(),
-                    name: name.clone(),
-                    arguments: arguments.clone(),
-                })
-                .await;
-
-            // Append the assistant's tool call to the conversation.
-            let tool_call_msg = Message {
-                role: Role::Assistant,
-                content: MessageContent::ToolCall {
-                    id: id.clone(),
-                    name: name.clone(),
-                    arguments: arguments.clone(),
-                },
-                timestamp: Utc::now(),
-            };
-            persist_message(&state.store, &conversation_id, &tool_call_msg);
-            messages.push(tool_call_msg);
-
-            // Execute the skill (with approval check for non-ReadOnly skills).
-            let result_content = match registry.get(name) {
-                Some(skill) => {
-                    let perm = skill.permission_level();
-                    let approved = if perm == PermissionLevel::ReadOnly {
-                        true
-                    } else {
-                        check_approval(
-                            &state, &approval_overrides, &tx, &conversation_id, name, arguments, perm,
-                        ).await
-                    };
-
-                    if !approved {
-                        format!("User denied execution of {name}")
-                    } else {
-                        let mut input: serde_json::Value = serde_json::from_str(arguments)
-                            .unwrap_or_else(|_| serde_json::json!({}));
-                        // Inject conversation context so skills can access per-conversation state.
-                        if let Some(obj) = input.as_object_mut() {
-                            obj.insert(
-                                "conversation_id".to_string(),
-                                serde_json::Value::String(conversation_id.clone()),
-                            );
-                        }
-                        match skill.execute(input).await {
-                            Ok(output) => serde_json::to_string(&output)
-                                .unwrap_or_else(|_| "{}".to_string()),
-                            Err(e) => format!("Error: {e}"),
-                        }
-                    }
-                }
-                None => format!("Error: unknown tool '{name}'"),
-            };
-
-            // Notify the client of the result.
-            let _ = tx
-                .send(ChatEvent::ToolCallResult {
-                    id: id.clone(),
-                    content: result_content.clone(),
-                })
-                .await;
-
-            // Append the tool result to the conversation.
-            let tool_result_msg = Message {
-                role: Role::User,
-                content: MessageContent::ToolResult {
-                    id: id.clone(),
-                    name: name.clone(),
-                    content: result_content,
-                },
-                timestamp: Utc::now(),
-            };
-            persist_message(&state.store, &conversation_id, &tool_result_msg);
-            messages.push(tool_result_msg);
-        }
-
-        // Loop: call the provider again with updated messages.
+            EngineEvent::Cancelled { .. } => {
+                let _ = tx.send(ChatEvent::Cancelled).await;
+                ChatEvent::Done
+            }
+            EngineEvent::Done { .. } => ChatEvent::Done,
+        };
+        let _ = tx.send(chat_event).await;
     }
-
-    // Exceeded the maximum number of iterations.
-    let _ = tx
-        .send(ChatEvent::Error {
-            message: format!(
-                "Tool call loop exceeded maximum of {MAX_TOOL_ITERATIONS} iterations"
-            ),
-        })
-        .await;
-    let _ = tx.send(ChatEvent::Done).await;
 }
@@ -31,11 +31,11 @@ fn validate_provider(
     i: usize,
     errors: &mut Vec<FieldError>,
 ) {
-    if !["openai", "mistral", "lmstudio", "ollama", "gemini", "local"].contains(&p.provider_type.as_str()) {
+    if !["openai", "mistral", "lmstudio", "ollama", "gemini", "anthropic", "local"].contains(&p.provider_type.as_str()) {
         errors.push(FieldError {
             field: format!("{prefix}[{i}].type"),
             message: format!(
-                "unknown provider type '{}'; expected openai, mistral, lmstudio, ollama, gemini, or local",
+                "unknown provider type '{}'; expected openai, mistral, lmstudio, ollama, gemini, anthropic, or local",
                 p.provider_type
             ),
         });
@@ -46,6 +46,88 @@ fn validate_provider(
             message: "must not be empty".into(),
         });
     }
+    if p.context_window == Some(0) {
+        errors.push(FieldError {
+            field: format!("{prefix}[{i}].context_window"),
+            message: "must be greater than 0".into(),
+        });
+    }
+    validate_generation_params(p, prefix, i, errors);
+    if p.retry.max_retries.is_some_and(|n| n > MAX_RETRIES) {
+        errors.push(FieldError {
+            field: format!("{prefix}[{i}].max_retries"),
+            message: format!("must be at most {MAX_RETRIES}"),
+        });
+    }
+}
+
+/// Upper bound on `max_retries`; beyond this a user waits minutes per request.
+const MAX_RETRIES: u32 = 10;
+
+/// Largest accepted temperature and number of stop sequences per provider
+/// type (`None` means no documented limit).
+fn generation_limits(provider_type: &str) -> (f64, Option<usize>) {
+    match provider_type {
+        "openai" => (2.0, Some(4)),
+        "gemini" => (2.0, Some(5)),
+        "mistral" => (1.5, None),
+        "anthropic" => (1.0, None),
+        _ => (2.0, None),
+    }
+}
+
+fn validate_generation_params(
+    p: &buddy_core::config::ProviderEntry,
+    prefix: &str,
+    i: usize,
+    errors: &mut Vec<FieldError>,
+) {
+    let params = &p.generation;
+    let (max_temperature, max_stop) = generation_limits(&p.provider_type);
+    if let Some(t) = params.temperature
+        && !(0.0..=max_temperature).contains(&t)
+    {
+        errors.push(FieldError {
+            field: format!("{prefix}[{i}].temperature"),
+            message: format!(
+                "must be between 0 and {max_temperature} for provider type '{}'",
+                p.provider_type
+            ),
+        });
+    }
+    if let Some(top_p) = params.top_p
+        && !(0.0..=1.0).contains(&top_p)
+    {
+        errors.push(FieldError {
+            field: format!("{prefix}[{i}].top_p"),
+            message: "must be between 0 and 1".into(),
+        });
+    }
+    if params.max_tokens == Some(0) {
+        errors.push(FieldError {
+            field: format!("{prefix}[{i}].max_tokens"),
+            message: "must be greater than 0".into(),
+        });
+    }
+    if let Some(stop) = &params.stop {
+        if stop.iter().any(|s| s.is_empty()) {
+            errors.push(FieldError {
+                field: format!("{prefix}[{i}].stop"),
+                message: "stop sequences must not be empty".into(),
+            });
+        }
+        if let Some(max) = max_stop
+            && stop.len() > max
+        {
+            errors.push(FieldError {
+                field: format!("{prefix}[{i}].stop"),
+                message: format!(
+                    "at most {max} stop sequences are allowed for provider type '{}'",
+                    p.provider_type
+                ),
+            });
+        }
+    }
 }
 
 fn validate_models(models: &buddy_core::config::ModelsConfig) -> Vec<FieldError> {
@@ -111,6 +193,80 @@ pub(crate) fn validate_tools(tools: &buddy_core::config::ToolsConfig) -> Vec<Fie
                 });
             }
         }
+        for (i, network) in fu.allowed_private_networks.iter().enumerate() {
+            if let Err(message) = network.parse::<buddy_core::skill::ssrf::IpNetwork>() {
+                errors.push(FieldError {
+                    field: format!("tools.fetch_url.allowed_private_networks[{i}]"),
+                    message,
+                });
+            }
+        }
+    }
+    if let Some(ref hr) = tools.http_request {
+        for (i, domain) in hr.allowed_domains.iter().enumerate() {
+            if domain.is_empty() {
+                errors.push(FieldError {
+                    field: format!("tools.http_request.allowed_domains[{i}]"),
+                    message: "must not be empty".into(),
+                });
+            }
+        }
+        for (i, network) in hr.allowed_private_networks.iter().enumerate() {
+            if let Err(message) = network.parse::<buddy_core::skill::ssrf::IpNetwork>() {
+                errors.push(FieldError {
+                    field: format!("tools.http_request.allowed_private_networks[{i}]"),
+                    message,
+                });
+            }
+        }
+        for (pattern, creds) in &hr.credentials {
+            let names = creds.headers.keys().chain(creds.headers_env.keys());
+            for name in names {
+                if reqwest::header::HeaderName::try_from(name.as_str()).is_err() {
+                    errors.push(FieldError {
+                        field: format!("tools.http_request.credentials.{pattern}.headers"),
+                        message: format!("'{name}' is not a valid header name"),
+                    });
+                }
+            }
+        }
+    }
+    if let Some(ref ws) = tools.web_search {
+        let valid = url::Url::parse(&ws.endpoint)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !valid {
+            errors.push(FieldError {
+                field: "tools.web_search.endpoint".into(),
+                message: format!("'{}' is not an http(s) URL", ws.endpoint),
+            });
+        }
+    }
+    if let Some(ref sr) = tools.schedule_reminder
+        && sr.max_per_conversation == Some(0)
+    {
+        errors.push(FieldError {
+            field: "tools.schedule_reminder.max_per_conversation".into(),
+            message: "must be at least 1".into(),
+        });
+    }
+    if let Some(ref rc) = tools.run_command {
+        for (i, command) in rc.allowed_commands.iter().enumerate() {
+            if command.trim().is_empty() {
+                errors.push(FieldError {
+                    field: format!("tools.run_command.allowed_commands[{i}]"),
+                    message: "must not be empty".into(),
+                });
+            }
+        }
+        for (i, dir) in rc.allowed_directories.iter().enumerate() {
+            let path = std::path::Path::new(dir);
+            if !path.is_dir() {
+                errors.push(FieldError {
+                    field: format!("tools.run_command.allowed_directories[{i}]"),
+                    message: format!("'{}' does not exist or is not a directory", dir),
+                });
+            }
+        }
     }
     errors
 }
@@ -306,11 +462,11 @@ pub async fn test_provider<P: Provider + 'static>(
     Json(entry): Json<buddy_core::config::ProviderEntry>,
 ) -> axum::response::Response {
     // Validate provider type.
-    if !["openai", "mistral", "lmstudio", "ollama", "gemini", "local"].contains(&entry.provider_type.as_str()) {
+    if !["openai", "mistral", "lmstudio", "ollama", "gemini", "anthropic", "local"].contains(&entry.provider_type.as_str()) {
         let errors = vec![FieldError {
             field: "type".into(),
             message: format!(
-                "unknown provider type '{}'; expected openai, mistral, lmstudio, ollama, gemini, or local",
+                "unknown provider type '{}'; expected openai, mistral, lmstudio, ollama, gemini, anthropic, or local",
                 entry.provider_type
             ),
         }];
@@ -340,8 +496,8 @@ pub async fn test_provider<P: Provider + 'static>(
         }
     };
 
-    // OpenAI, Mistral, and Gemini types require an API key.
-    if (entry.provider_type == "openai" || entry.provider_type == "mistral" || entry.provider_type == "gemini") && api_key.is_empty() {
+    // OpenAI, Mistral, Gemini, and Anthropic types require an API key.
+    if ["openai", "mistral", "gemini", "anthropic"].contains(&entry.provider_type.as_str()) && api_key.is_empty() {
         return Json(TestProviderResponse {
             status: "error".into(),
             message: format!("an API key is required when type = \"{}\"", entry.provider_type),
@@ -361,11 +517,12 @@ pub async fn test_provider<P: Provider + 'static>(
     let endpoint = match &entry.endpoint {
         Some(ep) => ep.clone(),
         None => {
-            // Ollama, Mistral, and Gemini have default endpoints.
+            // Ollama, Mistral, Gemini, and Anthropic have default endpoints.
             match entry.provider_type.as_str() {
                 "ollama" => "http://localhost:11434".to_string(),
                 "mistral" => "https://api.mistral.ai".to_string(),
                 "gemini" => "https://generativelanguage.googleapis.com".to_string(),
+                "anthropic" => ANTHROPIC_DEFAULT_ENDPOINT.to_string(),
                 _ => {
                     return Json(TestProviderResponse {
                         status: "error".into(),
@@ -469,8 +626,20 @@ pub async fn test_provider<P: Provider + 'static>(
     }
 
     // Send a minimal non-streaming chat completion request.
-    // Gemini uses a different API format.
-    let request = if entry.provider_type == "gemini" {
+    // Gemini and Anthropic use different API formats.
+    let request = if entry.provider_type == "anthropic" {
+        let url = format!("{}/v1/messages", endpoint.trim_end_matches('/'));
+        let body = serde_json::json!({
+            "model": entry.model,
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_tokens": 1,
+        });
+        client
+            .post(&url)
+            .header("x-api-key", &api_key)
+            .header("anthropic-version", buddy_core::provider::anthropic::ANTHROPIC_VERSION)
+            .json(&body)
+    } else if entry.provider_type == "gemini" {
         let url = format!(
             "{}/v1beta/models/{}:generateContent?key={}",
             endpoint.trim_end_matches('/'),
@@ -522,7 +691,12 @@ pub async fn test_provider<P: Provider + 'static>(
                 .into_response()
             } else {
                 let body_text = response.text().await.unwrap_or_default();
-                let error = if entry.provider_type == "gemini" {
+                let error = if entry.provider_type == "anthropic" {
+                    buddy_core::provider::anthropic::map_anthropic_error(
+                        status_code.as_u16(),
+                        &body_text,
+                    )
+                } else if entry.provider_type == "gemini" {
                     buddy_core::provider::gemini::map_gemini_error(
                         status_code.as_u16(),
                         &body_text,
@@ -550,9 +724,19 @@ pub async fn test_provider<P: Provider + 'static>(
 
 // ── Model discovery ─────────────────────────────────────────────────
 
+const ANTHROPIC_DEFAULT_ENDPOINT: &str = "https://api.anthropic.com";
+
 #[derive(Deserialize)]
 pub struct DiscoverModelsRequest {
+    #[serde(default)]
     pub endpoint: String,
+    /// Provider type; defaults to LM Studio / OpenAI-compatible discovery.
+    #[serde(rename = "type", default)]
+    pub provider_type: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -641,11 +825,125 @@ async fn try_openai_discovery(
     Some(models)
 }
 
-/// `POST /api/config/discover-models` — query an LM Studio endpoint for available models.
+/// List models from Anthropic's `/v1/models` endpoint.
+async fn try_anthropic_discovery(
+    client: &reqwest::Client,
+    endpoint: &str,
+    api_key: &str,
+) -> Result<Vec<DiscoveredModel>, String> {
+    let url = format!("{}/v1/models", endpoint.trim_end_matches('/'));
+    let resp = client
+        .get(&url)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", buddy_core::provider::anthropic::ANTHROPIC_VERSION)
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {e}"))?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        let error = buddy_core::provider::anthropic::map_anthropic_error(status.as_u16(), &body);
+        return Err(format!("{error}"));
+    }
+    let json: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("failed to parse models response: {e}"))?;
+    let data = json
+        .get("data")
+        .and_then(|d| d.as_array())
+        .ok_or_else(|| "invalid models response: missing 'data' array".to_string())?;
+    Ok(data
+        .iter()
+        .filter_map(|m| {
+            let id = m.get("id")?.as_str()?.to_string();
+            Some(DiscoveredModel {
+                id,
+                loaded: None,
+                context_length: None,
+            })
+        })
+        .collect())
+}
+
+/// Discovery for `type = "anthropic"`: requires an API key, endpoint optional.
+async fn discover_anthropic_models(
+    client: &reqwest::Client,
+    req: &DiscoverModelsRequest,
+) -> DiscoverModelsResponse {
+    let key_source = buddy_core::config::ProviderEntry {
+        provider_type: "anthropic".into(),
+        model: String::new(),
+        endpoint: None,
+        api_key_env: req.api_key_env.clone(),
+        api_key: req.api_key.clone(),
+        context_window: None,
+        vision: None,
+        send_reasoning: false,
+        tool_mode: buddy_core::config::ToolMode::Native,
+        generation: Default::default(),
+        retry: Default::default(),
+    };
+    let api_key = match key_source.resolve_api_key() {
+        Ok(key) if !key.is_empty() => key,
+        Ok(_) => {
+            return DiscoverModelsResponse {
+                status: "error".into(),
+                message: Some("an API key is required when type = \"anthropic\"".into()),
+                models: None,
+            };
+        }
+        Err(msg) => {
+            return DiscoverModelsResponse {
+                status: "error".into(),
+                message: Some(msg),
+                models: None,
+            };
+        }
+    };
+    let endpoint = if req.endpoint.trim().is_empty() {
+        ANTHROPIC_DEFAULT_ENDPOINT
+    } else {
+        req.endpoint.trim()
+    };
+    match try_anthropic_discovery(client, endpoint, &api_key).await {
+        Ok(models) => DiscoverModelsResponse {
+            status: "ok".into(),
+            message: None,
+            models: Some(models),
+        },
+        Err(message) => DiscoverModelsResponse {
+            status: "error".into(),
+            message: Some(message),
+            models: None,
+        },
+    }
+}
+
+/// `POST /api/config/discover-models` — query a provider endpoint for available
+/// models. LM Studio / OpenAI-compatible endpoints by default; Anthropic when
+/// `type = "anthropic"`.
 pub async fn discover_models<P: Provider + 'static>(
     State(_state): State<Arc<AppState<P>>>,
     Json(req): Json<DiscoverModelsRequest>,
 ) -> Json<DiscoverModelsResponse> {
+    if req.provider_type.as_deref() == Some("anthropic") {
+        let client = match reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                return Json(DiscoverModelsResponse {
+                    status: "error".into(),
+                    message: Some(format!("failed to build HTTP client: {e}")),
+                    models: None,
+                });
+            }
+        };
+        return Json(discover_anthropic_models(&client, &req).await);
+    }
+
     if req.endpoint.trim().is_empty() {
         return Json(DiscoverModelsResponse {
             status: "error".into(),
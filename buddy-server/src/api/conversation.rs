@@ -2,11 +2,15 @@
 
 use std::sync::Arc;
 
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
 use axum::Json;
 
-use super::{ApiError, AppState, internal_error, not_found_error};
+use super::{
+    ApiError, AppState, ConversationQuery, ConversationView, SwitchBranchRequest, internal_error,
+    not_found_error,
+};
 use buddy_core::provider::Provider;
 
 /// `GET /api/conversations` — list all conversation summaries.
@@ -25,16 +29,65 @@ pub async fn create_conversation<P: Provider + 'static>(
     Ok((StatusCode::CREATED, Json(conv)))
 }
 
-/// `GET /api/conversations/:id` — get a single conversation with all messages.
+/// `GET /api/conversations/:id` — get a single conversation with the
+/// messages on its active branch. `?tree=true` also returns every branch.
 pub async fn get_conversation<P: Provider + 'static>(
     State(state): State<Arc<AppState<P>>>,
     Path(id): Path<String>,
-) -> Result<Json<buddy_core::store::Conversation>, (StatusCode, Json<ApiError>)> {
-    let conv = state.store.get_conversation(&id).map_err(|e| internal_error(e))?;
-    match conv {
-        Some(c) => Ok(Json(c)),
-        None => Err(not_found_error(format!("conversation '{id}' not found"))),
+    Query(query): Query<ConversationQuery>,
+) -> Result<Json<ConversationView>, (StatusCode, Json<ApiError>)> {
+    conversation_view(&state, &id, query.tree).map(Json)
+}
+
+/// `PUT /api/conversations/:id/branch` — make the branch through
+/// `message_id` active and return the conversation along it.
+pub async fn switch_branch<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(id): Path<String>,
+    Json(body): Json<SwitchBranchRequest>,
+) -> Result<Json<ConversationView>, (StatusCode, Json<ApiError>)> {
+    let tree = state.store.get_message_tree(&id).map_err(internal_error)?;
+    let Some(tree) = tree else {
+        return Err(not_found_error(format!("conversation '{id}' not found")));
+    };
+    if !tree.iter().any(|node| node.id == body.message_id) {
+        return Err(not_found_error(format!("message '{}' not found", body.message_id)));
     }
+    state
+        .store
+        .switch_branch(&id, &body.message_id)
+        .map_err(internal_error)?;
+    conversation_view(&state, &id, false).map(Json)
+}
+
+fn conversation_view<P: Provider>(
+    state: &AppState<P>,
+    id: &str,
+    include_tree: bool,
+) -> Result<ConversationView, (StatusCode, Json<ApiError>)> {
+    let conv = state.store.get_conversation(id).map_err(internal_error)?;
+    let Some(conv) = conv else {
+        return Err(not_found_error(format!("conversation '{id}' not found")));
+    };
+    let messages = state
+        .store
+        .get_active_path(id)
+        .map_err(internal_error)?
+        .unwrap_or_default();
+    let tree = if include_tree {
+        state.store.get_message_tree(id).map_err(internal_error)?
+    } else {
+        None
+    };
+    Ok(ConversationView {
+        id: conv.id,
+        title: conv.title,
+        source: conv.source,
+        created_at: conv.created_at,
+        updated_at: conv.updated_at,
+        messages,
+        tree,
+    })
 }
 
 /// `DELETE /api/conversations/:id` — delete a conversation and all messages.
@@ -49,3 +102,15 @@ pub async fn delete_conversation<P: Provider + 'static>(
         Err(not_found_error(format!("conversation '{id}' not found")))
     }
 }
+
+/// `GET /api/blobs/:id` — the raw bytes of a stored blob (such as an image
+/// attached to a message), served with its MIME type.
+pub async fn get_blob<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    match state.store.get_blob(&id).map_err(internal_error)? {
+        Some(blob) => Ok(([(header::CONTENT_TYPE, blob.mime_type)], blob.data)),
+        None => Err(not_found_error(format!("blob '{id}' not found"))),
+    }
+}
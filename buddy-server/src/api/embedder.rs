@@ -115,7 +115,7 @@ pub async fn get_embedder_health<P: Provider + 'static>(
 mod tests {
     use super::*;
     use buddy_core::config::{Config, MemoryConfig};
-    use crate::api::new_pending_approvals;
+    use crate::api::{new_active_generations, new_pending_approvals};
     use buddy_core::embedding::Embedder;
     use buddy_core::skill::ToolRegistry;
     use buddy_core::store::Store;
@@ -156,6 +156,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: Duration::from_secs(60),
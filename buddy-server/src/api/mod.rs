@@ -19,6 +19,8 @@ mod conversation;
 mod embedder;
 mod interfaces;
 mod memory;
+mod todos;
+mod usage;
 #[cfg(test)]
 mod tests;
 
@@ -33,19 +35,24 @@ use buddy_core::provider::Provider;
 pub use buddy_core::state::AppState;
 #[allow(unused_imports)]
 pub use buddy_core::state::new_pending_approvals;
+#[allow(unused_imports)]
+pub use buddy_core::state::new_active_generations;
 
 // Re-export handler functions for use in main.rs router setup.
-pub use chat::{approve_handler, chat_handler};
+pub use chat::{approve_handler, cancel_handler, chat_handler, edit_message, regenerate_message};
 pub use config::{
     discover_models, get_config, put_config_chat, put_config_memory, put_config_models,
     put_config_server, put_config_tools, test_provider,
 };
 pub use conversation::{
-    create_conversation, delete_conversation, get_conversation, list_conversations,
+    create_conversation, delete_conversation, get_blob, get_conversation, list_conversations,
+    switch_branch,
 };
 pub use embedder::get_embedder_health;
 pub use interfaces::{check_interface_connection, get_interfaces_status, put_config_interfaces};
 pub use memory::{clear_memory, get_memory_status, migrate_memory};
+pub use todos::{create_todo, delete_todo, delete_todo_list, list_todos, update_todo};
+pub use usage::{get_conversation_usage, get_usage};
 
 // ── Shared types ────────────────────────────────────────────────────────
 
@@ -59,6 +66,49 @@ pub struct ChatRequest {
     pub disable_memory: bool,
 }
 
+/// Request body for `POST /api/conversations/{id}/messages/{message_id}/regenerate`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RegenerateRequest {
+    #[serde(default)]
+    pub disable_memory: bool,
+}
+
+/// Request body for `POST /api/conversations/{id}/messages/{message_id}/edit`.
+#[derive(Serialize, Deserialize)]
+pub struct EditMessageRequest {
+    pub text: String,
+    #[serde(default)]
+    pub disable_memory: bool,
+}
+
+/// Request body for `PUT /api/conversations/{id}/branch`.
+#[derive(Serialize, Deserialize)]
+pub struct SwitchBranchRequest {
+    pub message_id: String,
+}
+
+/// Query parameters for `GET /api/conversations/{id}`.
+#[derive(Deserialize)]
+pub struct ConversationQuery {
+    #[serde(default)]
+    pub tree: bool,
+}
+
+/// A conversation as returned by the API.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConversationView {
+    pub id: String,
+    pub title: String,
+    pub source: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Messages on the active branch, root first.
+    pub messages: Vec<buddy_core::store::MessageNode>,
+    /// Every message across all branches, only when `?tree=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<Vec<buddy_core::store::MessageNode>>,
+}
+
 /// A recalled memory snippet surfaced to the frontend.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct MemorySnippet {
@@ -77,11 +127,24 @@ pub enum ChatEvent {
     Warning { message: String },
     MemoryContext { memories: Vec<MemorySnippet> },
     TokenDelta { content: String },
+    /// A delta of the model's reasoning, shown apart from the reply.
+    ReasoningDelta { content: String },
+    /// The provider failed mid-stream; discard the text of the current reply.
+    StreamReset { message: String },
     ToolCallStart { id: String, name: String, arguments: String },
     ToolCallResult { id: String, content: String },
-    ApprovalRequest { id: String, skill_name: String, arguments: serde_json::Value, permission_level: String },
+    ApprovalRequest {
+        id: String,
+        skill_name: String,
+        arguments: serde_json::Value,
+        /// The tool's rendering of the call, e.g. a diff of a file edit.
+        preview: Option<String>,
+        permission_level: String,
+    },
     Done,
     Error { message: String },
+    /// The turn was cancelled; a `Done` frame follows.
+    Cancelled,
 }
 
 /// Structured API error response.
@@ -110,6 +173,16 @@ pub(crate) fn internal_error(message: String) -> (StatusCode, Json<ApiError>) {
     )
 }
 
+pub(crate) fn bad_request(message: String) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiError {
+            code: "bad_request".into(),
+            message,
+        }),
+    )
+}
+
 pub(crate) fn not_found_error(message: String) -> (StatusCode, Json<ApiError>) {
     (
         StatusCode::NOT_FOUND,
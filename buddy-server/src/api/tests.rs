@@ -1,5 +1,5 @@
 use super::*;
-use super::chat::MAX_TOOL_ITERATIONS;
+use buddy_core::engine::MAX_TOOL_ITERATIONS;
 use std::collections::HashMap;
 use axum::body::Body;
 use axum::http::Request;
@@ -11,14 +11,14 @@ use tower::ServiceExt;
 use tower_http::services::ServeDir;
 
 use buddy_core::types::MessageContent;
-use buddy_core::provider::ProviderChain;
+use buddy_core::provider::{ProviderChain, Token};
 use buddy_core::skill::{SkillRegistry, ToolRegistry};
 use buddy_core::testutil::{
     ConfigurableMockProvider, FailingSkill, MockEchoSkill, MockProvider, MockResponse,
     SequencedProvider,
 };
 use crate::testutil::{
-    make_chat_body, make_chat_body_with_conversation, post_chat, post_chat_raw,
+    make_chat_body, make_chat_body_with_conversation, post_chat, post_chat_raw, post_sse,
 };
 
 // ── Helpers ─────────────────────────────────────────────────────────
@@ -134,6 +134,7 @@ impl TestAppBuilder {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -184,6 +185,7 @@ fn conversation_app(tokens: Vec<String>) -> (Arc<AppState<MockProvider>>, Router
         memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
         warnings: buddy_core::warning::new_shared_warnings(),
         pending_approvals: new_pending_approvals(),
+        active_generations: new_active_generations(),
         conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
         approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
         approval_timeout: std::time::Duration::from_secs(1),
@@ -202,6 +204,36 @@ fn conversation_app(tokens: Vec<String>) -> (Arc<AppState<MockProvider>>, Router
             "/api/conversations/{id}",
             get(get_conversation::<MockProvider>).delete(delete_conversation::<MockProvider>),
         )
+        .route(
+            "/api/conversations/{id}/usage",
+            get(get_conversation_usage::<MockProvider>),
+        )
+        .route(
+            "/api/conversations/{id}/branch",
+            axum::routing::put(switch_branch::<MockProvider>),
+        )
+        .route(
+            "/api/conversations/{id}/messages/{message_id}/regenerate",
+            post(regenerate_message::<MockProvider>),
+        )
+        .route(
+            "/api/conversations/{id}/messages/{message_id}/edit",
+            post(edit_message::<MockProvider>),
+        )
+        .route("/api/usage", get(get_usage::<MockProvider>))
+        .route(
+            "/api/todos",
+            get(list_todos::<MockProvider>).post(create_todo::<MockProvider>),
+        )
+        .route(
+            "/api/todos/{id}",
+            axum::routing::patch(update_todo::<MockProvider>).delete(delete_todo::<MockProvider>),
+        )
+        .route(
+            "/api/todos/lists/{name}",
+            axum::routing::delete(delete_todo_list::<MockProvider>),
+        )
+        .route("/api/blobs/{id}", get(get_blob::<MockProvider>))
         .with_state(state.clone());
     (state, router)
 }
@@ -232,6 +264,35 @@ mod chat {
         assert_eq!(events[2], ChatEvent::Done);
     }
 
+    #[tokio::test]
+    async fn reasoning_streams_as_reasoning_deltas() {
+        let app = sequenced_app(
+            vec![MockResponse::Tokens(vec![
+                Token::Reasoning {
+                    text: "Thinking".into(),
+                },
+                Token::Text {
+                    text: "Answer".into(),
+                },
+            ])],
+            ToolRegistry::new(),
+        );
+        let events = post_chat(app, &make_chat_body()).await;
+
+        assert_eq!(
+            events,
+            vec![
+                ChatEvent::ReasoningDelta {
+                    content: "Thinking".into()
+                },
+                ChatEvent::TokenDelta {
+                    content: "Answer".into()
+                },
+                ChatEvent::Done,
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn malformed_json_returns_400_with_structured_error() {
         let app = test_app(vec![]);
@@ -548,6 +609,7 @@ mod tool_loop {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -770,6 +832,7 @@ mod conversations {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -855,6 +918,192 @@ mod conversations {
         assert_eq!(tg["source"], "telegram");
     }
 
+    async fn get_view(app: Router, uri: &str) -> ConversationView {
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn text_of(node: &buddy_core::store::MessageNode) -> &str {
+        match &node.message.content {
+            MessageContent::Text { text } => text,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn regenerate_adds_a_sibling_reply_and_keeps_the_old_one() {
+        let (state, app) = conversation_app(vec!["Reply".into()]);
+        let conv = state.store.create_conversation("Branching").unwrap();
+        post_chat_raw(app.clone(), &make_chat_body_with_conversation(&conv.id)).await;
+        let before = get_view(app.clone(), &format!("/api/conversations/{}", conv.id)).await;
+        assert_eq!(before.messages.len(), 2);
+        assert!(before.tree.is_none());
+
+        let events = post_sse(
+            app.clone(),
+            &format!("/api/conversations/{}/messages/{}/regenerate", conv.id, before.messages[1].id),
+            "",
+        )
+        .await;
+        assert_eq!(events.last(), Some(&ChatEvent::Done));
+
+        let after = get_view(app, &format!("/api/conversations/{}?tree=true", conv.id)).await;
+        assert_eq!(after.messages.len(), 2);
+        assert_eq!(after.messages[0].id, before.messages[0].id);
+        assert_ne!(after.messages[1].id, before.messages[1].id);
+        assert_eq!(after.messages[1].parent_id.as_ref(), Some(&before.messages[0].id));
+        assert_eq!(after.tree.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn edit_resends_on_a_new_branch_and_branches_can_be_switched() {
+        let (state, app) = conversation_app(vec!["Reply".into()]);
+        let conv = state.store.create_conversation("Branching").unwrap();
+        post_chat_raw(app.clone(), &make_chat_body_with_conversation(&conv.id)).await;
+        let original = get_view(app.clone(), &format!("/api/conversations/{}", conv.id)).await;
+
+        let events = post_sse(
+            app.clone(),
+            &format!("/api/conversations/{}/messages/{}/edit", conv.id, original.messages[0].id),
+            r#"{"text":"Hello instead"}"#,
+        )
+        .await;
+        assert!(events.iter().any(|e| matches!(e, ChatEvent::TokenDelta { .. })));
+
+        let edited = get_view(app.clone(), &format!("/api/conversations/{}?tree=true", conv.id)).await;
+        assert_eq!(text_of(&edited.messages[0]), "Hello instead");
+        assert_eq!(edited.messages[0].parent_id, None);
+        assert_eq!(text_of(&edited.messages[1]), "Reply");
+        assert_eq!(edited.tree.unwrap().len(), 4);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri(format!("/api/conversations/{}/branch", conv.id))
+                    .header("content-type", "application/json")
+                    .body(Body::from(format!(
+                        r#"{{"message_id":"{}"}}"#,
+                        original.messages[0].id
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let switched: ConversationView = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<_> = switched.messages.iter().map(|m| &m.id).collect();
+        let original_ids: Vec<_> = original.messages.iter().map(|m| &m.id).collect();
+        assert_eq!(ids, original_ids);
+    }
+
+    #[tokio::test]
+    async fn edit_rejects_assistant_messages_and_unknown_ids() {
+        let (state, app) = conversation_app(vec!["Reply".into()]);
+        let conv = state.store.create_conversation("Branching").unwrap();
+        post_chat_raw(app.clone(), &make_chat_body_with_conversation(&conv.id)).await;
+        let view = get_view(app.clone(), &format!("/api/conversations/{}", conv.id)).await;
+
+        for (message_id, expected) in [
+            (view.messages[1].id.as_str(), StatusCode::BAD_REQUEST),
+            ("missing", StatusCode::NOT_FOUND),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/api/conversations/{}/messages/{message_id}/edit", conv.id))
+                        .header("content-type", "application/json")
+                        .body(Body::from(r#"{"text":"x"}"#))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), expected);
+        }
+    }
+
+    fn image_chat_body(mime_type: &str, data: &str) -> String {
+        serde_json::json!({
+            "messages": [
+                {
+                    "role": "user",
+                    "content": { "type": "image", "mime_type": mime_type, "data": data },
+                    "timestamp": "2024-01-01T00:00:00Z"
+                },
+                {
+                    "role": "user",
+                    "content": { "type": "text", "text": "What is this?" },
+                    "timestamp": "2024-01-01T00:00:00Z"
+                }
+            ]
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn chat_image_is_stored_as_blob_and_served() {
+        let (state, app) = conversation_app(vec!["A cat.".into()]);
+        // "cG5n" is base64 for "png".
+        let events = post_chat_raw(app.clone(), &image_chat_body("image/png", "cG5n")).await;
+        assert_eq!(events.last(), Some(&ChatEvent::Done));
+
+        let conv = &state.store.list_conversations().unwrap()[0];
+        assert_eq!(conv.title, "What is this?");
+        let view = get_view(app.clone(), &format!("/api/conversations/{}", conv.id)).await;
+        let MessageContent::Image { blob_id, mime_type, data } = &view.messages[0].message.content
+        else {
+            panic!("expected an image, got {:?}", view.messages[0].message.content);
+        };
+        assert_eq!(mime_type, "image/png");
+        assert!(data.is_none());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/blobs/{blob_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"png");
+    }
+
+    #[tokio::test]
+    async fn chat_rejects_invalid_images() {
+        let (state, app) = conversation_app(vec![]);
+        for body in [
+            image_chat_body("application/pdf", "cG5n"),
+            image_chat_body("image/png", "not base64!"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/chat")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(state.store.list_conversations().unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn get_conversation_includes_source_field() {
         let (state, app) = conversation_app(vec![]);
@@ -882,6 +1131,241 @@ mod conversations {
     }
 }
 
+// ── Usage tests ─────────────────────────────────────────────────────
+
+mod usage {
+    use super::*;
+    use buddy_core::store::MessageUsage;
+    use buddy_core::types::{Message, Role};
+
+    fn record(state: &AppState<MockProvider>, conversation_id: &str, model: &str, prompt: u32, completion: u32) {
+        let message = Message {
+            role: Role::Assistant,
+            content: MessageContent::Text { text: "reply".into() },
+            timestamp: chrono::Utc::now(),
+        };
+        let usage = MessageUsage {
+            provider: "openai".into(),
+            model: model.into(),
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+        };
+        state
+            .store
+            .append_message_with_usage(conversation_id, &message, Some(&usage))
+            .unwrap();
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn usage_grouped_by_provider_is_priced() {
+        let (state, app) = conversation_app(vec![]);
+        state.config.write().unwrap().pricing.insert(
+            "gpt-4o".into(),
+            buddy_core::config::ModelPrice { prompt: 2.0, completion: 10.0 },
+        );
+        let conv = state.store.create_conversation("Test").unwrap();
+        record(&state, &conv.id, "gpt-4o", 500_000, 100_000);
+        record(&state, &conv.id, "gpt-4o-mini", 1_000, 1_000);
+
+        let (status, json) = get_json(app, "/api/usage?group_by=provider").await;
+        assert_eq!(status, StatusCode::OK);
+        let reports = json.as_array().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["key"], "openai");
+        assert_eq!(reports[0]["prompt_tokens"], 501_000);
+        assert!((reports[0]["cost_usd"].as_f64().unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(reports[0]["unpriced_models"], serde_json::json!(["gpt-4o-mini"]));
+    }
+
+    #[tokio::test]
+    async fn usage_defaults_to_daily_grouping() {
+        let (state, app) = conversation_app(vec![]);
+        let conv = state.store.create_conversation("Test").unwrap();
+        record(&state, &conv.id, "gpt-4o", 10, 5);
+
+        let (_, json) = get_json(app, "/api/usage").await;
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        assert_eq!(json[0]["key"], today);
+        assert_eq!(json[0]["completion_tokens"], 5);
+    }
+
+    #[tokio::test]
+    async fn conversation_usage_broken_down_by_model() {
+        let (state, app) = conversation_app(vec![]);
+        let conv = state.store.create_conversation("Test").unwrap();
+        let other = state.store.create_conversation("Other").unwrap();
+        record(&state, &conv.id, "gpt-4o", 10, 5);
+        record(&state, &conv.id, "gpt-4o-mini", 3, 1);
+        record(&state, &other.id, "gpt-4o", 100, 100);
+
+        let (status, json) =
+            get_json(app, &format!("/api/conversations/{}/usage", conv.id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let reports = json.as_array().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0]["key"], "gpt-4o");
+        assert_eq!(reports[0]["prompt_tokens"], 10);
+    }
+
+    #[tokio::test]
+    async fn conversation_usage_for_unknown_conversation_returns_404() {
+        let (_, app) = conversation_app(vec![]);
+        let (status, _) = get_json(app, "/api/conversations/missing/usage").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn invalid_grouping_is_rejected() {
+        let (_, app) = conversation_app(vec![]);
+        let response = app
+            .oneshot(Request::builder().uri("/api/usage?group_by=week").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+// ── Todo tests ──────────────────────────────────────────────────────
+
+mod todos {
+    use super::*;
+
+    async fn send(
+        app: Router,
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let response = app.oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn todos_are_created_updated_and_listed() {
+        let (state, app) = conversation_app(vec![]);
+        let (status, milk) = send(
+            app.clone(),
+            "POST",
+            "/api/todos",
+            Some(serde_json::json!({
+                "list": "Groceries",
+                "text": "Milk",
+                "due_date": "2026-10-20"
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(milk["list"], "Groceries");
+        assert_eq!(milk["due_date"], "2026-10-20");
+        // Items added from chat land in the same lists.
+        state.store.add_todo("groceries", "Eggs", None, None).unwrap();
+
+        let id = milk["id"].as_str().unwrap();
+        let (status, done) = send(
+            app.clone(),
+            "PATCH",
+            &format!("/api/todos/{id}"),
+            Some(serde_json::json!({ "completed": true, "due_date": null })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(done["completed_at"].is_string());
+        assert!(done.get("due_date").is_none());
+
+        let (status, lists) = send(app.clone(), "GET", "/api/todos", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(lists[0]["name"], "Groceries");
+        assert_eq!(lists[0]["items"].as_array().unwrap().len(), 1);
+        assert_eq!(lists[0]["items"][0]["text"], "Eggs");
+        let (_, lists) = send(
+            app,
+            "GET",
+            "/api/todos?list=groceries&include_completed=true",
+            None,
+        )
+        .await;
+        assert_eq!(lists[0]["items"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn todos_and_lists_are_deleted() {
+        let (state, app) = conversation_app(vec![]);
+        let milk = state.store.add_todo("Groceries", "Milk", None, None).unwrap();
+        state.store.add_todo("Home", "Taxes", None, None).unwrap();
+
+        let uri = format!("/api/todos/{}", milk.id);
+        let (status, _) = send(app.clone(), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(app.clone(), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(app.clone(), "PATCH", &uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(app.clone(), "DELETE", "/api/todos/lists/home", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(app.clone(), "DELETE", "/api/todos/lists/home", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, lists) = send(app, "GET", "/api/todos", None).await;
+        assert_eq!(lists.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn empty_todo_text_is_rejected() {
+        let (_, app) = conversation_app(vec![]);
+        let (status, json) = send(
+            app,
+            "POST",
+            "/api/todos",
+            Some(serde_json::json!({ "text": "  " })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn overlong_todo_text_and_list_names_are_rejected() {
+        let (state, app) = conversation_app(vec![]);
+        let long_text = "x".repeat(501);
+        for body in [
+            serde_json::json!({ "text": long_text }),
+            serde_json::json!({ "text": "Milk", "list": "l".repeat(101) }),
+        ] {
+            let (status, json) = send(app.clone(), "POST", "/api/todos", Some(body)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(json["code"], "bad_request");
+        }
+
+        let milk = state.store.add_todo("Groceries", "Milk", None, None).unwrap();
+        let uri = format!("/api/todos/{}", milk.id);
+        let body = serde_json::json!({ "text": long_text });
+        let (status, _) = send(app.clone(), "PATCH", &uri, Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body = serde_json::json!({ "text": "é".repeat(500) });
+        let (status, _) = send(app, "PATCH", &uri, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
 // ── Warning system tests ──────────────────────────────────────────────
 
 mod warnings {
@@ -908,6 +1392,7 @@ mod warnings {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings,
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -971,6 +1456,7 @@ mod warnings {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: warnings.clone(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -1030,6 +1516,7 @@ mod warnings {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: warnings.clone(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -1163,6 +1650,7 @@ mod warnings {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings,
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -1319,6 +1807,7 @@ mod approval {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(overrides),
             approval_timeout: timeout,
@@ -1646,10 +2135,18 @@ mod approval {
         let approval = events.iter().find(|e| matches!(e, ChatEvent::ApprovalRequest { .. }));
         assert!(approval.is_some(), "should contain ApprovalRequest");
 
-        if let Some(ChatEvent::ApprovalRequest { id, skill_name, arguments, permission_level }) = approval {
+        if let Some(ChatEvent::ApprovalRequest {
+            id,
+            skill_name,
+            arguments,
+            preview,
+            permission_level,
+        }) = approval
+        {
             assert!(!id.is_empty(), "approval id should not be empty");
             assert_eq!(skill_name, "mutating");
             assert_eq!(arguments["value"], "hello");
+            assert_eq!(preview, &None, "the mock tool renders no preview");
             assert_eq!(permission_level, "mutating");
         }
     }
@@ -1686,6 +2183,99 @@ mod approval {
             );
         }
     }
+
+    // 11. Cancel endpoint abandons a pending approval
+    #[tokio::test]
+    async fn cancel_during_approval_ends_turn_and_persists_it() {
+        let (state, app) = approval_app(
+            vec![
+                MockResponse::ToolCalls(vec![(
+                    "c1".into(),
+                    "mutating".into(),
+                    r#"{"value":"hello"}"#.into(),
+                )]),
+                MockResponse::Text(vec!["Done.".into()]),
+            ],
+            registry_with_mutating(),
+            HashMap::new(),
+            std::time::Duration::from_secs(30),
+        );
+        let cancel_app = Router::new()
+            .route(
+                "/api/chat/{conversation_id}/cancel",
+                post(cancel_handler::<SequencedProvider>),
+            )
+            .with_state(state.clone());
+
+        // Cancel through the API once the approval is waiting.
+        let watcher = state.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                if watcher.pending_approvals.lock().await.is_empty() {
+                    continue;
+                }
+                let id = watcher.active_generations.lock().await.keys().next().cloned();
+                let response = cancel_app
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri(format!("/api/chat/{}/cancel", id.unwrap()))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                break;
+            }
+        });
+
+        let started = std::time::Instant::now();
+        let events = post_chat_raw(app, &make_chat_body()).await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert_eq!(&events[events.len() - 2..], &[ChatEvent::Cancelled, ChatEvent::Done]);
+        assert!(!events.iter().any(|e| matches!(e, ChatEvent::ToolCallResult { .. })));
+
+        let conversation_id = match &events[0] {
+            ChatEvent::ConversationMeta { conversation_id } => conversation_id.clone(),
+            other => panic!("expected ConversationMeta, got {other:?}"),
+        };
+        let messages = state.store.get_conversation(&conversation_id).unwrap().unwrap().messages;
+        assert!(matches!(
+            &messages.last().unwrap().content,
+            MessageContent::Text { text } if text == buddy_core::engine::CANCELLED_MARKER
+        ));
+        assert!(state.pending_approvals.lock().await.is_empty());
+    }
+
+    // 12. Cancelling with nothing in progress is a 404
+    #[tokio::test]
+    async fn cancel_without_generation_returns_404() {
+        let (state, _) = approval_app(
+            vec![],
+            registry_with_mutating(),
+            HashMap::new(),
+            std::time::Duration::from_secs(1),
+        );
+        let app = Router::new()
+            .route(
+                "/api/chat/{conversation_id}/cancel",
+                post(cancel_handler::<SequencedProvider>),
+            )
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/chat/nope/cancel")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
 
 mod config_api {
@@ -1705,6 +2295,7 @@ mod config_api {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -1968,6 +2559,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -2052,7 +2644,7 @@ endpoint = "http://localhost:1234/v1"
         let body = serde_json::json!({
             "chat": {
                 "providers": [{
-                    "type": "anthropic",
+                    "type": "cohere",
                     "model": "claude"
                 }]
             }
@@ -2106,6 +2698,78 @@ endpoint = "http://localhost:1234/v1"
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[tokio::test]
+    async fn put_models_out_of_range_generation_params_returns_400() {
+        let (dir, app) = config_write_app();
+        let body = serde_json::json!({
+            "chat": {
+                "providers": [{
+                    "type": "mistral",
+                    "model": "mistral-large-latest",
+                    "temperature": 1.8,
+                    "top_p": 1.5,
+                    "max_tokens": 0
+                }]
+            }
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/config/models")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let err: config::ValidationErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        let fields: Vec<&str> = err.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "models.chat.providers[0].temperature",
+                "models.chat.providers[0].top_p",
+                "models.chat.providers[0].max_tokens",
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn put_models_too_many_stop_sequences_returns_400() {
+        let (dir, app) = config_write_app();
+        let body = serde_json::json!({
+            "chat": {
+                "providers": [{
+                    "type": "openai",
+                    "model": "gpt-4o",
+                    "stop": ["a", "b", "c", "d", "e"]
+                }]
+            }
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/config/models")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let err: config::ValidationErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(err.errors.iter().any(|e| e.field == "models.chat.providers[0].stop"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[tokio::test]
     async fn put_models_direct_api_key_succeeds() {
         let (dir, app) = config_write_app();
@@ -2220,6 +2884,7 @@ approval = "once"
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -2458,6 +3123,62 @@ approval = "once"
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[tokio::test]
+    async fn tools_web_search_endpoint_must_be_a_url() {
+        let (dir, app) = tools_config_write_app();
+        let body = serde_json::json!({
+            "web_search": { "endpoint": "localhost:8888" }
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/config/tools")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let err: config::ValidationErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(err
+            .errors
+            .iter()
+            .any(|e| e.field == "tools.web_search.endpoint"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn tools_schedule_reminder_limit_must_be_positive() {
+        let (dir, app) = tools_config_write_app();
+        let body = serde_json::json!({
+            "schedule_reminder": { "max_per_conversation": 0 }
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/config/tools")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let err: config::ValidationErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(err
+            .errors
+            .iter()
+            .any(|e| e.field == "tools.schedule_reminder.max_per_conversation"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[tokio::test]
     async fn tools_readonly_has_no_approval_in_response() {
         let (dir, app) = tools_config_write_app();
@@ -2653,6 +3374,7 @@ approval = "once"
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -2832,6 +3554,77 @@ approval = "once"
             .contains("BUDDY_TEST_GEMINI_NOTSET_047"));
     }
 
+    #[tokio::test]
+    async fn test_provider_anthropic_mock_server() {
+        let mock_app = Router::new().route(
+            "/v1/messages",
+            post(|headers: axum::http::HeaderMap| async move {
+                match headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+                    Some("sk-test") => (
+                        StatusCode::OK,
+                        axum::Json(serde_json::json!({
+                            "type": "message",
+                            "role": "assistant",
+                            "content": [{ "type": "text", "text": "hi" }]
+                        })),
+                    ),
+                    Some("overloaded") => (
+                        StatusCode::from_u16(529).unwrap(),
+                        axum::Json(serde_json::json!({
+                            "type": "error",
+                            "error": { "type": "overloaded_error", "message": "Overloaded" }
+                        })),
+                    ),
+                    _ => (
+                        StatusCode::UNAUTHORIZED,
+                        axum::Json(serde_json::json!({
+                            "type": "error",
+                            "error": { "type": "authentication_error", "message": "invalid x-api-key" }
+                        })),
+                    ),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, mock_app).await.unwrap();
+        });
+
+        for (api_key, status, message) in [
+            ("sk-test", "ok", "Connected successfully"),
+            ("wrong", "error", "auth error: invalid x-api-key"),
+            ("overloaded", "error", "rate limited: Overloaded"),
+        ] {
+            let response = test_provider_app()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/config/test-provider")
+                        .header("content-type", "application/json")
+                        .body(Body::from(
+                            serde_json::json!({
+                                "type": "anthropic",
+                                "model": "claude-sonnet-4-5",
+                                "endpoint": format!("http://{addr}"),
+                                "api_key": api_key
+                            })
+                            .to_string(),
+                        ))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json["status"], status, "api_key = {api_key}");
+            assert_eq!(json["message"], message, "api_key = {api_key}");
+        }
+
+        server.abort();
+    }
+
     #[tokio::test]
     async fn test_provider_does_not_modify_config() {
         let app = test_provider_app();
@@ -3202,6 +3995,7 @@ mod discover_models {
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -3416,6 +4210,97 @@ mod discover_models {
 
         assert_eq!(before_bytes, after_bytes);
     }
+
+    #[tokio::test]
+    async fn discover_anthropic_models_sends_api_key() {
+        let mock_app = Router::new().route(
+            "/v1/models",
+            get(|headers: axum::http::HeaderMap| async move {
+                if headers.get("x-api-key").and_then(|v| v.to_str().ok()) != Some("sk-test")
+                    || headers.get("anthropic-version").is_none()
+                {
+                    return (
+                        StatusCode::UNAUTHORIZED,
+                        axum::Json(serde_json::json!({
+                            "type": "error",
+                            "error": { "type": "authentication_error", "message": "invalid x-api-key" }
+                        })),
+                    );
+                }
+                (
+                    StatusCode::OK,
+                    axum::Json(serde_json::json!({
+                        "data": [
+                            { "id": "claude-sonnet-4-5", "type": "model" },
+                            { "id": "claude-haiku-4-5", "type": "model" }
+                        ],
+                        "has_more": false
+                    })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, mock_app).await.unwrap();
+        });
+
+        let discover = |api_key: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/config/discover-models")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "type": "anthropic",
+                        "endpoint": format!("http://{addr}"),
+                        "api_key": api_key,
+                    })
+                    .to_string(),
+                ))
+                .unwrap()
+        };
+
+        let response = discover_app().oneshot(discover("sk-test")).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["status"], "ok");
+        let ids: Vec<&str> = json["models"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["claude-sonnet-4-5", "claude-haiku-4-5"]);
+
+        let response = discover_app().oneshot(discover("wrong")).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["status"], "error");
+        assert!(json["message"].as_str().unwrap().contains("auth error"));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn discover_anthropic_without_api_key_returns_error() {
+        let app = discover_app();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/config/discover-models")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::json!({ "type": "anthropic" }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["status"], "error");
+        assert!(json["message"].as_str().unwrap().contains("API key"));
+    }
 }
 
 /// Tests for task 034 — Settings page layout.
@@ -3440,6 +4325,7 @@ mod settings_page {
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -3488,6 +4374,7 @@ similarity_threshold = 0.5
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -3811,6 +4698,7 @@ endpoint = "http://localhost:1234/v1"
             ),
             warnings,
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -3823,6 +4711,7 @@ endpoint = "http://localhost:1234/v1"
                 let registry = buddy_core::skill::build_tool_registry(
                     &config.tools,
                     Some(state.working_memory.clone()),
+                    Some(&state.store),
                 );
                 state.registry.store(Arc::new(registry));
 
@@ -4114,6 +5003,7 @@ model = "all-minilm"
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -4285,6 +5175,7 @@ endpoint = "http://localhost:1234/v1"
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -4381,6 +5272,7 @@ endpoint = "https://api.openai.com/v1"
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -4536,6 +5428,7 @@ mod auth {
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -4769,6 +5662,7 @@ mod interfaces_api {
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -4814,6 +5708,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: new_pending_approvals(),
+            active_generations: new_active_generations(),
             conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(1),
@@ -5081,6 +5976,7 @@ fn health_check_app(config: buddy_core::config::Config) -> Router {
         memory_config: arc_swap::ArcSwap::from_pointee(buddy_core::config::MemoryConfig::default()),
         warnings: buddy_core::warning::new_shared_warnings(),
         pending_approvals: new_pending_approvals(),
+        active_generations: new_active_generations(),
         conversation_approvals: Arc::new(Mutex::new(HashMap::new())),
         approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
         approval_timeout: std::time::Duration::from_secs(1),
@@ -0,0 +1,126 @@
+//! Todo list endpoints. The lists are the same ones the todo tools manage
+//! from chat, so changes show up on every interface.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{ApiError, AppState, bad_request, internal_error, not_found_error};
+use buddy_core::provider::Provider;
+use buddy_core::skill::todos::{DEFAULT_LIST, MAX_LIST_CHARS, MAX_TEXT_CHARS};
+use buddy_core::store::{TodoItem, TodoList, TodoUpdate};
+
+/// Query parameters for `GET /api/todos`.
+#[derive(Deserialize)]
+pub struct TodoQuery {
+    #[serde(default)]
+    pub list: Option<String>,
+    #[serde(default)]
+    pub include_completed: bool,
+}
+
+/// Request body for `POST /api/todos`.
+#[derive(Deserialize)]
+pub struct CreateTodoRequest {
+    #[serde(default)]
+    pub list: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+}
+
+/// `GET /api/todos?list=&include_completed=` — todo lists with their items,
+/// open items only unless `include_completed` is set.
+pub async fn list_todos<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Query(query): Query<TodoQuery>,
+) -> Result<Json<Vec<TodoList>>, (StatusCode, Json<ApiError>)> {
+    let lists = state
+        .store
+        .list_todos(query.list.as_deref(), query.include_completed)
+        .map_err(internal_error)?;
+    Ok(Json(lists))
+}
+
+/// `POST /api/todos` — add an item, creating its list if needed. The list
+/// defaults to the one the tools use.
+pub async fn create_todo<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Json(body): Json<CreateTodoRequest>,
+) -> Result<(StatusCode, Json<TodoItem>), (StatusCode, Json<ApiError>)> {
+    let text = body.text.trim();
+    if text.is_empty() {
+        return Err(bad_request("todo text must not be empty".into()));
+    }
+    let list = body
+        .list
+        .as_deref()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .unwrap_or(DEFAULT_LIST);
+    check_length("todo text", text, MAX_TEXT_CHARS)?;
+    check_length("list name", list, MAX_LIST_CHARS)?;
+    let notes = body.notes.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let item = state
+        .store
+        .add_todo(list, text, notes, body.due_date)
+        .map_err(internal_error)?;
+    Ok((StatusCode::CREATED, Json(item)))
+}
+
+/// `PATCH /api/todos/:id` — change an item's text, notes, due date or
+/// completion. `null` clears the notes or due date.
+pub async fn update_todo<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(id): Path<String>,
+    Json(update): Json<TodoUpdate>,
+) -> Result<Json<TodoItem>, (StatusCode, Json<ApiError>)> {
+    if let Some(text) = update.text.as_deref() {
+        if text.trim().is_empty() {
+            return Err(bad_request("todo text must not be empty".into()));
+        }
+        check_length("todo text", text.trim(), MAX_TEXT_CHARS)?;
+    }
+    match state.store.update_todo(&id, &update).map_err(internal_error)? {
+        Some(item) => Ok(Json(item)),
+        None => Err(not_found_error(format!("todo item '{id}' not found"))),
+    }
+}
+
+/// Reject text longer than the todo tools accept from the model.
+fn check_length(field: &str, value: &str, max: u64) -> Result<(), (StatusCode, Json<ApiError>)> {
+    if value.chars().count() as u64 > max {
+        return Err(bad_request(format!("{field} must be at most {max} characters")));
+    }
+    Ok(())
+}
+
+/// `DELETE /api/todos/:id` — delete an item.
+pub async fn delete_todo<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    if state.store.delete_todo(&id).map_err(internal_error)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found_error(format!("todo item '{id}' not found")))
+    }
+}
+
+/// `DELETE /api/todos/lists/:name` — delete a list and all of its items.
+pub async fn delete_todo_list<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    if state.store.delete_todo_list(&name).map_err(internal_error)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found_error(format!("todo list '{name}' not found")))
+    }
+}
@@ -0,0 +1,101 @@
+//! Token usage and spend endpoints.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, AppState, internal_error, not_found_error};
+use buddy_core::config::ModelPrice;
+use buddy_core::provider::Provider;
+use buddy_core::store::{UsageGrouping, UsageTotal};
+
+/// Query parameters for `GET /api/usage`.
+#[derive(Deserialize)]
+pub struct UsageQuery {
+    #[serde(default = "default_grouping")]
+    pub group_by: UsageGrouping,
+}
+
+fn default_grouping() -> UsageGrouping {
+    UsageGrouping::Day
+}
+
+/// Aggregated usage and spend for one group.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct UsageReport {
+    pub key: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Spend in USD for the models that have a configured price.
+    pub cost_usd: f64,
+    /// Models in this group with no entry in the price table.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unpriced_models: Vec<String>,
+}
+
+/// Fold per-model totals into one report per key, pricing each model.
+fn build_reports(
+    totals: Vec<UsageTotal>,
+    pricing: &BTreeMap<String, ModelPrice>,
+) -> Vec<UsageReport> {
+    let mut reports: Vec<UsageReport> = Vec::new();
+    for total in totals {
+        if reports.last().is_none_or(|r| r.key != total.key) {
+            reports.push(UsageReport {
+                key: total.key.clone(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                cost_usd: 0.0,
+                unpriced_models: Vec::new(),
+            });
+        }
+        let report = reports.last_mut().unwrap();
+        report.prompt_tokens += total.prompt_tokens;
+        report.completion_tokens += total.completion_tokens;
+        match pricing.get(&total.model) {
+            Some(price) => {
+                report.cost_usd += price.cost(total.prompt_tokens, total.completion_tokens)
+            }
+            None if !report.unpriced_models.contains(&total.model) => {
+                report.unpriced_models.push(total.model)
+            }
+            None => {}
+        }
+    }
+    reports
+}
+
+/// `GET /api/usage?group_by=day|provider|model|conversation` — token usage
+/// and spend across all conversations. Defaults to grouping by day.
+pub async fn get_usage<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<Vec<UsageReport>>, (StatusCode, Json<ApiError>)> {
+    let totals = state
+        .store
+        .usage_totals(query.group_by, None)
+        .map_err(internal_error)?;
+    let pricing = state.config.read().unwrap().pricing.clone();
+    Ok(Json(build_reports(totals, &pricing)))
+}
+
+/// `GET /api/conversations/:id/usage` — token usage and spend for one
+/// conversation, broken down by model.
+pub async fn get_conversation_usage<P: Provider + 'static>(
+    State(state): State<Arc<AppState<P>>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<UsageReport>>, (StatusCode, Json<ApiError>)> {
+    if state.store.get_conversation(&id).map_err(internal_error)?.is_none() {
+        return Err(not_found_error(format!("conversation '{id}' not found")));
+    }
+    let totals = state
+        .store
+        .usage_totals(UsageGrouping::Model, Some(&id))
+        .map_err(internal_error)?;
+    let pricing = state.config.read().unwrap().pricing.clone();
+    Ok(Json(build_reports(totals, &pricing)))
+}
@@ -30,7 +30,7 @@ fn load_config() -> Result<(buddy_core::config::Config, PathBuf), String> {
     Ok((config, cli.config))
 }
 
-use api::{approve_handler, chat_handler, check_interface_connection, clear_memory, create_conversation, delete_conversation, discover_models, get_config, get_conversation, get_embedder_health, get_interfaces_status, get_memory_status, get_warnings, list_conversations, migrate_memory, put_config_chat, put_config_interfaces, put_config_memory, put_config_models, put_config_server, put_config_tools, test_provider};
+use api::{approve_handler, cancel_handler, chat_handler, check_interface_connection, clear_memory, create_conversation, create_todo, delete_conversation, delete_todo, delete_todo_list, discover_models, edit_message, get_config, get_conversation, get_blob, get_embedder_health, get_interfaces_status, get_conversation_usage, get_memory_status, get_usage, get_warnings, list_conversations, list_todos, migrate_memory, put_config_chat, put_config_interfaces, put_config_memory, put_config_models, put_config_server, put_config_tools, regenerate_message, switch_branch, test_provider, update_todo};
 use api::auth::{auth_middleware, auth_status, verify_token};
 use buddy_core::provider::{AnyProvider, ProviderChain};
 use buddy_core::state::AppState;
@@ -78,7 +78,17 @@ async fn main() {
         .route("/api/chat", post(chat_handler::<AppProvider>))
         .route("/api/conversations", get(list_conversations::<AppProvider>).post(create_conversation::<AppProvider>))
         .route("/api/conversations/{id}", get(get_conversation::<AppProvider>).delete(delete_conversation::<AppProvider>))
+        .route("/api/conversations/{id}/branch", put(switch_branch::<AppProvider>))
+        .route("/api/conversations/{id}/messages/{message_id}/regenerate", post(regenerate_message::<AppProvider>))
+        .route("/api/conversations/{id}/messages/{message_id}/edit", post(edit_message::<AppProvider>))
+        .route("/api/blobs/{id}", get(get_blob::<AppProvider>))
+        .route("/api/conversations/{id}/usage", get(get_conversation_usage::<AppProvider>))
+        .route("/api/usage", get(get_usage::<AppProvider>))
+        .route("/api/todos", get(list_todos::<AppProvider>).post(create_todo::<AppProvider>))
+        .route("/api/todos/{id}", axum::routing::patch(update_todo::<AppProvider>).delete(delete_todo::<AppProvider>))
+        .route("/api/todos/lists/{name}", axum::routing::delete(delete_todo_list::<AppProvider>))
         .route("/api/chat/{conversation_id}/approve", post(approve_handler::<AppProvider>))
+        .route("/api/chat/{conversation_id}/cancel", post(cancel_handler::<AppProvider>))
         .route("/api/memory/migrate", post(migrate_memory::<AppProvider>))
         .route("/api/memory/status", get(get_memory_status::<AppProvider>))
         .route("/api/memory", axum::routing::delete(clear_memory::<AppProvider>))
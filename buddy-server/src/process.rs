@@ -165,6 +165,7 @@ mod tests {
             ),
             warnings: buddy_core::warning::new_shared_warnings(),
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             approval_overrides: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             approval_timeout: std::time::Duration::from_secs(60),
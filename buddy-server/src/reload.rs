@@ -26,6 +26,7 @@ pub fn reload_from_config(
     let vector_store = build_vector_store(&embedder)?;
     let registry = build_tool_registry(
         config,
+        &state.store,
         state.working_memory.clone(),
         &embedder,
         &vector_store,
@@ -86,6 +87,7 @@ model = "all-MiniLM-L6-v2"
         let working_memory = skill::working_memory::new_working_memory_map();
         let registry = build_tool_registry(
             &config_with_external,
+            &store,
             working_memory.clone(),
             &embedder,
             &vector_store,
@@ -108,6 +110,7 @@ model = "all-MiniLM-L6-v2"
             memory_config: arc_swap::ArcSwap::from_pointee(config_with_external.memory.clone()),
             warnings,
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
@@ -167,8 +170,13 @@ endpoint = "http://localhost:1234/v1"
         let embedder = build_embedder(&config_v1).unwrap();
         let vector_store = build_vector_store(&embedder).unwrap();
         let working_memory = skill::working_memory::new_working_memory_map();
-        let registry =
-            build_tool_registry(&config_v1, working_memory.clone(), &embedder, &vector_store);
+        let registry = build_tool_registry(
+            &config_v1,
+            &store,
+            working_memory.clone(),
+            &embedder,
+            &vector_store,
+        );
         let approval_overrides = build_approval_overrides(&config_v1);
         let warnings = warning::new_shared_warnings();
 
@@ -187,6 +195,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(config_v1.memory.clone()),
             warnings,
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
@@ -240,8 +249,13 @@ endpoint = "http://localhost:1234/v1"
         let embedder = build_embedder(&config).unwrap();
         let vector_store = build_vector_store(&embedder).unwrap();
         let working_memory = skill::working_memory::new_working_memory_map();
-        let registry =
-            build_tool_registry(&config, working_memory.clone(), &embedder, &vector_store);
+        let registry = build_tool_registry(
+            &config,
+            &store,
+            working_memory.clone(),
+            &embedder,
+            &vector_store,
+        );
         let approval_overrides = build_approval_overrides(&config);
         let warnings = warning::new_shared_warnings();
 
@@ -260,6 +274,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(config.memory.clone()),
             warnings,
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
@@ -302,6 +317,7 @@ endpoint = "http://localhost:1234/v1"
         let working_memory = skill::working_memory::new_working_memory_map();
         let registry = build_tool_registry(
             &config_valid,
+            &store,
             working_memory.clone(),
             &embedder,
             &vector_store,
@@ -324,6 +340,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(config_valid.memory.clone()),
             warnings,
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
@@ -381,6 +398,7 @@ endpoint = "http://localhost:1234/v1"
         let working_memory = skill::working_memory::new_working_memory_map();
         let registry = build_tool_registry(
             &config_no_embedder,
+            &store,
             working_memory.clone(),
             &embedder,
             &vector_store,
@@ -403,6 +421,7 @@ endpoint = "http://localhost:1234/v1"
             memory_config: arc_swap::ArcSwap::from_pointee(config_no_embedder.memory.clone()),
             warnings,
             pending_approvals: buddy_core::state::new_pending_approvals(),
+            active_generations: buddy_core::state::new_active_generations(),
             conversation_approvals: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
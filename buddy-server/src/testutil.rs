@@ -56,11 +56,16 @@ pub fn make_chat_body_with_conversation(conversation_id: &str) -> String {
 
 /// Post to /api/chat and return all SSE events (including ConversationMeta).
 pub async fn post_chat_raw(app: Router, body: &str) -> Vec<ChatEvent> {
+    post_sse(app, "/api/chat", body).await
+}
+
+/// Post to a streaming endpoint and return all SSE events.
+pub async fn post_sse(app: Router, uri: &str, body: &str) -> Vec<ChatEvent> {
     let response = app
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/api/chat")
+                .uri(uri)
                 .header("content-type", "application/json")
                 .body(Body::from(body.to_owned()))
                 .unwrap(),
@@ -1,19 +1,56 @@
 use buddy_core::types::Message;
 use chrono::Utc;
+use teloxide::types::PhotoSize;
 
 /// Telegram's maximum message length (in characters after entity parsing).
 const TELEGRAM_MAX_LENGTH: usize = 4096;
 
-#[allow(dead_code)]
-pub fn telegram_to_buddy(message: &teloxide::types::Message) -> Option<buddy_core::types::Message> {
-    let text = message.text()?;
-    Some(buddy_core::types::Message {
+/// Telegram re-encodes every photo it receives as JPEG.
+pub const PHOTO_MIME_TYPE: &str = "image/jpeg";
+
+/// The highest-resolution size of a photo message, which is the one to
+/// download.
+pub fn largest_photo(message: &teloxide::types::Message) -> Option<&PhotoSize> {
+    message
+        .photo()?
+        .iter()
+        .max_by_key(|size| size.width * size.height)
+}
+
+/// Converts a Telegram message to buddy-core `Message`s.
+///
+/// A text message becomes one text message. A photo becomes an `Image`
+/// referring to `photo_blob_id` (where the downloaded photo was stored),
+/// followed by its caption if it has one. Returns an empty list for other
+/// message types and for photos that were not downloaded.
+pub fn telegram_to_buddy(
+    message: &teloxide::types::Message,
+    photo_blob_id: Option<&str>,
+) -> Vec<buddy_core::types::Message> {
+    let user_message = |content| buddy_core::types::Message {
         role: buddy_core::types::Role::User,
-        content: buddy_core::types::MessageContent::Text {
-            text: text.to_string(),
-        },
+        content,
         timestamp: Utc::now(),
-    })
+    };
+    if let Some(text) = message.text() {
+        return vec![user_message(buddy_core::types::MessageContent::Text {
+            text: text.to_string(),
+        })];
+    }
+    let (Some(_), Some(blob_id)) = (message.photo(), photo_blob_id) else {
+        return Vec::new();
+    };
+    let mut messages = vec![user_message(buddy_core::types::MessageContent::Image {
+        blob_id: blob_id.to_string(),
+        mime_type: PHOTO_MIME_TYPE.to_string(),
+        data: None,
+    })];
+    if let Some(caption) = message.caption().filter(|c| !c.trim().is_empty()) {
+        messages.push(user_message(buddy_core::types::MessageContent::Text {
+            text: caption.to_string(),
+        }));
+    }
+    messages
 }
 
 #[allow(dead_code)]
@@ -24,6 +61,8 @@ pub fn buddy_to_telegram(message: &Message) -> String {
             format!("Using tool: {name}...")
         }
         buddy_core::types::MessageContent::ToolResult { content, .. } => content.clone(),
+        buddy_core::types::MessageContent::Image { .. } => "[image]".to_string(),
+        buddy_core::types::MessageContent::Reasoning { text } => text.clone(),
     }
 }
 
@@ -119,8 +158,8 @@ mod tests {
         .expect("valid telegram text message JSON")
     }
 
-    fn make_telegram_photo_message() -> teloxide::types::Message {
-        serde_json::from_value(serde_json::json!({
+    fn make_telegram_photo_message(caption: Option<&str>) -> teloxide::types::Message {
+        let mut json = serde_json::json!({
             "message_id": 2,
             "date": 1_234_567_890,
             "chat": {
@@ -128,28 +167,58 @@ mod tests {
                 "type": "private"
             },
             "photo": [{
-                "file_id": "abc",
+                "file_id": "small",
                 "file_unique_id": "def",
-                "width": 100,
-                "height": 100
+                "width": 90,
+                "height": 60
+            }, {
+                "file_id": "large",
+                "file_unique_id": "ghi",
+                "width": 1280,
+                "height": 853
             }]
-        }))
-        .expect("valid telegram photo message JSON")
+        });
+        if let Some(caption) = caption {
+            json["caption"] = caption.into();
+        }
+        serde_json::from_value(json).expect("valid telegram photo message JSON")
     }
 
     #[test]
     fn telegram_text_converts_to_buddy_message() {
         let tg_msg = make_telegram_text_message("Hello");
-        let result = telegram_to_buddy(&tg_msg);
-        let buddy_msg = result.expect("should produce a Message");
-        assert_eq!(buddy_msg.role, Role::User);
-        assert!(matches!(&buddy_msg.content, MessageContent::Text { text } if text == "Hello"));
+        let result = telegram_to_buddy(&tg_msg, None);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].role, Role::User);
+        assert!(matches!(&result[0].content, MessageContent::Text { text } if text == "Hello"));
+    }
+
+    #[test]
+    fn telegram_photo_converts_to_image_and_caption() {
+        let tg_msg = make_telegram_photo_message(Some("What is this?"));
+        let result = telegram_to_buddy(&tg_msg, Some("blob-1"));
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].content,
+            MessageContent::Image {
+                blob_id: "blob-1".into(),
+                mime_type: "image/jpeg".into(),
+                data: None,
+            }
+        );
+        assert!(matches!(&result[1].content, MessageContent::Text { text } if text == "What is this?"));
+    }
+
+    #[test]
+    fn telegram_photo_without_download_is_skipped() {
+        let tg_msg = make_telegram_photo_message(None);
+        assert!(telegram_to_buddy(&tg_msg, None).is_empty());
     }
 
     #[test]
-    fn telegram_photo_returns_none() {
-        let tg_msg = make_telegram_photo_message();
-        assert!(telegram_to_buddy(&tg_msg).is_none());
+    fn largest_photo_picks_highest_resolution() {
+        let tg_msg = make_telegram_photo_message(None);
+        assert_eq!(largest_photo(&tg_msg).unwrap().file.id.0, "large");
     }
 
     #[test]
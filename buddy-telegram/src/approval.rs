@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use buddy_core::skill::approval_details;
 use teloxide::payloads::SendMessageSetters;
 use teloxide::prelude::Requester;
 use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
@@ -37,6 +38,10 @@ fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
 }
 
+/// Longest tool preview (e.g. a diff) shown in an approval message;
+/// Telegram caps messages at 4096 characters.
+const PREVIEW_MAX_LEN: usize = 3000;
+
 /// Send an approval request message with Approve/Deny buttons, wait for callback or timeout.
 /// Returns true if approved, false if denied or timed out. Edits the message on timeout.
 pub async fn request_approval(
@@ -46,10 +51,9 @@ pub async fn request_approval(
     timeout: Duration,
     skill_name: &str,
     arguments: &str,
+    preview: Option<&str>,
 ) -> bool {
-    let formatted_args = serde_json::from_str::<serde_json::Value>(arguments)
-        .map(|v| serde_json::to_string_pretty(&v).unwrap_or_else(|_| arguments.to_string()))
-        .unwrap_or_else(|_| arguments.to_string());
+    let formatted_args = approval_details(arguments, preview, PREVIEW_MAX_LEN);
     let text = approval_message_text(skill_name, &formatted_args);
     let keyboard = InlineKeyboardMarkup::new([
         vec![
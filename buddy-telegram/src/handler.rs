@@ -1,35 +1,35 @@
-//! Message processing and tool-call loop for Telegram.
+//! Message processing for Telegram.
 //!
-//! Runs the same tool loop as the web chat: provider may return tool calls,
-//! we check permission level and approval policy, request approval when needed
-//! (via Telegram inline buttons), execute skills, persist messages, and loop.
+//! Runs the shared `buddy_core::engine` tool loop and renders its events for
+//! Telegram: approval requests become inline buttons and tool results are
+//! collected as code blocks to send before the final reply.
 
-use std::collections::HashMap;
 use std::time::Duration;
 
-use buddy_core::config::ApprovalPolicy;
-use buddy_core::provider::{Provider, ProviderError, Token};
-use buddy_core::skill::{PermissionLevel, SkillRegistry, ToolRegistry};
-use buddy_core::state::ConversationApprovals;
-use buddy_core::store::Store;
+use buddy_core::engine::{Engine, EngineError, EngineEvent};
+use buddy_core::provider::{Provider, ProviderError};
+use buddy_core::scheduler;
+use buddy_core::skill::truncate_result;
+use buddy_core::store::{Job, Store};
 use buddy_core::types::{Message, MessageContent, Role};
 use chrono::Utc;
 use futures_util::StreamExt;
 
+use crate::adapter::{PHOTO_MIME_TYPE, telegram_to_buddy};
 use crate::approval::TelegramPendingApprovals;
 
-/// Maximum number of tool-call loop iterations (same as web).
-const MAX_TOOL_ITERATIONS: usize = 10;
-
 /// Maximum length for tool result in a Telegram message before truncation.
 const RESULT_MAX_LEN: usize = 2000;
 
 /// Outcome of processing a Telegram message.
 pub enum ProcessResult {
     /// Final text and any tool results to send (in order): tool results first, then final text.
+    /// `warnings` (e.g. a fallback model, or images the model cannot view)
+    /// are sent before both.
     Response {
         final_text: String,
         tool_results: Vec<String>,
+        warnings: Vec<String>,
     },
     /// The provider produced no output (empty stream, no tool calls).
     Empty,
@@ -68,22 +68,17 @@ pub struct TelegramApprovalContext<'a> {
 }
 
 
-/// Process a Telegram text message: resolve conversation, run provider with tool loop,
-/// apply approval policy (ReadOnly execute; Mutating/Network per Trust/Once/Always),
-/// persist all messages, return final response text.
+/// Process a Telegram text message: resolve conversation, persist the user
+/// message, and run the shared engine. Approval requests are sent as inline
+/// buttons (denied when there is no approval context); tool results are
+/// collected for display. Returns the final response text.
 pub async fn process_message<P: Provider>(
-    store: &Store,
-    provider: &P,
-    registry: &ToolRegistry,
-    skill_registry: &SkillRegistry,
-    approval_overrides: &HashMap<String, ApprovalPolicy>,
-    conversation_approvals: &ConversationApprovals,
+    engine: &Engine<'_, P>,
     chat_id: i64,
     user_text: &str,
     approval_ctx: Option<TelegramApprovalContext<'_>>,
 ) -> Result<ProcessResult, ProcessError> {
-    let conversation_id = resolve_conversation(store, chat_id, user_text)?;
-
+    let conversation_id = resolve_conversation(engine.store(), chat_id, user_text)?;
     let user_msg = Message {
         role: Role::User,
         content: MessageContent::Text {
@@ -91,264 +86,136 @@ pub async fn process_message<P: Provider>(
         },
         timestamp: Utc::now(),
     };
-    store
-        .append_message(&conversation_id, &user_msg)
+    run_turn(engine, &conversation_id, vec![user_msg], approval_ctx).await
+}
+
+/// Process a Telegram photo whose largest size has been downloaded: store
+/// it as a blob, persist it (and its caption) as user messages, and run the
+/// shared engine as [`process_message`] does.
+pub async fn process_photo_message<P: Provider>(
+    engine: &Engine<'_, P>,
+    message: &teloxide::types::Message,
+    photo: &[u8],
+    approval_ctx: Option<TelegramApprovalContext<'_>>,
+) -> Result<ProcessResult, ProcessError> {
+    let store = engine.store();
+    let title = message.caption().unwrap_or("Photo");
+    let conversation_id = resolve_conversation(store, message.chat.id.0, title)?;
+    let blob_id = store
+        .put_blob(&conversation_id, PHOTO_MIME_TYPE, photo)
         .map_err(|e| {
+            log::error!("Failed to store photo: {e}");
+            ProcessError::Store
+        })?;
+    let user_messages = telegram_to_buddy(message, Some(&blob_id));
+    run_turn(engine, &conversation_id, user_messages, approval_ctx).await
+}
+
+/// Run a scheduled prompt in the conversation it was scheduled from. Nobody
+/// is there to approve tool calls, so calls that need approval are denied.
+pub async fn process_scheduled_prompt<P: Provider>(
+    engine: &Engine<'_, P>,
+    job: &Job,
+) -> Result<ProcessResult, ProcessError> {
+    let message = scheduler::prompt_message(job);
+    run_turn(engine, &job.conversation_id, vec![message], None).await
+}
+
+/// Persist `user_messages` and run the engine over the conversation.
+async fn run_turn<P: Provider>(
+    engine: &Engine<'_, P>,
+    conversation_id: &str,
+    user_messages: Vec<Message>,
+    approval_ctx: Option<TelegramApprovalContext<'_>>,
+) -> Result<ProcessResult, ProcessError> {
+    let store = engine.store();
+    for message in &user_messages {
+        store.append_message(conversation_id, message).map_err(|e| {
             log::error!("Failed to append user message: {e}");
             ProcessError::Store
         })?;
+    }
 
-    let messages = match store.get_conversation(&conversation_id) {
+    let messages = match store.get_conversation(conversation_id) {
         Ok(Some(conv)) => conv.messages,
-        Ok(None) => vec![user_msg.clone()],
+        Ok(None) => user_messages,
         Err(e) => {
             log::error!("Failed to load conversation: {e}");
-            vec![user_msg.clone()]
-        }
-    };
-
-    let tools = {
-        let defs = registry.tool_definitions();
-        let mut skill_defs = skill_registry.tool_definitions();
-        let mut all_defs = defs;
-        all_defs.extend(skill_defs);
-        if all_defs.is_empty() {
-            None
-        } else {
-            Some(all_defs)
+            user_messages
         }
     };
 
     let mut tool_results_to_send: Vec<String> = Vec::new();
-    let mut all_messages = messages;
-    for _iteration in 0..MAX_TOOL_ITERATIONS {
-        let token_stream = match provider
-            .complete(all_messages.clone(), tools.clone())
-            .await
-        {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("Provider error: {e}");
-                return Err(classify_provider_error(e));
+    let mut warnings: Vec<String> = Vec::new();
+    let events = engine.run(conversation_id, messages);
+    tokio::pin!(events);
+    while let Some(event) = events.next().await {
+        match event {
+            EngineEvent::Warning { message } => {
+                log::warn!("Provider warning: {message}");
+                warnings.push(message);
             }
-        };
-
-        let mut tool_calls: Vec<(String, String, String)> = Vec::new();
-        let mut full_text = String::new();
-
-        tokio::pin!(token_stream);
-        while let Some(result) = token_stream.next().await {
-            match result {
-                Ok(Token::Text { text }) => full_text.push_str(&text),
-                Ok(Token::Warning { message }) => {
-                    log::warn!("Provider warning: {message}");
+            EngineEvent::StreamReset { message } => log::warn!("Provider stream reset: {message}"),
+            EngineEvent::ToolCallResult { content, .. } => {
+                if approval_ctx.is_some() {
+                    tool_results_to_send.push(format_tool_result_for_telegram(&content));
                 }
-                Ok(Token::ToolCall {
-                    id,
-                    name,
-                    arguments,
-                }) => tool_calls.push((id, name, arguments)),
-                Err(e) => {
-                    log::error!("Stream error: {e}");
-                    return Err(classify_provider_error(e));
-                }
-            }
-        }
-
-        if tool_calls.is_empty() {
-            if full_text.is_empty() {
-                return Ok(ProcessResult::Empty);
             }
-            let assistant_msg = Message {
-                role: Role::Assistant,
-                content: MessageContent::Text {
-                    text: full_text.clone(),
-                },
-                timestamp: Utc::now(),
-            };
-            if let Err(e) = store.append_message(&conversation_id, &assistant_msg) {
-                log::error!("Failed to append assistant message: {e}");
-            }
-            return Ok(ProcessResult::Response {
-                final_text: full_text,
-                tool_results: tool_results_to_send,
-            });
-        }
-
-        for (id, name, arguments) in &tool_calls {
-            let tool_call_msg = Message {
-                role: Role::Assistant,
-                content: MessageContent::ToolCall {
-                    id: id.clone(),
-                    name: name.clone(),
-                    arguments: arguments.clone(),
-                },
-                timestamp: Utc::now(),
-            };
-            persist_message(store, &conversation_id, &tool_call_msg);
-            all_messages.push(tool_call_msg);
-
-            let result_content = match registry.get(name) {
-                Some(skill) => {
-                    let perm = skill.permission_level();
-                    let approved = if perm == PermissionLevel::ReadOnly {
-                        true
-                    } else {
-                        check_approval_telegram(
-                            approval_overrides,
-                            conversation_approvals,
-                            &conversation_id,
-                            name,
-                            approval_ctx.as_ref(),
-                            &arguments,
+            EngineEvent::ApprovalNeeded(request) => {
+                let approved = match &approval_ctx {
+                    Some(ctx) => {
+                        crate::approval::request_approval(
+                            ctx.bot,
+                            ctx.chat_id,
+                            ctx.pending,
+                            ctx.timeout,
+                            &request.skill_name,
+                            &request.arguments,
+                            request.preview.as_deref(),
                         )
                         .await
-                    };
-
-                    if !approved {
-                        format!("User denied execution of {name}")
-                    } else {
-                        let policy = approval_overrides.get(name).copied().unwrap_or(ApprovalPolicy::Always);
-                        if policy == ApprovalPolicy::Once {
-                            let mut approvals = conversation_approvals.lock().await;
-                            approvals
-                                .entry(conversation_id.clone())
-                                .or_default()
-                                .insert(name.clone());
-                        }
-                        let mut input: serde_json::Value =
-                            serde_json::from_str(arguments)
-                                .unwrap_or_else(|_| serde_json::json!({}));
-                        if let Some(obj) = input.as_object_mut() {
-                            obj.insert(
-                                "conversation_id".to_string(),
-                                serde_json::Value::String(conversation_id.clone()),
-                            );
-                        }
-                        match skill.execute(input).await {
-                            Ok(output) => serde_json::to_string(&output)
-                                .unwrap_or_else(|_| "{}".to_string()),
-                            Err(e) => format!("❌ Tool error: {e}"),
-                        }
                     }
+                    None => false,
+                };
+                request.respond(approved);
+            }
+            EngineEvent::Error(EngineError::Provider(e)) => {
+                log::error!("Provider error: {e}");
+                return Err(classify_provider_error(e));
+            }
+            EngineEvent::Error(EngineError::MaxIterations) => {
+                return Ok(ProcessResult::Response {
+                    final_text: "Tool loop reached maximum iterations.".to_string(),
+                    tool_results: tool_results_to_send,
+                    warnings,
+                });
+            }
+            EngineEvent::Done { final_text } => {
+                if final_text.is_empty() && tool_results_to_send.is_empty() {
+                    return Ok(ProcessResult::Empty);
                 }
-                None => "❌ Tool error: unknown tool".to_string(),
-            };
-
-            if approval_ctx.is_some() {
-                tool_results_to_send.push(format_tool_result_for_telegram(&result_content));
+                return Ok(ProcessResult::Response {
+                    final_text,
+                    tool_results: tool_results_to_send,
+                    warnings,
+                });
             }
-
-            let tool_result_msg = Message {
-                role: Role::User,
-                content: MessageContent::ToolResult {
-                    id: id.clone(),
-                    name: name.clone(),
-                    content: result_content.clone(),
-                },
-                timestamp: Utc::now(),
-            };
-            persist_message(store, &conversation_id, &tool_result_msg);
-            all_messages.push(tool_result_msg);
+            EngineEvent::MemoryContext { .. }
+            | EngineEvent::TextDelta { .. }
+            | EngineEvent::ReasoningDelta { .. }
+            | EngineEvent::ToolCallStart { .. }
+            | EngineEvent::Cancelled { .. } => {}
         }
     }
-
-    let mut full_text = String::new();
-    let token_stream = match provider.complete(all_messages, tools).await {
-        Ok(s) => s,
-        Err(e) => {
-            log::error!("Provider error: {e}");
-            return Err(classify_provider_error(e));
-        }
-    };
-    tokio::pin!(token_stream);
-    while let Some(result) = token_stream.next().await {
-        match result {
-            Ok(Token::Text { text }) => full_text.push_str(&text),
-            Ok(Token::Warning { message }) => log::warn!("Provider warning: {message}"),
-            Ok(Token::ToolCall { .. }) => {}
-            Err(e) => return Err(classify_provider_error(e)),
-        }
-    }
-    if full_text.is_empty() {
-        full_text = "Tool loop reached maximum iterations.".to_string();
-    }
-    let assistant_msg = Message {
-        role: Role::Assistant,
-        content: MessageContent::Text {
-            text: full_text.clone(),
-        },
-        timestamp: Utc::now(),
-    };
-    if let Err(e) = store.append_message(&conversation_id, &assistant_msg) {
-        log::error!("Failed to append assistant message: {e}");
-    }
-    Ok(ProcessResult::Response {
-        final_text: full_text,
-        tool_results: tool_results_to_send,
-    })
+    Ok(ProcessResult::Empty)
 }
 
 /// Format tool result for Telegram: code block, truncate at RESULT_MAX_LEN, keep error prefix.
 pub fn format_tool_result_for_telegram(content: &str) -> String {
-    let truncated = if content.len() > RESULT_MAX_LEN {
-        let mut end = RESULT_MAX_LEN;
-        while !content.is_char_boundary(end) {
-            end -= 1;
-        }
-        format!("{}... (truncated)", &content[..end])
-    } else {
-        content.to_string()
-    };
+    let truncated = truncate_result(content, RESULT_MAX_LEN);
     format!("```\n{truncated}\n```")
 }
 
-fn persist_message(store: &Store, conversation_id: &str, message: &Message) {
-    if let Err(e) = store.append_message(conversation_id, message) {
-        log::error!("Failed to persist message: {e}");
-    }
-}
-
-async fn check_approval_telegram(
-    approval_overrides: &HashMap<String, ApprovalPolicy>,
-    conversation_approvals: &ConversationApprovals,
-    conversation_id: &str,
-    skill_name: &str,
-    approval_ctx: Option<&TelegramApprovalContext<'_>>,
-    arguments: &str,
-) -> bool {
-    let policy = approval_overrides
-        .get(skill_name)
-        .copied()
-        .unwrap_or(ApprovalPolicy::Always);
-
-    match policy {
-        ApprovalPolicy::Trust => return true,
-        ApprovalPolicy::Once => {
-            let approvals = conversation_approvals.lock().await;
-            if let Some(skills) = approvals.get(conversation_id) {
-                if skills.contains(skill_name) {
-                    return true;
-                }
-            }
-        }
-        ApprovalPolicy::Always => {}
-    }
-
-    let Some(ctx) = approval_ctx else {
-        return false;
-    };
-    crate::approval::request_approval(
-        ctx.bot,
-        ctx.chat_id,
-        ctx.pending,
-        ctx.timeout,
-        skill_name,
-        arguments,
-    )
-    .await
-}
-
 /// Look up or create a buddy conversation for a Telegram chat.
 fn resolve_conversation(
     store: &Store,
@@ -373,7 +240,7 @@ fn resolve_conversation(
 
 fn classify_provider_error(e: ProviderError) -> ProcessError {
     match e {
-        ProviderError::Network(_) | ProviderError::RateLimit(_) => {
+        ProviderError::Network(_) | ProviderError::RateLimit { .. } => {
             ProcessError::AllUnavailable
         }
         _ => ProcessError::Provider,
@@ -384,9 +251,12 @@ fn classify_provider_error(e: ProviderError) -> ProcessError {
 mod tests {
     use std::sync::Arc;
 
+    use std::collections::HashMap;
+
     use super::*;
     use buddy_core::config::ApprovalPolicy;
-    use buddy_core::skill::ToolRegistry;
+    use buddy_core::engine::MAX_TOOL_ITERATIONS;
+    use buddy_core::skill::{SkillRegistry, ToolRegistry};
     use buddy_core::testutil::{
         MockEchoSkill, MockMutatingSkill, MockNetworkSkill, MockProvider, MockResponse,
         SequencedProvider,
@@ -425,12 +295,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             12345,
             "Hi there",
             None,
@@ -451,6 +323,111 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn scheduled_prompt_runs_in_its_conversation() {
+        use buddy_core::store::{JobKind, NewJob};
+
+        let store = Store::open_in_memory().unwrap();
+        let conv = store.create_conversation_with_source("Chat", "telegram").unwrap();
+        let job = store
+            .create_job(&NewJob {
+                conversation_id: &conv.id,
+                channel: "telegram",
+                target: "12345",
+                kind: JobKind::Prompt,
+                text: "What's on today?",
+                schedule: Some("0 8 * * *"),
+                next_run_at: Utc::now(),
+            })
+            .unwrap();
+        let provider = MockProvider {
+            tokens: vec!["Nothing planned.".into()],
+        };
+        let registry = ToolRegistry::new();
+        let overrides = HashMap::new();
+        let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+
+        let result = process_scheduled_prompt(
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
+            &job,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Ok(ProcessResult::Response { ref final_text, .. }) if final_text == "Nothing planned."
+        ));
+
+        let conv = store.get_conversation(&conv.id).unwrap().unwrap();
+        assert!(matches!(
+            &conv.messages[0].content,
+            MessageContent::Text { text } if text == "[Scheduled task] What's on today?"
+        ));
+    }
+
+    #[tokio::test]
+    async fn photo_is_stored_and_unsupported_model_warns() {
+        use buddy_core::provider::ProviderChain;
+
+        let store = Store::open_in_memory().unwrap();
+        let provider = ProviderChain::new(vec![(
+            MockProvider {
+                tokens: vec!["I can't see it.".into()],
+            },
+            "text-only".to_string(),
+        )])
+        .with_vision(vec![false]);
+        let registry = ToolRegistry::new();
+        let overrides = HashMap::new();
+        let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let photo: teloxide::types::Message = serde_json::from_value(serde_json::json!({
+            "message_id": 2,
+            "date": 1_234_567_890,
+            "chat": { "id": 12345, "type": "private" },
+            "photo": [{ "file_id": "abc", "file_unique_id": "def", "width": 100, "height": 100 }],
+            "caption": "What is this?"
+        }))
+        .unwrap();
+
+        let result = process_photo_message(
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
+            &photo,
+            b"jpeg bytes",
+            None,
+        )
+        .await;
+        let warnings = match result {
+            Ok(ProcessResult::Response { warnings, .. }) => warnings,
+            other => panic!("expected Response, got {other:?}"),
+        };
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("text-only cannot view images"));
+
+        let convs = store.list_conversations().unwrap();
+        let conv = store.get_conversation(&convs[0].id).unwrap().unwrap();
+        let MessageContent::Image { blob_id, .. } = &conv.messages[0].content else {
+            panic!("expected an image, got {:?}", conv.messages[0].content);
+        };
+        assert_eq!(store.get_blob(blob_id).unwrap().unwrap().data, b"jpeg bytes");
+        assert!(matches!(
+            &conv.messages[1].content,
+            MessageContent::Text { text } if text == "What is this?"
+        ));
+    }
+
     #[tokio::test]
     async fn provider_called_with_history_and_response_returned() {
         let store = Store::open_in_memory().unwrap();
@@ -462,12 +439,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             100,
             "Help me",
             None,
@@ -500,12 +479,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             555,
             "First message",
             None,
@@ -513,12 +494,14 @@ mod tests {
         .await
         .unwrap();
         process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             555,
             "Second message",
             None,
@@ -549,12 +532,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             200,
             "Use echo",
             None,
@@ -594,12 +579,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             200,
             "Use network",
             None,
@@ -638,12 +625,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             200,
             "Do something",
             None,
@@ -680,12 +669,14 @@ mod tests {
         };
         let registry = registry_with_mutating();
         process_message(
-            &store,
-            &setup_provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &setup_provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             300,
             "hello",
             None,
@@ -713,12 +704,14 @@ mod tests {
             MockResponse::Text(vec!["Done.".into()]),
         ]);
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             300,
             "mutate something",
             None,
@@ -767,12 +760,14 @@ mod tests {
         let conversation_approvals = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
         let result = process_message(
-            &store,
-            &provider,
-            &registry,
-            &empty_skill_registry(),
-            &overrides,
-            &conversation_approvals,
+            &Engine::new(
+                &store,
+                &provider,
+                &registry,
+                &empty_skill_registry(),
+                &overrides,
+                &conversation_approvals,
+            ),
             200,
             "Loop",
             None,
@@ -807,7 +802,7 @@ mod tests {
     impl std::fmt::Debug for ProcessResult {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
-                Self::Response { final_text, tool_results } => {
+                Self::Response { final_text, tool_results, .. } => {
                     write!(f, "Response(final_text: {final_text:?}, {} results)", tool_results.len())
                 }
                 Self::Empty => write!(f, "Empty"),
@@ -2,11 +2,14 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::Parser;
+use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::ParseMode;
+use teloxide::types::{ParseMode, PhotoSize};
 
 use buddy_core::provider::{AnyProvider, ProviderChain};
+use buddy_core::scheduler;
 use buddy_core::state::AppState;
+use buddy_core::store::{Job, JobKind, MAX_IMAGE_BYTES};
 
 mod adapter;
 mod approval;
@@ -57,6 +60,8 @@ async fn main() {
 
     println!("buddy-telegram started (polling)");
 
+    tokio::spawn(deliver_scheduled_jobs(bot.clone(), state.clone()));
+
     let message_handler = Update::filter_message().endpoint(handle_message);
     let callback_handler = Update::filter_callback_query().endpoint(handle_callback);
     let handler = dptree::entry()
@@ -77,9 +82,10 @@ async fn handle_message(
     state: Arc<AppState<ProviderChain<AnyProvider>>>,
     pending: approval::TelegramPendingApprovals,
 ) -> ResponseResult<()> {
-    let user_text = match msg.text() {
-        Some(t) => t,
-        None => return Ok(()),
+    let summary = match (msg.text(), adapter::largest_photo(&msg)) {
+        (Some(text), _) => text.to_string(),
+        (None, Some(_)) => format!("[photo] {}", msg.caption().unwrap_or_default()),
+        (None, None) => return Ok(()),
     };
 
     let chat_id = msg.chat.id;
@@ -88,12 +94,10 @@ async fn handle_message(
         .as_ref()
         .map(|u| u.first_name.as_str())
         .unwrap_or("unknown");
-    log::info!("[chat {}] {}: {}", chat_id, sender, user_text);
+    log::info!("[chat {}] {}: {}", chat_id, sender, summary.trim_end());
 
-    let provider = state.provider.load();
-    let registry = state.registry.load();
-    let skill_registry = state.skill_registry.load();
-    let approval_overrides = state.approval_overrides.load();
+    let snapshot = state.engine_snapshot();
+    let engine = snapshot.engine(&state);
     let approval_ctx = handler::TelegramApprovalContext {
         bot: &bot,
         chat_id,
@@ -101,24 +105,30 @@ async fn handle_message(
         timeout: state.approval_timeout,
     };
 
-    let result = handler::process_message(
-        &state.store,
-        &**provider,
-        &registry,
-        &skill_registry,
-        &approval_overrides,
-        &state.conversation_approvals,
-        chat_id.0,
-        user_text,
-        Some(approval_ctx),
-    )
-    .await;
+    let result = match (msg.text(), adapter::largest_photo(&msg)) {
+        (Some(user_text), _) => {
+            handler::process_message(&engine, chat_id.0, user_text, Some(approval_ctx)).await
+        }
+        (None, Some(photo)) => match download_photo(&bot, photo).await {
+            Ok(data) => {
+                handler::process_photo_message(&engine, &msg, &data, Some(approval_ctx)).await
+            }
+            Err(e) => {
+                log::error!("Failed to download photo: {e}");
+                bot.send_message(chat_id, "Sorry, I couldn't download that photo.")
+                    .await?;
+                return Ok(());
+            }
+        },
+        (None, None) => return Ok(()),
+    };
 
-    let (final_text, tool_results) = match result {
+    let (final_text, tool_results, warnings) = match result {
         Ok(handler::ProcessResult::Response {
             final_text,
             tool_results,
-        }) => (final_text, tool_results),
+            warnings,
+        }) => (final_text, tool_results, warnings),
         Ok(handler::ProcessResult::Empty) => return Ok(()),
         Err(e) => {
             bot.send_message(chat_id, e.user_message()).await?;
@@ -126,6 +136,9 @@ async fn handle_message(
         }
     };
 
+    for warning in warnings {
+        bot.send_message(chat_id, format!("Warning: {warning}")).await?;
+    }
     for part in tool_results {
         for chunk in adapter::split_message(&part) {
             bot.send_message(chat_id, chunk).await?;
@@ -142,6 +155,65 @@ async fn handle_message(
     Ok(())
 }
 
+/// Deliver reminders and scheduled prompts to Telegram chats as they fall due.
+async fn deliver_scheduled_jobs(bot: Bot, state: Arc<AppState<ProviderChain<AnyProvider>>>) {
+    scheduler::run(&state.store, "telegram", scheduler::POLL_INTERVAL, |job| {
+        deliver_job(&bot, &state, job)
+    })
+    .await;
+}
+
+async fn deliver_job(
+    bot: &Bot,
+    state: &AppState<ProviderChain<AnyProvider>>,
+    job: Job,
+) -> Result<(), String> {
+    let chat_id = job
+        .target
+        .parse()
+        .map(ChatId)
+        .map_err(|_| format!("invalid chat id '{}'", job.target))?;
+    log::info!("[chat {}] delivering scheduled {} {}", chat_id, job.kind.as_str(), job.id);
+
+    let text = match job.kind {
+        JobKind::Message => scheduler::reminder_text(&job),
+        JobKind::Prompt => {
+            let snapshot = state.engine_snapshot();
+            let engine = snapshot.engine(state);
+            match handler::process_scheduled_prompt(&engine, &job).await {
+                Ok(handler::ProcessResult::Response { final_text, .. }) => final_text,
+                Ok(handler::ProcessResult::Empty) => return Ok(()),
+                Err(e) => e.user_message().to_string(),
+            }
+        }
+    };
+
+    let escaped = adapter::escape_markdown_v2(&text);
+    for part in adapter::split_message(&escaped) {
+        bot.send_message(chat_id, part)
+            .parse_mode(ParseMode::MarkdownV2)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Download a photo into memory, refusing anything over `MAX_IMAGE_BYTES`.
+async fn download_photo(bot: &Bot, photo: &PhotoSize) -> Result<Vec<u8>, String> {
+    if photo.file.size as usize > MAX_IMAGE_BYTES {
+        return Err(format!("photo is larger than {MAX_IMAGE_BYTES} bytes"));
+    }
+    let file = bot
+        .get_file(photo.file.id.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut data = Vec::with_capacity(file.size as usize);
+    bot.download_file(&file.path, &mut data)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
 async fn handle_callback(
     bot: Bot,
     q: CallbackQuery,
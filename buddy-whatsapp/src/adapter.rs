@@ -27,7 +27,7 @@ pub struct ChangeValue {
     pub messages: Vec<WhatsAppMessage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct WhatsAppMessage {
     pub id: String,
     pub from: String,
@@ -39,10 +39,22 @@ pub struct WhatsAppMessage {
     pub text: Option<TextBody>,
     #[serde(default)]
     pub interactive: Option<InteractiveReply>,
+    #[serde(default)]
+    pub image: Option<MediaBody>,
+}
+
+/// Media attached to a message. The webhook only carries the media id; the
+/// bytes are fetched with `WhatsAppClient::download_media`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaBody {
+    pub id: String,
+    pub mime_type: String,
+    #[serde(default)]
+    pub caption: Option<String>,
 }
 
 /// Interactive reply payload (button replies from quick-reply buttons).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct InteractiveReply {
     #[serde(rename = "type")]
     #[allow(dead_code)] // Deserialized from webhook payload; may be used in future
@@ -51,38 +63,58 @@ pub struct InteractiveReply {
 }
 
 /// A single button reply from an interactive message.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ButtonReply {
     pub id: String,
     #[allow(dead_code)] // Deserialized from webhook payload; may be used in future
     pub title: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct TextBody {
     pub body: String,
 }
 
-/// Converts a WhatsApp text message to a buddy-core `Message`.
+/// Converts a WhatsApp message to buddy-core `Message`s.
 ///
-/// Returns `None` if the message is not a text message.
-#[allow(dead_code)]
-pub fn whatsapp_to_buddy(message: &WhatsAppMessage) -> Option<Message> {
-    if message.message_type != "text" {
-        return None;
-    }
-    let text = message.text.as_ref()?.body.clone();
+/// A text message becomes one text message. An image message becomes an
+/// `Image` referring to `image_blob_id` (where the downloaded media was
+/// stored), followed by its caption if it has one. Returns an empty list for
+/// other message types and for images that were not downloaded.
+pub fn whatsapp_to_buddy(message: &WhatsAppMessage, image_blob_id: Option<&str>) -> Vec<Message> {
     let timestamp = message
         .timestamp
         .as_deref()
         .and_then(|ts| ts.parse::<i64>().ok())
         .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
         .unwrap_or_else(Utc::now);
-    Some(Message {
+    let user_message = |content| Message {
         role: Role::User,
-        content: MessageContent::Text { text },
+        content,
         timestamp,
-    })
+    };
+    match (message.message_type.as_str(), &message.text, &message.image) {
+        ("text", Some(text), _) => vec![user_message(MessageContent::Text {
+            text: text.body.clone(),
+        })],
+        ("image", _, Some(media)) => {
+            let Some(blob_id) = image_blob_id else {
+                return Vec::new();
+            };
+            let mut messages = vec![user_message(MessageContent::Image {
+                blob_id: blob_id.to_string(),
+                mime_type: media.mime_type.clone(),
+                data: None,
+            })];
+            if let Some(caption) = media.caption.as_ref().filter(|c| !c.trim().is_empty()) {
+                messages.push(user_message(MessageContent::Text {
+                    text: caption.clone(),
+                }));
+            }
+            messages
+        }
+        _ => Vec::new(),
+    }
 }
 
 /// Converts a buddy-core `Message` to plain text suitable for WhatsApp.
@@ -92,6 +124,8 @@ pub fn buddy_to_whatsapp(message: &Message) -> String {
         MessageContent::Text { text } => text.clone(),
         MessageContent::ToolCall { name, .. } => format!("Using tool: {name}..."),
         MessageContent::ToolResult { content, .. } => content.clone(),
+        MessageContent::Image { .. } => "[image]".to_string(),
+        MessageContent::Reasoning { text } => text.clone(),
     }
 }
 
@@ -196,10 +230,11 @@ mod tests {
                 body: text.to_string(),
             }),
             interactive: None,
+            image: None,
         }
     }
 
-    fn make_image_message(from: &str) -> WhatsAppMessage {
+    fn make_image_message(from: &str, caption: Option<&str>) -> WhatsAppMessage {
         WhatsAppMessage {
             id: "wamid.test456".to_string(),
             from: from.to_string(),
@@ -207,22 +242,67 @@ mod tests {
             timestamp: Some("1234567890".to_string()),
             text: None,
             interactive: None,
+            image: Some(MediaBody {
+                id: "MEDIA_ID".to_string(),
+                mime_type: "image/jpeg".to_string(),
+                caption: caption.map(String::from),
+            }),
         }
     }
 
     #[test]
     fn whatsapp_text_converts_to_buddy_message() {
         let msg = make_text_message("15551234567", "Hello");
-        let result = whatsapp_to_buddy(&msg);
-        let buddy_msg = result.expect("should produce a Message");
-        assert_eq!(buddy_msg.role, Role::User);
-        assert!(matches!(&buddy_msg.content, MessageContent::Text { text } if text == "Hello"));
+        let result = whatsapp_to_buddy(&msg, None);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].role, Role::User);
+        assert!(matches!(&result[0].content, MessageContent::Text { text } if text == "Hello"));
+    }
+
+    #[test]
+    fn whatsapp_image_converts_to_image_and_caption() {
+        let msg = make_image_message("15551234567", Some("What is this?"));
+        let result = whatsapp_to_buddy(&msg, Some("blob-1"));
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].content,
+            MessageContent::Image {
+                blob_id: "blob-1".into(),
+                mime_type: "image/jpeg".into(),
+                data: None,
+            }
+        );
+        assert!(matches!(&result[1].content, MessageContent::Text { text } if text == "What is this?"));
+
+        let uncaptioned = make_image_message("15551234567", None);
+        assert_eq!(whatsapp_to_buddy(&uncaptioned, Some("blob-1")).len(), 1);
+    }
+
+    #[test]
+    fn whatsapp_image_without_download_is_skipped() {
+        let msg = make_image_message("15551234567", None);
+        assert!(whatsapp_to_buddy(&msg, None).is_empty());
     }
 
     #[test]
-    fn whatsapp_image_returns_none() {
-        let msg = make_image_message("15551234567");
-        assert!(whatsapp_to_buddy(&msg).is_none());
+    fn image_payload_deserializes_media_body() {
+        let msg: WhatsAppMessage = serde_json::from_value(serde_json::json!({
+            "id": "wamid.img",
+            "from": "15559876543",
+            "timestamp": "1700000000",
+            "type": "image",
+            "image": {
+                "id": "MEDIA_ID",
+                "mime_type": "image/jpeg",
+                "sha256": "abc",
+                "caption": "Look"
+            }
+        }))
+        .expect("valid image message");
+        let media = msg.image.expect("image body");
+        assert_eq!(media.id, "MEDIA_ID");
+        assert_eq!(media.mime_type, "image/jpeg");
+        assert_eq!(media.caption.as_deref(), Some("Look"));
     }
 
     #[test]
@@ -367,6 +447,7 @@ mod tests {
                     title: Some("Approve".to_string()),
                 }),
             }),
+            image: None,
         };
         let result = extract_button_reply(&msg);
         assert!(result.is_some());
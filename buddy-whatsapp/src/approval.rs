@@ -8,6 +8,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use buddy_core::skill::approval_details;
 use tokio::sync::{Mutex, oneshot};
 
 use crate::client::WhatsAppClient;
@@ -32,6 +33,10 @@ fn approval_body_text(skill_name: &str, formatted_arguments: &str) -> String {
     )
 }
 
+/// Longest tool preview (e.g. a diff) shown in an approval message;
+/// WhatsApp caps interactive message bodies at 1024 characters.
+const PREVIEW_MAX_LEN: usize = 800;
+
 /// Send an interactive approval message with Approve/Deny buttons, wait for
 /// the button reply or timeout. Returns `true` if approved, `false` if denied
 /// or timed out.
@@ -42,10 +47,9 @@ pub async fn request_approval(
     timeout: Duration,
     skill_name: &str,
     arguments: &str,
+    preview: Option<&str>,
 ) -> bool {
-    let formatted_args = serde_json::from_str::<serde_json::Value>(arguments)
-        .map(|v| serde_json::to_string_pretty(&v).unwrap_or_else(|_| arguments.to_string()))
-        .unwrap_or_else(|_| arguments.to_string());
+    let formatted_args = approval_details(arguments, preview, PREVIEW_MAX_LEN);
 
     let body_text = approval_body_text(skill_name, &formatted_args);
     let buttons = [(BUTTON_APPROVE, "Approve"), (BUTTON_DENY, "Deny")];
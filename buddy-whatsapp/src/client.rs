@@ -1,4 +1,6 @@
-use reqwest::Client;
+use buddy_core::store::MAX_IMAGE_BYTES;
+use reqwest::{Client, Response};
+use serde::Deserialize;
 use serde_json::json;
 
 const GRAPH_API_BASE: &str = "https://graph.facebook.com/v22.0";
@@ -48,16 +50,7 @@ impl WhatsAppClient {
             .send()
             .await
             .map_err(|e| WhatsAppError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "failed to read response body".to_string());
-            return Err(WhatsAppError(format!("HTTP {status}: {body}")));
-        }
-
+        check_status(response).await?;
         Ok(())
     }
 
@@ -100,18 +93,67 @@ impl WhatsAppClient {
             .send()
             .await
             .map_err(|e| WhatsAppError(e.to_string()))?;
+        check_status(response).await?;
+        Ok(())
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "failed to read response body".to_string());
-            return Err(WhatsAppError(format!("HTTP {status}: {body}")));
+    /// Download the media with `media_id`: look up its URL, then fetch it
+    /// with the API token. Media larger than `MAX_IMAGE_BYTES` is refused.
+    pub async fn download_media(&self, media_id: &str) -> Result<Vec<u8>, WhatsAppError> {
+        #[derive(Deserialize)]
+        struct MediaInfo {
+            url: String,
+            #[serde(default)]
+            file_size: Option<u64>,
         }
 
-        Ok(())
+        let url = format!("{}/{}", GRAPH_API_BASE, media_id);
+        let response = self
+            .http
+            .get(&url)
+            .bearer_auth(&self.api_token)
+            .send()
+            .await
+            .map_err(|e| WhatsAppError(e.to_string()))?;
+        let info: MediaInfo = check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| WhatsAppError(format!("invalid media info: {e}")))?;
+        if info.file_size.is_some_and(|size| size > MAX_IMAGE_BYTES as u64) {
+            return Err(WhatsAppError(format!("media {media_id} is too large")));
+        }
+
+        let response = self
+            .http
+            .get(&info.url)
+            .bearer_auth(&self.api_token)
+            .send()
+            .await
+            .map_err(|e| WhatsAppError(e.to_string()))?;
+        let bytes = check_status(response)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| WhatsAppError(e.to_string()))?;
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(WhatsAppError(format!("media {media_id} is too large")));
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Turn a non-success response into a `WhatsAppError` carrying its body.
+async fn check_status(response: Response) -> Result<Response, WhatsAppError> {
+    if response.status().is_success() {
+        return Ok(response);
     }
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "failed to read response body".to_string());
+    Err(WhatsAppError(format!("HTTP {status}: {body}")))
 }
 
 #[cfg(test)]
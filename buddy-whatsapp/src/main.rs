@@ -15,7 +15,9 @@ use sha2::Sha256;
 use tokio::signal;
 
 use buddy_core::provider::{AnyProvider, ProviderChain};
+use buddy_core::scheduler;
 use buddy_core::state::AppState as CoreState;
+use buddy_core::store::{Job, JobKind};
 
 mod adapter;
 mod approval;
@@ -124,6 +126,8 @@ async fn main() {
         pending_approvals: approval::new_whatsapp_pending_approvals(),
     });
 
+    tokio::spawn(deliver_scheduled_jobs(state.clone()));
+
     let app = create_router(state);
 
     let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{port}"))
@@ -239,17 +243,20 @@ async fn receive_webhook(
             continue;
         }
 
-        if msg.message_type != "text" {
-            log::info!(
-                "[WhatsApp] from {}: non-text message ({})",
-                msg.from,
-                msg.message_type
-            );
-            continue;
-        }
-        let text = match msg.text.as_ref() {
-            Some(t) => t.body.clone(),
-            None => continue,
+        let summary = match (msg.message_type.as_str(), &msg.text, &msg.image) {
+            ("text", Some(text), _) => text.body.clone(),
+            ("image", _, Some(media)) => match &media.caption {
+                Some(caption) => format!("[image] {caption}"),
+                None => "[image]".to_string(),
+            },
+            _ => {
+                log::info!(
+                    "[WhatsApp] from {}: unsupported message ({})",
+                    msg.from,
+                    msg.message_type
+                );
+                continue;
+            }
         };
 
         if !state.dedup.check_and_insert(&msg.id) {
@@ -261,22 +268,21 @@ async fn receive_webhook(
             continue;
         }
 
-        log::info!("[WhatsApp] from {}: {}", msg.from, text);
+        log::info!("[WhatsApp] from {}: {}", msg.from, summary);
 
         let state = Arc::clone(&state);
-        let phone = msg.from.clone();
+        let msg = msg.clone();
         tokio::spawn(async move {
-            process_incoming_message(&state, &phone, &text).await;
+            process_incoming_message(&state, &msg).await;
         });
     }
     StatusCode::OK
 }
 
-async fn process_incoming_message(state: &AppState, phone: &str, text: &str) {
-    let provider = state.core.provider.load();
-    let registry = state.core.registry.load();
-    let skill_registry = state.core.skill_registry.load();
-    let approval_overrides = state.core.approval_overrides.load();
+async fn process_incoming_message(state: &AppState, msg: &adapter::WhatsAppMessage) {
+    let phone = msg.from.as_str();
+    let snapshot = state.core.engine_snapshot();
+    let engine = snapshot.engine(&state.core);
 
     let approval_ctx = conversation::WhatsAppApprovalContext {
         client: &state.client,
@@ -285,24 +291,32 @@ async fn process_incoming_message(state: &AppState, phone: &str, text: &str) {
         timeout: state.core.approval_timeout,
     };
 
-    let result = conversation::process_message(
-        &state.core.store,
-        &**provider,
-        &registry,
-        &skill_registry,
-        &approval_overrides,
-        &state.core.conversation_approvals,
-        phone,
-        text,
-        Some(approval_ctx),
-    )
-    .await;
+    let result = match (&msg.image, &msg.text) {
+        (Some(media), _) => match state.client.download_media(&media.id).await {
+            Ok(image) => {
+                conversation::process_image_message(&engine, msg, &image, Some(approval_ctx)).await
+            }
+            Err(e) => {
+                log::error!("Failed to download WhatsApp media {}: {e}", media.id);
+                let _ = state
+                    .client
+                    .send_text_message(phone, "Sorry, I couldn't download that image.")
+                    .await;
+                return;
+            }
+        },
+        (None, Some(text)) => {
+            conversation::process_message(&engine, phone, &text.body, Some(approval_ctx)).await
+        }
+        (None, None) => return,
+    };
 
-    let (final_text, tool_results) = match result {
+    let (final_text, tool_results, warnings) = match result {
         Ok(conversation::ProcessResult::Response {
             final_text,
             tool_results,
-        }) => (final_text, tool_results),
+            warnings,
+        }) => (final_text, tool_results, warnings),
         Ok(conversation::ProcessResult::Empty) => return,
         Err(e) => {
             let _ = state
@@ -313,7 +327,13 @@ async fn process_incoming_message(state: &AppState, phone: &str, text: &str) {
         }
     };
 
-    // Send tool results first, then the final text.
+    // Send warnings and tool results first, then the final text.
+    for warning in warnings {
+        let text = format!("Warning: {warning}");
+        if let Err(e) = state.client.send_text_message(phone, &text).await {
+            log::error!("Failed to send WhatsApp message to {phone}: {e}");
+        }
+    }
     for part in tool_results {
         for chunk in adapter::split_message(&part) {
             if let Err(e) = state.client.send_text_message(phone, &chunk).await {
@@ -331,6 +351,43 @@ async fn process_incoming_message(state: &AppState, phone: &str, text: &str) {
     }
 }
 
+/// Deliver reminders and scheduled prompts to WhatsApp numbers as they fall
+/// due.
+async fn deliver_scheduled_jobs(state: Arc<AppState>) {
+    scheduler::run(&state.core.store, "whatsapp", scheduler::POLL_INTERVAL, |job| {
+        deliver_job(&state, job)
+    })
+    .await;
+}
+
+async fn deliver_job(state: &AppState, job: Job) -> Result<(), String> {
+    let phone = job.target.as_str();
+    log::info!("[{phone}] delivering scheduled {} {}", job.kind.as_str(), job.id);
+
+    let text = match job.kind {
+        JobKind::Message => scheduler::reminder_text(&job),
+        JobKind::Prompt => {
+            let snapshot = state.core.engine_snapshot();
+            let engine = snapshot.engine(&state.core);
+            match conversation::process_scheduled_prompt(&engine, &job).await {
+                Ok(conversation::ProcessResult::Response { final_text, .. }) => final_text,
+                Ok(conversation::ProcessResult::Empty) => return Ok(()),
+                Err(e) => e.user_message().to_string(),
+            }
+        }
+    };
+
+    let response_text = adapter::markdown_to_whatsapp(&text);
+    for part in adapter::split_message(&response_text) {
+        state
+            .client
+            .send_text_message(phone, &part)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -83,6 +83,16 @@ export async function fetchConversation(id) {
   return res.json();
 }
 
+/**
+ * Fetch a stored image and return an object URL for displaying it.
+ * @param {string} id
+ */
+export async function fetchBlobUrl(id) {
+  const res = await authFetch(`/api/blobs/${id}`);
+  if (!res.ok) throw new Error('Failed to load image');
+  return URL.createObjectURL(await res.blob());
+}
+
 /**
  * Delete a conversation.
  * @param {string} id
@@ -150,7 +160,7 @@ export function putConfigModels(models) {
 }
 
 /**
- * Update the tools config (read_file, write_file, fetch_url).
+ * Update the tools config (read_file, write_file, fetch_url, run_command).
  * @param {object} tools
  */
 export function putConfigTools(tools) {
@@ -242,21 +252,49 @@ export function formatApiError(e, { includeField = true } = {}) {
  * Groups tool_call + tool_result pairs into single blocks.
  *
  * Display item shapes:
- *   { kind: 'text', role, content, timestamp }
+ *   { kind: 'text', role, content, reasoning?, timestamp }
+ *   { kind: 'image', role, blobId, src, timestamp }
  *   { kind: 'tool_call', id, name, arguments, result }
  * @param {Array<{role: string, content: {type: string, [key: string]: any}, timestamp?: string}>} messages
  */
 export function toDisplayItems(messages) {
   const items = [];
+  const toolCalls = new Map();
   for (let i = 0; i < messages.length; i++) {
     const msg = messages[i];
-    if (msg.content.type === 'text') {
+    const last = items[items.length - 1];
+    if (msg.content.type === 'reasoning') {
+      items.push({
+        kind: 'text',
+        role: msg.role,
+        content: '',
+        reasoning: msg.content.text,
+        timestamp: msg.timestamp,
+      });
+    } else if (
+      msg.content.type === 'text' &&
+      last?.kind === 'text' &&
+      last.reasoning &&
+      !last.content &&
+      msg.role === last.role
+    ) {
+      // The reply that followed stored reasoning.
+      last.content = msg.content.text;
+    } else if (msg.content.type === 'text') {
       items.push({
         kind: 'text',
         role: msg.role,
         content: msg.content.text,
         timestamp: msg.timestamp,
       });
+    } else if (msg.content.type === 'image') {
+      items.push({
+        kind: 'image',
+        role: msg.role,
+        blobId: msg.content.blob_id,
+        src: null,
+        timestamp: msg.timestamp,
+      });
     } else if (msg.content.type === 'tool_call') {
       const block = {
         kind: 'tool_call',
@@ -265,18 +303,13 @@ export function toDisplayItems(messages) {
         arguments: msg.content.arguments,
         result: null,
       };
-      // Pair with the following tool_result if it matches.
-      if (
-        i + 1 < messages.length &&
-        messages[i + 1].content.type === 'tool_result' &&
-        messages[i + 1].content.id === msg.content.id
-      ) {
-        block.result = messages[i + 1].content.content;
-        i++;
-      }
+      toolCalls.set(msg.content.id, block);
       items.push(block);
+    } else if (msg.content.type === 'tool_result') {
+      // Results follow all of a turn's calls, so pair them up by id.
+      const block = toolCalls.get(msg.content.id);
+      if (block) block.result = msg.content.content;
     }
-    // Standalone tool_result (shouldn't normally happen) — skip.
   }
   return items;
 }